@@ -0,0 +1,31 @@
+//! Records the resolved versions of Vello and wgpu, for reporting at run
+//! time. Cargo only sets `CARGO_PKG_VERSION` for this crate, so the versions
+//! of dependencies are read from the lock file.
+
+use std::fs;
+
+/// The version of `package` in the lock file, or "unknown" if not found.
+fn locked_version(lock: &str, package: &str) -> String {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == name {
+            if let Some(version) = lines
+                .next()
+                .and_then(|line| line.strip_prefix("version = \""))
+                .and_then(|version| version.strip_suffix('"'))
+            {
+                return version.to_string();
+            }
+        }
+    }
+    "unknown".to_string()
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    for (package, var) in [("vello", "VELLO_VERSION"), ("wgpu", "WGPU_VERSION")] {
+        println!("cargo:rustc-env={}={}", var, locked_version(&lock, package));
+    }
+}
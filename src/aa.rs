@@ -0,0 +1,209 @@
+//! Antialiasing configuration helpers.
+
+use std::num::NonZeroUsize;
+
+use log::info;
+use serde::Serialize;
+use web_time::Instant;
+
+use vello::wgpu;
+use vello::{AaConfig, AaSupport, Renderer, RendererOptions};
+
+/// The MSAA sample count used by an AA config, or None for area AA.
+pub fn sample_count(aa: AaConfig) -> Option<u32> {
+    match aa {
+        AaConfig::Area => None,
+        AaConfig::Msaa8 => Some(8),
+        AaConfig::Msaa16 => Some(16),
+    }
+}
+
+/// Parse an AA mode name. `off` disables MSAA, giving `None`.
+pub fn parse(name: &str) -> Result<Option<AaConfig>, String> {
+    match name {
+        "off" => Ok(None),
+        "area" => Ok(Some(AaConfig::Area)),
+        "msaa8" => Ok(Some(AaConfig::Msaa8)),
+        "msaa16" => Ok(Some(AaConfig::Msaa16)),
+        _ => Err(format!("unknown AA mode '{}'", name)),
+    }
+}
+
+/// The name of `aa` as [`parse`] takes it.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn name(aa: AaConfig) -> &'static str {
+    match aa {
+        AaConfig::Area => "area",
+        AaConfig::Msaa8 => "msaa8",
+        AaConfig::Msaa16 => "msaa16",
+    }
+}
+
+/// Parse two AA configs, as in `?split_aa` and `?layer_aa`, from
+/// `FIRST,SECOND` mode names with `off` for area AA, or nothing for `default`.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn parse_pair(names: &str, default: [AaConfig; 2]) -> Result<[AaConfig; 2], String> {
+    if names.is_empty() {
+        return Ok(default);
+    }
+    let Some((first, second)) = names.split_once(',') else {
+        return Err(format!("'{}' is not two modes separated by a comma", names));
+    };
+    let aa = |name| parse(name).map(|aa| aa.unwrap_or(AaConfig::Area));
+    Ok([aa(first)?, aa(second)?])
+}
+
+/// The next lower AA config to try if `aa` isn't supported.
+fn fallback(aa: AaConfig) -> Option<AaConfig> {
+    match aa {
+        AaConfig::Msaa16 => Some(AaConfig::Msaa8),
+        AaConfig::Msaa8 => Some(AaConfig::Area),
+        AaConfig::Area => None,
+    }
+}
+
+/// Check whether the adapter supports the sample count of `aa` for the surface format.
+/// Area AA doesn't multisample, so it is always supported.
+pub fn is_supported(adapter: &wgpu::Adapter, format: wgpu::TextureFormat, aa: AaConfig) -> bool {
+    match sample_count(aa) {
+        None => true,
+        Some(count) => adapter
+            .get_texture_format_features(format)
+            .flags
+            .sample_count_supported(count),
+    }
+}
+
+/// Select the requested AA config, falling back to the next lower
+/// supported level if the adapter can't multisample the surface format.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn select(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    requested: AaConfig,
+) -> AaConfig {
+    let mut aa = requested;
+    while !is_supported(adapter, format, aa) {
+        let Some(next) = fallback(aa) else {
+            break;
+        };
+        info!(
+            "{:?} not supported for {:?}, falling back to {:?}",
+            aa, format, next
+        );
+        aa = next;
+    }
+    aa
+}
+
+/// Log which AA pipelines a renderer was built with, and what the adapter
+/// can multisample for `format`.
+///
+/// Vello's MSAA modes don't use the GPU's multisampling: the samples are
+/// computed and resolved in Vello's fine rasterisation shader, and the
+/// result is a plain single-sample texture. So the format's multisample
+/// flags say what a hardware resolve could do, for comparison, not which
+/// path Vello takes. Without the adapter-specific format features the flags
+/// are only those the backend guarantees for every adapter.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn log_pipelines(adapter: &wgpu::Adapter, format: wgpu::TextureFormat, support: AaSupport) {
+    let built: Vec<&str> = [
+        (support.area, "area"),
+        (support.msaa8, "msaa8"),
+        (support.msaa16, "msaa16"),
+    ]
+    .into_iter()
+    .filter_map(|(built, name)| built.then_some(name))
+    .collect();
+    info!(
+        "Vello AA pipelines built: {} (MSAA is resolved in Vello's compute shaders)",
+        built.join(", ")
+    );
+    let flags = adapter.get_texture_format_features(format).flags;
+    let adapter_specific = adapter
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    info!(
+        "{:?} hardware multisampling: sample counts {:?}, resolve {}, {}",
+        format,
+        flags.supported_sample_counts(),
+        if flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE) {
+            "supported"
+        } else {
+            "not supported"
+        },
+        if adapter_specific {
+            "as reported by the adapter"
+        } else {
+            "as guaranteed by the backend"
+        },
+    );
+}
+
+/// The time taken to build one AA config's fine rasterisation pipeline.
+#[derive(Debug, Serialize)]
+pub struct PipelineTime {
+    pub aa: String,
+    /// Building a renderer with only this config.
+    pub total_ms: f64,
+    /// The part of that over building one with no AA config at all.
+    pub pipeline_ms: f64,
+}
+
+/// The time taken to build Vello's pipelines, the stages every AA config
+/// shares and each config's own.
+#[derive(Debug, Serialize)]
+pub struct PipelineTimes {
+    pub shared_ms: f64,
+    pub configs: Vec<PipelineTime>,
+}
+
+/// Build a renderer with the shared stages only, then one with each of
+/// `configs` alone, timing each, and log the breakdown.
+///
+/// Shaders compiled once may be cached by the driver or the browser for
+/// the next renderer, so the times are approximate, and on the web creating
+/// a pipeline can return before the browser has finished compiling it.
+pub fn time_pipelines(
+    device: &wgpu::Device,
+    surface_format: Option<wgpu::TextureFormat>,
+    configs: &[AaConfig],
+) -> Result<PipelineTimes, vello::Error> {
+    let build = |support: AaSupport| {
+        let start = Instant::now();
+        Renderer::new(
+            device,
+            RendererOptions {
+                surface_format,
+                use_cpu: false,
+                antialiasing_support: support,
+                num_init_threads: NonZeroUsize::new(1),
+            },
+        )?;
+        Ok::<_, vello::Error>(start.elapsed().as_secs_f64() * 1000.0)
+    };
+    let shared_ms = build(AaSupport {
+        area: false,
+        msaa8: false,
+        msaa16: false,
+    })?;
+    info!("Vello shared pipelines built in {:.1} ms", shared_ms);
+    let mut times = PipelineTimes {
+        shared_ms,
+        configs: Vec::new(),
+    };
+    for &aa in configs {
+        let total_ms = build([aa].into_iter().collect())?;
+        let pipeline_ms = (total_ms - shared_ms).max(0.0);
+        info!(
+            "Vello {:?} pipeline built in {:.1} ms ({:.1} ms with the shared ones)",
+            aa, pipeline_ms, total_ms
+        );
+        times.configs.push(PipelineTime {
+            aa: format!("{:?}", aa),
+            total_ms,
+            pipeline_ms,
+        });
+    }
+    Ok(times)
+}
@@ -0,0 +1,92 @@
+//! Surface alpha mode selection.
+//!
+//! The alpha mode tells the compositor how to interpret the canvas alpha
+//! channel when drawing it over the rest of the page:
+//!
+//! - `Opaque` ignores alpha, so the canvas always covers what's behind it.
+//! - `PreMultiplied` expects the colour channels to already be scaled by
+//!   alpha, so a 50% red is stored as `(0.5, 0, 0, 0.5)`.
+//! - `PostMultiplied` ("straight") expects unscaled colour, `(1, 0, 0, 0.5)`.
+//!
+//! Vello works in premultiplied colour internally, and its surface blit
+//! writes premultiplied colour, so only `Opaque` and `PreMultiplied` present
+//! correctly. Fills and the base colour are encoded as normal (straight)
+//! colours either way.
+
+use vello::wgpu;
+
+/// Size in physical pixels of the checkerboard cells used to show transparency.
+pub const CHECKER_SIZE: f64 = 16.0;
+
+/// Parse an alpha mode name.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn parse(name: &str) -> Result<wgpu::CompositeAlphaMode, String> {
+    match name {
+        "auto" => Ok(wgpu::CompositeAlphaMode::Auto),
+        "opaque" => Ok(wgpu::CompositeAlphaMode::Opaque),
+        "premultiplied" => Ok(wgpu::CompositeAlphaMode::PreMultiplied),
+        "straight" => Ok(wgpu::CompositeAlphaMode::PostMultiplied),
+        _ => Err(format!("unknown alpha mode '{}'", name)),
+    }
+}
+
+/// Whether content behind the canvas can show through in this mode.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn is_transparent(mode: wgpu::CompositeAlphaMode) -> bool {
+    matches!(
+        mode,
+        wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied
+    )
+}
+
+/// The alpha modes the surface supports, according to its capabilities.
+///
+/// wgpu's WebGPU backend only reports `Opaque`, although every WebGPU canvas
+/// also supports premultiplied alpha and wgpu passes it through, so that is
+/// added for the browser backend.
+pub fn supported_modes(
+    backend: wgpu::Backend,
+    capabilities: &wgpu::SurfaceCapabilities,
+) -> Vec<wgpu::CompositeAlphaMode> {
+    let mut modes = capabilities.alpha_modes.clone();
+    if backend == wgpu::Backend::BrowserWebGpu
+        && !modes.contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+    {
+        modes.push(wgpu::CompositeAlphaMode::PreMultiplied);
+    }
+    modes
+}
+
+/// Check that the requested alpha mode can be used, given the supported modes.
+/// `Auto` is always accepted, and lets wgpu pick a supported mode.
+pub fn check(
+    supported: &[wgpu::CompositeAlphaMode],
+    requested: wgpu::CompositeAlphaMode,
+) -> Result<(), String> {
+    match requested {
+        wgpu::CompositeAlphaMode::Auto => Ok(()),
+        wgpu::CompositeAlphaMode::PostMultiplied => {
+            Err("straight alpha is not supported, as Vello presents premultiplied colour".into())
+        }
+        mode if supported.contains(&mode) => Ok(()),
+        mode => Err(format!(
+            "alpha mode {:?} is not supported by the surface (supported: {:?})",
+            mode, supported
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_against_capabilities() {
+        use wgpu::CompositeAlphaMode::*;
+        let supported = [Opaque, PreMultiplied];
+        assert!(check(&supported, Auto).is_ok());
+        assert!(check(&supported, PreMultiplied).is_ok());
+        assert!(check(&[Opaque], PreMultiplied).is_err());
+        assert!(check(&[Opaque, PostMultiplied], PostMultiplied).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Timing a fixed number of frames, after a warm-up.
+//!
+//! The first frames are slow for reasons that have nothing to do with the
+//! steady state: shaders are compiled and pipelines created lazily, and
+//! buffers grow to fit the scene. A [`Benchmark`] renders a number of
+//! warm-up frames whose times are discarded, then collects the times of the
+//! frames that follow and summarises them, so that runs can be compared.
+
+use log::info;
+use serde::Serialize;
+
+/// The distribution of one timing over the measured frames, in milliseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Timing {
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Timing {
+    fn of(samples: &[f64]) -> Self {
+        if samples.is_empty() {
+            return Timing::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        // The nearest-rank percentile.
+        let rank = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).max(1) - 1];
+        Timing {
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            median: rank(0.5),
+            p95: rank(0.95),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// The summary of a finished benchmark.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub warmup_frames: usize,
+    pub frames: usize,
+    /// Times from the start of each frame to rendering being submitted, to
+    /// the texture being presented, and to the device having been polled.
+    pub render_ms: Timing,
+    pub present_ms: Timing,
+    pub poll_ms: Timing,
+}
+
+/// A benchmark in progress.
+#[derive(Debug)]
+pub struct Benchmark {
+    warmup_frames: usize,
+    frames: usize,
+    /// The frames recorded so far, including warm-up frames.
+    seen: usize,
+    render: Vec<f64>,
+    present: Vec<f64>,
+    poll: Vec<f64>,
+}
+
+impl Benchmark {
+    /// Measure `frames` frames, after discarding `warmup_frames`.
+    pub fn new(warmup_frames: usize, frames: usize) -> Self {
+        Benchmark {
+            warmup_frames,
+            frames,
+            seen: 0,
+            render: Vec::with_capacity(frames),
+            present: Vec::with_capacity(frames),
+            poll: Vec::with_capacity(frames),
+        }
+    }
+
+    /// Record the times of a presented frame. Returns the summary once the
+    /// last frame to measure has been recorded, and `None` before and after.
+    pub fn record(&mut self, render_ms: f64, present_ms: f64, poll_ms: f64) -> Option<Summary> {
+        self.seen += 1;
+        if self.seen <= self.warmup_frames {
+            if self.seen == self.warmup_frames {
+                info!(
+                    "Benchmark warm-up complete after {} frames, measuring {}",
+                    self.warmup_frames, self.frames
+                );
+            }
+            return None;
+        }
+        if self.render.len() == self.frames {
+            return None;
+        }
+        self.render.push(render_ms);
+        self.present.push(present_ms);
+        self.poll.push(poll_ms);
+        (self.render.len() == self.frames).then(|| Summary {
+            warmup_frames: self.warmup_frames,
+            frames: self.frames,
+            render_ms: Timing::of(&self.render),
+            present_ms: Timing::of(&self.present),
+            poll_ms: Timing::of(&self.poll),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warmup_frames_are_discarded() {
+        let mut bench = Benchmark::new(2, 4);
+        // Slow warm-up frames, then steady ones.
+        assert_eq!(bench.record(500.0, 500.0, 500.0), None);
+        assert_eq!(bench.record(100.0, 100.0, 100.0), None);
+        for ms in [1.0, 2.0, 3.0] {
+            assert_eq!(bench.record(ms, ms + 1.0, ms + 2.0), None);
+        }
+        let summary = bench.record(10.0, 11.0, 12.0).unwrap();
+        assert_eq!(summary.frames, 4);
+        assert_eq!(
+            summary.render_ms,
+            Timing {
+                mean: 4.0,
+                median: 2.0,
+                p95: 10.0,
+                max: 10.0,
+            }
+        );
+        assert_eq!(summary.poll_ms.max, 12.0);
+        assert_eq!(bench.record(1.0, 1.0, 1.0), None);
+    }
+}
@@ -0,0 +1,300 @@
+//! Drawing textures the scene was rendered into into an output.
+//!
+//! The passes that do more than render the scene straight into the surface
+//! texture render it into textures of their own, and then draw those into
+//! the output with a triangle covering it and a small fragment shader that
+//! reads them pixel for pixel. [`Blit`] holds what they share: the vertex
+//! shader, the bind group layout of the textures with an optional `vec4`
+//! uniform after them, the pipelines for each fragment shader, and the
+//! render pass drawing them. Each pass only gives its fragment shaders and
+//! the textures it binds.
+
+use vello::wgpu;
+
+/// The format of the textures the scene is rendered into, as Vello needs.
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Prefixed to each pass's fragment shaders.
+const VERTEX: &str = r#"
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole output.
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+/// A texture of [`FORMAT`], and the view of it.
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl Texture {
+    /// A texture of the given size, with `usage` as well as being read by
+    /// a blit.
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        usage: wgpu::TextureUsages,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Texture { texture, view }
+    }
+
+    /// A texture Vello can render into.
+    pub fn target(device: &wgpu::Device, label: &str, width: u32, height: u32) -> Self {
+        Self::new(
+            device,
+            label,
+            width,
+            height,
+            wgpu::TextureUsages::STORAGE_BINDING,
+        )
+    }
+}
+
+/// A texture, and the bind group reading it alone.
+pub struct Source {
+    pub texture: Texture,
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// The shaders and bindings of a pass drawing textures into an output.
+pub struct Blit {
+    label: &'static str,
+    module: wgpu::ShaderModule,
+    layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    uniform: Option<wgpu::Buffer>,
+}
+
+impl Blit {
+    /// The blit for `fragment`, the pass's fragment shaders, which read
+    /// `textures` textures bound from 0 and, with `uniform`, a `vec4` bound
+    /// after them.
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        fragment: &str,
+        textures: u32,
+        uniform: bool,
+    ) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(format!("{}{}", VERTEX, fragment).into()),
+        });
+        let texture = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let mut entries: Vec<_> = (0..textures).map(texture).collect();
+        if uniform {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: textures,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            });
+        }
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries: &entries,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        // A vec4, the smallest uniform size.
+        let uniform = uniform.then(|| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: 16,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        Blit {
+            label,
+            module,
+            layout,
+            pipeline_layout,
+            uniform,
+        }
+    }
+
+    /// A pipeline drawing with the fragment shader `entry_point` into
+    /// outputs of `format`, blended with `blend`, if any.
+    pub fn pipeline(
+        &self,
+        device: &wgpu::Device,
+        entry_point: &str,
+        format: wgpu::TextureFormat,
+        blend: Option<wgpu::BlendState>,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(self.label),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.module,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &self.module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// A bind group reading `views`, and the uniform if there is one.
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        views: &[&wgpu::TextureView],
+    ) -> wgpu::BindGroup {
+        let mut entries: Vec<_> = (0..)
+            .zip(views)
+            .map(|(binding, view)| wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect();
+        if let Some(uniform) = &self.uniform {
+            entries.push(wgpu::BindGroupEntry {
+                binding: views.len() as u32,
+                resource: uniform.as_entire_binding(),
+            });
+        }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(self.label),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
+
+    /// A texture, as [`Texture::new`] makes, with a bind group reading it alone.
+    pub fn source(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        usage: wgpu::TextureUsages,
+    ) -> Source {
+        let texture = Texture::new(device, label, width, height, usage);
+        let bind_group = self.bind_group(device, &[&texture.view]);
+        Source {
+            texture,
+            bind_group,
+        }
+    }
+
+    /// Set the uniform, as the words of a `vec4`; floats by their bits.
+    pub fn set_uniform(&self, queue: &wgpu::Queue, words: [u32; 4]) {
+        if let Some(uniform) = &self.uniform {
+            let mut bytes = [0; 16];
+            for (bytes, word) in bytes.chunks_mut(4).zip(words) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+            queue.write_buffer(uniform, 0, &bytes);
+        }
+    }
+
+    /// Record a render pass into `output` that loads it with `load`, then
+    /// draws each of `draws`, a pipeline with the bind group it reads.
+    pub fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        draws: &[(&wgpu::RenderPipeline, &wgpu::BindGroup)],
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        for (pipeline, bind_group) in draws {
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, *bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Draw `draws` into `output` as [`draw`](Self::draw) does, in a
+    /// command buffer of its own, and submit it.
+    pub fn submit(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        output: &wgpu::TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+        draws: &[(&wgpu::RenderPipeline, &wgpu::BindGroup)],
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(self.label),
+        });
+        self.draw(&mut encoder, output, load, draws);
+        queue.submit([encoder.finish()]);
+    }
+}
+
+/// The clear colour for `color`, as a load op.
+pub fn clear(color: vello::peniko::Color) -> wgpu::LoadOp<wgpu::Color> {
+    let [r, g, b, a] = color.components;
+    wgpu::LoadOp::Clear(wgpu::Color {
+        r: r as f64,
+        g: g as f64,
+        b: b as f64,
+        a: a as f64,
+    })
+}
@@ -0,0 +1,92 @@
+//! Bookkeeping of where content is drawn.
+//!
+//! A Vello `Scene` can only be appended to: once shapes are encoded there's
+//! no asking it where they are. [`Bounds`] keeps the bounding box of each
+//! shape as it is drawn instead, so that the extent of the content is known,
+//! e.g. to fit it to the view.
+
+use std::f64::consts::SQRT_2;
+
+use vello::kurbo::{Affine, Cap, Join, Rect, Shape, Stroke};
+
+/// The bounding boxes of drawn shapes.
+#[derive(Clone, Debug, Default)]
+pub struct Bounds {
+    boxes: Vec<Rect>,
+}
+
+impl Bounds {
+    pub fn clear(&mut self) {
+        self.boxes.clear();
+    }
+
+    /// Add the box of content drawn in `rect`.
+    pub fn add(&mut self, rect: Rect) {
+        self.boxes.push(rect);
+    }
+
+    /// Add the box of a stroked shape: the shape's box grown by as far as
+    /// the stroke can reach beyond the path, at its joins and caps.
+    pub fn stroke(&mut self, style: &Stroke, transform: Affine, shape: &impl Shape) {
+        let mut reach: f64 = 1.0;
+        if style.join == Join::Miter {
+            reach = reach.max(style.miter_limit);
+        }
+        if style.start_cap == Cap::Square || style.end_cap == Cap::Square {
+            reach = reach.max(SQRT_2);
+        }
+        let rect = shape
+            .bounding_box()
+            .inflate(style.width / 2.0 * reach, style.width / 2.0 * reach);
+        self.add(transform.transform_rect_bbox(rect));
+    }
+
+    /// The union of all the boxes, or `None` if nothing has been drawn.
+    pub fn union(&self) -> Option<Rect> {
+        self.boxes.iter().copied().reduce(|a, b| a.union(b))
+    }
+}
+
+/// The view transform drawing `content` as large as fits in a viewport of
+/// the given size, centred, leaving at least `padding` on every side.
+pub fn fit(width: f64, height: f64, content: Rect, padding: f64) -> Affine {
+    // A point or a horizontal or vertical line has no size to scale up.
+    let size = (content.width().max(1.0), content.height().max(1.0));
+    let scale = ((width - padding * 2.0) / size.0).min((height - padding * 2.0) / size.1);
+    Affine::translate((width / 2.0, height / 2.0))
+        * Affine::scale(scale.max(f64::EPSILON))
+        * Affine::translate(-content.center().to_vec2())
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::Line;
+
+    use super::*;
+
+    #[test]
+    fn union_of_boxes_and_strokes() {
+        let mut bounds = Bounds::default();
+        assert_eq!(bounds.union(), None);
+        bounds.add(Rect::new(10.0, 0.0, 20.0, 10.0));
+        // Round caps reach half the width beyond the ends.
+        bounds.stroke(
+            &Stroke::new(4.0),
+            Affine::IDENTITY,
+            &Line::new((0.0, 50.0), (30.0, 50.0)),
+        );
+        assert_eq!(bounds.union(), Some(Rect::new(-2.0, 0.0, 32.0, 52.0)));
+        bounds.clear();
+        assert_eq!(bounds.union(), None);
+    }
+
+    #[test]
+    fn fit_fills_the_padded_viewport() {
+        let content = Rect::new(100.0, 100.0, 300.0, 200.0);
+        let transform = fit(440.0, 440.0, content, 20.0);
+        let fitted = transform.transform_rect_bbox(content);
+        // The width is the tighter dimension, so it fills the padded width.
+        assert!((fitted.x0 - 20.0).abs() < 1e-9 && (fitted.x1 - 420.0).abs() < 1e-9);
+        assert!((fitted.center().y - 220.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,88 @@
+//! Bursts of frames without vsync, to catch tearing.
+//!
+//! A [`Burst`] presents a set number of frames with `AutoNoVsync`, which
+//! wgpu resolves to `Immediate` or `Mailbox` where the surface has them, as
+//! fast as they can be drawn, before the present mode it replaced is put
+//! back. An artifact that comes and goes with the burst is in the present
+//! path, while one that stays is in rendering. The browser composites the
+//! canvas at its own rate whatever the present mode, so on the web frames
+//! stay at the display's rate.
+
+use web_time::Instant;
+
+use vello::wgpu;
+
+/// How many frames a burst lasts without `?vsync_burst=N`.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const DEFAULT_FRAMES: u32 = 120;
+
+#[derive(Debug)]
+pub struct Burst {
+    /// The present mode to restore when the burst is over.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub restore: wgpu::PresentMode,
+    frames: u32,
+    presented: u32,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Burst {
+    /// A burst of `frames` frames, restoring `restore` afterwards.
+    pub fn new(frames: u32, restore: wgpu::PresentMode) -> Self {
+        Burst {
+            restore,
+            frames,
+            presented: 0,
+            first: None,
+            last: None,
+        }
+    }
+
+    /// Record a frame presented at `presented`, returning the instantaneous
+    /// frame rate since the one before, if any.
+    pub fn presented(&mut self, presented: Instant) -> Option<f64> {
+        self.presented += 1;
+        self.first.get_or_insert(presented);
+        let last = self.last.replace(presented)?;
+        Some(1.0 / (presented - last).as_secs_f64().max(f64::EPSILON))
+    }
+
+    /// Whether every frame of the burst has been presented.
+    pub fn is_over(&self) -> bool {
+        self.presented >= self.frames
+    }
+
+    /// The frames presented so far, and their mean rate.
+    pub fn summary(&self) -> (u32, Option<f64>) {
+        let mean = match (self.first, self.last) {
+            (Some(first), Some(last)) if last > first => {
+                Some((self.presented - 1) as f64 / (last - first).as_secs_f64())
+            }
+            _ => None,
+        };
+        (self.presented, mean)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn frame_rates_over_the_burst() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut burst = Burst::new(3, wgpu::PresentMode::Fifo);
+        assert_eq!(burst.presented(ms(0)), None);
+        assert!((burst.presented(ms(4)).unwrap() - 250.0).abs() < 1e-6);
+        assert!(!burst.is_over());
+        assert!((burst.presented(ms(20)).unwrap() - 62.5).abs() < 1e-6);
+        assert!(burst.is_over());
+        let (frames, mean) = burst.summary();
+        assert_eq!(frames, 3);
+        assert!((mean.unwrap() - 100.0).abs() < 1e-6);
+    }
+}
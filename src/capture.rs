@@ -0,0 +1,164 @@
+//! Capturing consecutive frames into a ZIP of PNGs.
+//!
+//! A [`Capture`] takes the next `count` frames presented: each is rendered
+//! again into a texture, as for a screenshot, and read back. Reads finish
+//! asynchronously, so each frame is [`Pending`] until its pixels arrive,
+//! when it is encoded as a PNG and written into the archive. Frames are
+//! written in order, so one read finishing before an earlier one is held
+//! until the earlier is written; otherwise no more than one PNG is held
+//! outside the archive at a time, and no raw pixels outlive their encoding.
+//! The archive is finished, and handed back for downloading, once the last
+//! frame is written. Frames are named by their index in the capture, padded
+//! with zeros so that they sort in order.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::zip::ZipWriter;
+
+/// The archive the frames of a capture are written into, as they arrive.
+struct Archive {
+    /// `None` once a frame failed, when the capture is abandoned.
+    zip: Option<ZipWriter>,
+    count: u32,
+    /// The index of the next frame to write.
+    next: u32,
+    /// Frames that arrived before an earlier one, by index.
+    held: BTreeMap<u32, Vec<u8>>,
+}
+
+/// The frames still to be taken.
+pub struct Capture {
+    /// The number of the first frame taken, for the download's name.
+    pub first: u64,
+    taken: u32,
+    archive: Rc<RefCell<Archive>>,
+}
+
+/// A frame taken, waiting for its PNG.
+pub struct Pending {
+    index: u32,
+    archive: Rc<RefCell<Archive>>,
+}
+
+/// The name of frame `index` of `count` in the archive.
+pub fn frame_name(index: u32, count: u32) -> String {
+    let width = count.saturating_sub(1).max(1).ilog10() as usize + 1;
+    format!("frame-{:0width$}.png", index, width = width)
+}
+
+impl Capture {
+    /// A capture of `count` frames, the first of them frame number `first`.
+    pub fn new(first: u64, count: u32) -> Self {
+        let archive = Archive {
+            zip: Some(ZipWriter::default()),
+            count,
+            next: 0,
+            held: BTreeMap::new(),
+        };
+        Capture {
+            first,
+            taken: 0,
+            archive: Rc::new(RefCell::new(archive)),
+        }
+    }
+
+    /// The name of the download.
+    pub fn filename(&self) -> String {
+        format!("frames-{}.zip", self.first)
+    }
+
+    /// Take the next frame.
+    pub fn take(&mut self) -> Pending {
+        let index = self.taken;
+        self.taken += 1;
+        Pending {
+            index,
+            archive: self.archive.clone(),
+        }
+    }
+
+    /// Whether every frame has been taken, though not necessarily written.
+    pub fn is_over(&self) -> bool {
+        self.taken >= self.archive.borrow().count
+    }
+}
+
+impl Pending {
+    /// Write the frame's PNG into the archive, or abandon the capture if
+    /// the frame failed. Returns the finished archive once the frame written
+    /// is the last, and the error the first time one fails.
+    pub fn finish(self, png: Result<Vec<u8>, String>) -> Result<Option<Vec<u8>>, String> {
+        let mut archive = self.archive.borrow_mut();
+        let archive = &mut *archive;
+        if archive.zip.is_none() {
+            return Ok(None);
+        }
+        let written = png
+            .map_err(|e| format!("frame {}: {}", self.index, e))
+            .and_then(|png| {
+                archive.held.insert(self.index, png);
+                let zip = archive.zip.as_mut().unwrap();
+                while let Some(png) = archive.held.remove(&archive.next) {
+                    zip.add(&frame_name(archive.next, archive.count), &png)?;
+                    archive.next += 1;
+                }
+                Ok(())
+            });
+        if let Err(e) = written {
+            archive.zip = None;
+            archive.held.clear();
+            return Err(e);
+        }
+        if archive.next < archive.count {
+            return Ok(None);
+        }
+        Ok(archive.zip.take().map(ZipWriter::finish))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The names of the files in `zip`, in the order they were written.
+    fn names(zip: &[u8]) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut at = 0;
+        while zip[at..at + 4] == 0x04034b50u32.to_le_bytes() {
+            let field =
+                |offset: usize| u16::from_le_bytes([zip[at + offset], zip[at + offset + 1]]);
+            let size = u32::from_le_bytes(zip[at + 18..at + 22].try_into().unwrap()) as usize;
+            let (name_len, extra_len) = (field(26) as usize, field(28) as usize);
+            let name = &zip[at + 30..at + 30 + name_len];
+            names.push(String::from_utf8(name.to_vec()).unwrap());
+            at += 30 + name_len + extra_len + size;
+        }
+        names
+    }
+
+    #[test]
+    fn frames_are_written_in_order() {
+        assert_eq!(frame_name(3, 1), "frame-3.png");
+        assert_eq!(frame_name(3, 10), "frame-3.png");
+        assert_eq!(frame_name(3, 11), "frame-03.png");
+        assert_eq!(frame_name(42, 1000), "frame-042.png");
+
+        let mut capture = Capture::new(7, 3);
+        assert_eq!(capture.filename(), "frames-7.zip");
+        let (first, second, third) = (capture.take(), capture.take(), capture.take());
+        assert!(capture.is_over());
+        // The second arriving first is held for the first.
+        assert_eq!(second.finish(Ok(b"1".to_vec())), Ok(None));
+        assert_eq!(first.finish(Ok(b"0".to_vec())), Ok(None));
+        let zip = third.finish(Ok(b"2".to_vec())).unwrap().unwrap();
+        assert_eq!(names(&zip), ["frame-0.png", "frame-1.png", "frame-2.png"]);
+
+        // A failed frame abandons the capture.
+        let mut capture = Capture::new(0, 2);
+        let (first, second) = (capture.take(), capture.take());
+        assert!(first.finish(Err("lost".to_string())).is_err());
+        assert_eq!(second.finish(Ok(b"1".to_vec())), Ok(None));
+    }
+}
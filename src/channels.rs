@@ -0,0 +1,210 @@
+//! Showing one channel of the final image at a time.
+//!
+//! To tell which channel a colour artifact is in, or whether it is in the
+//! alpha a compositor would blend with, [`ChannelPass`] renders the scene
+//! into a texture and then draws one of its channels into the output as an
+//! opaque grey. Vello stores its output with straight alpha, so a colour
+//! channel shows the colour as it would be before blending, and the alpha
+//! channel shows coverage and opacity alone: black where the page would
+//! show through, white where nothing would.
+
+use std::str::FromStr;
+
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+use crate::blit::{Blit, Source};
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> weights: vec4<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let value = dot(textureLoad(source, vec2<i32>(position.xy), 0), weights);
+    return vec4<f32>(value, value, value, 1.0);
+}
+"#;
+
+/// A channel of the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Red => "red",
+            Channel::Green => "green",
+            Channel::Blue => "blue",
+            Channel::Alpha => "alpha",
+        }
+    }
+
+    /// The channel after `channel` when cycling through them, with `None`
+    /// for the whole image before the first and after the last.
+    pub fn next(channel: Option<Channel>) -> Option<Channel> {
+        match channel {
+            None => Some(Channel::Red),
+            Some(Channel::Red) => Some(Channel::Green),
+            Some(Channel::Green) => Some(Channel::Blue),
+            Some(Channel::Blue) => Some(Channel::Alpha),
+            Some(Channel::Alpha) => None,
+        }
+    }
+
+    /// The weights picking the channel out of an RGBA colour.
+    fn weights(self) -> [f32; 4] {
+        let mut weights = [0.0; 4];
+        weights[self as usize] = 1.0;
+        weights
+    }
+}
+
+impl FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "red" => Ok(Channel::Red),
+            "green" => Ok(Channel::Green),
+            "blue" => Ok(Channel::Blue),
+            "alpha" => Ok(Channel::Alpha),
+            _ => Err(format!("unknown channel '{}'", s)),
+        }
+    }
+}
+
+/// The texture the scene is rendered into, and the bind group reading it.
+struct Target {
+    source: Source,
+    width: u32,
+    height: u32,
+}
+
+/// Renders scenes showing one channel, into outputs of one format.
+pub struct ChannelPass {
+    blit: Blit,
+    pipeline: wgpu::RenderPipeline,
+    target: Option<Target>,
+}
+
+impl ChannelPass {
+    /// Build the pipeline, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let blit = Blit::new(device, "channel", SHADER, 1, true);
+        let pipeline = blit.pipeline(device, "fs_main", format, None);
+        ChannelPass {
+            blit,
+            pipeline,
+            target: None,
+        }
+    }
+
+    /// The target to render into at the given size, replacing one of another size.
+    fn target(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Target {
+        if let Some(target) = self.target.take() {
+            if target.width == width && target.height == height {
+                return target;
+            }
+        }
+        let usage = wgpu::TextureUsages::STORAGE_BINDING;
+        let source = self
+            .blit
+            .source(device, "channel source", width, height, usage);
+        Target {
+            source,
+            width,
+            height,
+        }
+    }
+
+    /// Render `scene` with `params`, then draw its `channel` into `output`
+    /// as grey.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+        channel: Channel,
+    ) -> Result<(), vello::Error> {
+        self.blit
+            .set_uniform(queue, channel.weights().map(f32::to_bits));
+        let target = self.target(device, params.width, params.height);
+        let view = &target.source.texture.view;
+        let rendered = renderer.render_to_texture(device, queue, scene, view, params);
+        let target = self.target.insert(target);
+        rendered?;
+
+        let draw = (&self.pipeline, &target.source.bind_group);
+        let load = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+        self.blit.submit(device, queue, output, load, &[draw]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Affine, Rect};
+    use vello::peniko::{Color, Fill};
+    use vello::AaConfig;
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    #[test]
+    fn channels_are_shown_as_grey() {
+        let Some((context, dev_id, mut renderer)) = renderer(&[AaConfig::Area]) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 1, 1);
+        let mut scene = Scene::new();
+        let color = Color::from_rgba8(200, 100, 50, 128);
+        let rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+        let params = RenderParams {
+            base_color: Color::TRANSPARENT,
+            width: 1,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let mut pass = ChannelPass::new(device, blit::FORMAT);
+        let mut channel = None;
+        // The colour comes out as it was, with straight alpha.
+        for expected in [200, 100, 50, 128] {
+            channel = Channel::next(channel);
+            let channel = channel.unwrap();
+            pass.render(
+                &mut renderer,
+                device,
+                queue,
+                &scene,
+                &view,
+                &params,
+                channel,
+            )
+            .unwrap();
+            let pixels =
+                block_on(Readback::new(device, queue, &output, 1, 1).read(device)).unwrap();
+            let [r, g, b, a] = pixels[..] else {
+                panic!("{:?}", pixels);
+            };
+            assert!(r.abs_diff(expected) <= 2, "{:?}: {:?}", channel, pixels);
+            assert_eq!((g, b, a), (r, r, 255), "{:?}", channel);
+        }
+        assert_eq!(Channel::next(channel), None);
+        assert_eq!("alpha".parse(), Ok(Channel::Alpha));
+    }
+}
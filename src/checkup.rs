@@ -0,0 +1,177 @@
+//! A full diagnostic run, to paste into a bug report.
+//!
+//! [`run`] gathers everything needed to triage a rendering problem on a
+//! device of its own, without the interactive client: the versions and the
+//! adapter, the device's features and limits, the colour self-test, the
+//! single pixel read back, a render of the known edge with each AA config,
+//! with its hash and profile, and the time each config's pipelines take to
+//! build. Each check that fails to run records
+//! why in `errors` and the others carry on, so a report always comes back.
+
+use std::num::NonZeroUsize;
+
+use log::{error, info};
+use serde::Serialize;
+
+use vello::peniko::Color;
+use vello::wgpu;
+use vello::{AaConfig, AaSupport, RenderParams, Renderer, RendererOptions};
+
+use crate::aa::{self, PipelineTimes};
+use crate::context::DeviceHandle;
+use crate::diagnostics::VersionInfo;
+use crate::edge::{self, EdgeProfile};
+use crate::readback::{self, ImageSummary};
+use crate::selftest::{self, PixelReport};
+
+/// The AA configs rendered, where the adapter supports them.
+const AA_CONFIGS: [AaConfig; 3] = [AaConfig::Area, AaConfig::Msaa8, AaConfig::Msaa16];
+
+/// The known edge rendered with one AA config.
+#[derive(Debug, Serialize)]
+pub struct AaRender {
+    pub aa: String,
+    pub supported: bool,
+    /// What was rendered, unless unsupported or the render failed.
+    pub summary: Option<ImageSummary>,
+    pub edge: Option<EdgeProfile>,
+}
+
+#[derive(Serialize)]
+pub struct DiagnosticReport {
+    /// Whether every check ran and passed.
+    pub pass: bool,
+    pub versions: VersionInfo,
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+    pub self_test: Option<selftest::Report>,
+    pub single_pixel: Option<PixelReport>,
+    pub aa: Vec<AaRender>,
+    /// How long each AA config's pipelines took to build.
+    pub pipelines: Option<PipelineTimes>,
+    /// Why any of the checks couldn't run.
+    pub errors: Vec<String>,
+}
+
+/// Render the known edge with each AA config the adapter supports.
+async fn render_edges(handle: &DeviceHandle) -> Result<Vec<AaRender>, String> {
+    let (device, queue) = (&handle.device, &handle.queue);
+    let format = wgpu::TextureFormat::Rgba8Unorm;
+    let supported = |aa| aa::is_supported(&handle.adapter, format, aa);
+    let aa_support: AaSupport = AA_CONFIGS.into_iter().filter(|&aa| supported(aa)).collect();
+    let mut renderer = Renderer::new(
+        device,
+        RendererOptions {
+            surface_format: None,
+            use_cpu: false,
+            antialiasing_support: aa_support,
+            num_init_threads: NonZeroUsize::new(1),
+        },
+    )
+    .map_err(|e| format!("couldn't create a renderer: {}", e))?;
+    let scene = edge::scene();
+    let mut renders = Vec::new();
+    for aa in AA_CONFIGS {
+        let mut render = AaRender {
+            aa: format!("{:?}", aa),
+            supported: supported(aa),
+            summary: None,
+            edge: None,
+        };
+        if render.supported {
+            let params = RenderParams {
+                base_color: Color::TRANSPARENT,
+                width: edge::SIZE,
+                height: edge::SIZE,
+                antialiasing_method: aa,
+            };
+            let pixels =
+                readback::render_to_readback(device, queue, &mut renderer, &scene, &params)
+                    .map_err(|e| format!("couldn't render with {:?}: {}", aa, e))?
+                    .read(device)
+                    .await
+                    .map_err(|e| format!("couldn't read back the {:?} render: {}", aa, e))?;
+            render.summary = Some(ImageSummary::of(&pixels));
+            render.edge = Some(EdgeProfile::of(&pixels));
+        }
+        renders.push(render);
+    }
+    Ok(renders)
+}
+
+/// Run every check on the device in `handle`.
+pub async fn run(handle: &DeviceHandle) -> DiagnosticReport {
+    let (device, queue) = (&handle.device, &handle.queue);
+    let mut errors = Vec::new();
+    let self_test = selftest::run(device, queue)
+        .await
+        .map_err(|e| errors.push(format!("self-test: {}", e)))
+        .ok();
+    let single_pixel = selftest::single_pixel(device, queue)
+        .await
+        .map_err(|e| errors.push(format!("single pixel: {}", e)))
+        .ok();
+    let aa = render_edges(handle)
+        .await
+        .map_err(|e| errors.push(format!("AA renders: {}", e)))
+        .unwrap_or_default();
+    let supported: Vec<AaConfig> = aa
+        .iter()
+        .zip(AA_CONFIGS)
+        .filter_map(|(render, aa)| render.supported.then_some(aa))
+        .collect();
+    let pipelines = aa::time_pipelines(device, None, &supported)
+        .map_err(|e| errors.push(format!("pipeline times: {}", e)))
+        .ok();
+    let pass = errors.is_empty()
+        && self_test.as_ref().is_some_and(|r| r.pass)
+        && single_pixel.as_ref().is_some_and(|r| r.pass)
+        && aa
+            .iter()
+            .filter_map(|render| render.edge.as_ref())
+            .all(|edge| !edge.is_abnormal());
+    if pass {
+        info!("Diagnostics passed");
+    } else {
+        error!("Diagnostics failed: {:?}", errors);
+    }
+    DiagnosticReport {
+        pass,
+        versions: VersionInfo::new(&handle.adapter),
+        features: device.features(),
+        limits: device.limits(),
+        self_test,
+        single_pixel,
+        aa,
+        pipelines,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RenderContext;
+    use crate::readback::testing::block_on;
+
+    #[test]
+    fn reports_every_check() {
+        let mut context = RenderContext::new(wgpu::PowerPreference::default());
+        let Some(dev_id) = block_on(context.device(None)) else {
+            eprintln!("No adapter available, skipping diagnostics");
+            return;
+        };
+        let report = block_on(run(&context.devices[dev_id]));
+        assert!(report.pass, "{:?}", report.errors);
+        assert_eq!(report.aa.len(), AA_CONFIGS.len());
+        assert!(report.aa[0].supported && report.aa[0].summary.is_some());
+        let supported = report.aa.iter().filter(|render| render.supported).count();
+        assert_eq!(report.pipelines.as_ref().unwrap().configs.len(), supported);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["aa"][0]["edge"]["pass"], true);
+        assert_eq!(
+            json["single_pixel"]["expected"],
+            serde_json::json!([32, 96, 160, 255])
+        );
+    }
+}
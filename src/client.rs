@@ -0,0 +1,3759 @@
+//! The interactive client, driven by the winit event loop.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
+
+use log::{debug, error, info, warn};
+
+use serde::Serialize;
+
+use vello::kurbo::{Affine, BezPath, Point, Rect, Vec2};
+use vello::peniko::{Color, Fill, Font, Image, Mix};
+use vello::util::RenderSurface;
+use vello::{AaConfig, AaSupport, Renderer, RendererOptions, Scene};
+use winit::application::ApplicationHandler;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+use winit::platform::web::WindowExtWebSys;
+use winit::window::{Theme, Window, WindowId};
+
+use vello::wgpu;
+
+use crate::bench::Benchmark;
+use crate::bounds::{self, Bounds};
+use crate::burst::{self, Burst};
+use crate::capture::Capture;
+use crate::channels::{Channel, ChannelPass};
+use crate::clock::{
+    Clock, FixedTimestep, FramePacer, ManualClock, PausableClock, RealClock, RefreshRate,
+};
+use crate::config::{self, ClientConfig};
+use crate::context::{self, DeviceHandle, RenderContext};
+use crate::diagnostics::{self, CapabilityReport, VersionInfo};
+use crate::dirty::{self, Change, DirtyPass, Fingerprint};
+use crate::draw::BackgroundFit;
+use crate::edge::{self, EdgeProfile, EdgeTest, HairlineProfile};
+use crate::fallback::{self, Detection};
+use crate::flipbook::Flipbook;
+use crate::frame::{FrameTimes, PollStrategy};
+use crate::gamma::GammaPass;
+use crate::grid::Grid;
+use crate::inspector::{Inspector, Sample};
+use crate::keys::{self, Binding};
+use crate::latency::LatencyProbe;
+use crate::layered::{self, LayerPass};
+use crate::layers::{self, Layers};
+use crate::minimap::Minimap;
+use crate::onion::OnionPass;
+use crate::overlay::FrameHistory;
+use crate::painter::Painter;
+use crate::panel::{Control, Panel};
+use crate::preserve::PreservePass;
+use crate::readback::{self, ImageSummary, Readback};
+use crate::record::{self, InputEvent, Replay};
+use crate::reel::{self, Reel};
+use crate::reuse::SceneReuse;
+use crate::scaling::{Downscaler, RenderScale};
+use crate::scenes::{self, Demo, DemoState};
+use crate::scroll;
+use crate::split::SplitPass;
+use crate::stats::{self, SceneSize};
+use crate::stress::{Progress, Stress};
+use crate::text::{self, Text};
+use crate::trace::{self, FrameTiming};
+use crate::watchdog::Watchdog;
+use crate::{aa, alpha, dom, draw, frame, outline, overlay, surface, Options};
+
+/// View scales selected with the number keys 1 to 4.
+const ZOOM_PRESETS: [f64; 4] = [0.25, 0.5, 1.0, 2.0];
+/// How far stepping a paused animation advances it, unless `?step` or
+/// `?fps` give a frame's time or the refresh rate has been measured: one
+/// frame at 60Hz.
+const SINGLE_STEP: Duration = Duration::from_micros(16_667);
+/// How long the animation frames are timed for to measure the refresh rate.
+const REFRESH_MEASUREMENT: Duration = Duration::from_secs(1);
+/// Space left around the content by `fit_to_view`, in pixels.
+const FIT_PADDING: f64 = 20.0;
+
+/// How often, in redraws, to log the number of frames presented.
+const PRESENT_LOG_INTERVAL: u64 = 600;
+
+/// The `L` key cycles the maximum frame latency from 1 up to this.
+const MAX_FRAME_LATENCY: u32 = 3;
+
+/// The largest tile `export_image` renders, in pixels on a side, below the
+/// texture limit so that each tile's readback buffer stays a moderate size.
+const EXPORT_TILE: u32 = 4096;
+
+/// Events sent to the client from outside the event loop, e.g. from JS.
+#[derive(Debug)]
+pub(crate) enum UserEvent {
+    Resize(u32, u32),
+    ViewTransform(Affine),
+    FrameLatency(u32),
+    Gamma(f32),
+    Supersample(f32),
+    PreservePrevious(bool),
+    Seed(u32),
+    Screenshot,
+    Export(u32, u32),
+    /// Capture this many frames, from the next, and download them as a ZIP.
+    ExportFrames(u32),
+    FitToView,
+    Zoom(Vec2),
+    Background(Option<Image>),
+    /// Draw this image beside the live content, which is then drawn in the
+    /// left half, or go back to the whole surface if `None`.
+    ReferenceImage(Option<Image>),
+    Replay(Replay),
+    Flipbook(Option<Flipbook>),
+    Drawing(Option<Box<Painter>>),
+    /// Draw text in this font, or the bundled one if `None`.
+    Font(Option<Font>),
+    /// Switch to the adapter at this index in `context::list_adapters`.
+    SwitchAdapter(usize),
+    /// A device on the adapter being switched to, or why there isn't one.
+    AdapterOpened(Result<DeviceHandle, String>),
+    /// Time to check that frames are still being presented.
+    WatchdogCheck,
+    /// The refresh rate measured from the animation frames, or `None` if too few came.
+    RefreshRate(Option<RefreshRate>),
+    /// Whether the page is visible, after it was shown or hidden.
+    PageVisible(bool),
+    /// A frame rendered with debug layers, giving back its renderer.
+    DebugFrame(Box<layers::Frame>),
+    /// Time to read the pixel under the cursor, for the inspector.
+    Inspect,
+    /// The pixel the inspector read, or `None` if it couldn't be read.
+    Inspected(Option<Sample>),
+    /// The profile of the edge tested with this AA config, or `None` if it
+    /// couldn't be read back.
+    EdgeTested(AaConfig, Option<EdgeProfile>),
+    /// Time for the demo reel with this number, counting from 1, to take its next step.
+    ReelStep(u32),
+    /// The render path found for the device in use.
+    RenderPath(Detection),
+    /// Put back the state a shared string held.
+    RestoreConfig(ClientConfig),
+}
+
+/// What the rows of the control panel do.
+#[derive(Clone, Copy, Debug)]
+enum PanelAction {
+    ToggleMsaa,
+    NextScene,
+    ToggleTiles,
+    ToggleOverlay,
+    Screenshot,
+}
+
+/// Details of a presented frame, passed to the present callback.
+#[derive(Clone, Debug, Serialize)]
+pub struct PresentInfo {
+    pub frame: u64,
+    pub width: u32,
+    pub height: u32,
+    /// Times from the start of the frame to rendering being submitted, to
+    /// the texture being presented, and to the device having been polled.
+    pub render_ms: f64,
+    pub present_ms: f64,
+    pub poll_ms: f64,
+}
+
+pub(crate) type PresentCallback = Box<dyn FnMut(&PresentInfo)>;
+
+thread_local! {
+    /// Called after each frame is presented, if set.
+    static PRESENT_CALLBACK: RefCell<Option<PresentCallback>> = const { RefCell::new(None) };
+}
+
+/// Set the callback called after each frame is presented, or remove it with `None`.
+pub(crate) fn set_present_callback(callback: Option<PresentCallback>) {
+    PRESENT_CALLBACK.set(callback);
+}
+
+/// Draws extra content into the scene each frame, given the drawable width
+/// and height, the frame number and the animation time in seconds.
+pub(crate) type DrawCallback = Box<dyn FnMut(&mut Scene, u32, u32, u64, f64)>;
+
+thread_local! {
+    /// Called as each frame's content is drawn, if set.
+    static DRAW_CALLBACK: RefCell<Option<DrawCallback>> = const { RefCell::new(None) };
+}
+
+/// Set the callback drawing content each frame, or remove it with `None`.
+pub(crate) fn set_draw_callback(callback: Option<DrawCallback>) {
+    DRAW_CALLBACK.set(callback);
+}
+
+/// Call the draw callback, taking it out while it runs so that it can replace itself.
+fn call_draw_callback(scene: &mut Scene, width: u32, height: u32, frame: u64, elapsed: f64) {
+    let Some(mut callback) = DRAW_CALLBACK.take() else {
+        return;
+    };
+    callback(scene, width, height, frame, elapsed);
+    DRAW_CALLBACK.with_borrow_mut(|current| {
+        if current.is_none() {
+            *current = Some(callback);
+        }
+    });
+}
+
+/// Call the present callback, taking it out while it runs so that it can replace itself.
+fn call_present_callback(info: &PresentInfo) {
+    let Some(mut callback) = PRESENT_CALLBACK.take() else {
+        return;
+    };
+    callback(info);
+    PRESENT_CALLBACK.with_borrow_mut(|current| {
+        if current.is_none() {
+            *current = Some(callback);
+        }
+    });
+}
+
+/// A frame read back for comparing later frames against.
+struct ReferenceFrame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+pub(crate) struct VelloClient<'a> {
+    /// The surface, until the client is shut down.
+    surface: Option<RenderSurface<'a>>,
+    window: Arc<Window>,
+    context: RenderContext,
+    renderers: Vec<Option<Renderer>>,
+    /// For sending events to the client from its own futures.
+    proxy: EventLoopProxy<UserEvent>,
+    /// Whether the renderers use Vello's CPU shaders, as in safe mode.
+    use_cpu: bool,
+    scene: Scene,
+    /// The scene content, before the view transform is applied.
+    content: Scene,
+    /// Transform applied to all the scene content, set from JS. The overlays are not transformed.
+    view_transform: Affine,
+    /// How far the wheel has scrolled the viewport across the content, in
+    /// pixels, applied after the view transform. Reset when it's set.
+    scroll: Vec2,
+    /// Draw the content in a centred area with this aspect ratio, letterboxed with `letterbox_color`.
+    aspect_lock: Option<f64>,
+    letterbox_color: Color,
+    /// Draw into the whole surface, rather than inset from its edges.
+    full_bleed: bool,
+    /// The selected (supported) AA config, used when `msaa_enabled` is set.
+    aa_config: AaConfig,
+    /// Every AA config the renderer was built with.
+    aa_configs: Vec<AaConfig>,
+    /// The AA config for screenshots, or `None` to use the live one.
+    export_aa: Option<AaConfig>,
+    /// Compare the AA configs once the first frame has been presented.
+    compare_aa: bool,
+    /// Profile an antialiased edge with each AA config after the first frame.
+    edge_profile: bool,
+    /// Cycling through every demo with every AA config, if started, and
+    /// how long each is shown.
+    reel: Option<Reel>,
+    reel_dwell: Duration,
+    /// Start the reel once the first frame is presented, with `?demo_reel`.
+    reel_on_start: bool,
+    /// How many reels have been started, to tell a stopped reel's timer
+    /// from the current one's.
+    reels_started: u32,
+    /// The device whose render path was last found, once it has presented a frame.
+    render_path_device: Option<usize>,
+    /// Keeps an edge tested with the AA config in use, with `?edge_test`.
+    edge_test: Option<EdgeTest>,
+    /// Read back every frame and compare it with `reference`.
+    frame_diff: bool,
+    /// Filled in when the read back of the reference frame completes.
+    reference: Rc<RefCell<Option<ReferenceFrame>>>,
+    msaa_enabled: bool,
+    verbose: bool,
+    capture_errors: bool,
+    frame: u64,
+    /// Redraws requested, and how many of them were presented.
+    redraws_requested: u64,
+    frames_presented: u64,
+    /// Times key presses to the frames that show them.
+    latency: LatencyProbe,
+    /// Notices when frames stop being presented, with `?watchdog`.
+    watchdog: Option<Watchdog>,
+    /// How the device is polled after presenting, and for the frames polled,
+    /// how many still had work in flight.
+    poll: PollStrategy,
+    /// How many times each frame is rendered into the surface texture.
+    passes: u32,
+    frames_polled: u64,
+    pending_after_poll: u64,
+    /// Internal resolution, lowered while frames are slow.
+    render_scale: RenderScale,
+    downscaler: Downscaler,
+    /// How many times the surface size to render at, filtered down by the
+    /// downscaler when above 1.
+    supersample: f32,
+    /// The gamma adjustment of the final image, applied by `gamma_pass`
+    /// unless 1. The pass is built when first needed.
+    gamma: f32,
+    gamma_pass: Option<GammaPass>,
+    /// Whether each frame is drawn over the last instead of a clear surface,
+    /// by `preserve_pass`, which holds the content kept so far.
+    preserve_previous: bool,
+    preserve_pass: Option<PreservePass>,
+    /// Whether the previous frame is drawn faintly beneath each frame, by
+    /// `onion_pass`, which keeps it.
+    onion_skin: bool,
+    onion_pass: Option<OnionPass>,
+    /// Whether each frame is rendered with two AA configs side by side, by
+    /// `split_pass`: `split_aa`, or the best and area AA without it.
+    show_split: bool,
+    split_aa: Option<[AaConfig; 2]>,
+    split_pass: Option<SplitPass>,
+    /// The one channel of each frame shown, as grey, by `channel_pass`, if any.
+    channel: Option<Channel>,
+    channel_pass: Option<ChannelPass>,
+    /// The AA configs of the content and of the overlays, if they are
+    /// rendered apart by `layer_pass`. The frame's scene is then built as
+    /// `content_layer` and `overlay_layer` too.
+    layer_aa: Option<[AaConfig; 2]>,
+    layer_pass: Option<LayerPass>,
+    content_layer: Scene,
+    overlay_layer: Scene,
+    /// Whether only the part of each frame that changed is rendered, by
+    /// `dirty_pass`, which keeps the rest, as told by `dirty_change`.
+    dirty_rect: bool,
+    /// Whether the scene can be drawn again unchanged, and how often it was,
+    /// with `?reuse_scene`.
+    scene_reuse: Option<SceneReuse>,
+    dirty_pass: Option<DirtyPass>,
+    dirty_change: Option<Change>,
+    /// Whether Vello's `debug_layers` are drawn over each frame. Such frames
+    /// are rendered in a future with `debug_renderer`, which is away until
+    /// the frame has been presented, meanwhile skipping redraws.
+    show_debug_layers: bool,
+    debug_layers: Layers,
+    debug_renderer: Option<Renderer>,
+    debug_frame_pending: bool,
+    redraw_skipped: bool,
+    /// When the previous frame started, for measuring frame times.
+    last_frame: Option<Instant>,
+    /// Recent frame times, graphed in the debug overlay.
+    frame_history: FrameHistory,
+    /// When `run_app` started, for measuring the time to the first frame.
+    started: Instant,
+    /// Timing the frames after a warm-up, until finished.
+    benchmark: Option<Benchmark>,
+    /// Growing the random scene until frames are over budget, until finished.
+    stress: Option<Stress>,
+    animate: bool,
+    idle_timeout: Option<Duration>,
+    last_input: Instant,
+    idle: bool,
+    clock: PausableClock,
+    /// How far stepping a paused animation advances it, if `?step` or `?fps` say.
+    single_step: Option<Duration>,
+    /// The display's refresh rate, once measured.
+    refresh: Option<RefreshRate>,
+    /// Advances the animation in fixed steps, when set, with frames drawn at
+    /// interpolated times. Driven by `clock`, read at `timestep_clock` last.
+    timestep: Option<FixedTimestep>,
+    timestep_clock: Duration,
+    /// Caps the frame rate while animating, if set.
+    pacer: Option<FramePacer>,
+    /// A burst of frames without vsync in progress, and how many frames
+    /// one lasts.
+    burst: Option<Burst>,
+    burst_frames: u32,
+    /// Frames being captured into a ZIP, while some are still to be taken.
+    capture: Option<Capture>,
+    demo: Demo,
+    demo_state: DemoState,
+    text: Text,
+    debug_overlay: bool,
+    /// Draw Vello's tile grid over the output.
+    debug_tiles: bool,
+    /// Outline every filled shape of the content.
+    debug_outlines: bool,
+    /// Draw a grid over the content, crisp at any zoom.
+    show_grid: bool,
+    /// Draw alignment crosshairs and a ruler, in render target pixels.
+    crosshairs: bool,
+    /// While on, reads the pixel under the cursor and shows it beside it.
+    inspector: Option<Inspector>,
+    /// Draw an overview of all the content in a corner, where clicks
+    /// recentre the view.
+    show_minimap: bool,
+    grid: Grid,
+    /// Mirror the whole scene, overlays included, left to right and upside
+    /// down, into `flipped`, which is then rendered instead.
+    mirror: bool,
+    flip: bool,
+    flipped: Scene,
+    /// Present modes and formats supported by the surface, for cycling through.
+    present_modes: Vec<wgpu::PresentMode>,
+    surface_formats: Vec<wgpu::TextureFormat>,
+    /// Show the key binding legend.
+    show_legend: bool,
+    /// Show the control panel, whose rows drive the same toggles as the keys.
+    show_panel: bool,
+    panel: Panel<PanelAction>,
+    /// Scenes loaded from JSON, drawn instead of the demo while loaded.
+    flipbook: Option<Flipbook>,
+    /// Shapes drawn from JS with a `Drawing`, over the demo or flipbook.
+    drawing: Option<Scene>,
+    /// An input log being replayed. Live input is ignored meanwhile.
+    replay: Option<Replay>,
+    /// False while the window is occluded, e.g. a hidden tab.
+    visible: bool,
+    /// False while the page is hidden, per the Page Visibility API. The
+    /// browser throttles animation frames in a background tab, but no
+    /// frames are requested at all meanwhile.
+    page_visible: bool,
+    /// Set by the first `Resized` event. Until then the surface has the size
+    /// requested at startup, which the window may not have settled at.
+    size_settled: bool,
+    /// The latest size reported by `Resized`, applied at the start of the
+    /// next redraw, so that however many arrive between two frames the
+    /// surface is reconfigured once, and always before the frame is drawn.
+    resize_pending: Option<(u32, u32)>,
+    /// Clear input-driven strokes every frame. When false they accumulate in
+    /// `strokes` and `sketch`, which grow with every stroke until `clear` is called.
+    clear_each_frame: bool,
+    /// Colour behind the scene: transparent when the page can show through
+    /// the canvas, and otherwise the background of the theme's palette.
+    base_color: Color,
+    /// The system's light or dark theme, which picks the palette of the
+    /// background and strokes.
+    theme: Theme,
+    /// An image drawn over `base_color`, under the content, scaled to the surface with `background_fit`.
+    background: Option<Image>,
+    background_fit: BackgroundFit,
+    /// A known-good render drawn in the right half, beside the live content
+    /// in the left, which the viewport then covers.
+    reference_image: Option<Image>,
+    /// Points of the freehand stroke in progress, while the left button is held.
+    current_stroke: Vec<Point>,
+    /// Committed freehand strokes.
+    strokes: Vec<BezPath>,
+    /// The committed strokes, encoded once when each is committed.
+    sketch: Scene,
+    /// The bounds of the committed strokes, kept alongside them.
+    stroke_bounds: Bounds,
+    cursor: Option<Point>,
+    button_down: bool,
+    /// The modifier keys held, to tell scrolling from Ctrl+wheel zooming.
+    modifiers: ModifiersState,
+}
+
+impl VelloClient<'_> {
+    /// Draw the frame into the scene, from the content up through the
+    /// overlays, advancing the clock and the frame number as it goes.
+    fn build_scene(&mut self, width: u32, height: u32) {
+        self.content.reset();
+        let elapsed = if self.animate {
+            match &self.timestep {
+                Some(timestep) => timestep.interpolated(),
+                None => self.clock.elapsed(),
+            }
+        } else {
+            Duration::ZERO
+        };
+        let viewport = self.viewport();
+        // The bounds change as the surface is resized and strokes are
+        // drawn, so keep the scroll offset within them.
+        let scroll_bounds = self.scroll_bounds();
+        self.scroll = scroll::clamp(self.scroll, scroll_bounds, viewport.size());
+        match &mut self.flipbook {
+            Some(flipbook) => {
+                self.content.append(flipbook.current(), None);
+                // Advance after drawing, so that every scene is shown for a frame.
+                if flipbook.playing {
+                    flipbook.step(true);
+                }
+            }
+            None => self.demo.draw(
+                &mut self.content,
+                &mut self.demo_state,
+                viewport.width() as u32,
+                viewport.height() as u32,
+                elapsed,
+            ),
+        }
+        // The frame number is the one the present callback will be
+        // given, and the time is the animation clock's, so with
+        // `?step` frame N is always drawn at the same time.
+        call_draw_callback(
+            &mut self.content,
+            viewport.width() as u32,
+            viewport.height() as u32,
+            self.frame + 1,
+            elapsed.as_secs_f64(),
+        );
+        if let Some(drawing) = &self.drawing {
+            self.content.append(drawing, None);
+        }
+        self.content.append(&self.sketch, None);
+        // Where the stroke in progress is in the content, to tell
+        // its changes from the rest of the frame's.
+        let stroke_start = self.content.encoding().stream_offsets();
+        if !self.current_stroke.is_empty() {
+            let path = draw::smooth_path(&self.current_stroke);
+            draw::draw_freehand(&mut self.content, &path, self.demo_state.stroke_color);
+        }
+        let stroke_end = self.content.encoding().stream_offsets();
+        self.scene.reset();
+        if let Some(image) = &self.background {
+            draw::draw_background(&mut self.scene, image, self.background_fit, width, height);
+        }
+        if self.aspect_lock.is_some() {
+            let area = Rect::new(0.0, 0.0, width as f64, height as f64);
+            draw::draw_letterbox(&mut self.scene, area, viewport, self.letterbox_color);
+            self.scene
+                .push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &viewport);
+        }
+        let content_start = self.scene.encoding().stream_offsets();
+        self.scene
+            .append(&self.content, Some(self.content_transform()));
+        let stroke_part = dirty::offsets_after(stroke_start, content_start)
+            ..dirty::offsets_after(stroke_end, content_start);
+        if self.debug_outlines {
+            // On the surface, so that zooming doesn't thicken the lines.
+            let transform = self.content_transform();
+            outline::draw_outlines(&mut self.scene, &self.content, transform);
+        }
+        if self.aspect_lock.is_some() {
+            self.scene.pop_layer();
+        }
+        if self.layer_aa.is_some() {
+            // The overlays go into a scene of their own from here.
+            std::mem::swap(&mut self.scene, &mut self.content_layer);
+            self.scene.reset();
+        }
+        if self.show_grid {
+            // Drawn on the surface rather than in the content, so that
+            // zooming moves the lines without scaling their width.
+            self.scene
+                .push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &viewport);
+            let transform = self.content_transform();
+            self.grid.draw(&mut self.scene, viewport, transform);
+            self.scene.pop_layer();
+        }
+        scroll::draw_scrollbars(&mut self.scene, self.scroll, scroll_bounds, viewport);
+        if let Some(image) = &self.reference_image {
+            draw::draw_reference(&mut self.scene, image, width, height);
+        }
+        if self.clear_each_frame {
+            self.strokes.clear();
+            self.sketch.reset();
+            self.stroke_bounds.clear();
+        }
+        self.clock.tick();
+        self.frame += 1;
+        if self.debug_tiles {
+            overlay::draw_tile_grid(&mut self.scene, width, height);
+        }
+        // Stack the panels down the left-hand side.
+        let mut origin = Point::new(4.0, 4.0);
+        if self.debug_overlay {
+            origin.y = self.draw_debug_overlay(origin).y1 + 4.0;
+            let graph = overlay::draw_frame_graph(&mut self.scene, &self.frame_history, origin);
+            origin.y = graph.y1 + 4.0;
+            // The frame being drawn hasn't been timed yet, so show the last one.
+            if let Some(timing) = trace::last() {
+                let bar = overlay::draw_phase_bar(&mut self.scene, &self.text, &timing, origin);
+                origin.y = bar.y1 + 4.0;
+            }
+        }
+        if self.show_panel {
+            origin.y = self.draw_control_panel(origin).y1 + 4.0;
+        }
+        if let Some(lines) = self.edge_test.as_ref().and_then(EdgeTest::lines) {
+            let panel = overlay::draw_panel(&mut self.scene, &self.text, &lines, origin);
+            origin.y = panel.y1 + 4.0;
+        }
+        if self.show_legend {
+            self.draw_legend(origin);
+        }
+        if self.mirror || self.flip {
+            // About the centre of the surface, not of the drawable area.
+            let config = &self.surface().config;
+            let (w, h) = (config.width as f64, config.height as f64);
+            let flip = draw::flip(w, h, self.mirror, self.flip);
+            self.flipped.reset();
+            self.flipped.append(&self.scene, Some(flip));
+            std::mem::swap(&mut self.scene, &mut self.flipped);
+            if self.layer_aa.is_some() {
+                self.flipped.reset();
+                self.flipped.append(&self.content_layer, Some(flip));
+                std::mem::swap(&mut self.content_layer, &mut self.flipped);
+            }
+        }
+        if self.crosshairs {
+            // After mirroring, so they stay on the pixels they mark.
+            overlay::draw_crosshairs(&mut self.scene, width, height);
+        }
+        let inspected = self.inspector.as_ref().and_then(|i| i.sample);
+        if let (Some(sample), Some(cursor)) = (inspected, self.cursor) {
+            // After mirroring, as the cursor isn't mirrored.
+            let area = Rect::new(0.0, 0.0, width as f64, height as f64);
+            let lines = sample.to_string();
+            overlay::draw_tooltip(&mut self.scene, &self.text, &lines, cursor, area);
+        }
+        if let Some(minimap) = self.minimap() {
+            // After mirroring too, so that it's where clicks hit it.
+            minimap.draw(&mut self.scene, &self.content);
+        }
+        if self.latency.flash {
+            let config = &self.surface().config;
+            let surface = Rect::new(0.0, 0.0, config.width as f64, config.height as f64);
+            self.scene.fill(
+                Fill::NonZero,
+                Affine::IDENTITY,
+                Color::WHITE,
+                None,
+                &surface,
+            );
+        }
+        if self.layer_aa.is_some() {
+            // Everything but the layer pass takes the frame as one scene.
+            std::mem::swap(&mut self.scene, &mut self.overlay_layer);
+            self.scene.reset();
+            self.scene.append(&self.content_layer, None);
+            self.scene.append(&self.overlay_layer, None);
+        }
+        self.dirty_change = self.dirty_rect.then(|| Change {
+            fingerprint: Fingerprint::of(&self.scene, stroke_part),
+            stroke: self.stroke_surface_bounds(),
+        });
+    }
+
+    /// Note something that may change the scene, so that the next frame
+    /// builds it again.
+    fn scene_changed(&mut self) {
+        if let Some(reuse) = &mut self.scene_reuse {
+            reuse.invalidate();
+        }
+    }
+
+    /// Whether the scene is drawn differently each frame, even with nothing
+    /// happening between them, so that it can't be reused.
+    fn frame_varies(&self) -> bool {
+        let animating = self.animate && !self.clock.is_paused();
+        let playing = self.flipbook.as_ref().is_some_and(|f| f.playing);
+        // The panels show the frame number and times, and the callback may
+        // draw anything.
+        let panels = self.debug_overlay || self.show_panel;
+        let callback = DRAW_CALLBACK.with_borrow(Option::is_some);
+        animating || playing || panels || callback
+    }
+
+    /// Report the encoded resource usage of the current scene.
+    fn scene_size(&self) -> SceneSize {
+        SceneSize::of(&self.scene)
+    }
+
+    /// The AA config to render with.
+    fn active_aa(&self) -> AaConfig {
+        if self.msaa_enabled {
+            self.aa_config
+        } else {
+            AaConfig::Area
+        }
+    }
+
+    /// The AA config each frame is rendered with, or those of its layers.
+    fn aa_description(&self) -> String {
+        match self.layer_aa {
+            Some(configs) => {
+                let layers = layered::NAMES.iter().zip(configs);
+                let layers: Vec<_> = layers.map(|(n, aa)| format!("{} {:?}", n, aa)).collect();
+                layers.join(", ")
+            }
+            None => format!("{:?}", self.active_aa()),
+        }
+    }
+
+    /// The AA configs drawn side by side in split frames: the best the
+    /// renderer has and area AA, unless `?split_aa` says otherwise.
+    fn split_configs(&self) -> [AaConfig; 2] {
+        self.split_aa.unwrap_or_else(|| {
+            let best = self
+                .aa_configs
+                .iter()
+                .copied()
+                .max_by_key(|&aa| aa::sample_count(aa));
+            [best.unwrap_or(AaConfig::Area), AaConfig::Area]
+        })
+    }
+
+    /// Warn of the passes that are on but don't draw the frame, as one
+    /// before them in the redraw does instead.
+    fn warn_overridden_passes(&self) {
+        let on = [
+            ("?preserve_previous", self.preserve_previous),
+            ("onion skinning", self.onion_skin),
+            ("?split_aa", self.show_split),
+            ("?channel", self.channel.is_some()),
+            ("?layer_aa", self.layer_aa.is_some()),
+            ("?dirty_rect", self.dirty_rect),
+            ("?gamma", self.gamma != 1.0),
+        ];
+        let mut on = on.iter().filter(|(_, on)| *on).map(|(name, _)| name);
+        if let Some(first) = on.next() {
+            for name in on {
+                warn!("{} has no effect while {} draws the frame", name, first);
+            }
+        }
+    }
+
+    /// The AA config screenshots are rendered with.
+    fn export_aa(&self) -> AaConfig {
+        self.export_aa.unwrap_or_else(|| self.active_aa())
+    }
+
+    /// The size frames are rendered at, relative to the surface: lowered
+    /// while frames are slow, and multiplied by the supersampling factor.
+    fn internal_scale(&self) -> f64 {
+        self.render_scale.scale() * self.supersample as f64
+    }
+
+    fn toggle_overlay(&mut self) {
+        self.debug_overlay = !self.debug_overlay;
+        self.window.request_redraw();
+    }
+
+    fn toggle_tiles(&mut self) {
+        self.debug_tiles = !self.debug_tiles;
+        self.window.request_redraw();
+    }
+
+    /// Record a frame of the stress run, growing the random scene or
+    /// reporting the result.
+    fn record_stress(&mut self, now: Instant, frame_time: Duration) {
+        let Some(stress) = &mut self.stress else {
+            return;
+        };
+        match stress.record(now, frame_time) {
+            Progress::Measuring => {}
+            Progress::Grow(shapes) => self.demo_state.shapes = shapes,
+            Progress::Done(summary) => {
+                info!(
+                    "Stress: {}",
+                    serde_json::to_string(&summary).unwrap_or_default()
+                );
+                stats::update(|s| s.stress = Some(summary));
+                self.stress = None;
+            }
+        }
+    }
+
+    /// Take the fixed timesteps due since the clock was last read, if stepping.
+    fn advance_timestep(&mut self) {
+        if let Some(timestep) = &mut self.timestep {
+            let now = self.clock.elapsed();
+            timestep.advance(now.saturating_sub(self.timestep_clock));
+            self.timestep_clock = now;
+        }
+    }
+
+    /// Stop or restart the animation clock.
+    fn set_paused(&mut self, paused: bool) {
+        self.clock.set_paused(paused);
+        // The time paused isn't a frame time.
+        self.last_frame = None;
+        info!(
+            "Animation {} at frame {}, time {:?}",
+            if paused { "paused" } else { "resumed" },
+            self.frame,
+            self.clock.elapsed()
+        );
+        self.window.request_redraw();
+    }
+
+    /// Pause the animation if it isn't already, and draw one frame a step further on.
+    fn step_frame(&mut self) {
+        if !self.animate {
+            info!("Not animating, nothing to step; see ?animate");
+            return;
+        }
+        if !self.clock.is_paused() {
+            self.set_paused(true);
+        }
+        let step = self
+            .single_step
+            .or(self.refresh.as_ref().map(RefreshRate::frame))
+            .unwrap_or(SINGLE_STEP);
+        self.clock.step(step);
+        self.advance_timestep();
+        info!(
+            "Stepped to frame {}, time {:?}",
+            self.frame + 1,
+            self.clock.elapsed()
+        );
+        self.window.request_redraw();
+    }
+
+    /// Draw text in `font` from now on, or in the bundled font if `None`: the
+    /// overlays, the text scene, and drawings made after this.
+    fn load_font(&mut self, font: Option<Font>) {
+        match &font {
+            Some(font) => info!("Loaded a font of {} bytes", font.data.len()),
+            None => info!("Using the bundled font"),
+        }
+        text::set_font(font);
+        self.text = Text::new();
+        self.demo_state.text = Text::new();
+        self.window.request_redraw();
+    }
+
+    /// Show the next or previous scene of the flipbook, if one is loaded.
+    fn step_flipbook(&mut self, forward: bool) {
+        if let Some(flipbook) = &mut self.flipbook {
+            flipbook.step(forward);
+            info!(
+                "Flipbook scene {} of {}",
+                flipbook.index() + 1,
+                flipbook.len()
+            );
+            self.window.request_redraw();
+        }
+    }
+
+    fn next_scene(&mut self) {
+        self.demo = self.demo.next();
+        info!("Scene now {}", self.demo.name());
+        self.window.request_redraw();
+    }
+
+    /// Start or stop cycling through every demo with every AA config.
+    fn toggle_reel(&mut self) {
+        if let Some(reel) = self.reel.take() {
+            (self.aa_config, self.msaa_enabled) = reel.restore;
+            info!("Demo reel stopped, AA mode back to {:?}", self.active_aa());
+            self.window.request_redraw();
+            return;
+        }
+        let restore = (self.aa_config, self.msaa_enabled);
+        self.reel = Some(Reel::new(self.demo, self.reel_dwell, restore));
+        self.reels_started += 1;
+        info!(
+            "Demo reel started, showing every demo with every AA config for {:?} each",
+            self.reel_dwell
+        );
+        self.reel_step(self.reels_started);
+    }
+
+    /// Show the next demo and AA config of the reel numbered `reel`, if it
+    /// is still running, and take the next step after its dwell.
+    fn reel_step(&mut self, reel: u32) {
+        let Some(current) = self.reel.as_mut().filter(|_| reel == self.reels_started) else {
+            return;
+        };
+        let step = current.next(&self.aa_configs);
+        let dwell = current.dwell;
+        if step.index == 0 && step.pass > 0 {
+            info!("Demo reel: pass {} done, starting over", step.pass);
+        }
+        info!(
+            "Demo reel {} of {}: {} with {:?}",
+            step.index + 1,
+            step.total,
+            step.demo.name(),
+            step.aa
+        );
+        self.demo = step.demo;
+        self.aa_config = step.aa;
+        self.msaa_enabled = true;
+        self.scene_changed();
+        self.window.request_redraw();
+        let proxy = self.proxy.clone();
+        let scheduled = dom::set_timeout(dwell, move || {
+            _ = proxy.send_event(UserEvent::ReelStep(reel));
+        });
+        if let Err(e) = scheduled {
+            error!("Failed to schedule the demo reel, stopping it: {:?}", e);
+            self.toggle_reel();
+        }
+    }
+
+    /// Toggle MSAA off and on, to isolate AA-related rendering problems.
+    fn toggle_msaa(&mut self) {
+        if self.aa_config == AaConfig::Area {
+            info!("MSAA pipelines were not built, restart without aa=off to enable");
+            return;
+        }
+        self.msaa_enabled = !self.msaa_enabled;
+        info!("AA mode now {:?}", self.active_aa());
+        self.window.request_redraw();
+    }
+
+    fn surface(&self) -> &RenderSurface<'_> {
+        self.surface.as_ref().expect("surface used after shutdown")
+    }
+
+    /// The size of the area drawn, inside the surface, or the whole surface when full bleed.
+    fn render_size(&self) -> (u32, u32) {
+        let config = &self.surface().config;
+        if self.full_bleed {
+            return (config.width, config.height);
+        }
+        (
+            config.width.saturating_sub(20),
+            config.height.saturating_sub(20),
+        )
+    }
+
+    /// The area the content is drawn into: the whole drawable area, or the
+    /// centred part of it with the locked aspect ratio.
+    fn viewport(&self) -> Rect {
+        let (width, height) = self.render_size();
+        let area = match self.reference_image {
+            // The reference image has the right half.
+            Some(_) => draw::split_halves(width, height).0,
+            None => Rect::new(0.0, 0.0, width as f64, height as f64),
+        };
+        match self.aspect_lock {
+            Some(aspect) => draw::letterbox(area, aspect),
+            None => area,
+        }
+    }
+
+    /// Switch to the palette of `theme`. The background only changes if
+    /// opaque, and strokes already committed keep their colour.
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        let palette = theme_palette(theme);
+        if !alpha::is_transparent(self.surface().config.alpha_mode) {
+            self.base_color = palette.background;
+        }
+        self.demo_state.stroke_color = palette.stroke;
+        info!("Theme now {:?}", theme);
+        self.window.request_redraw();
+    }
+
+    /// The active flip, for logging.
+    fn flip_state(&self) -> &'static str {
+        match (self.mirror, self.flip) {
+            (false, false) => "off",
+            (true, false) => "mirrored",
+            (false, true) => "flipped",
+            (true, true) => "mirrored and flipped",
+        }
+    }
+
+    /// The transform from content coordinates to the surface.
+    fn content_transform(&self) -> Affine {
+        Affine::translate(self.viewport().origin().to_vec2() - self.scroll) * self.view_transform
+    }
+
+    /// The bounds of all the content, in content coordinates: the demo,
+    /// taken to be the area inside the margin as for `zoom_to`, and every
+    /// freehand stroke, including one in progress.
+    fn content_bounds(&self) -> Rect {
+        let viewport = self.viewport();
+        let mut bounds = self.stroke_bounds.clone();
+        bounds.add(draw::border_rect(
+            viewport.width() as u32,
+            viewport.height() as u32,
+            self.demo_state.margin,
+        ));
+        if !self.current_stroke.is_empty() {
+            let path = draw::smooth_path(&self.current_stroke);
+            bounds.stroke(&draw::freehand_stroke(), Affine::IDENTITY, &path);
+        }
+        bounds.union().expect("the demo area is always included")
+    }
+
+    /// The bounds on the surface of the freehand stroke in progress, if there is one.
+    fn stroke_surface_bounds(&self) -> Option<Rect> {
+        if self.current_stroke.is_empty() {
+            return None;
+        }
+        let config = &self.surface().config;
+        let (w, h) = (config.width as f64, config.height as f64);
+        let transform = draw::flip(w, h, self.mirror, self.flip) * self.content_transform();
+        let mut bounds = Bounds::default();
+        let path = draw::smooth_path(&self.current_stroke);
+        bounds.stroke(&draw::freehand_stroke(), transform, &path);
+        bounds.union()
+    }
+
+    /// The content bounds in the viewport, under the view transform but not scrolled.
+    fn scroll_bounds(&self) -> Rect {
+        self.view_transform
+            .transform_rect_bbox(self.content_bounds())
+    }
+
+    /// Scroll the viewport across the content by `delta` pixels, within the
+    /// content bounds.
+    fn scroll_by(&mut self, delta: Vec2) {
+        let size = self.viewport().size();
+        let scroll = scroll::clamp(self.scroll + delta, self.scroll_bounds(), size);
+        if scroll != self.scroll {
+            self.scroll = scroll;
+            self.window.request_redraw();
+        }
+    }
+
+    /// Set the view transform to draw the content centred at `scale`, which
+    /// may differ along each axis, or at the uniform scale that makes it
+    /// fill the viewport if `None`. The content is taken to be the area
+    /// inside the margin, which holds the demos.
+    fn zoom_to(&mut self, scale: Option<Vec2>) {
+        let viewport = self.viewport();
+        let (width, height) = (viewport.width(), viewport.height());
+        let content = draw::border_rect(width as u32, height as u32, self.demo_state.margin);
+        let scale = scale.unwrap_or_else(|| {
+            let fit = draw::fit_scale(width, height, content);
+            Vec2::new(fit, fit)
+        });
+        self.view_transform = draw::zoom(width, height, content, scale);
+        self.scroll = Vec2::ZERO;
+        if scale.x == scale.y {
+            info!("Zoom now {:.0}%", scale.x * 100.0);
+        } else {
+            info!("Zoom now {:.0}% x {:.0}%", scale.x * 100.0, scale.y * 100.0);
+        }
+        self.window.request_redraw();
+    }
+
+    /// Set the view transform to fit all the content in the viewport, with
+    /// `FIT_PADDING` around it. Strokes drawn after panning can be far
+    /// outside the demo, so this shows everything drawn so far.
+    fn fit_to_view(&mut self) {
+        let viewport = self.viewport();
+        let content = self.content_bounds();
+        self.view_transform =
+            bounds::fit(viewport.width(), viewport.height(), content, FIT_PADDING);
+        self.scroll = Vec2::ZERO;
+        info!("Fit {:?} to the view", content);
+        self.window.request_redraw();
+    }
+
+    /// The minimap, if shown.
+    fn minimap(&self) -> Option<Minimap> {
+        self.show_minimap.then(|| {
+            Minimap::new(
+                self.viewport(),
+                self.content_bounds(),
+                self.content_transform(),
+            )
+        })
+    }
+
+    /// Move the view to show the content point `point` at the centre of the
+    /// viewport, at the same scale. Like zooming, this resets the scroll
+    /// offset, moving the view transform instead.
+    fn center_on(&mut self, point: Point) {
+        let offset = self.viewport().center() - self.content_transform() * point;
+        self.view_transform = Affine::translate(offset - self.scroll) * self.view_transform;
+        self.scroll = Vec2::ZERO;
+        self.window.request_redraw();
+    }
+
+    /// Reconfigure the surface with its current configuration at a new size,
+    /// and request a redraw, so that the surface is never left blank until
+    /// the next event. Every reconfiguration goes through here.
+    fn reconfigure(&mut self, width: u32, height: u32) {
+        if let Some(surface) = &mut self.surface {
+            self.context.resize_surface(surface, width, height);
+        }
+        self.window.request_redraw();
+    }
+
+    /// Render the current scene to a texture with `aa`, and copy it for reading back.
+    fn render_readback(&mut self, aa: AaConfig) -> Result<Readback, vello::Error> {
+        let (width, height) = self.render_size();
+        let dev_id = self.surface().dev_id;
+        let device_handle = &self.context.devices[dev_id];
+        readback::render_to_readback(
+            &device_handle.device,
+            &device_handle.queue,
+            self.renderers[dev_id].as_mut().unwrap(),
+            &self.scene,
+            &vello::RenderParams {
+                base_color: self.base_color,
+                width,
+                height,
+                antialiasing_method: aa,
+            },
+        )
+    }
+
+    /// Render the current scene with each AA config the renderer was built
+    /// with, read the results back, and log a summary of each for comparison.
+    fn compare_aa_configs(&mut self) {
+        let mut reads = Vec::new();
+        for aa in self.aa_configs.clone() {
+            match self.render_readback(aa) {
+                Ok(readback) => {
+                    let device = &self.context.devices[self.surface().dev_id].device;
+                    reads.push((aa, readback.read(device)));
+                }
+                Err(e) => {
+                    error!("AA comparison: failed to render with {:?}: {}", aa, e);
+                    return;
+                }
+            }
+        }
+        let (width, height) = self.render_size();
+        info!(
+            "Comparing AA configs {:?} at {} x {}",
+            self.aa_configs, width, height
+        );
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut results = Vec::new();
+            for (aa, read) in reads {
+                match read.await {
+                    Ok(pixels) => {
+                        let summary = ImageSummary::of(&pixels);
+                        info!("AA {:?}: {}", aa, summary);
+                        results.push((format!("{:?}", aa), summary));
+                    }
+                    Err(e) => error!("AA comparison: failed to read back {:?}: {}", aa, e),
+                }
+            }
+            // A single line, for collecting the results programmatically.
+            info!(
+                "AA comparison: {}",
+                serde_json::to_string(&results).unwrap_or_default()
+            );
+        });
+    }
+
+    /// Render a known edge and a hairline with each AA config the renderer
+    /// was built with, read the results back, and log the pixel values
+    /// across each.
+    fn profile_edges(&mut self) {
+        let scenes = [edge::scene(), edge::hairline_scene()];
+        let dev_id = self.surface().dev_id;
+        let device_handle = &self.context.devices[dev_id];
+        let renderer = self.renderers[dev_id].as_mut().unwrap();
+        let mut reads = Vec::new();
+        for &aa in &self.aa_configs {
+            let params = vello::RenderParams {
+                base_color: Color::WHITE,
+                width: edge::SIZE,
+                height: edge::SIZE,
+                antialiasing_method: aa,
+            };
+            for (i, scene) in scenes.iter().enumerate() {
+                match readback::render_to_readback(
+                    &device_handle.device,
+                    &device_handle.queue,
+                    renderer,
+                    scene,
+                    &params,
+                ) {
+                    Ok(readback) => reads.push((aa, i == 1, readback.read(&device_handle.device))),
+                    Err(e) => {
+                        error!("Edge profile: failed to render with {:?}: {}", aa, e);
+                        return;
+                    }
+                }
+            }
+        }
+        wasm_bindgen_futures::spawn_local(async move {
+            let (mut edges, mut hairlines) = (Vec::new(), Vec::new());
+            for (aa, hairline, read) in reads {
+                let pixels = match read.await {
+                    Ok(pixels) => pixels,
+                    Err(e) => {
+                        error!("Edge profile: failed to read back {:?}: {}", aa, e);
+                        continue;
+                    }
+                };
+                if hairline {
+                    let profile = HairlineProfile::of(&pixels);
+                    if profile.is_abnormal() {
+                        warn!("Hairline profile {:?}: {}", aa, profile);
+                    } else {
+                        info!("Hairline profile {:?}: {}", aa, profile);
+                    }
+                    hairlines.push((format!("{:?}", aa), profile));
+                } else {
+                    let profile = EdgeProfile::of(&pixels);
+                    if profile.is_abnormal() {
+                        warn!("Edge profile {:?}: {}", aa, profile);
+                    } else {
+                        info!("Edge profile {:?}: {}", aa, profile);
+                    }
+                    edges.push((format!("{:?}", aa), profile));
+                }
+            }
+            // Single lines, for collecting the results programmatically.
+            info!(
+                "Edge profiles: {}",
+                serde_json::to_string(&edges).unwrap_or_default()
+            );
+            info!(
+                "Hairline profiles: {}",
+                serde_json::to_string(&hairlines).unwrap_or_default()
+            );
+        });
+    }
+
+    /// Render the current scene and download it as a PNG. The image is only
+    /// read back once the GPU has finished rendering it; see [`Readback::read`].
+    fn screenshot(&mut self) {
+        let aa = self.export_aa();
+        let (width, height) = self.render_size();
+        let filename = format!("frame-{}.png", self.frame);
+        let Some(png) = self.screenshot_png() else {
+            return;
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            let Some(png) = png.await else {
+                return;
+            };
+            if let Err(e) = dom::download(&filename, "image/png", &png) {
+                error!("Screenshot download failed: {:?}", e);
+            } else {
+                info!(
+                    "Saved screenshot {} ({} x {}, {:?})",
+                    filename, width, height, aa
+                );
+            }
+        });
+    }
+
+    /// Copy a screenshot of the current frame to the clipboard, as a PNG.
+    fn copy_screenshot(&mut self) {
+        let aa = self.export_aa();
+        let (width, height) = self.render_size();
+        let Some(png) = self.screenshot_png() else {
+            return;
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            let Some(png) = png.await else {
+                return;
+            };
+            match dom::copy_png(&png).await {
+                Ok(()) => info!(
+                    "Copied screenshot to the clipboard ({} x {}, {:?})",
+                    width, height, aa
+                ),
+                // Most likely the page lacks focus or the user refused.
+                Err(e) => error!("Couldn't copy the screenshot to the clipboard: {:?}", e),
+            }
+        });
+    }
+
+    /// The state to share, as `save_config()` and `K` give it.
+    fn client_config(&self) -> ClientConfig {
+        let aa = match self.msaa_enabled {
+            true => aa::name(self.aa_config),
+            false => "off",
+        };
+        let rgba = |color: Color| {
+            let rgba = color.to_rgba8();
+            [rgba.r, rgba.g, rgba.b, rgba.a]
+        };
+        ClientConfig {
+            aa: aa.to_string(),
+            demo: self.demo.name().to_string(),
+            transform: self.view_transform.as_coeffs(),
+            scroll: [self.scroll.x, self.scroll.y],
+            base_color: rgba(self.base_color),
+            stroke_color: rgba(self.demo_state.stroke_color),
+            present_mode: self.surface().config.present_mode,
+            shapes: self.demo_state.shapes,
+            seed: self.demo_state.seed,
+            margin: self.demo_state.margin,
+        }
+    }
+
+    /// Copy the client's state to the clipboard.
+    fn copy_config(&self) {
+        let text = self.client_config().encode();
+        wasm_bindgen_futures::spawn_local(async move {
+            match dom::copy_text(&text).await {
+                Ok(()) => info!("Copied the client config to the clipboard: {}", text),
+                Err(e) => error!("Couldn't copy the client config to the clipboard: {:?}", e),
+            }
+        });
+    }
+
+    /// Put back the state `config` holds. Whatever this client can't do,
+    /// such as an AA mode or present mode it doesn't support, is left as it
+    /// is, with a warning.
+    fn restore_config(&mut self, config: ClientConfig) {
+        match config.demo.parse::<Demo>() {
+            Ok(demo) => self.demo = demo,
+            Err(e) => warn!("Not restoring the demo: {}", e),
+        }
+        match aa::parse(&config.aa) {
+            Ok(None | Some(AaConfig::Area)) => self.msaa_enabled = false,
+            Ok(Some(aa)) if self.aa_configs.contains(&aa) => {
+                self.aa_config = aa;
+                self.msaa_enabled = true;
+            }
+            Ok(Some(aa)) => warn!("Not restoring the AA mode: {:?} is not supported", aa),
+            Err(e) => warn!("Not restoring the AA mode: {}", e),
+        }
+        self.view_transform = Affine::new(config.transform);
+        self.scroll = Vec2::new(config.scroll[0], config.scroll[1]);
+        let [r, g, b, a] = config.base_color;
+        self.base_color = Color::from_rgba8(r, g, b, a);
+        let [r, g, b, a] = config.stroke_color;
+        self.demo_state.stroke_color = Color::from_rgba8(r, g, b, a);
+        self.demo_state.shapes = config.shapes;
+        self.demo_state.seed = config.seed;
+        self.demo_state.margin = config.margin;
+        let present_mode = config.present_mode;
+        if !self.present_modes.contains(&present_mode) {
+            warn!(
+                "Not restoring the present mode: {:?} is not supported",
+                present_mode
+            );
+        } else if let Some(surface) = &mut self.surface {
+            if surface.config.present_mode != present_mode {
+                surface.config.present_mode = present_mode;
+                let (width, height) = (surface.config.width, surface.config.height);
+                self.reconfigure(width, height);
+            }
+        }
+        info!(
+            "Client config restored: {} with {:?}",
+            self.demo.name(),
+            self.active_aa()
+        );
+        self.window.request_redraw();
+    }
+
+    /// Render the current frame as for a screenshot and read it back,
+    /// giving a future of it encoded as a PNG, or of `None` having logged
+    /// why not.
+    fn screenshot_png(&mut self) -> Option<impl Future<Output = Option<Vec<u8>>>> {
+        let readback = match self.render_readback(self.export_aa()) {
+            Ok(readback) => readback,
+            Err(e) => {
+                error!("Screenshot failed: {}", e);
+                return None;
+            }
+        };
+        let read = readback.read(&self.context.devices[self.surface().dev_id].device);
+        let (width, height) = self.render_size();
+        Some(async move {
+            let pixels = read
+                .await
+                .map_err(|e| error!("Screenshot read back failed: {}", e))
+                .ok()?;
+            readback::encode_png(width, height, &pixels)
+                .map_err(|e| error!("Screenshot encoding failed: {}", e))
+                .ok()
+        })
+    }
+
+    /// Render the current scene scaled to fit an image of the given size,
+    /// keeping its aspect ratio, and download it as a PNG. Images larger
+    /// than a texture can be are rendered in tiles and stitched together.
+    fn export_image(&mut self, width: u32, height: u32) {
+        let aa = self.export_aa();
+        let (surface_width, surface_height) = self.render_size();
+        let scale =
+            (width as f64 / surface_width as f64).min(height as f64 / surface_height as f64);
+        let offset = (
+            (width as f64 - surface_width as f64 * scale) / 2.0,
+            (height as f64 - surface_height as f64 * scale) / 2.0,
+        );
+        let mut scene = Scene::new();
+        scene.append(
+            &self.scene,
+            Some(Affine::translate(offset) * Affine::scale(scale)),
+        );
+        let dev_id = self.surface().dev_id;
+        let device_handle = &self.context.devices[dev_id];
+        let device = &device_handle.device;
+        let max_tile = device.limits().max_texture_dimension_2d.min(EXPORT_TILE);
+        let rendered = readback::render_tiled(
+            device,
+            &device_handle.queue,
+            self.renderers[dev_id].as_mut().unwrap(),
+            &scene,
+            &vello::RenderParams {
+                base_color: self.base_color,
+                width,
+                height,
+                antialiasing_method: aa,
+            },
+            max_tile,
+        );
+        let read = match rendered {
+            Ok(readback) => readback.read(device),
+            Err(e) => {
+                error!("Export failed: {}", e);
+                return;
+            }
+        };
+        let tiles = readback::tiles(width, height, max_tile).len();
+        let filename = format!("export-{}x{}.png", width, height);
+        wasm_bindgen_futures::spawn_local(async move {
+            let png = match read.await {
+                Ok(pixels) => readback::encode_png(width, height, &pixels),
+                Err(e) => {
+                    error!("Export read back failed: {}", e);
+                    return;
+                }
+            };
+            match png {
+                Ok(png) => {
+                    if let Err(e) = dom::download(&filename, "image/png", &png) {
+                        error!("Export download failed: {:?}", e);
+                    } else {
+                        info!(
+                            "Exported {} ({} x {} in {} tiles, {:?})",
+                            filename, width, height, tiles, aa
+                        );
+                    }
+                }
+                Err(e) => error!("Export encoding failed: {}", e),
+            }
+        });
+    }
+
+    /// Start capturing the next `count` frames into a ZIP of PNGs.
+    fn export_frames(&mut self, count: u32) {
+        if let Some(capture) = &self.capture {
+            warn!(
+                "Already capturing frames from frame {}, ignoring the new capture",
+                capture.first
+            );
+            return;
+        }
+        info!("Capturing {} frames, from frame {}", count, self.frame + 1);
+        self.capture = Some(Capture::new(self.frame + 1, count));
+        self.window.request_redraw();
+    }
+
+    /// Take the frame just presented for the capture in progress, if there
+    /// is one, rendering it again as for a screenshot. Each PNG is written
+    /// into the archive when its read back completes, and the archive is
+    /// downloaded once the last is written.
+    fn capture_frame(&mut self) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        let pending = capture.take();
+        let filename = capture.filename();
+        if capture.is_over() {
+            self.capture = None;
+        } else {
+            self.window.request_redraw();
+        }
+        let (width, height) = self.render_size();
+        let read = self
+            .render_readback(self.export_aa())
+            .map(|readback| readback.read(&self.context.devices[self.surface().dev_id].device));
+        wasm_bindgen_futures::spawn_local(async move {
+            let png = match read {
+                Ok(read) => read.await.map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+            .and_then(|pixels| {
+                readback::encode_png(width, height, &pixels).map_err(|e| e.to_string())
+            });
+            match pending.finish(png) {
+                Ok(Some(zip)) => match dom::download(&filename, "application/zip", &zip) {
+                    Ok(()) => info!("Exported {} ({} bytes)", filename, zip.len()),
+                    Err(e) => error!("Frame capture download failed: {:?}", e),
+                },
+                Ok(None) => {}
+                Err(e) => error!("Frame capture failed, abandoning it: {}", e),
+            }
+        });
+    }
+
+    /// Render the current scene and read it back as the reference frame for `frame_diff`.
+    fn capture_reference(&mut self) {
+        let readback = match self.render_readback(self.active_aa()) {
+            Ok(readback) => readback,
+            Err(e) => {
+                error!("Reference frame capture failed: {}", e);
+                return;
+            }
+        };
+        let read = readback.read(&self.context.devices[self.surface().dev_id].device);
+        let (width, height) = self.render_size();
+        let frame = self.frame;
+        let reference = self.reference.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match read.await {
+                Ok(pixels) => {
+                    info!("Captured frame {} as the reference frame", frame);
+                    *reference.borrow_mut() = Some(ReferenceFrame {
+                        width,
+                        height,
+                        pixels,
+                    });
+                }
+                Err(e) => error!("Reference frame read back failed: {}", e),
+            }
+        });
+    }
+
+    /// Render the current frame again into a texture, read it back and log its
+    /// largest difference from the reference frame, if one has been captured.
+    fn diff_frame(&mut self) {
+        if self.reference.borrow().is_none() {
+            return;
+        }
+        let readback = match self.render_readback(self.active_aa()) {
+            Ok(readback) => readback,
+            Err(e) => {
+                error!("Frame diff: failed to render frame {}: {}", self.frame, e);
+                return;
+            }
+        };
+        let read = readback.read(&self.context.devices[self.surface().dev_id].device);
+        let (width, height) = self.render_size();
+        let frame = self.frame;
+        let reference = self.reference.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let pixels = match read.await {
+                Ok(pixels) => pixels,
+                Err(e) => {
+                    error!("Frame diff: failed to read back frame {}: {}", frame, e);
+                    return;
+                }
+            };
+            let reference = reference.borrow();
+            let Some(reference) = reference.as_ref() else {
+                return;
+            };
+            if (reference.width, reference.height) != (width, height) {
+                debug!(
+                    "Frame diff: frame {} is {} x {}, the reference is {} x {}",
+                    frame, width, height, reference.width, reference.height
+                );
+                return;
+            }
+            match readback::max_difference(&reference.pixels, &pixels) {
+                0 => debug!("Frame diff: frame {} matches the reference", frame),
+                diff => info!("Frame diff: frame {} max difference {}", frame, diff),
+            }
+        });
+    }
+
+    /// Reconfigure the surface with the next supported present mode.
+    fn cycle_present_mode(&mut self) {
+        let current = self.surface().config.present_mode;
+        let next = match self.present_modes.iter().position(|&m| m == current) {
+            Some(i) => self.present_modes[(i + 1) % self.present_modes.len()],
+            None => match self.present_modes.first() {
+                Some(&first) => first,
+                None => return,
+            },
+        };
+        if next == current {
+            info!("Present mode {:?} is the only one supported", current);
+            return;
+        }
+        let Some(surface) = &mut self.surface else {
+            return;
+        };
+        surface.config.present_mode = next;
+        let (width, height) = (surface.config.width, surface.config.height);
+        self.reconfigure(width, height);
+        info!("Present mode now {:?}", next);
+    }
+
+    /// Reconfigure the surface with the next supported format, and rebuild
+    /// the renderer to blit into it. The passes drawing into the surface are
+    /// rebuilt for the new format when next used. If the renderer can't be
+    /// built, the current format is kept.
+    fn cycle_surface_format(&mut self) {
+        let surface = self.surface();
+        let (dev_id, current) = (surface.dev_id, surface.format);
+        let next = match self.surface_formats.iter().position(|&f| f == current) {
+            Some(i) => self.surface_formats[(i + 1) % self.surface_formats.len()],
+            None => match self.surface_formats.first() {
+                Some(&first) => first,
+                None => return,
+            },
+        };
+        if next == current {
+            info!("Surface format {:?} is the only one supported", current);
+            return;
+        }
+        let handle = &self.context.devices[dev_id];
+        let (renderer, aa_configs) = match self.renderer_for(handle, next) {
+            Ok(built) => built,
+            Err(e) => {
+                error!("Failed to build a renderer for {:?}: {}", next, e);
+                return;
+            }
+        };
+        diagnostics::set_capabilities(CapabilityReport::new(
+            &handle.adapter,
+            &handle.device,
+            &self.surface().surface,
+            next,
+        ));
+        self.use_renderer(dev_id, renderer, aa_configs);
+        let Some(surface) = &mut self.surface else {
+            return;
+        };
+        surface.format = next;
+        surface.config.format = next;
+        let (width, height) = (surface.config.width, surface.config.height);
+        self.reconfigure(width, height);
+        info!("Surface format now {:?}", next);
+        if next.is_srgb() {
+            // Vello writes colours already encoded, which an sRGB target encodes again.
+            warn!(
+                "{:?} is an sRGB format, so colours are encoded twice and look lighter",
+                next
+            );
+        }
+    }
+
+    /// Switch to `AutoNoVsync` for a burst of frames drawn as fast as they
+    /// can be, after which the current present mode is restored.
+    fn start_burst(&mut self) {
+        if self.burst.is_some() {
+            info!("A burst without vsync is already running");
+            return;
+        }
+        let Some(surface) = &mut self.surface else {
+            return;
+        };
+        let restore = surface.config.present_mode;
+        surface.config.present_mode = wgpu::PresentMode::AutoNoVsync;
+        let (width, height) = (surface.config.width, surface.config.height);
+        self.reconfigure(width, height);
+        info!(
+            "Presenting {} frames without vsync, then back to {:?}",
+            self.burst_frames, restore
+        );
+        self.burst = Some(Burst::new(self.burst_frames, restore));
+    }
+
+    /// Count a frame presented during a burst, ending the burst after its last.
+    fn burst_presented(&mut self, presented: Instant) {
+        let Some(burst) = &mut self.burst else {
+            return;
+        };
+        if let Some(fps) = burst.presented(presented) {
+            info!("Burst frame {}: {:.1} fps", self.frame, fps);
+        }
+        if !burst.is_over() {
+            return;
+        }
+        let (frames, mean) = burst.summary();
+        let restore = burst.restore;
+        self.burst = None;
+        let Some(surface) = &mut self.surface else {
+            return;
+        };
+        surface.config.present_mode = restore;
+        let (width, height) = (surface.config.width, surface.config.height);
+        self.reconfigure(width, height);
+        match mean {
+            Some(fps) => info!(
+                "Burst of {} frames at {:.1} fps, present mode back to {:?}",
+                frames, fps, restore
+            ),
+            None => info!(
+                "Burst of {} frames, present mode back to {:?}",
+                frames, restore
+            ),
+        }
+    }
+
+    /// Reconfigure the surface with a new maximum frame latency.
+    fn set_frame_latency(&mut self, latency: u32) {
+        let Some(surface) = &mut self.surface else {
+            return;
+        };
+        surface.config.desired_maximum_frame_latency = latency;
+        let (width, height) = (surface.config.width, surface.config.height);
+        self.reconfigure(width, height);
+        info!("Maximum frame latency now {}", latency);
+    }
+
+    /// Draw the frame number, surface size and AA mode in the top-left corner,
+    /// so that captures are self-documenting.
+    fn draw_debug_overlay(&mut self, origin: Point) -> Rect {
+        let info = format!(
+            "frame {}\n{} x {}\naa {}\nscale {}",
+            self.frame,
+            self.surface().config.width,
+            self.surface().config.height,
+            self.aa_description(),
+            self.internal_scale()
+        );
+        overlay::draw_panel(&mut self.scene, &self.text, &info, origin)
+    }
+
+    /// A summary of the client's state and the last frame, for pasting into a bug report.
+    fn state_summary(&self) -> String {
+        let surface = self.surface();
+        let [a, b, c, d, e, f] = self.view_transform.as_coeffs();
+        let base = self.base_color.to_rgba8();
+        let size = self.scene_size();
+        let timing = match trace::last() {
+            Some(t) => format!(
+                "frame {}: render {:.2} ms, present {:.2} ms, poll {:.2} ms",
+                t.frame, t.render_ms, t.present_ms, t.poll_ms
+            ),
+            None => "none presented".to_string(),
+        };
+        format!(
+            "frame {}, scene {}, {}\n\
+             surface {} x {}, {:?}, {:?}, render scale {}\n\
+             view transform [{} {} {} {} {} {}], scroll ({}, {})\n\
+             aa {}\n\
+             base color #{:02x}{:02x}{:02x}{:02x}\n\
+             {} paths, {} segments, {} clips, {} draw objects, {} bytes encoded\n\
+             last frame {}",
+            self.frame,
+            self.demo.name(),
+            if self.animate { "animating" } else { "static" },
+            surface.config.width,
+            surface.config.height,
+            surface.format,
+            surface.config.present_mode,
+            self.internal_scale(),
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            self.scroll.x,
+            self.scroll.y,
+            self.aa_description(),
+            base.r,
+            base.g,
+            base.b,
+            base.a,
+            size.paths,
+            size.path_segments,
+            size.clips,
+            size.draw_objects,
+            size.encoded_bytes,
+            timing
+        )
+    }
+
+    /// Log the state summary, and show it on the page.
+    fn dump_state(&self) {
+        let summary = self.state_summary();
+        info!("State:\n{}", summary);
+        _ = dom::display_banner(dom::Severity::Info, &format!("<pre>{}</pre>", summary));
+    }
+
+    /// The key bindings, with the current state of each toggle.
+    fn bindings(&self) -> Vec<Binding> {
+        vec![
+            Binding::new("?", "show this legend"),
+            Binding::new("a", "MSAA").with_state(format!("{:?}", self.active_aa())),
+            Binding::new("v", "present mode")
+                .with_state(format!("{:?}", self.surface().config.present_mode)),
+            Binding::new("V", "burst without vsync").with_state(keys::on_off(self.burst.is_some())),
+            Binding::new("F", "surface format").with_state(format!("{:?}", self.surface().format)),
+            Binding::new("l", "frame latency").with_state(
+                self.surface()
+                    .config
+                    .desired_maximum_frame_latency
+                    .to_string(),
+            ),
+            Binding::new("s", "next scene").with_state(self.demo.name()),
+            Binding::new("Left/Right", "step flipbook"),
+            Binding::new("Space", "pause animation")
+                .with_state(keys::on_off(self.clock.is_paused())),
+            Binding::new("n", "step one frame"),
+            Binding::new("b", "play flipbook").with_state(keys::on_off(
+                self.flipbook.as_ref().is_some_and(|f| f.playing),
+            )),
+            Binding::new("j", "line joins")
+                .with_state(format!("{:?}", self.demo_state.line_style.join)),
+            Binding::new("k", "line caps")
+                .with_state(format!("{:?}", self.demo_state.line_style.caps)),
+            Binding::new("1-4", "zoom 25%, 50%, 100%, 200%"),
+            Binding::new("0", "zoom to fit"),
+            Binding::new("f", "fit all content to view"),
+            Binding::new("x", "pattern extend").with_state(format!("{:?}", self.demo_state.extend)),
+            Binding::new("h", "text hinting").with_state(self.demo_state.hinting.name()),
+            Binding::new("o", "debug overlay").with_state(keys::on_off(self.debug_overlay)),
+            Binding::new("d", "control panel").with_state(keys::on_off(self.show_panel)),
+            Binding::new("D", "dump state summary"),
+            Binding::new("t", "tile grid").with_state(keys::on_off(self.debug_tiles)),
+            Binding::new("w", "shape outlines").with_state(keys::on_off(self.debug_outlines)),
+            Binding::new("W", "Vello debug layers")
+                .with_state(keys::on_off(self.show_debug_layers)),
+            Binding::new("g", "content grid").with_state(keys::on_off(self.show_grid)),
+            Binding::new("G", "crosshairs").with_state(keys::on_off(self.crosshairs)),
+            Binding::new("I", "inspector").with_state(keys::on_off(self.inspector.is_some())),
+            Binding::new("M", "minimap").with_state(keys::on_off(self.show_minimap)),
+            Binding::new("m", "mirror scene").with_state(keys::on_off(self.mirror)),
+            Binding::new("y", "flip scene").with_state(keys::on_off(self.flip)),
+            Binding::new("z", "demo reel").with_state(keys::on_off(self.reel.is_some())),
+            Binding::new("c", "compare AA configs"),
+            Binding::new("C", "copy screenshot"),
+            Binding::new("K", "copy client config"),
+            Binding::new("e", "profile AA edge"),
+            Binding::new("p", "save screenshot").with_state(format!("{:?}", self.export_aa())),
+            Binding::new("r", "capture reference frame"),
+            Binding::new("R", "rebuild renderer"),
+            Binding::new("i", "latency flash").with_state(keys::on_off(self.latency.flash)),
+            Binding::new("u", "onion skin").with_state(keys::on_off(self.onion_skin)),
+            Binding::new("A", "split AA configs").with_state(keys::on_off(self.show_split)),
+            Binding::new("q", "show one channel")
+                .with_state(self.channel.map_or("all", Channel::name)),
+            Binding::new("Del", "clear strokes"),
+        ]
+    }
+
+    /// Draw the control panel with its top-left corner at `origin`, with live
+    /// stats above its controls.
+    fn draw_control_panel(&mut self, origin: Point) -> Rect {
+        let (width, height) = self.render_size();
+        let last_frame = self.frame_history.iter().last().unwrap_or_default();
+        let info = format!(
+            "frame {}\n{} x {}\npresented {} of {}\nframe time {:.1}ms",
+            self.frame,
+            width,
+            height,
+            self.frames_presented,
+            self.redraws_requested,
+            last_frame.as_secs_f64() * 1000.0
+        );
+        let controls = [
+            Control::new(
+                "AA",
+                format!("{:?}", self.active_aa()),
+                PanelAction::ToggleMsaa,
+            ),
+            Control::new("scene", self.demo.name(), PanelAction::NextScene),
+            Control::new(
+                "tile grid",
+                keys::on_off(self.debug_tiles),
+                PanelAction::ToggleTiles,
+            ),
+            Control::new(
+                "overlay",
+                keys::on_off(self.debug_overlay),
+                PanelAction::ToggleOverlay,
+            ),
+            Control::new("screenshot", "save", PanelAction::Screenshot),
+        ];
+        self.panel
+            .draw(&mut self.scene, &self.text, origin, &info, &controls)
+    }
+
+    fn panel_action(&mut self, action: PanelAction) {
+        debug!("Panel action {:?}", action);
+        match action {
+            PanelAction::ToggleMsaa => self.toggle_msaa(),
+            PanelAction::NextScene => self.next_scene(),
+            PanelAction::ToggleTiles => self.toggle_tiles(),
+            PanelAction::ToggleOverlay => self.toggle_overlay(),
+            PanelAction::Screenshot => self.screenshot(),
+        }
+    }
+
+    /// Draw the key binding legend with its top-left corner at `origin`.
+    fn draw_legend(&mut self, origin: Point) -> Rect {
+        let legend = keys::legend(&self.bindings());
+        overlay::draw_panel(&mut self.scene, &self.text, &legend, origin)
+    }
+
+    /// Discard any accumulated strokes.
+    fn clear(&mut self) {
+        self.strokes.clear();
+        self.sketch.reset();
+        self.stroke_bounds.clear();
+        self.window.request_redraw();
+        info!("Cleared drawing");
+    }
+
+    /// Collect the freehand stroke while the left button is held.
+    fn cursor_moved(&mut self, position: Point) {
+        self.cursor = Some(position);
+        if self.inspector.is_some() {
+            // The tooltip follows the cursor.
+            self.schedule_inspect();
+            self.window.request_redraw();
+        }
+        if self.button_down {
+            // Strokes are drawn as content, under the view transform.
+            self.current_stroke
+                .push(self.content_transform().inverse() * position);
+            self.window.request_redraw();
+        }
+    }
+
+    /// Read the pixel under the cursor for the inspector, as soon as its
+    /// throttle allows.
+    fn schedule_inspect(&mut self) {
+        let now = Instant::now();
+        if let Some(delay) = self.inspector.as_mut().and_then(|i| i.moved(now)) {
+            self.inspect_after(delay);
+        }
+    }
+
+    fn inspect_after(&mut self, delay: Duration) {
+        if delay.is_zero() {
+            self.inspect();
+            return;
+        }
+        let proxy = self.proxy.clone();
+        let scheduled = dom::set_timeout(delay, move || {
+            _ = proxy.send_event(UserEvent::Inspect);
+        });
+        if let Err(e) = scheduled {
+            error!("Inspector: failed to schedule a read: {:?}", e);
+            self.inspect();
+        }
+    }
+
+    /// Render the pixel of the last frame under the cursor into a texture
+    /// of its own, and read it back for the inspector.
+    fn inspect(&mut self) {
+        let Some(inspector) = &mut self.inspector else {
+            // Turned off since the read was scheduled.
+            return;
+        };
+        inspector.started(Instant::now());
+        let (Some(cursor), Some(surface)) = (self.cursor, &self.surface) else {
+            self.inspected(None);
+            return;
+        };
+        let config = &surface.config;
+        let pixel = (
+            (cursor.x.max(0.0) as u32).min(config.width.saturating_sub(1)),
+            (cursor.y.max(0.0) as u32).min(config.height.saturating_sub(1)),
+        );
+        let point = self.content_transform().inverse() * cursor;
+        let mut shifted = Scene::new();
+        let origin = Vec2::new(-(pixel.0 as f64), -(pixel.1 as f64));
+        shifted.append(&self.scene, Some(Affine::translate(origin)));
+        let params = vello::RenderParams {
+            base_color: self.base_color,
+            width: 1,
+            height: 1,
+            antialiasing_method: self.active_aa(),
+        };
+        let dev_id = surface.dev_id;
+        let device_handle = &self.context.devices[dev_id];
+        let readback = readback::render_to_readback(
+            &device_handle.device,
+            &device_handle.queue,
+            self.renderers[dev_id].as_mut().unwrap(),
+            &shifted,
+            &params,
+        );
+        let read = match readback {
+            Ok(readback) => readback.read(&device_handle.device),
+            Err(e) => {
+                error!("Inspector: failed to render the pixel: {}", e);
+                self.inspected(None);
+                return;
+            }
+        };
+        let proxy = self.proxy.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let sample = match read.await {
+                Ok(pixels) => Some(Sample {
+                    pixel,
+                    point,
+                    rgba: [pixels[0], pixels[1], pixels[2], pixels[3]],
+                }),
+                Err(e) => {
+                    error!("Inspector: failed to read back the pixel: {}", e);
+                    None
+                }
+            };
+            _ = proxy.send_event(UserEvent::Inspected(sample));
+        });
+    }
+
+    /// Test the edge with the AA config in use, for `?edge_test`, unless it
+    /// has been already. The result is shown once it has been read back.
+    fn test_edge(&mut self) {
+        let aa = self.active_aa();
+        let Some(test) = self.edge_test.as_mut().filter(|test| test.due(aa)) else {
+            return;
+        };
+        test.started();
+        let scene = edge::scene_at(test.angle);
+        let params = vello::RenderParams {
+            base_color: Color::WHITE,
+            width: edge::SIZE,
+            height: edge::SIZE,
+            antialiasing_method: aa,
+        };
+        let dev_id = self.surface().dev_id;
+        let device_handle = &self.context.devices[dev_id];
+        let readback = readback::render_to_readback(
+            &device_handle.device,
+            &device_handle.queue,
+            self.renderers[dev_id].as_mut().unwrap(),
+            &scene,
+            &params,
+        );
+        let read = match readback {
+            Ok(readback) => readback.read(&device_handle.device),
+            Err(e) => {
+                error!("Edge test: failed to render with {:?}: {}", aa, e);
+                self.edge_tested(aa, None);
+                return;
+            }
+        };
+        let proxy = self.proxy.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let profile = match read.await {
+                Ok(pixels) => Some(EdgeProfile::of(&pixels)),
+                Err(e) => {
+                    error!("Edge test: failed to read back {:?}: {}", aa, e);
+                    None
+                }
+            };
+            _ = proxy.send_event(UserEvent::EdgeTested(aa, profile));
+        });
+    }
+
+    /// Find whether the device in use renders on the GPU, once it has
+    /// presented a frame, unless that was found already. The browser is only
+    /// asked of the adapter the client started on, as it can't be asked of
+    /// any other.
+    fn check_render_path(&mut self) {
+        let dev_id = self.surface().dev_id;
+        if self.render_path_device == Some(dev_id) {
+            return;
+        }
+        let ask_browser = self.render_path_device.is_none();
+        self.render_path_device = Some(dev_id);
+        let info = self.context.devices[dev_id].adapter.get_info();
+        let use_cpu = self.use_cpu;
+        let power_preference = match self.context.power_preference {
+            wgpu::PowerPreference::HighPerformance => Some("high-performance"),
+            wgpu::PowerPreference::LowPower => Some("low-power"),
+            wgpu::PowerPreference::None => None,
+        };
+        let proxy = self.proxy.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let browser = match ask_browser && info.backend == wgpu::Backend::BrowserWebGpu {
+                true => dom::browser_adapter(power_preference).await,
+                false => None,
+            };
+            let detection = fallback::detect(&info, browser, use_cpu);
+            _ = proxy.send_event(UserEvent::RenderPath(detection));
+        });
+    }
+
+    /// Record the render path found, and warn of it if it's slower than
+    /// the GPU.
+    fn render_path_found(&mut self, detection: Detection) {
+        diagnostics::set_render_path(&detection);
+        if !detection.is_degraded() {
+            info!(
+                "Render path {:?} on {:?}: {}",
+                detection.path, detection.backend, detection.reason
+            );
+            return;
+        }
+        warn!(
+            "RENDERING IS NOT ON THE GPU: render path {:?} on {:?}, as {}. \
+             Frames will be much slower than on a GPU, and may differ from its.",
+            detection.path, detection.backend, detection.reason
+        );
+        _ = dom::display_banner(dom::Severity::Warning, &detection.notice());
+    }
+
+    /// Show the result of an edge test, and log it.
+    fn edge_tested(&mut self, aa: AaConfig, profile: Option<EdgeProfile>) {
+        let Some(test) = &mut self.edge_test else {
+            return;
+        };
+        if let Some(profile) = &profile {
+            let angle = test.angle;
+            if profile.pass {
+                info!("Edge test {}° {:?} passed: {}", angle, aa, profile);
+            } else {
+                warn!("Edge test {}° {:?} failed: {}", angle, aa, profile);
+            }
+        }
+        test.finished(aa, profile);
+        self.window.request_redraw();
+    }
+
+    /// Show the pixel the inspector read, and read again if the cursor has
+    /// moved since.
+    fn inspected(&mut self, sample: Option<Sample>) {
+        let Some(inspector) = &mut self.inspector else {
+            return;
+        };
+        if let Some(delay) = inspector.finished(sample, Instant::now()) {
+            self.inspect_after(delay);
+        }
+        self.window.request_redraw();
+    }
+
+    fn button_changed(&mut self, pressed: bool) {
+        // A click on the panel works its control rather than starting a stroke.
+        let on_panel = self.cursor.and_then(|cursor| self.panel.hit(cursor));
+        if let (true, Some(action)) = (pressed, on_panel) {
+            self.panel_action(action);
+            return;
+        }
+        let on_minimap = self.cursor.and_then(|cursor| self.minimap()?.hit(cursor));
+        if let (true, Some(point)) = (pressed, on_minimap) {
+            self.center_on(point);
+            return;
+        }
+        self.button_down = pressed;
+        if pressed {
+            self.current_stroke.clear();
+            self.current_stroke.extend(self.cursor);
+        } else if !self.current_stroke.is_empty() {
+            self.commit_stroke();
+        }
+    }
+
+    /// Smooth the stroke in progress and add it to the committed strokes.
+    fn commit_stroke(&mut self) {
+        let path = draw::smooth_path(&self.current_stroke);
+        self.current_stroke.clear();
+        draw::draw_freehand(&mut self.sketch, &path, self.demo_state.stroke_color);
+        self.stroke_bounds
+            .stroke(&draw::freehand_stroke(), Affine::IDENTITY, &path);
+        self.strokes.push(path);
+        self.window.request_redraw();
+    }
+
+    /// Handle a key press producing `text`.
+    fn key_pressed(&mut self, text: &str) {
+        match text {
+            "a" => self.toggle_msaa(),
+            "o" => self.toggle_overlay(),
+            "t" => self.toggle_tiles(),
+            "w" => {
+                self.debug_outlines = !self.debug_outlines;
+                self.window.request_redraw();
+            }
+            "W" => self.toggle_debug_layers(),
+            "g" => {
+                self.show_grid = !self.show_grid;
+                self.window.request_redraw();
+            }
+            "G" => {
+                self.crosshairs = !self.crosshairs;
+                self.window.request_redraw();
+            }
+            "M" => {
+                self.show_minimap = !self.show_minimap;
+                self.window.request_redraw();
+            }
+            "m" | "y" => {
+                if text == "m" {
+                    self.mirror = !self.mirror;
+                } else {
+                    self.flip = !self.flip;
+                }
+                info!("Flip now {}", self.flip_state());
+                self.window.request_redraw();
+            }
+            "D" => self.dump_state(),
+            "d" => {
+                self.show_panel = !self.show_panel;
+                if !self.show_panel {
+                    self.panel.clear();
+                }
+                self.window.request_redraw();
+            }
+            "v" => self.cycle_present_mode(),
+            "V" => self.start_burst(),
+            "F" => self.cycle_surface_format(),
+            "l" => {
+                let latency = self.surface().config.desired_maximum_frame_latency;
+                self.set_frame_latency(latency % MAX_FRAME_LATENCY + 1);
+            }
+            "j" | "k" => {
+                let style = &mut self.demo_state.line_style;
+                *style = if text == "j" {
+                    style.next_join()
+                } else {
+                    style.next_caps()
+                };
+                info!("Line style now {:?}", style);
+                self.window.request_redraw();
+            }
+            "1" | "2" | "3" | "4" => {
+                let preset = text.parse::<usize>().unwrap() - 1;
+                let scale = ZOOM_PRESETS[preset];
+                self.zoom_to(Some(Vec2::new(scale, scale)));
+            }
+            "0" => self.zoom_to(None),
+            "f" => self.fit_to_view(),
+            "x" => {
+                self.demo_state.extend = scenes::next_extend(self.demo_state.extend);
+                info!("Pattern extend now {:?}", self.demo_state.extend);
+                self.window.request_redraw();
+            }
+            "h" => {
+                self.demo_state.hinting = self.demo_state.hinting.next();
+                info!("Text hinting now {}", self.demo_state.hinting.name());
+                self.window.request_redraw();
+            }
+            "c" => self.compare_aa_configs(),
+            "e" => self.profile_edges(),
+            "r" => self.capture_reference(),
+            "R" => self.rebuild_renderer(),
+            "i" => {
+                self.latency.pressed(Instant::now());
+                self.window.request_redraw();
+            }
+            "I" => {
+                if self.inspector.take().is_some() {
+                    info!("Inspector off");
+                } else {
+                    info!("Inspector on");
+                    self.inspector = Some(Inspector::default());
+                    self.schedule_inspect();
+                }
+                self.window.request_redraw();
+            }
+            "u" => {
+                self.onion_skin = !self.onion_skin;
+                // Start without a previous frame when next turned on.
+                if let Some(pass) = &mut self.onion_pass {
+                    pass.clear();
+                }
+                info!("Onion skin now {}", self.onion_skin);
+                self.warn_overridden_passes();
+                self.window.request_redraw();
+            }
+            "A" => {
+                self.show_split = !self.show_split;
+                if self.show_split {
+                    let [left, right] = self.split_configs();
+                    info!(
+                        "Split AA on: {:?} on the left, {:?} on the right",
+                        left, right
+                    );
+                } else {
+                    info!("Split AA off");
+                }
+                self.warn_overridden_passes();
+                self.window.request_redraw();
+            }
+            "q" => {
+                self.channel = Channel::next(self.channel);
+                match self.channel {
+                    Some(channel) => info!("Showing the {} channel", channel.name()),
+                    None => info!("Showing all channels"),
+                }
+                self.warn_overridden_passes();
+                self.window.request_redraw();
+            }
+            "p" => self.screenshot(),
+            "C" => self.copy_screenshot(),
+            "K" => self.copy_config(),
+            "s" => self.next_scene(),
+            "z" => self.toggle_reel(),
+            "ArrowLeft" | "ArrowRight" => self.step_flipbook(text == "ArrowRight"),
+            "Space" => self.set_paused(!self.clock.is_paused()),
+            "n" => self.step_frame(),
+            "b" => {
+                if let Some(flipbook) = &mut self.flipbook {
+                    flipbook.playing = !flipbook.playing;
+                    info!("Flipbook playing: {}", flipbook.playing);
+                    self.window.request_redraw();
+                }
+            }
+            "?" => {
+                self.show_legend = !self.show_legend;
+                self.window.request_redraw();
+            }
+            _ => (),
+        }
+    }
+
+    /// Handle an input event, live or replayed.
+    fn handle_input(&mut self, input: InputEvent) {
+        self.scene_changed();
+        match input {
+            InputEvent::Key { text } => self.key_pressed(&text),
+            InputEvent::Clear => self.clear(),
+            InputEvent::CursorMoved { x, y } => self.cursor_moved(Point::new(x, y)),
+            InputEvent::Button { pressed } => self.button_changed(pressed),
+            // The offset moves the other way to the content.
+            InputEvent::Scroll { dx, dy } => self.scroll_by(Vec2::new(-dx, -dy)),
+        }
+    }
+
+    /// Record user interaction, waking up from idle if necessary.
+    fn input_received(&mut self) {
+        self.last_input = Instant::now();
+        if self.idle {
+            info!("Input received, leaving idle");
+            self.idle = false;
+            self.window.request_redraw();
+        }
+    }
+
+    /// Resize the surface and canvas, independently of winit's resize events.
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), String> {
+        if width == 0 || height == 0 {
+            return Err(format!("invalid surface size {} x {}", width, height));
+        }
+        // This size is newer than any the window has reported.
+        self.resize_pending = None;
+        self.reconfigure(width, height);
+        // The surface size may have been clamped, and the canvas must match it.
+        let config = &self.surface().config;
+        if let Some(canvas) = self.window.canvas() {
+            canvas.set_width(config.width);
+            canvas.set_height(config.height);
+        }
+        info!("Programmatic resize to {}, {}", width, height);
+        Ok(())
+    }
+
+    /// Start switching the whole renderer to the adapter at `index` in
+    /// `context::list_adapters`. The device is created asynchronously, then
+    /// handed to `use_device`; until then, and if that fails, rendering
+    /// carries on with the current adapter.
+    fn switch_adapter(&mut self, index: usize) {
+        info!("Switching to adapter {}", index);
+        let instance = self.context.instance.clone();
+        let features = self.context.optional_features;
+        let limits = self.context.limits;
+        let proxy = self.proxy.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let handle = context::open_listed_adapter(&instance, index, features, limits).await;
+            // If the event loop has exited, there's nothing left to switch.
+            _ = proxy.send_event(UserEvent::AdapterOpened(handle));
+        });
+    }
+
+    /// Whether frames are being drawn continuously, rather than only on demand.
+    fn expects_frames(&self) -> bool {
+        let playing = self.flipbook.as_ref().is_some_and(|f| f.playing);
+        (self.animate || playing || self.burst.is_some())
+            && !self.idle
+            && self.visible
+            && self.page_visible
+            && !self.clock.is_paused()
+    }
+
+    /// Check that frames are still being presented, try to recover if they
+    /// aren't, and schedule the next check.
+    fn check_watchdog(&mut self) {
+        let expected = self.expects_frames();
+        let Some(watchdog) = &mut self.watchdog else {
+            return;
+        };
+        if let Some(stalled) = watchdog.check(Instant::now(), expected) {
+            self.recover_from_stall(stalled);
+        }
+        self.schedule_watchdog();
+    }
+
+    /// Check the watchdog after its period, on a timer rather than a redraw,
+    /// so that it runs when no frames are being drawn.
+    fn schedule_watchdog(&self) {
+        let Some(watchdog) = &self.watchdog else {
+            return;
+        };
+        let proxy = self.proxy.clone();
+        let scheduled = dom::set_timeout(watchdog.period(), move || {
+            // If the event loop has exited, there's nothing left to watch.
+            _ = proxy.send_event(UserEvent::WatchdogCheck);
+        });
+        if let Err(e) = scheduled {
+            error!("Failed to schedule the watchdog: {:?}", e);
+        }
+    }
+
+    /// Follow the page being shown and hidden.
+    fn watch_visibility(&self) {
+        let proxy = self.proxy.clone();
+        let watching = dom::on_visibility_change(move |visible| {
+            _ = proxy.send_event(UserEvent::PageVisible(visible));
+        });
+        if let Err(e) = watching {
+            warn!("Couldn't follow the page's visibility: {:?}", e);
+        }
+    }
+
+    /// Pause rendering while the page is hidden, and draw a frame when it's
+    /// shown again.
+    fn set_page_visible(&mut self, visible: bool) {
+        if visible == self.page_visible {
+            return;
+        }
+        self.page_visible = visible;
+        if visible {
+            info!("Page visible, resuming rendering");
+            self.window.request_redraw();
+        } else {
+            info!("Page hidden, pausing rendering");
+        }
+    }
+
+    /// Time the browser's animation frames for a while, to measure the
+    /// display's refresh rate.
+    fn measure_refresh_rate(&self) {
+        let proxy = self.proxy.clone();
+        let measuring = dom::animation_frame_times(REFRESH_MEASUREMENT, move |times| {
+            let rate = RefreshRate::from_frame_times(&times);
+            _ = proxy.send_event(UserEvent::RefreshRate(rate));
+        });
+        if let Err(e) = measuring {
+            warn!("Couldn't measure the refresh rate: {:?}", e);
+        }
+    }
+
+    /// Report the display's measured refresh rate, and check the frame rate
+    /// cap against it.
+    fn set_refresh_rate(&mut self, rate: RefreshRate) {
+        if rate.variable {
+            info!(
+                "Display refresh rate {:.1}Hz, varying from {:.1}Hz to {:.1}Hz",
+                rate.hz, rate.min_hz, rate.max_hz
+            );
+        } else {
+            info!("Display refresh rate {:.1}Hz", rate.hz);
+        }
+        if let Some(fps) = self.pacer.as_ref().map(FramePacer::fps) {
+            if !rate.divides(fps) {
+                warn!(
+                    "?fps={} doesn't divide the {:.1}Hz refresh rate, so frames will be uneven",
+                    fps, rate.hz
+                );
+            }
+        }
+        stats::update(|s| s.refresh = Some(rate));
+        self.refresh = Some(rate);
+    }
+
+    /// Try to get frames presented again after none for `stalled`: reconfigure
+    /// the surface, and start opening a new device on the same adapter, which
+    /// is switched to as with `switch_adapter`. This is only tried once until a
+    /// frame is presented, and on failure the current device is kept.
+    fn recover_from_stall(&mut self, stalled: Duration) {
+        let info = self.context.devices[self.surface().dev_id]
+            .adapter
+            .get_info();
+        error!(
+            "No frame presented for {:.1?}, the GPU may have hung; \
+             reconfiguring the surface and reopening '{}'",
+            stalled, info.name
+        );
+        _ = dom::display_banner(
+            dom::Severity::Error,
+            &format!(
+                "Rendering has stopped: no frame was presented for {:.0} seconds. \
+                 Trying to recover with a new device; see the console for details.",
+                stalled.as_secs_f64()
+            ),
+        );
+        let config = &self.surface().config;
+        self.reconfigure(config.width, config.height);
+        let instance = self.context.instance.clone();
+        let features = self.context.optional_features;
+        let limits = self.context.limits;
+        let proxy = self.proxy.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let handle = context::reopen_adapter(&instance, &info, features, limits).await;
+            _ = proxy.send_event(UserEvent::AdapterOpened(handle));
+        });
+    }
+
+    /// Build a renderer on `device` for the surface's `format`, with the
+    /// pipelines for `aa_support`.
+    fn new_renderer(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        aa_support: AaSupport,
+    ) -> Result<Renderer, vello::Error> {
+        Renderer::new(
+            device,
+            RendererOptions {
+                surface_format: Some(format),
+                use_cpu: self.use_cpu,
+                antialiasing_support: aa_support,
+                num_init_threads: NonZeroUsize::new(1),
+            },
+        )
+    }
+
+    /// Toggle drawing Vello's debug layers, if the linked Vello draws them.
+    fn toggle_debug_layers(&mut self) {
+        if !layers::AVAILABLE {
+            warn!("Vello's debug layers aren't built in: build with the debug_layers feature");
+            return;
+        }
+        self.show_debug_layers = !self.show_debug_layers;
+        if self.show_debug_layers {
+            info!("Vello debug layers on: {:?}", self.debug_layers);
+        } else {
+            info!("Vello debug layers off");
+        }
+        self.window.request_redraw();
+    }
+
+    /// Render the scene to `surface_texture` with Vello's debug layers over
+    /// it and present it, in a future, which gives the renderer back in a
+    /// `DebugFrame` event. The frame is at full resolution, without any of
+    /// the other passes.
+    fn render_debug_frame(
+        &mut self,
+        surface_texture: wgpu::SurfaceTexture,
+        width: u32,
+        height: u32,
+    ) {
+        let surface = self.surface();
+        let (dev_id, format) = (surface.dev_id, surface.format);
+        let handle = self.context.devices[dev_id].clone();
+        let renderer = match self.debug_renderer.take() {
+            Some(renderer) => renderer,
+            None => {
+                let aa_support = self.aa_configs.iter().copied().collect();
+                match self.new_renderer(&handle.device, format, aa_support) {
+                    Ok(renderer) => renderer,
+                    Err(e) => {
+                        error!(
+                            "Failed to build a renderer for the debug layers, turning them off: {}",
+                            e
+                        );
+                        self.show_debug_layers = false;
+                        self.window.request_redraw();
+                        return;
+                    }
+                }
+            }
+        };
+        let params = vello::RenderParams {
+            base_color: self.base_color,
+            width,
+            height,
+            antialiasing_method: self.active_aa(),
+        };
+        let (scene, layers, proxy) = (self.scene.clone(), self.debug_layers, self.proxy.clone());
+        self.debug_frame_pending = true;
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut renderer = renderer;
+            let result = layers::render(
+                &handle,
+                &mut renderer,
+                &scene,
+                surface_texture,
+                &params,
+                layers,
+            )
+            .await
+            .map_err(|e| e.to_string());
+            let frame = layers::Frame {
+                dev_id,
+                format,
+                renderer,
+                result,
+            };
+            _ = proxy.send_event(UserEvent::DebugFrame(Box::new(frame)));
+        });
+    }
+
+    /// Once the first frame is presented, at `presented`, whether with the
+    /// debug layers or not: take down the loading overlay and start what
+    /// waits for it.
+    fn on_first_present(&mut self, presented: Instant) {
+        let time_to_first_frame = presented - self.started;
+        info!("Time to first frame: {:.2?}", time_to_first_frame);
+        stats::update(|s| {
+            s.time_to_first_frame_ms = Some(time_to_first_frame.as_secs_f64() * 1000.0)
+        });
+        dom::remove_loading_overlay();
+        if self.compare_aa {
+            self.compare_aa_configs();
+        }
+        if self.edge_profile {
+            self.profile_edges();
+        }
+        if self.reel_on_start {
+            self.toggle_reel();
+        }
+    }
+
+    /// Count a frame rendered with debug layers as presented, keep its
+    /// renderer for the next, and draw any frame skipped meanwhile.
+    fn debug_frame_done(&mut self, frame: layers::Frame) {
+        self.debug_frame_pending = false;
+        match frame.result {
+            Ok(()) => {
+                let presented = Instant::now();
+                self.frames_presented += 1;
+                stats::update(|s| s.frames_presented = self.frames_presented);
+                let watchdog = self.watchdog.as_mut();
+                if let Some(stalled) = watchdog.and_then(|w| w.presented(presented)) {
+                    info!("Frames presented again after {:.1?}", stalled);
+                }
+                if let Some(pacer) = &mut self.pacer {
+                    pacer.presented(presented);
+                }
+                if self.frames_presented == 1 {
+                    self.on_first_present(presented);
+                }
+            }
+            Err(e) => {
+                error!(
+                    "Failed to render with debug layers, turning them off: {}",
+                    e
+                );
+                self.show_debug_layers = false;
+            }
+        }
+        // Unless the device or the surface format changed meanwhile.
+        let surface = self.surface.as_ref();
+        if surface.is_some_and(|s| s.dev_id == frame.dev_id && s.format == frame.format) {
+            self.debug_renderer = Some(frame.renderer);
+        }
+        if std::mem::take(&mut self.redraw_skipped) {
+            self.window.request_redraw();
+        }
+    }
+
+    /// Replace the renderer with a new one on the same device, with the same
+    /// options, leaving the device and surface alone. A problem this fixes
+    /// is in the renderer's state rather than deeper in the device. If the new
+    /// renderer can't be built, the old one is kept.
+    fn rebuild_renderer(&mut self) {
+        let surface = self.surface();
+        let (dev_id, format) = (surface.dev_id, surface.format);
+        let aa_support = self.aa_configs.iter().copied().collect();
+        let started = Instant::now();
+        let device = &self.context.devices[dev_id].device;
+        match self.new_renderer(device, format, aa_support) {
+            Ok(renderer) => {
+                self.renderers[dev_id] = Some(renderer);
+                info!("Rebuilt the renderer in {:.2?}", started.elapsed());
+                self.window.request_redraw();
+            }
+            Err(e) => error!("Failed to rebuild the renderer, keeping the old one: {}", e),
+        }
+    }
+
+    /// A renderer on `handle`'s device for the surface's `format`, with the
+    /// pipelines of the AA configs in use that the adapter supports with it,
+    /// and those configs.
+    fn renderer_for(
+        &self,
+        handle: &DeviceHandle,
+        format: wgpu::TextureFormat,
+    ) -> Result<(Renderer, Vec<AaConfig>), vello::Error> {
+        let aa_configs: Vec<AaConfig> = self
+            .aa_configs
+            .iter()
+            .copied()
+            .filter(|&aa| aa::is_supported(&handle.adapter, format, aa))
+            .collect();
+        let aa_support: AaSupport = aa_configs.iter().copied().collect();
+        let renderer = self.new_renderer(&handle.device, format, aa_support)?;
+        Ok((renderer, aa_configs))
+    }
+
+    /// Render with `renderer` on device `dev_id`, built for `aa_configs`
+    /// by [`renderer_for`](Self::renderer_for). Any AA config chosen that it
+    /// wasn't built for falls back, and the passes drawing into the surface
+    /// are dropped, to be built again for it when next used.
+    fn use_renderer(&mut self, dev_id: usize, renderer: Renderer, aa_configs: Vec<AaConfig>) {
+        if !aa_configs.contains(&self.aa_config) {
+            info!("{:?} is not supported, using area AA", self.aa_config);
+            self.aa_config = AaConfig::Area;
+        }
+        if let Some(aa) = self.export_aa.filter(|aa| !aa_configs.contains(aa)) {
+            info!("{:?} is not supported, screenshots now use the live AA", aa);
+            self.export_aa = None;
+        }
+        if let Some(configs) = self
+            .split_aa
+            .filter(|configs| !configs.iter().all(|aa| aa_configs.contains(aa)))
+        {
+            info!(
+                "{:?} are not all supported, splitting the best and area AA",
+                configs
+            );
+            self.split_aa = None;
+        }
+        if let Some(configs) = self
+            .layer_aa
+            .filter(|configs| !configs.iter().all(|aa| aa_configs.contains(aa)))
+        {
+            info!(
+                "{:?} are not all supported, rendering the layers as one",
+                configs
+            );
+            self.layer_aa = None;
+        }
+        self.aa_configs = aa_configs;
+        self.renderers[dev_id] = Some(renderer);
+        self.drop_passes();
+    }
+
+    /// Drop the passes drawing into the surface and the textures they hold.
+    fn drop_passes(&mut self) {
+        self.downscaler = Downscaler::default();
+        self.gamma_pass = None;
+        self.preserve_pass = None;
+        self.onion_pass = None;
+        self.split_pass = None;
+        self.channel_pass = None;
+        self.dirty_pass = None;
+        self.layer_pass = None;
+        self.debug_renderer = None;
+    }
+
+    /// Render with a new device: build a renderer on it and reconfigure the
+    /// surface for it. Everything is checked before anything is replaced, so
+    /// on failure the previous device is still in use.
+    fn use_device(&mut self, handle: DeviceHandle) -> Result<(), Box<dyn std::error::Error>> {
+        let surface = self.surface();
+        let (format, previous) = (surface.format, surface.dev_id);
+        if !handle.adapter.is_surface_supported(&surface.surface) {
+            return Err("the adapter can't present to the surface".into());
+        }
+        let capabilities = surface.surface.get_capabilities(&handle.adapter);
+        if !capabilities.formats.contains(&format) {
+            return Err(format!(
+                "the adapter doesn't support the surface format {:?}",
+                format
+            )
+            .into());
+        }
+        let (renderer, aa_configs) = self.renderer_for(&handle, format)?;
+        let aa_support = aa_configs.iter().copied().collect();
+        aa::log_pipelines(&handle.adapter, format, aa_support);
+
+        let info = handle.adapter.get_info();
+        diagnostics::set_versions(&VersionInfo::new(&handle.adapter));
+        diagnostics::set_capabilities(CapabilityReport::new(
+            &handle.adapter,
+            &handle.device,
+            &surface.surface,
+            format,
+        ));
+        self.present_modes = capabilities.present_modes;
+        self.surface_formats = capabilities.formats;
+        let dev_id = self.context.add_device(handle);
+        self.renderers.resize_with(dev_id + 1, || None);
+        self.use_renderer(dev_id, renderer, aa_configs);
+        // The previous device is kept so that ids stay valid, but its pipelines are released.
+        self.renderers[previous] = None;
+
+        let surface = self.surface.as_mut().unwrap();
+        surface.dev_id = dev_id;
+        surface.config.present_mode =
+            surface::choose_present_mode(surface.config.present_mode, &self.present_modes);
+        let (width, height) = (surface.config.width, surface.config.height);
+        self.reconfigure(width, height);
+        info!(
+            "Switched to adapter '{}' ({:?}, {:?})",
+            info.name, info.device_type, info.backend
+        );
+        Ok(())
+    }
+
+    /// Release the GPU resources in dependency order: the renderers and the
+    /// textures they reference first, then the surface, then the devices.
+    /// Pending work is flushed before the devices are dropped. Does nothing if
+    /// already shut down.
+    fn shutdown(&mut self) {
+        let Some(surface) = self.surface.take() else {
+            return;
+        };
+        info!("Shutdown: dropping renderers");
+        self.renderers.clear();
+        self.drop_passes();
+        info!("Shutdown: dropping surface");
+        drop(surface);
+        info!("Shutdown: waiting for the GPU and dropping devices");
+        for device_handle in self.context.devices.drain(..) {
+            device_handle.device.poll(wgpu::Maintain::Wait);
+        }
+        info!("Shutdown complete");
+    }
+}
+
+/// The input event the client handles for a window event, if any.
+fn input_event(event: &WindowEvent, modifiers: ModifiersState) -> Option<InputEvent> {
+    match event {
+        WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+            match &event.logical_key {
+                Key::Named(NamedKey::Delete | NamedKey::Backspace) => Some(InputEvent::Clear),
+                Key::Character(text) => Some(InputEvent::Key {
+                    text: text.to_string(),
+                }),
+                Key::Named(
+                    named @ (NamedKey::ArrowLeft | NamedKey::ArrowRight | NamedKey::Space),
+                ) => Some(InputEvent::Key {
+                    text: format!("{:?}", named),
+                }),
+                _ => None,
+            }
+        }
+        WindowEvent::CursorMoved { position, .. } => Some(InputEvent::CursorMoved {
+            x: position.x,
+            y: position.y,
+        }),
+        WindowEvent::MouseInput {
+            state,
+            button: MouseButton::Left,
+            ..
+        } => Some(InputEvent::Button {
+            pressed: *state == ElementState::Pressed,
+        }),
+        // Ctrl+wheel is left for zooming.
+        WindowEvent::MouseWheel { delta, .. } if !modifiers.control_key() => {
+            let (dx, dy) = match *delta {
+                MouseScrollDelta::LineDelta(x, y) => (
+                    x as f64 * scroll::LINE_HEIGHT,
+                    y as f64 * scroll::LINE_HEIGHT,
+                ),
+                MouseScrollDelta::PixelDelta(position) => (position.x, position.y),
+            };
+            Some(InputEvent::Scroll { dx, dy })
+        }
+        _ => None,
+    }
+}
+
+fn theme_palette(theme: Theme) -> draw::Palette {
+    match theme {
+        Theme::Light => draw::Palette::LIGHT,
+        Theme::Dark => draw::Palette::DARK,
+    }
+}
+
+fn is_input(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput { .. }
+            | WindowEvent::CursorMoved { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::Touch(_)
+    )
+}
+
+impl Drop for VelloClient<'_> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl ApplicationHandler<UserEvent> for VelloClient<'_> {
+    fn resumed(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {}
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.shutdown();
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: UserEvent) {
+        if self.surface.is_none() {
+            return;
+        }
+        // Those that draw nothing themselves come often enough to matter.
+        if !matches!(event, UserEvent::WatchdogCheck | UserEvent::Inspect) {
+            self.scene_changed();
+        }
+        match event {
+            UserEvent::Resize(width, height) => {
+                if let Err(e) = self.resize(width, height) {
+                    error!("Resize failed: {}", e);
+                }
+            }
+            UserEvent::ViewTransform(transform) => {
+                self.view_transform = transform;
+                self.scroll = Vec2::ZERO;
+                self.window.request_redraw();
+            }
+            UserEvent::FrameLatency(latency) => self.set_frame_latency(latency),
+            UserEvent::Gamma(gamma) => {
+                self.gamma = gamma;
+                info!("Gamma now {}", gamma);
+                self.warn_overridden_passes();
+                self.window.request_redraw();
+            }
+            UserEvent::Supersample(factor) => {
+                self.supersample = factor;
+                info!("Supersampling now {}x", factor);
+                self.window.request_redraw();
+            }
+            UserEvent::PreservePrevious(preserve) => {
+                self.preserve_previous = preserve;
+                // Start again from the base colour when next turned on.
+                if let Some(pass) = &mut self.preserve_pass {
+                    pass.clear();
+                }
+                info!("Preserve previous frame now {}", preserve);
+                self.warn_overridden_passes();
+                self.window.request_redraw();
+            }
+            UserEvent::Seed(seed) => {
+                self.demo_state.seed = seed;
+                self.demo = Demo::Random;
+                self.window.request_redraw();
+            }
+            UserEvent::Screenshot => self.screenshot(),
+            UserEvent::Export(width, height) => self.export_image(width, height),
+            UserEvent::ExportFrames(count) => self.export_frames(count),
+            UserEvent::FitToView => self.fit_to_view(),
+            UserEvent::Zoom(scale) => self.zoom_to(Some(scale)),
+            UserEvent::Flipbook(flipbook) => {
+                match &flipbook {
+                    Some(flipbook) => info!("Loaded a flipbook of {} scenes", flipbook.len()),
+                    None => info!("Flipbook removed"),
+                }
+                self.flipbook = flipbook;
+                self.window.request_redraw();
+            }
+            UserEvent::Drawing(painter) => {
+                self.drawing = painter.map(|painter| painter.into_scene());
+                self.window.request_redraw();
+            }
+            UserEvent::Font(font) => self.load_font(font),
+            UserEvent::Replay(replay) => {
+                info!("Replaying {} input events", replay.len());
+                self.replay = Some(replay);
+                self.input_received();
+            }
+            UserEvent::Background(image) => {
+                match &image {
+                    Some(image) => info!("Background image {} x {}", image.width, image.height),
+                    None => info!("Background image removed"),
+                }
+                self.background = image;
+                self.window.request_redraw();
+            }
+            UserEvent::ReferenceImage(image) => {
+                match &image {
+                    Some(image) => {
+                        let (width, height) = self.render_size();
+                        let (left, _) = draw::split_halves(width, height);
+                        info!(
+                            "Reference image {} x {}, beside the live half of {} x {}",
+                            image.width,
+                            image.height,
+                            left.width(),
+                            left.height()
+                        );
+                    }
+                    None => info!("Reference image removed"),
+                }
+                self.reference_image = image;
+                self.window.request_redraw();
+            }
+            UserEvent::SwitchAdapter(index) => self.switch_adapter(index),
+            UserEvent::AdapterOpened(Ok(handle)) => {
+                let previous = self.context.devices[self.surface().dev_id]
+                    .adapter
+                    .get_info()
+                    .name;
+                if let Err(e) = self.use_device(handle) {
+                    error!("Failed to switch adapter: {}; keeping '{}'", e, previous);
+                }
+            }
+            UserEvent::AdapterOpened(Err(e)) => error!("Failed to open adapter: {}", e),
+            UserEvent::WatchdogCheck => self.check_watchdog(),
+            UserEvent::RefreshRate(Some(rate)) => self.set_refresh_rate(rate),
+            UserEvent::PageVisible(visible) => self.set_page_visible(visible),
+            UserEvent::DebugFrame(frame) => self.debug_frame_done(*frame),
+            UserEvent::Inspect => self.inspect(),
+            UserEvent::Inspected(sample) => self.inspected(sample),
+            UserEvent::EdgeTested(aa, profile) => self.edge_tested(aa, profile),
+            UserEvent::RenderPath(detection) => self.render_path_found(detection),
+            UserEvent::ReelStep(reel) => self.reel_step(reel),
+            UserEvent::RestoreConfig(config) => self.restore_config(config),
+            UserEvent::RefreshRate(None) => {
+                warn!("Too few animation frames to measure the refresh rate")
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        // Events can still arrive between shutting down and the loop exiting.
+        if self.surface.is_none() {
+            return;
+        }
+        if is_input(&event) {
+            self.input_received();
+        }
+        if let Some(input) = input_event(&event, self.modifiers) {
+            if self.replay.is_some() {
+                debug!("Ignoring {:?} during replay", input);
+            } else {
+                record::record(&input);
+                self.handle_input(input);
+            }
+            return;
+        }
+        if !matches!(event, WindowEvent::RedrawRequested) {
+            self.scene_changed();
+        }
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+
+            WindowEvent::ThemeChanged(theme) => self.set_theme(theme),
+
+            WindowEvent::Occluded(occluded) => {
+                self.visible = !occluded;
+                if self.visible {
+                    info!("Window visible, resuming rendering");
+                    self.window.request_redraw();
+                } else {
+                    info!("Window occluded, pausing rendering");
+                }
+            }
+
+            WindowEvent::CloseRequested => {
+                info!("Closing");
+                self.shutdown();
+                event_loop.exit();
+            }
+
+            WindowEvent::Resized(size) => {
+                if size.width == 0 || size.height == 0 {
+                    debug!("Ignoring resize to {} x {}", size.width, size.height);
+                    return;
+                }
+                // Always reconfigure, even if the size is unchanged: the first
+                // resize reports the size the window actually settled at.
+                self.resize_pending = Some((size.width, size.height));
+                self.window.request_redraw();
+                if !self.size_settled {
+                    self.size_settled = true;
+                    info!("First resize to {}, {}", size.width, size.height);
+                } else {
+                    info!("Resize to {}, {}", size.width, size.height);
+                }
+            }
+
+            WindowEvent::RedrawRequested => {
+                // Draw at the size the window has now, not the one it had
+                // when the surface was last configured.
+                if let Some((width, height)) = self.resize_pending.take() {
+                    debug!("Applying pending resize to {} x {}", width, height);
+                    self.reconfigure(width, height);
+                }
+                if !self.size_settled {
+                    // The size request at startup may have been deferred, so
+                    // match the window's current size until it reports one.
+                    let size = self.window.inner_size();
+                    let surface = self.surface();
+                    let max = self.context.max_surface_dimension(surface.dev_id);
+                    let fitted = surface::clamp_size(size.width, size.height, max);
+                    if size.width > 0
+                        && size.height > 0
+                        && fitted != (surface.config.width, surface.config.height)
+                    {
+                        info!(
+                            "Window is {} x {} before its first resize, reconfiguring",
+                            size.width, size.height
+                        );
+                        self.reconfigure(size.width, size.height);
+                    }
+                }
+                self.redraws_requested += 1;
+                stats::update(|s| s.redraws_requested = self.redraws_requested);
+                if self.redraws_requested.is_multiple_of(PRESENT_LOG_INTERVAL) {
+                    info!(
+                        "Presented {} of {} requested frames",
+                        self.frames_presented, self.redraws_requested
+                    );
+                }
+                let (width, height) = self.render_size();
+                if width == 0 || height == 0 {
+                    debug!("Surface too small to draw, skipping frame");
+                    return;
+                }
+                if self.debug_frame_pending {
+                    // Drawn once the frame with debug layers has been presented.
+                    self.redraw_skipped = true;
+                    return;
+                }
+
+                // Draw the output into the scene.
+                let start = Instant::now();
+                // Frame times are only meaningful when redrawing continuously.
+                if self.animate && !self.idle && !self.clock.is_paused() {
+                    if let Some(last) = self.last_frame {
+                        self.frame_history.push(start - last);
+                        // Stressing measures full resolution frames.
+                        if self.stress.is_some() {
+                            self.record_stress(start, start - last);
+                        } else if let Some(scale) = self.render_scale.record(start - last) {
+                            info!("Frame times changed, render scale now {}", scale);
+                        }
+                    }
+                    self.last_frame = Some(start);
+                }
+                let size = (width, height);
+                let varies = self.frame_varies();
+                let reused = match &mut self.scene_reuse {
+                    Some(reuse) => reuse.reuse(size, varies),
+                    None => false,
+                };
+                if reused {
+                    self.clock.tick();
+                    self.frame += 1;
+                } else {
+                    // Strokes cleared as this frame is drawn are gone from the next.
+                    let clearing = self.clear_each_frame && !self.sketch.encoding().is_empty();
+                    let build_start = Instant::now();
+                    self.build_scene(width, height);
+                    if let Some(reuse) = &mut self.scene_reuse {
+                        reuse.built(size, build_start.elapsed());
+                        if clearing {
+                            reuse.invalidate();
+                        }
+                    }
+                }
+                if let Some(reuse) = &self.scene_reuse {
+                    if self.verbose {
+                        let how = if reused { "reused" } else { "built" };
+                        debug!("Frame {}: scene {}", self.frame, how);
+                    }
+                    let summary = reuse.summary();
+                    if self.frame.is_multiple_of(PRESENT_LOG_INTERVAL) {
+                        info!(
+                            "Scenes built {} (mean {:.3} ms), reused {}, saving about {:.1} ms",
+                            summary.built, summary.mean_build_ms, summary.reused, summary.saved_ms
+                        );
+                    }
+                    stats::update(|s| s.scene_reuse = Some(summary));
+                }
+                let scene_size = self.scene_size();
+                if self.verbose {
+                    debug!("Frame {}: {:?}", self.frame, scene_size);
+                }
+                stats::update(|s| {
+                    s.frames = self.frame;
+                    s.scene = scene_size;
+                });
+
+                // Get a handle to the device
+                let dev_id = self.surface().dev_id;
+                let device_handle = &self.context.devices[dev_id];
+
+                // Get the surface's texture
+                let surface_texture = match self.surface().surface.get_current_texture() {
+                    Ok(texture) => texture,
+                    Err(e) => {
+                        error!("Failed to get surface texture, skipping frame: {}", e);
+                        return;
+                    }
+                };
+                if self.show_debug_layers {
+                    self.render_debug_frame(surface_texture, width, height);
+                    return;
+                }
+
+                // Capture validation errors raised while rendering this frame.
+                if self.capture_errors {
+                    device_handle
+                        .device
+                        .push_error_scope(wgpu::ErrorFilter::Validation);
+                }
+
+                // Render to the surface's texture, present it and poll the device
+                let aa = self.active_aa();
+                let scale = self.internal_scale();
+                let split_configs = self.split_configs();
+                let renderer = self.renderers[dev_id].as_mut().unwrap();
+                let params = vello::RenderParams {
+                    base_color: self.base_color,
+                    width,
+                    height,
+                    antialiasing_method: aa,
+                };
+                let device = &device_handle.device;
+                let queue = &device_handle.queue;
+                let passes = self.passes;
+                // The passes below draw through textures of their own,
+                // always at full resolution: the downscaler renders to the
+                // surface itself. Only the first that is on draws the frame.
+                let format = self.surface.as_ref().unwrap().format;
+                let poll = self.poll;
+                let scene = &self.scene;
+                let rendered = if self.preserve_previous {
+                    render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.preserve_pass,
+                        || PreservePass::new(device, format),
+                        |pass, view| pass.render(renderer, device, queue, scene, view, &params),
+                    )
+                } else if self.onion_skin {
+                    let rendered = render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.onion_pass,
+                        || OnionPass::new(device, format),
+                        |pass, view| pass.render(renderer, device, queue, scene, view, &params),
+                    );
+                    if let (Ok(_), Some(pass)) = (&rendered, &mut self.onion_pass) {
+                        pass.finish_frame();
+                    }
+                    rendered
+                } else if self.show_split {
+                    render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.split_pass,
+                        || SplitPass::new(device, format),
+                        |pass, view| {
+                            pass.render(
+                                renderer,
+                                device,
+                                queue,
+                                scene,
+                                view,
+                                &params,
+                                split_configs,
+                            )
+                        },
+                    )
+                } else if let Some(channel) = self.channel {
+                    render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.channel_pass,
+                        || ChannelPass::new(device, format),
+                        |pass, view| {
+                            pass.render(renderer, device, queue, scene, view, &params, channel)
+                        },
+                    )
+                } else if let Some(configs) = self.layer_aa {
+                    let layers = [&self.content_layer, &self.overlay_layer];
+                    render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.layer_pass,
+                        || LayerPass::new(device, format),
+                        |pass, view| {
+                            pass.render(renderer, device, queue, layers, view, &params, configs)
+                        },
+                    )
+                } else if let Some(change) = self.dirty_change {
+                    let (verbose, frame) = (self.verbose, self.frame);
+                    render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.dirty_pass,
+                        || DirtyPass::new(device, format),
+                        |pass, view| {
+                            let redrawn =
+                                pass.render(renderer, device, queue, scene, view, &params, change)?;
+                            if verbose {
+                                debug!("Frame {}: redrew {:?}", frame, redrawn);
+                            }
+                            Ok(())
+                        },
+                    )
+                } else if self.gamma != 1.0 {
+                    let gamma = self.gamma;
+                    render_offscreen(
+                        device,
+                        surface_texture,
+                        poll,
+                        passes,
+                        &mut self.gamma_pass,
+                        || GammaPass::new(device, format),
+                        |pass, view| {
+                            pass.render(renderer, device, queue, scene, view, &params, gamma)
+                        },
+                    )
+                } else if scale != 1.0 {
+                    let downscaler = &mut self.downscaler;
+                    frame::render_frame_with(device, surface_texture, poll, passes, |texture| {
+                        downscaler.render(renderer, device, queue, scene, texture, &params, scale)
+                    })
+                } else {
+                    frame::render_frame_with(device, surface_texture, poll, passes, |texture| {
+                        renderer.render_to_surface(device, queue, scene, texture, &params)
+                    })
+                };
+                if self.capture_errors {
+                    let scope = device_handle.device.pop_error_scope();
+                    let context = format!(
+                        "frame {}, size {}x{}, aa {:?}",
+                        self.frame, width, height, aa
+                    );
+                    wasm_bindgen_futures::spawn_local(async move {
+                        if let Some(e) = scope.await {
+                            error!("Validation error ({}): {}", context, e);
+                        }
+                    });
+                }
+                let times = match rendered {
+                    Ok(times) => times,
+                    Err(e) => {
+                        error!("Failed to render to surface, skipping frame: {}", e);
+                        return;
+                    }
+                };
+                let render_time = times.rendered - start;
+                info!("Render complete, time = {:2?}", render_time);
+                self.frames_presented += 1;
+                stats::update(|s| s.frames_presented = self.frames_presented);
+                if let Some(latency) = self.latency.presented(times.presented) {
+                    info!(
+                        "Input latency {:.2} ms (mean {:.2} ms, max {:.2} ms over {})",
+                        latency.last_ms, latency.mean_ms, latency.max_ms, latency.samples
+                    );
+                    stats::update(|s| s.input_latency = Some(latency));
+                }
+                let watchdog = self.watchdog.as_mut();
+                if let Some(stalled) = watchdog.and_then(|w| w.presented(times.presented)) {
+                    info!("Frames presented again after {:.1?}", stalled);
+                }
+                if let Some(pacer) = &mut self.pacer {
+                    pacer.presented(times.presented);
+                }
+                self.burst_presented(times.presented);
+                self.capture_frame();
+                config::set_current(self.client_config());
+                let present_time = times.presented - start;
+                info!("surface present, time = {:2?}", present_time);
+                let poll_time = times.polled - start;
+                match times.queue_empty {
+                    Some(queue_empty) => {
+                        info!(
+                            "After device poll ({:?}), time = {:2?}, work pending = {}",
+                            self.poll, poll_time, !queue_empty
+                        );
+                        self.frames_polled += 1;
+                        self.pending_after_poll += u64::from(!queue_empty);
+                        stats::update(|s| {
+                            s.frames_polled = self.frames_polled;
+                            s.pending_after_poll = self.pending_after_poll;
+                        });
+                    }
+                    None => info!("Device poll skipped, time = {:2?}", poll_time),
+                }
+                trace::record(FrameTiming {
+                    frame: self.frame,
+                    render_ms: render_time.as_secs_f64() * 1000.0,
+                    present_ms: present_time.as_secs_f64() * 1000.0,
+                    poll_ms: poll_time.as_secs_f64() * 1000.0,
+                });
+                call_present_callback(&PresentInfo {
+                    frame: self.frame,
+                    width,
+                    height,
+                    render_ms: render_time.as_secs_f64() * 1000.0,
+                    present_ms: present_time.as_secs_f64() * 1000.0,
+                    poll_ms: poll_time.as_secs_f64() * 1000.0,
+                });
+                let summary = self.benchmark.as_mut().and_then(|bench| {
+                    bench.record(
+                        render_time.as_secs_f64() * 1000.0,
+                        present_time.as_secs_f64() * 1000.0,
+                        poll_time.as_secs_f64() * 1000.0,
+                    )
+                });
+                if let Some(summary) = summary {
+                    info!(
+                        "Benchmark: {}",
+                        serde_json::to_string(&summary).unwrap_or_default()
+                    );
+                    stats::update(|s| s.benchmark = Some(summary));
+                    self.benchmark = None;
+                }
+                if self.frame_diff {
+                    self.diff_frame();
+                }
+                self.test_edge();
+                self.check_render_path();
+                if self.frames_presented == 1 {
+                    self.on_first_present(times.presented);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // Anything waiting for a deadline below sets it again each time.
+        event_loop.set_control_flow(ControlFlow::Wait);
+        if let Some(replay) = &mut self.replay {
+            for input in replay.due() {
+                self.handle_input(input);
+            }
+            self.input_received();
+        }
+        // Wake for the next replayed event, however idle the client is.
+        match self.replay.as_ref().map(Replay::next_due) {
+            Some(Some(next)) => event_loop.set_control_flow(ControlFlow::WaitUntil(next)),
+            Some(None) => {
+                info!("Replay finished");
+                self.replay = None;
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            None => {}
+        }
+        // Check for updates, redraw if necessary.
+        if !self.expects_frames() {
+            return;
+        }
+        if let Some(timeout) = self.idle_timeout {
+            if self.last_input.elapsed() >= timeout {
+                info!("No input for {:?}, entering idle", timeout);
+                self.idle = true;
+                return;
+            }
+        }
+        // Wait for the next frame's deadline, or the next replayed event if sooner.
+        // A burst draws as fast as it can, whatever the cap.
+        let pacer = self.pacer.as_ref().filter(|_| self.burst.is_none());
+        if let Some(deadline) = pacer.and_then(|p| p.waiting(Instant::now())) {
+            let wake = match event_loop.control_flow() {
+                ControlFlow::WaitUntil(next) => next.min(deadline),
+                _ => deadline,
+            };
+            event_loop.set_control_flow(ControlFlow::WaitUntil(wake));
+            return;
+        }
+        self.advance_timestep();
+        self.window.request_redraw();
+    }
+}
+
+/// Render a frame with `pass`, built by `new` the first time, as
+/// [`frame::render_frame_with`] does: `render` renders the scene at full
+/// resolution into the pass's own textures, then draws them into the view
+/// of the surface texture it is given.
+fn render_offscreen<P>(
+    device: &wgpu::Device,
+    surface_texture: wgpu::SurfaceTexture,
+    poll: PollStrategy,
+    passes: u32,
+    pass: &mut Option<P>,
+    new: impl FnOnce() -> P,
+    mut render: impl FnMut(&mut P, &wgpu::TextureView) -> Result<(), vello::Error>,
+) -> Result<FrameTimes, vello::Error> {
+    let pass = pass.get_or_insert_with(new);
+    frame::render_frame_with(device, surface_texture, poll, passes, |texture| {
+        let view = texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        render(pass, &view)
+    })
+}
+
+/// Check that the surface's device exists. `create_surface` only returns a
+/// surface once it has a device for it, so this only fails if that changes.
+fn check_surface_device(
+    render_cx: &RenderContext,
+    surface: &RenderSurface<'_>,
+) -> Result<(), String> {
+    match render_cx.devices.len() {
+        0 => Err("no GPU device could be created for the surface".to_string()),
+        count if surface.dev_id >= count => Err(format!(
+            "the surface's device {} doesn't exist; only {} were created",
+            surface.dev_id, count
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn run(
+    event_loop: EventLoop<UserEvent>,
+    render_cx: RenderContext,
+    surface: RenderSurface<'_>,
+    window: Arc<Window>,
+    options: Options,
+    started: Instant,
+) {
+    if let Err(e) = check_surface_device(&render_cx, &surface) {
+        error!("Can't start the client: {}", e);
+        _ = dom::display_banner(
+            dom::Severity::Error,
+            &format!(
+                "Can't start the client: {}. See the console for details.",
+                dom::escape_html(&e.to_string())
+            ),
+        );
+        return;
+    }
+    // Fall back to a lower AA level if the adapter can't multisample the surface format.
+    let adapter = &render_cx.devices[surface.dev_id].adapter;
+    let aa_config = options
+        .aa_config
+        .map_or(AaConfig::Area, |aa| aa::select(adapter, surface.format, aa));
+    info!("Using AA config {:?}", aa_config);
+    diagnostics::set_versions(&VersionInfo::new(adapter));
+    diagnostics::set_capabilities(CapabilityReport::new(
+        adapter,
+        &render_cx.devices[surface.dev_id].device,
+        &surface.surface,
+        surface.format,
+    ));
+    let capabilities = surface.surface.get_capabilities(adapter);
+    let (present_modes, surface_formats) = (capabilities.present_modes, capabilities.formats);
+    // Only build the pipelines that can be selected: area, and the MSAA level if any.
+    // To compare them, build every supported config instead.
+    let mut aa_configs = if options.compare_aa {
+        [AaConfig::Area, AaConfig::Msaa8, AaConfig::Msaa16]
+            .into_iter()
+            .filter(|&aa| aa::is_supported(adapter, surface.format, aa))
+            .collect()
+    } else {
+        vec![AaConfig::Area, aa_config]
+    };
+    aa_configs.dedup();
+    // Screenshots render to an RGBA texture, so their AA is checked against that format.
+    let export_aa = options
+        .export_aa
+        .map(|aa| aa::select(adapter, wgpu::TextureFormat::Rgba8Unorm, aa));
+    if let Some(aa) = export_aa {
+        info!("Using AA config {:?} for screenshots", aa);
+        if !aa_configs.contains(&aa) {
+            aa_configs.push(aa);
+        }
+    }
+    // The split configs fall back like `?aa`, and are built too.
+    let split_aa = options
+        .split_aa
+        .map(|configs| configs.map(|aa| aa::select(adapter, surface.format, aa)));
+    for aa in split_aa.into_iter().flatten() {
+        if !aa_configs.contains(&aa) {
+            aa_configs.push(aa);
+        }
+    }
+    // And so do the layers'.
+    let layer_aa = options
+        .layer_aa
+        .map(|configs| configs.map(|aa| aa::select(adapter, surface.format, aa)));
+    if let Some([content, overlays]) = layer_aa {
+        info!("Layer AA: content {:?}, overlays {:?}", content, overlays);
+        for aa in [content, overlays] {
+            if !aa_configs.contains(&aa) {
+                aa_configs.push(aa);
+            }
+        }
+    }
+    let aa_support: AaSupport = aa_configs.iter().copied().collect();
+
+    let theme = window.theme().unwrap_or(Theme::Light);
+    let theme_palette = theme_palette(theme);
+    let stress = options
+        .stress
+        .map(|budget| Stress::new(budget, options.shapes));
+    // The stress run starts from at least one shape.
+    let shapes = stress.as_ref().map_or(options.shapes, Stress::shapes);
+    info!("Theme {:?}", theme);
+    match options.debug_layers {
+        Some(layers) if layers::AVAILABLE => info!("Vello debug layers: {:?}", layers),
+        Some(_) => warn!("Ignoring 'debug_layers': build with the debug_layers feature"),
+        None => {}
+    }
+    let base_color = if alpha::is_transparent(surface.config.alpha_mode) {
+        Color::TRANSPARENT
+    } else {
+        theme_palette.background
+    };
+
+    let renderers = {
+        let mut renderers = vec![];
+        renderers.resize_with(render_cx.devices.len(), || None);
+        let id = surface.dev_id;
+        let renderer = Renderer::new(
+            &render_cx.devices[id].device,
+            RendererOptions {
+                surface_format: Some(surface.format),
+                use_cpu: options.safe_mode,
+                antialiasing_support: aa_support,
+                // We currently initialise on one thread on WASM, but mark this here
+                // anyway
+                num_init_threads: NonZeroUsize::new(1),
+            },
+        );
+        let renderer = match renderer {
+            Ok(renderer) => renderer,
+            Err(e) if options.safe_mode => {
+                error!("Failed to create renderer in safe mode: {}", e);
+                dom::display_safe_mode_error("create the renderer", &e.to_string());
+                return;
+            }
+            Err(e) => panic!("Failed to create renderer: {}", e),
+        };
+        aa::log_pipelines(&render_cx.devices[id].adapter, surface.format, aa_support);
+        renderers[id] = Some(renderer);
+        renderers
+    };
+
+    // Text sizes use the scale factor at startup, like the initial surface size.
+    let scale_factor = window.scale_factor();
+    let mut app = VelloClient {
+        surface: Some(surface),
+        window,
+        context: render_cx,
+        renderers,
+        proxy: event_loop.create_proxy(),
+        use_cpu: options.safe_mode,
+        scene: Scene::new(),
+        content: Scene::new(),
+        view_transform: Affine::IDENTITY,
+        scroll: Vec2::ZERO,
+        aspect_lock: options.aspect_lock,
+        full_bleed: options.full_bleed,
+        letterbox_color: options.letterbox_color,
+        aa_config,
+        aa_configs,
+        export_aa,
+        compare_aa: options.compare_aa,
+        edge_profile: options.edge_profile,
+        edge_test: options.edge_test.map(EdgeTest::new),
+        reel: None,
+        reel_dwell: options.demo_reel.unwrap_or(reel::DEFAULT_DWELL),
+        reel_on_start: options.demo_reel.is_some(),
+        reels_started: 0,
+        render_path_device: None,
+        frame_diff: options.frame_diff,
+        benchmark: options
+            .benchmark
+            .map(|frames| Benchmark::new(options.warmup_frames, frames)),
+        stress,
+        reference: Rc::default(),
+        msaa_enabled: true,
+        verbose: options.verbose,
+        capture_errors: options.capture_errors,
+        frame: 0,
+        redraws_requested: 0,
+        frames_presented: 0,
+        latency: LatencyProbe::default(),
+        watchdog: options
+            .watchdog
+            .map(|timeout| Watchdog::new(timeout, Instant::now())),
+        poll: options.poll,
+        passes: options.passes,
+        frames_polled: 0,
+        pending_after_poll: 0,
+        started,
+        render_scale: RenderScale::default(),
+        downscaler: Downscaler::default(),
+        supersample: options.supersample,
+        gamma: options.gamma,
+        gamma_pass: None,
+        preserve_previous: options.preserve_previous,
+        preserve_pass: None,
+        onion_skin: false,
+        onion_pass: None,
+        show_split: split_aa.is_some(),
+        split_aa,
+        split_pass: None,
+        channel: options.channel,
+        channel_pass: None,
+        layer_aa,
+        layer_pass: None,
+        content_layer: Scene::new(),
+        overlay_layer: Scene::new(),
+        dirty_rect: options.dirty_rect,
+        scene_reuse: options.reuse_scene.then(SceneReuse::default),
+        dirty_pass: None,
+        dirty_change: None,
+        show_debug_layers: options.debug_layers.is_some() && layers::AVAILABLE,
+        debug_layers: options.debug_layers.unwrap_or(Layers::ALL),
+        debug_renderer: None,
+        debug_frame_pending: false,
+        redraw_skipped: false,
+        last_frame: None,
+        frame_history: FrameHistory::default(),
+        animate: options.animate,
+        idle_timeout: options.idle_timeout,
+        last_input: Instant::now(),
+        idle: false,
+        clock: PausableClock::new(match options.frame_step {
+            Some(step) => Box::new(ManualClock::new(step)),
+            None => Box::new(RealClock::new()),
+        }),
+        single_step: options.frame_step.or(options
+            .target_fps
+            .map(|fps| Duration::from_secs_f64(1.0 / fps))),
+        refresh: None,
+        timestep: options.timestep.map(FixedTimestep::new),
+        timestep_clock: Duration::ZERO,
+        pacer: options.target_fps.map(FramePacer::new),
+        burst: None,
+        burst_frames: options.vsync_burst.unwrap_or(burst::DEFAULT_FRAMES),
+        capture: None,
+        demo: options.demo,
+        demo_state: DemoState {
+            scale_factor,
+            stroke_color: theme_palette.stroke,
+            shapes,
+            ..DemoState::new(&options)
+        },
+        text: Text::new(),
+        debug_overlay: options.debug_overlay,
+        debug_tiles: options.debug_tiles,
+        debug_outlines: options.debug_outlines,
+        show_grid: options.show_grid,
+        crosshairs: false,
+        inspector: None,
+        show_minimap: false,
+        mirror: options.mirror,
+        flip: options.flip,
+        flipped: Scene::new(),
+        grid: options.grid,
+        show_legend: false,
+        show_panel: options.control_panel,
+        panel: Panel::default(),
+        replay: None,
+        flipbook: None,
+        drawing: None,
+        present_modes,
+        surface_formats,
+        visible: true,
+        page_visible: dom::page_visible(),
+        size_settled: false,
+        resize_pending: None,
+        clear_each_frame: options.clear_each_frame,
+        base_color,
+        theme,
+        background: None,
+        reference_image: None,
+        background_fit: options.background_fit,
+        current_stroke: Vec::new(),
+        strokes: Vec::new(),
+        sketch: Scene::new(),
+        stroke_bounds: Bounds::default(),
+        cursor: None,
+        button_down: false,
+        modifiers: ModifiersState::empty(),
+    };
+
+    if options.vsync_burst.is_some() {
+        app.start_burst();
+    }
+    app.warn_overridden_passes();
+    app.schedule_watchdog();
+    app.measure_refresh_rate();
+    app.watch_visibility();
+    event_loop.run_app(&mut app).expect("run to completion");
+}
@@ -0,0 +1,360 @@
+//! Time sources for animation.
+//!
+//! The client reads animation time through the [`Clock`] trait so that
+//! a [`ManualClock`] can stand in for the real one, making frame N render
+//! at a known time regardless of how long frames actually take.
+//!
+//! [`FixedTimestep`] decouples the animation from the display rate: the
+//! animation state advances in fixed steps, and frames are drawn at a time
+//! interpolated between the last two steps.
+//!
+//! [`FramePacer`] caps the frame rate, giving the deadline before which the
+//! next frame shouldn't be drawn.
+//!
+//! [`RefreshRate`] is the display's refresh rate, measured from the times
+//! of the browser's animation frames.
+//!
+//! [`PausableClock`] stops the time of another clock while paused, and
+//! advances it by hand a frame at a time, for stepping through an animation.
+
+use std::time::Duration;
+
+use serde::Serialize;
+use web_time::Instant;
+
+pub trait Clock {
+    /// Animation time elapsed since the clock started.
+    fn elapsed(&self) -> Duration;
+
+    /// Called once per rendered frame.
+    fn tick(&mut self) {}
+}
+
+/// Wall clock time.
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn new() -> Self {
+        RealClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A virtual clock that advances by a fixed step every frame.
+pub struct ManualClock {
+    now: Duration,
+    step: Duration,
+}
+
+impl ManualClock {
+    pub fn new(step: Duration) -> Self {
+        ManualClock {
+            now: Duration::ZERO,
+            step,
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    fn elapsed(&self) -> Duration {
+        self.now
+    }
+
+    fn tick(&mut self) {
+        self.now += self.step;
+    }
+}
+
+/// Another clock's time, which stands still while paused except when stepped.
+pub struct PausableClock {
+    inner: Box<dyn Clock>,
+    /// The time shown when `inner` last started running.
+    base: Duration,
+    /// The time of `inner` then, or `None` while paused.
+    resumed_at: Option<Duration>,
+}
+
+impl PausableClock {
+    pub fn new(inner: Box<dyn Clock>) -> Self {
+        let resumed_at = Some(inner.elapsed());
+        PausableClock {
+            inner,
+            base: Duration::ZERO,
+            resumed_at,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.resumed_at.is_none()
+    }
+
+    /// Stop the time where it is, or start it again from there.
+    pub fn set_paused(&mut self, paused: bool) {
+        if paused {
+            self.base = self.elapsed();
+            self.resumed_at = None;
+        } else if self.is_paused() {
+            self.resumed_at = Some(self.inner.elapsed());
+        }
+    }
+
+    /// Advance the time by `step`, if paused.
+    pub fn step(&mut self, step: Duration) {
+        if self.is_paused() {
+            self.base += step;
+        }
+    }
+}
+
+impl Clock for PausableClock {
+    fn elapsed(&self) -> Duration {
+        match self.resumed_at {
+            Some(at) => self.base + self.inner.elapsed().saturating_sub(at),
+            None => self.base,
+        }
+    }
+
+    fn tick(&mut self) {
+        self.inner.tick();
+    }
+}
+
+/// The most steps taken in one update. If updates fall further behind than
+/// this, e.g. after the tab was hidden, the remaining time is dropped.
+const MAX_STEPS: u32 = 10;
+
+/// A fixed timestep accumulator, holding the previous and current animation states.
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    previous: Duration,
+    current: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> Self {
+        FixedTimestep {
+            step,
+            accumulator: Duration::ZERO,
+            previous: Duration::ZERO,
+            current: Duration::ZERO,
+        }
+    }
+
+    /// Take as many whole steps as fit in `elapsed` plus the time left over from earlier updates.
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == MAX_STEPS {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.previous = self.current;
+            self.current += self.step;
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+    }
+
+    /// The animation time to draw: the previous state, interpolated towards the
+    /// current one by the fraction of a step left over.
+    pub fn interpolated(&self) -> Duration {
+        let alpha = self.accumulator.as_secs_f64() / self.step.as_secs_f64();
+        self.previous + (self.current - self.previous).mul_f64(alpha)
+    }
+}
+
+/// Deadlines for drawing frames at a capped rate.
+pub struct FramePacer {
+    interval: Duration,
+    next: Option<Instant>,
+}
+
+impl FramePacer {
+    pub fn new(fps: f64) -> Self {
+        FramePacer {
+            interval: Duration::from_secs_f64(1.0 / fps),
+            next: None,
+        }
+    }
+
+    /// The frame rate being capped to.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn fps(&self) -> f64 {
+        1.0 / self.interval.as_secs_f64()
+    }
+
+    /// The deadline of the next frame, if it is still to come at `now`.
+    pub fn waiting(&self, now: Instant) -> Option<Instant> {
+        self.next.filter(|&next| now < next)
+    }
+
+    /// Schedule the next frame, after one presented at `presented`. Frames
+    /// on time keep to the cadence of the deadlines, even if each is a
+    /// little late. If a whole interval was missed, because rendering can't
+    /// keep up, the next deadline is an interval after this frame instead,
+    /// so that the lag doesn't build up into a burst of catch-up frames.
+    pub fn presented(&mut self, presented: Instant) {
+        let next = match self.next {
+            Some(next) if presented < next + self.interval => next + self.interval,
+            _ => presented + self.interval,
+        };
+        self.next = Some(next);
+    }
+}
+
+/// The refresh rate of the display, in Hz. On a variable refresh display,
+/// frames come at a range of rates rather than one.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RefreshRate {
+    /// The rate of the median interval between frames.
+    pub hz: f64,
+    /// The rates of the 90th and 10th percentile intervals, ignoring the
+    /// outliers of dropped and doubled frames.
+    pub min_hz: f64,
+    pub max_hz: f64,
+    /// Whether the range is wider than jitter at one rate would make it.
+    pub variable: bool,
+}
+
+impl RefreshRate {
+    /// The fewest intervals that give a rate worth reporting.
+    const MIN_INTERVALS: usize = 10;
+
+    /// The rate from a run of animation frame timestamps, in milliseconds,
+    /// or `None` if there are too few of them.
+    pub fn from_frame_times(times: &[f64]) -> Option<Self> {
+        let mut intervals: Vec<f64> = times.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        if intervals.len() < Self::MIN_INTERVALS {
+            return None;
+        }
+        intervals.sort_by(f64::total_cmp);
+        let hz = |fraction: f64| {
+            let index = ((intervals.len() - 1) as f64 * fraction).round() as usize;
+            1000.0 / intervals[index]
+        };
+        let (min_hz, max_hz) = (hz(0.9), hz(0.1));
+        Some(RefreshRate {
+            hz: hz(0.5),
+            min_hz,
+            max_hz,
+            variable: max_hz > min_hz * 1.1,
+        })
+    }
+
+    /// The time of one frame.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn frame(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.hz)
+    }
+
+    /// Whether frames at `fps` can be drawn every so many refreshes, evenly.
+    pub fn divides(&self, fps: f64) -> bool {
+        let ratio = self.hz / fps;
+        ratio >= 0.95 && (ratio - ratio.round()).abs() < 0.05
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_timestep_interpolates() {
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10));
+        timestep.advance(Duration::from_millis(25));
+        // Two whole steps, with half a step left over.
+        assert_eq!(timestep.interpolated(), Duration::from_millis(15));
+        timestep.advance(Duration::from_millis(5));
+        assert_eq!(timestep.interpolated(), Duration::from_millis(20));
+        // Falling far behind drops the excess.
+        timestep.advance(Duration::from_secs(10));
+        assert_eq!(timestep.interpolated(), Duration::from_millis(120));
+    }
+
+    #[test]
+    fn frame_pacer_keeps_cadence_without_lag() {
+        let mut pacer = FramePacer::new(50.0);
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        assert_eq!(pacer.waiting(start), None);
+        pacer.presented(start);
+        assert_eq!(pacer.waiting(start), Some(start + ms(20)));
+        assert_eq!(pacer.waiting(start + ms(20)), None);
+        // A frame a little late keeps to the cadence.
+        pacer.presented(start + ms(23));
+        assert_eq!(pacer.waiting(start + ms(23)), Some(start + ms(40)));
+        // One too slow to keep up starts again from when it was presented.
+        pacer.presented(start + ms(95));
+        assert_eq!(pacer.waiting(start + ms(95)), Some(start + ms(115)));
+    }
+
+    #[test]
+    fn refresh_rates_are_measured() {
+        // 60Hz, with jitter and one dropped frame.
+        let mut times = vec![0.0];
+        for i in 1..=60 {
+            let jitter = if i % 2 == 0 { 0.3 } else { -0.3 };
+            let dropped = if i == 30 { 16.667 } else { 0.0 };
+            times.push(times[i - 1] + 16.667 + jitter + dropped);
+        }
+        let rate = RefreshRate::from_frame_times(&times).unwrap();
+        assert!((rate.hz - 60.0).abs() < 1.5, "{:?}", rate);
+        assert!(!rate.variable);
+        assert!(rate.divides(30.0) && rate.divides(60.0) && !rate.divides(45.0));
+        // Frames anywhere from 48Hz to 120Hz.
+        let mut times = vec![0.0];
+        for i in 0..60 {
+            times.push(times[i] + [8.333, 13.333, 20.833][i % 3]);
+        }
+        assert!(RefreshRate::from_frame_times(&times).unwrap().variable);
+        assert_eq!(RefreshRate::from_frame_times(&times[..5]), None);
+    }
+
+    #[test]
+    fn manual_clock_steps_per_tick() {
+        let mut clock = ManualClock::new(Duration::from_millis(16));
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+        for _ in 0..10 {
+            clock.tick();
+        }
+        assert_eq!(clock.elapsed(), Duration::from_millis(160));
+    }
+
+    #[test]
+    fn paused_clocks_only_move_when_stepped() {
+        let ms = Duration::from_millis;
+        let mut clock = PausableClock::new(Box::new(ManualClock::new(ms(10))));
+        clock.tick();
+        assert_eq!(clock.elapsed(), ms(10));
+        clock.set_paused(true);
+        clock.tick();
+        assert_eq!(clock.elapsed(), ms(10));
+        clock.step(ms(16));
+        clock.tick();
+        assert_eq!(clock.elapsed(), ms(26));
+        // Resuming carries on from the stepped time, without the time spent paused.
+        clock.set_paused(false);
+        clock.tick();
+        assert_eq!(clock.elapsed(), ms(36));
+        clock.step(ms(16));
+        assert_eq!(clock.elapsed(), ms(36));
+    }
+}
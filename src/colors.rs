@@ -0,0 +1,67 @@
+//! Colours given in an explicit colour space.
+//!
+//! Vello's `Color` is sRGB, and it blends in that space. Colours from a
+//! specification are often given in linear sRGB, or in Display P3 with its
+//! wider gamut, and using those components directly as sRGB gives the wrong
+//! colour. [`InputColor`] tags the components with their space, and converts
+//! them to the sRGB colour Vello needs.
+
+use vello::peniko::color::{AlphaColor, DisplayP3, LinearSrgb, Srgb};
+use vello::peniko::Color;
+
+/// A colour with its components tagged by colour space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputColor {
+    /// sRGB encoded 8-bit components, e.g. from CSS hex colours.
+    Srgb8([u8; 4]),
+    /// Linear light sRGB components, from 0 to 1, with alpha.
+    Linear([f32; 4]),
+    /// Display P3 encoded components, from 0 to 1, with alpha.
+    DisplayP3([f32; 4]),
+}
+
+impl InputColor {
+    /// The colour in sRGB, for drawing with Vello. Colours outside the sRGB
+    /// gamut, e.g. saturated P3 colours, are clipped to it.
+    pub fn to_color(self) -> Color {
+        let color = match self {
+            InputColor::Srgb8([r, g, b, a]) => return Color::from_rgba8(r, g, b, a),
+            InputColor::Linear(components) => {
+                AlphaColor::<LinearSrgb>::new(components).convert::<Srgb>()
+            }
+            InputColor::DisplayP3(components) => {
+                AlphaColor::<DisplayP3>::new(components).convert::<Srgb>()
+            }
+        };
+        color.map(|r, g, b, a| [r, g, b, a].map(|c| c.clamp(0.0, 1.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near(color: Color, expected: [f32; 4]) -> bool {
+        color
+            .components
+            .iter()
+            .zip(expected)
+            .all(|(c, e)| (c - e).abs() < 1e-3)
+    }
+
+    #[test]
+    fn conversions() {
+        let red = InputColor::Srgb8([255, 0, 0, 255]).to_color();
+        assert!(near(red, [1.0, 0.0, 0.0, 1.0]));
+        // Linear mid grey is lighter than sRGB mid grey.
+        let grey = InputColor::Linear([0.5, 0.5, 0.5, 1.0]).to_color();
+        assert!(near(grey, [0.7354, 0.7354, 0.7354, 1.0]));
+        assert!(near(
+            InputColor::DisplayP3([1.0, 1.0, 1.0, 0.5]).to_color(),
+            [1.0, 1.0, 1.0, 0.5]
+        ));
+        // P3 red is outside the sRGB gamut, so it is clipped.
+        let p3_red = InputColor::DisplayP3([1.0, 0.0, 0.0, 1.0]).to_color();
+        assert!(near(p3_red, [1.0, 0.0, 0.0, 1.0]));
+    }
+}
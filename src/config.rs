@@ -0,0 +1,149 @@
+//! The client's state, as a string to share and restore.
+//!
+//! The URL's options only say how the client started; by the time someone
+//! sees a problem they may have zoomed, switched AA mode, scene and present
+//! mode, and reseeded the random demo. A [`ClientConfig`] holds that state,
+//! and is shared as JSON in URL-safe base64, so that it can be pasted into
+//! an issue, or a URL, without escaping. The client keeps the config of the
+//! last frame presented, encoded only when `save_config()` asks for it, and
+//! `restore_config()` puts it back.
+
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+
+use vello::wgpu;
+
+/// The state shared.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClientConfig {
+    /// The AA mode, as `?aa` names it.
+    pub aa: String,
+    /// The demo, as `?scene` names it.
+    pub demo: String,
+    /// The coefficients of the view transform.
+    pub transform: [f64; 6],
+    pub scroll: [f64; 2],
+    pub base_color: [u8; 4],
+    pub stroke_color: [u8; 4],
+    pub present_mode: wgpu::PresentMode,
+    /// The shape count and seed of the random demo.
+    pub shapes: u32,
+    pub seed: u32,
+    pub margin: f64,
+}
+
+impl ClientConfig {
+    /// The config as a string to share.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        encode_base64(json.as_bytes())
+    }
+
+    /// The config a string from [`encode`](Self::encode) holds.
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let json = decode_base64(text.trim())?;
+        serde_json::from_slice(&json).map_err(|e| format!("not a client config: {}", e))
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<ClientConfig>> = const { RefCell::new(None) };
+}
+
+/// Keep `config` as the client's current state.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn set_current(config: ClientConfig) {
+    CURRENT.set(Some(config));
+}
+
+/// The client's current state as a string to share, once a frame has been
+/// presented.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn current() -> Option<String> {
+    CURRENT.with_borrow(|config| config.as_ref().map(ClientConfig::encode))
+}
+
+/// The URL-safe base64 alphabet.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// `bytes` in URL-safe base64, without padding.
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | ((b as u32) << (16 - 8 * i)));
+        // Each byte takes up a character and a bit more.
+        for i in 0..=chunk.len() {
+            text.push(ALPHABET[((bits >> (18 - 6 * i)) & 63) as usize] as char);
+        }
+    }
+    text
+}
+
+/// The bytes URL-safe base64 `text` holds, with or without padding.
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let text = text.trim_end_matches('=').as_bytes();
+    if text.len() % 4 == 1 {
+        return Err("not base64: a character too many".to_string());
+    }
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let Some(value) = ALPHABET.iter().position(|&a| a == c) else {
+                return Err(format!("not base64: '{}'", c as char));
+            };
+            bits |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trips() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg");
+        assert_eq!(encode_base64(b"fo"), "Zm8");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(&[0xfb, 0xff]), "-_8");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        for len in 0..8 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 97 + 13) as u8).collect();
+            assert_eq!(decode_base64(&encode_base64(&bytes)).unwrap(), bytes);
+        }
+        assert!(decode_base64("Zm9vY").is_err());
+        assert!(decode_base64("Zm9+").is_err());
+    }
+
+    #[test]
+    fn configs_round_trip() {
+        let config = ClientConfig {
+            aa: "msaa16".to_string(),
+            demo: "random".to_string(),
+            transform: [2.0, 0.0, 0.0, 2.0, -10.5, 4.0],
+            scroll: [0.0, 120.0],
+            base_color: [0, 0, 0, 255],
+            stroke_color: [255, 255, 255, 255],
+            present_mode: wgpu::PresentMode::Fifo,
+            shapes: 250,
+            seed: 7,
+            margin: 10.0,
+        };
+        let text = config.encode();
+        assert!(text.bytes().all(|c| ALPHABET.contains(&c)), "{}", text);
+        assert_eq!(ClientConfig::decode(&text), Ok(config));
+        assert!(ClientConfig::decode("e30")
+            .unwrap_err()
+            .starts_with("not a client config"));
+    }
+}
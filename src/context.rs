@@ -0,0 +1,435 @@
+//! GPU context management.
+//!
+//! This mirrors `vello::util::RenderContext`, but allows the adapter
+//! request options to be chosen by the caller rather than the environment.
+
+use std::fmt;
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use log::{info, warn};
+
+use serde::Serialize;
+
+use vello::util::RenderSurface;
+use vello::wgpu;
+
+use crate::limits::LimitsRequest;
+use crate::surface::{self, SurfaceOptions};
+
+/// Why no device could be found or created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceError {
+    /// No adapter was returned at all. With WebGPU available, this usually
+    /// means the browser doesn't support or has blocklisted the GPU.
+    NoAdapter,
+    /// An adapter was found, but creating a device on it failed.
+    NoDevice,
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceError::NoAdapter => write!(f, "no compatible GPU adapter is available"),
+            DeviceError::NoDevice => write!(f, "failed to create a device on the GPU adapter"),
+        }
+    }
+}
+
+impl std::error::Error for DeviceError {}
+
+#[derive(Debug)]
+pub struct DeviceHandle {
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+pub struct RenderContext {
+    /// Shared so that adapters can be requested from futures outliving a borrow of the context.
+    pub instance: Rc<wgpu::Instance>,
+    /// Shared so that a frame rendered in a future can keep its device.
+    pub devices: Vec<Rc<DeviceHandle>>,
+    pub power_preference: wgpu::PowerPreference,
+    /// Features requested for new devices, if the adapter supports them.
+    pub optional_features: wgpu::Features,
+    /// The limits requested for new devices, clamped to the adapter's.
+    pub limits: LimitsRequest,
+    /// A directory to record a wgpu API trace of new devices into, for
+    /// replaying offline. Native only: browsers have no trace to record.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub trace_path: Option<PathBuf>,
+}
+
+/// Parse a backend name, for forcing the instance to use only that backend.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn parse_backend(name: &str) -> Result<wgpu::Backends, String> {
+    match name {
+        "vulkan" => Ok(wgpu::Backends::VULKAN),
+        "metal" => Ok(wgpu::Backends::METAL),
+        "dx12" => Ok(wgpu::Backends::DX12),
+        "gl" => Ok(wgpu::Backends::GL),
+        "webgpu" => Ok(wgpu::Backends::BROWSER_WEBGPU),
+        _ => Err(format!("unknown backend '{}'", name)),
+    }
+}
+
+impl RenderContext {
+    pub fn new(power_preference: wgpu::PowerPreference) -> Self {
+        Self::with_backends(power_preference, None)
+    }
+
+    /// Create a context whose instance only uses `backends`, if given, to
+    /// compare rendering across backends. Otherwise the backends are taken
+    /// from the `WGPU_BACKEND` environment variable natively, or are all
+    /// those available. A backend that this build doesn't include for the
+    /// platform, e.g. Metal on Linux, falls back to all of them, with a warning.
+    pub fn with_backends(
+        power_preference: wgpu::PowerPreference,
+        backends: Option<wgpu::Backends>,
+    ) -> Self {
+        let available = wgpu::Instance::enabled_backend_features();
+        let backends = match backends {
+            Some(backends) if available.intersects(backends) => {
+                info!("Using only the {:?} backend", backends);
+                backends
+            }
+            Some(backends) => {
+                warn!(
+                    "The {:?} backend is not available (available: {:?}), using all backends",
+                    backends, available
+                );
+                wgpu::Backends::all()
+            }
+            None => wgpu::util::backend_bits_from_env().unwrap_or_default(),
+        };
+        let instance = Rc::new(wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            flags: wgpu::InstanceFlags::from_build_config().with_env(),
+            dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
+            gles_minor_version: wgpu::util::gles_minor_version_from_env().unwrap_or_default(),
+        }));
+        RenderContext {
+            instance,
+            devices: Vec::new(),
+            power_preference,
+            optional_features: wgpu::Features::CLEAR_TEXTURE,
+            limits: LimitsRequest::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            trace_path: None,
+        }
+    }
+
+    /// Where new devices record a wgpu trace, if anywhere.
+    fn trace_path(&self) -> Option<&Path> {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.trace_path.as_deref();
+        #[cfg(target_arch = "wasm32")]
+        None
+    }
+
+    /// Creates a new surface for the specified window and dimensions,
+    /// configured as `options` requests. Fails if the surface doesn't support
+    /// any of the requested options.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub async fn create_surface<'w>(
+        &mut self,
+        window: impl Into<wgpu::SurfaceTarget<'w>>,
+        width: u32,
+        height: u32,
+        options: &SurfaceOptions,
+    ) -> Result<RenderSurface<'w>, Box<dyn std::error::Error>> {
+        let surface = self.instance.create_surface(window.into())?;
+        let dev_id = self.find_device(Some(&surface)).await?;
+
+        let (width, height) = self.fit_surface_size(dev_id, width, height);
+        let adapter = &self.devices[dev_id].adapter;
+        let capabilities = surface.get_capabilities(adapter);
+        let config = surface::configure(
+            adapter.get_info().backend,
+            &capabilities,
+            options,
+            width,
+            height,
+        )?;
+        info!("Surface configuration: {:?}", config);
+        let surface = RenderSurface {
+            surface,
+            format: config.format,
+            config,
+            dev_id,
+        };
+        self.configure_surface(&surface);
+        Ok(surface)
+    }
+
+    /// The size a surface on the device is configured with for a requested
+    /// size, clamped to the device's largest texture dimension. A larger
+    /// surface couldn't be created, which can happen with a large window at
+    /// a high scale factor. Clamping keeps the aspect ratio, and on the web
+    /// the canvas is then scaled up to its CSS size.
+    pub fn fit_surface_size(&self, dev_id: usize, width: u32, height: u32) -> (u32, u32) {
+        let max = self.max_surface_dimension(dev_id);
+        let fitted = surface::clamp_size(width, height, max);
+        if fitted != (width, height) {
+            warn!(
+                "Surface size {} x {} exceeds the maximum texture dimension {}, clamping to {} x {}",
+                width, height, max, fitted.0, fitted.1
+            );
+        }
+        fitted
+    }
+
+    /// The largest width or height of a surface on the device.
+    pub fn max_surface_dimension(&self, dev_id: usize) -> u32 {
+        self.devices[dev_id]
+            .device
+            .limits()
+            .max_texture_dimension_2d
+    }
+
+    /// Resizes the surface to the new dimensions, clamped as `fit_surface_size` says.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn resize_surface(&self, surface: &mut RenderSurface<'_>, width: u32, height: u32) {
+        let (width, height) = self.fit_surface_size(surface.dev_id, width, height);
+        surface.config.width = width;
+        surface.config.height = height;
+        self.configure_surface(surface);
+    }
+
+    fn configure_surface(&self, surface: &RenderSurface<'_>) {
+        let device = &self.devices[surface.dev_id].device;
+        surface.surface.configure(device, &surface.config);
+    }
+
+    /// Finds or creates a compatible device handle id.
+    pub async fn device(
+        &mut self,
+        compatible_surface: Option<&wgpu::Surface<'_>>,
+    ) -> Option<usize> {
+        self.find_device(compatible_surface).await.ok()
+    }
+
+    /// As [`RenderContext::device`], saying why if there is none.
+    async fn find_device(
+        &mut self,
+        compatible_surface: Option<&wgpu::Surface<'_>>,
+    ) -> Result<usize, DeviceError> {
+        let compatible = match compatible_surface {
+            Some(s) => self
+                .devices
+                .iter()
+                .position(|d| d.adapter.is_surface_supported(s)),
+            None => (!self.devices.is_empty()).then_some(0),
+        };
+        match compatible {
+            Some(dev_id) => Ok(dev_id),
+            None => self.new_device(compatible_surface).await,
+        }
+    }
+
+    /// Requests an adapter using the configured power preference, and creates a device on it.
+    async fn new_device(
+        &mut self,
+        compatible_surface: Option<&wgpu::Surface<'_>>,
+    ) -> Result<usize, DeviceError> {
+        let adapter = self
+            .instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: self.power_preference,
+                force_fallback_adapter: false,
+                compatible_surface,
+            })
+            .await
+            .ok_or(DeviceError::NoAdapter)?;
+        let info = adapter.get_info();
+        info!(
+            "Selected adapter '{}' ({:?}, {:?}) for power preference {:?}",
+            info.name, info.device_type, info.backend, self.power_preference
+        );
+        let handle = create_device(
+            adapter,
+            self.optional_features,
+            self.limits,
+            self.trace_path(),
+        )
+        .await
+        .ok_or(DeviceError::NoDevice)?;
+        Ok(self.add_device(handle))
+    }
+
+    /// Add a device created elsewhere, returning its id.
+    pub fn add_device(&mut self, handle: DeviceHandle) -> usize {
+        self.devices.push(Rc::new(handle));
+        self.devices.len() - 1
+    }
+}
+
+/// How to request an adapter again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdapterRequest {
+    pub power_preference: wgpu::PowerPreference,
+    pub force_fallback_adapter: bool,
+}
+
+/// WebGPU can't enumerate adapters, only request one for a power preference,
+/// so every adapter is found by making each of these requests in turn.
+const ADAPTER_REQUESTS: [AdapterRequest; 4] = [
+    AdapterRequest {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter: false,
+    },
+    AdapterRequest {
+        power_preference: wgpu::PowerPreference::LowPower,
+        force_fallback_adapter: false,
+    },
+    AdapterRequest {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter: true,
+    },
+    AdapterRequest {
+        power_preference: wgpu::PowerPreference::LowPower,
+        force_fallback_adapter: true,
+    },
+];
+
+/// One of the adapters found by [`list_adapters`].
+#[derive(Debug, Serialize)]
+pub struct AdapterListing {
+    pub index: usize,
+    pub name: String,
+    pub backend: String,
+    pub device_type: String,
+    pub driver: String,
+    #[serde(skip)]
+    pub request: AdapterRequest,
+}
+
+/// List the distinct adapters the instance can provide. They are always
+/// found in the same order, so an index into the list names the same
+/// adapter each time it is made.
+pub async fn list_adapters(instance: &wgpu::Instance) -> Vec<(AdapterListing, wgpu::Adapter)> {
+    let mut adapters: Vec<(AdapterListing, wgpu::Adapter)> = Vec::new();
+    for request in ADAPTER_REQUESTS {
+        let Some(adapter) = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: request.power_preference,
+                force_fallback_adapter: request.force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+        else {
+            continue;
+        };
+        let info = adapter.get_info();
+        let seen = adapters
+            .iter()
+            .any(|(_, adapter)| same_adapter(&adapter.get_info(), &info));
+        if seen {
+            continue;
+        }
+        let listing = AdapterListing {
+            index: adapters.len(),
+            name: info.name,
+            backend: format!("{:?}", info.backend),
+            device_type: format!("{:?}", info.device_type),
+            driver: info.driver,
+            request,
+        };
+        adapters.push((listing, adapter));
+    }
+    adapters
+}
+
+/// Whether two adapter infos describe the same adapter.
+fn same_adapter(a: &wgpu::AdapterInfo, b: &wgpu::AdapterInfo) -> bool {
+    a.name == b.name && a.backend == b.backend && a.device == b.device
+}
+
+/// Create a new device on the adapter described by `info`, found again in
+/// the [`list_adapters`] list, e.g. to replace a device that has stopped working.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub async fn reopen_adapter(
+    instance: &wgpu::Instance,
+    info: &wgpu::AdapterInfo,
+    optional_features: wgpu::Features,
+    limits: LimitsRequest,
+) -> Result<DeviceHandle, String> {
+    let index = list_adapters(instance)
+        .await
+        .iter()
+        .position(|(_, adapter)| same_adapter(&adapter.get_info(), info))
+        .ok_or_else(|| format!("adapter '{}' is no longer available", info.name))?;
+    open_listed_adapter(instance, index, optional_features, limits).await
+}
+
+/// Create a device on the adapter at `index` in the [`list_adapters`] list.
+pub async fn open_listed_adapter(
+    instance: &wgpu::Instance,
+    index: usize,
+    optional_features: wgpu::Features,
+    limits: LimitsRequest,
+) -> Result<DeviceHandle, String> {
+    let (listing, adapter) = list_adapters(instance)
+        .await
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("no adapter {}", index))?;
+    info!(
+        "Opening adapter {} '{}' ({}, {}) from {:?}",
+        index, listing.name, listing.device_type, listing.backend, listing.request
+    );
+    create_device(adapter, optional_features, limits, None)
+        .await
+        .ok_or_else(|| format!("failed to create a device on '{}'", listing.name))
+}
+
+/// Create a device on the adapter with the `limits` requested, as far as
+/// it supports them, and with the optional features it supports, recording a trace of it into
+/// `trace_path` if given.
+///
+/// wgpu 23 has temporarily removed tracing
+/// (<https://github.com/gfx-rs/wgpu/issues/5974>), and only logs an error
+/// when given a trace path, so nothing is recorded until it returns.
+async fn create_device(
+    adapter: wgpu::Adapter,
+    optional_features: wgpu::Features,
+    limits: LimitsRequest,
+    trace_path: Option<&Path>,
+) -> Option<DeviceHandle> {
+    let features = adapter.features();
+    // WebGPU has no pipeline statistics, but native adapters may support them.
+    let pipeline_statistics = features.contains(wgpu::Features::PIPELINE_STATISTICS_QUERY);
+    info!(
+        "Pipeline statistics queries supported: {}",
+        pipeline_statistics
+    );
+    let supported = adapter.limits();
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: features & optional_features,
+                required_limits: limits.limits(&supported),
+                memory_hints: Default::default(),
+            },
+            trace_path,
+        )
+        .await
+        .ok()?;
+    limits.log(&supported, &device.limits());
+    if crate::messages::is_enabled() {
+        crate::messages::log_uncaptured_errors(&device);
+    }
+    if let Some(path) = trace_path {
+        info!("Recording a wgpu trace into {}", path.display());
+    }
+    crate::stats::update(|s| s.pipeline_statistics = pipeline_statistics);
+    Some(DeviceHandle {
+        adapter,
+        device,
+        queue,
+    })
+}
@@ -0,0 +1,143 @@
+//! Reports on the GPU and surface capabilities, for gathering data
+//! about rendering problems across browsers and devices.
+
+use std::cell::RefCell;
+
+use log::info;
+use serde::Serialize;
+
+use vello::wgpu;
+
+use crate::fallback::Detection;
+
+/// Features of one texture format, as supported by the adapter.
+#[derive(Serialize)]
+pub struct FormatFeatures {
+    pub format: wgpu::TextureFormat,
+    pub sample_counts: Vec<u32>,
+    pub features: wgpu::TextureFormatFeatures,
+}
+
+/// The capabilities of the selected adapter, device and surface.
+#[derive(Serialize)]
+pub struct CapabilityReport {
+    pub adapter: wgpu::AdapterInfo,
+    pub surface_format: wgpu::TextureFormat,
+    pub formats: Vec<wgpu::TextureFormat>,
+    pub present_modes: Vec<wgpu::PresentMode>,
+    pub alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    pub usages: wgpu::TextureUsages,
+    pub format_features: Vec<FormatFeatures>,
+    pub limits: wgpu::Limits,
+}
+
+impl CapabilityReport {
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn new(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        surface: &wgpu::Surface<'_>,
+        surface_format: wgpu::TextureFormat,
+    ) -> Self {
+        let caps = surface.get_capabilities(adapter);
+        let format_features = caps
+            .formats
+            .iter()
+            .map(|&format| {
+                let features = adapter.get_texture_format_features(format);
+                FormatFeatures {
+                    format,
+                    sample_counts: features.flags.supported_sample_counts(),
+                    features,
+                }
+            })
+            .collect();
+        CapabilityReport {
+            adapter: adapter.get_info(),
+            surface_format,
+            formats: caps.formats,
+            present_modes: caps.present_modes,
+            alpha_modes: caps.alpha_modes,
+            usages: caps.usages,
+            format_features,
+            limits: device.limits(),
+        }
+    }
+}
+
+/// The versions of this crate, Vello and wgpu, and of the adapter in use
+/// and its driver, for bug reports.
+#[derive(Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub vello: &'static str,
+    pub wgpu: &'static str,
+    pub adapter: wgpu::AdapterInfo,
+}
+
+impl VersionInfo {
+    pub fn new(adapter: &wgpu::Adapter) -> Self {
+        VersionInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            // Set by the build script from the lock file.
+            vello: env!("VELLO_VERSION"),
+            wgpu: env!("WGPU_VERSION"),
+            adapter: adapter.get_info(),
+        }
+    }
+}
+
+/// The capability report as given, with the render path found once a frame
+/// has been rendered.
+#[derive(Serialize)]
+struct Report<'a> {
+    #[serde(flatten)]
+    capabilities: &'a CapabilityReport,
+    render_path: Option<&'a Detection>,
+}
+
+thread_local! {
+    static CAPABILITIES: RefCell<Option<CapabilityReport>> = const { RefCell::new(None) };
+    static RENDER_PATH: RefCell<Option<Detection>> = const { RefCell::new(None) };
+    static VERSIONS: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Record the capability report, for later retrieval as JSON.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn set_capabilities(report: CapabilityReport) {
+    CAPABILITIES.set(Some(report));
+}
+
+/// Record the render path found, to add to the capability report.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn set_render_path(detection: &Detection) {
+    RENDER_PATH.set(Some(detection.clone()));
+}
+
+/// The capability report as a JSON string, if the client has started.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn capabilities_json() -> Option<String> {
+    CAPABILITIES.with_borrow(|capabilities| {
+        RENDER_PATH.with_borrow(|render_path| {
+            let report = Report {
+                capabilities: capabilities.as_ref()?,
+                render_path: render_path.as_ref(),
+            };
+            Some(serde_json::to_string(&report).unwrap_or_default())
+        })
+    })
+}
+
+/// Log the versions as a single line of JSON, and record them for later retrieval.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn set_versions(versions: &VersionInfo) {
+    let json = serde_json::to_string(versions).unwrap_or_default();
+    info!("Versions: {}", json);
+    VERSIONS.set(Some(json));
+}
+
+/// The versions as a JSON string, if the client has started.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn versions_json() -> Option<String> {
+    VERSIONS.with_borrow(|v| v.clone())
+}
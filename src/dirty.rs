@@ -0,0 +1,426 @@
+//! Redrawing only the part of the frame that changed, with `?dirty_rect`.
+//!
+//! Each frame the client takes a [`Fingerprint`] of its scene: a hash of
+//! the encoding with the freehand stroke in progress left out, and one of
+//! the stroke alone. When everything but the stroke hashes as it did last
+//! frame, only the area the stroke covers now or covered then can have
+//! changed, and [`DirtyPass`] renders just that rectangle. It translates
+//! the scene by whole pixels so that the rectangle's corner is at the
+//! origin and renders it into a target the rectangle's size, which clips
+//! it without a layer, so Vello never rasterizes the tiles outside it and
+//! its pixels come out as in a full render. When nothing changed at all,
+//! nothing is rendered.
+//!
+//! Vello's fine stage writes every pixel of its target, and a surface's
+//! textures are not kept from one frame to the next: with double or triple
+//! buffering the texture handed out for a frame holds one from two or
+//! three frames ago, or nothing, so drawing only the rectangle into it
+//! would show stale content around it. The pass instead keeps a canvas
+//! texture of the whole frame, copies the rectangle into it, and copies
+//! the whole canvas into each surface texture, which costs a full-screen
+//! blit but no rendering outside the rectangle. Any change it can't
+//! place, such as a resize, an animating demo or an overlay updating,
+//! redraws the whole frame, as does a new canvas.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use vello::kurbo::{Affine, Rect, Vec2};
+use vello::peniko::color::cache_key::BitHash;
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+use vello_encoding::{DrawTag, Patch, PathTag, StreamOffsets, Style, Transform};
+
+use crate::blit::{Blit, Source, Texture};
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
+"#;
+
+/// `offsets`, taken in a scene, once that scene is appended to one whose
+/// streams had got to `base`.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn offsets_after(offsets: StreamOffsets, base: StreamOffsets) -> StreamOffsets {
+    StreamOffsets {
+        path_tags: base.path_tags + offsets.path_tags,
+        path_data: base.path_data + offsets.path_data,
+        draw_tags: base.draw_tags + offsets.draw_tags,
+        draw_data: base.draw_data + offsets.draw_data,
+        transforms: base.transforms + offsets.transforms,
+        styles: base.styles + offsets.styles,
+    }
+}
+
+fn hash_transform(transform: &Transform, hasher: &mut DefaultHasher) {
+    for value in transform.matrix.iter().chain(&transform.translation) {
+        value.to_bits().hash(hasher);
+    }
+}
+
+/// Hash the items of `stream` with `hash`, into `part` for those in `range`
+/// and into `rest` for those before and after it.
+fn hash_stream<T>(
+    stream: &[T],
+    range: Range<usize>,
+    rest: &mut DefaultHasher,
+    part: &mut DefaultHasher,
+    hash: impl Fn(&[T], &mut DefaultHasher),
+) {
+    let end = range.end.min(stream.len());
+    let start = range.start.min(end);
+    hash(&stream[..start], rest);
+    hash(&stream[start..end], part);
+    hash(&stream[end..], rest);
+    (start, stream.len() - end).hash(rest);
+}
+
+/// Hash each item of a stream with `hash`.
+fn each<T>(hash: impl Fn(&T, &mut DefaultHasher)) -> impl Fn(&[T], &mut DefaultHasher) {
+    move |items, hasher| items.iter().for_each(|item| hash(item, hasher))
+}
+
+/// Hashes of a scene's encoding: of all of it but one part, and of that part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fingerprint {
+    rest: u64,
+    part: u64,
+}
+
+impl Fingerprint {
+    /// Hash `scene`, apart from the part between the offsets in `part`. The
+    /// part must not use images, gradients or text, whose resources are
+    /// all hashed with the rest.
+    pub fn of(scene: &Scene, part: Range<StreamOffsets>) -> Self {
+        let encoding = scene.encoding();
+        let (mut rest, mut inside) = (DefaultHasher::new(), DefaultHasher::new());
+        let (start, end) = (part.start, part.end);
+        let (r, p) = (&mut rest, &mut inside);
+        let tags = start.path_tags..end.path_tags;
+        hash_stream(
+            &encoding.path_tags,
+            tags,
+            r,
+            p,
+            each(|tag: &PathTag, h| tag.0.hash(h)),
+        );
+        let data = start.path_data..end.path_data;
+        hash_stream(&encoding.path_data, data, r, p, |bytes, h| h.write(bytes));
+        let tags = start.draw_tags..end.draw_tags;
+        hash_stream(
+            &encoding.draw_tags,
+            tags,
+            r,
+            p,
+            each(|tag: &DrawTag, h| tag.0.hash(h)),
+        );
+        let data = start.draw_data..end.draw_data;
+        hash_stream(&encoding.draw_data, data, r, p, |bytes, h| h.write(bytes));
+        let transforms = start.transforms..end.transforms;
+        hash_stream(&encoding.transforms, transforms, r, p, each(hash_transform));
+        let styles = start.styles..end.styles;
+        hash_stream(
+            &encoding.styles,
+            styles,
+            r,
+            p,
+            each(|style: &Style, h| {
+                (style.flags_and_miter_limit, style.line_width.to_bits()).hash(h);
+            }),
+        );
+        // The resources are found by index, or by offsets that move with the
+        // part's length, so only what they hold is hashed.
+        let resources = &encoding.resources;
+        for patch in &resources.patches {
+            match patch {
+                Patch::Ramp { stops, extend, .. } => {
+                    (0u8, stops.start, stops.end, *extend as u8).hash(r);
+                }
+                Patch::GlyphRun { index } => (1u8, index).hash(r),
+                Patch::Image { image, .. } => {
+                    (2u8, image.data.id(), image.width, image.height).hash(r);
+                }
+            }
+        }
+        resources.color_stops.bit_hash(r);
+        for glyph in &resources.glyphs {
+            (glyph.id, glyph.x.to_bits(), glyph.y.to_bits()).hash(r);
+        }
+        for run in &resources.glyph_runs {
+            (run.font.data.id(), run.font.index, run.font_size.to_bits()).hash(r);
+            (run.glyphs.start, run.glyphs.end, run.hint).hash(r);
+            hash_transform(&run.transform, r);
+            if let Some(transform) = &run.glyph_transform {
+                hash_transform(transform, r);
+            }
+        }
+        Fingerprint {
+            rest: rest.finish(),
+            part: inside.finish(),
+        }
+    }
+}
+
+/// A frame's scene, as far as telling what changed goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Change {
+    /// With the stroke in progress as its part.
+    pub fingerprint: Fingerprint,
+    /// The bounds of the stroke in progress on the surface, if there is one.
+    pub stroke: Option<Rect>,
+}
+
+/// What needs redrawing in a frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dirty {
+    Nothing,
+    /// A rectangle of whole pixels within the frame.
+    Rect(Rect),
+    All,
+}
+
+/// The last frame's change, to compare the next with.
+#[derive(Debug, Default)]
+struct Tracker {
+    last: Option<(Change, (u32, u32))>,
+}
+
+impl Tracker {
+    /// What needs redrawing in a frame of `size` with `change`.
+    fn update(&mut self, change: Change, size: (u32, u32)) -> Dirty {
+        let Some((last, last_size)) = self.last.replace((change, size)) else {
+            return Dirty::All;
+        };
+        if last_size != size || last.fingerprint.rest != change.fingerprint.rest {
+            return Dirty::All;
+        }
+        if last.fingerprint.part == change.fingerprint.part {
+            return Dirty::Nothing;
+        }
+        // What the stroke covered before and covers now, and the pixels
+        // its antialiasing touches.
+        let rect = match (last.stroke, change.stroke) {
+            (Some(a), Some(b)) => a.union(b),
+            (Some(rect), None) | (None, Some(rect)) => rect,
+            (None, None) => return Dirty::Nothing,
+        };
+        let frame = Rect::new(0.0, 0.0, size.0 as f64, size.1 as f64);
+        let rect = rect.inflate(1.0, 1.0).expand().intersect(frame);
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return Dirty::Nothing;
+        }
+        Dirty::Rect(rect)
+    }
+}
+
+/// The canvas the frame is kept in, and the scratch texture rectangles of
+/// it are rendered into.
+struct Targets {
+    canvas: Source,
+    scratch: Texture,
+    width: u32,
+    height: u32,
+}
+
+/// Renders the parts of scenes that changed since the last, into outputs
+/// of one format.
+pub struct DirtyPass {
+    blit: Blit,
+    pipeline: wgpu::RenderPipeline,
+    tracker: Tracker,
+    targets: Option<Targets>,
+}
+
+impl DirtyPass {
+    /// Build the pipeline, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let blit = Blit::new(device, "dirty", SHADER, 1, false);
+        let pipeline = blit.pipeline(device, "fs_main", format, None);
+        DirtyPass {
+            blit,
+            pipeline,
+            tracker: Tracker::default(),
+            targets: None,
+        }
+    }
+
+    /// The targets at the given size, and whether they are new, replacing
+    /// those of another size.
+    fn targets(&mut self, device: &wgpu::Device, width: u32, height: u32) -> (Targets, bool) {
+        if let Some(targets) = self.targets.take() {
+            if targets.width == width && targets.height == height {
+                return (targets, false);
+            }
+        }
+        let usage = wgpu::TextureUsages::STORAGE_BINDING;
+        let canvas = self.blit.source(
+            device,
+            "dirty canvas",
+            width,
+            height,
+            usage | wgpu::TextureUsages::COPY_DST,
+        );
+        let scratch = Texture::new(
+            device,
+            "dirty scratch",
+            width,
+            height,
+            usage | wgpu::TextureUsages::COPY_SRC,
+        );
+        let targets = Targets {
+            canvas,
+            scratch,
+            width,
+            height,
+        };
+        (targets, true)
+    }
+
+    /// Render what changed in `scene` with `params` since the last frame,
+    /// as `change` tells, and draw the whole frame into `output`. Returns
+    /// what was redrawn.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+        change: Change,
+    ) -> Result<Dirty, vello::Error> {
+        let (targets, fresh) = self.targets(device, params.width, params.height);
+        let mut dirty = self.tracker.update(change, (params.width, params.height));
+        if fresh {
+            dirty = Dirty::All;
+        }
+        let rendered = match dirty {
+            Dirty::Nothing => Ok(()),
+            Dirty::Rect(rect) => {
+                let mut shifted = Scene::new();
+                let origin = Vec2::new(-rect.x0, -rect.y0);
+                shifted.append(scene, Some(Affine::translate(origin)));
+                let rect_params = RenderParams {
+                    width: rect.width() as u32,
+                    height: rect.height() as u32,
+                    ..*params
+                };
+                let view = &targets.scratch.view;
+                renderer.render_to_texture(device, queue, &shifted, view, &rect_params)
+            }
+            Dirty::All => {
+                let view = &targets.canvas.texture.view;
+                renderer.render_to_texture(device, queue, scene, view, params)
+            }
+        };
+        let targets = self.targets.insert(targets);
+        if let Err(e) = rendered {
+            // The canvas may be part drawn, so start again next frame.
+            self.tracker = Tracker::default();
+            return Err(e);
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("dirty"),
+        });
+        if let Dirty::Rect(rect) = dirty {
+            encoder.copy_texture_to_texture(
+                targets.scratch.texture.as_image_copy(),
+                wgpu::ImageCopyTexture {
+                    origin: wgpu::Origin3d {
+                        x: rect.x0 as u32,
+                        y: rect.y0 as u32,
+                        z: 0,
+                    },
+                    ..targets.canvas.texture.texture.as_image_copy()
+                },
+                wgpu::Extent3d {
+                    width: rect.width() as u32,
+                    height: rect.height() as u32,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        let draw = (&self.pipeline, &targets.canvas.bind_group);
+        let load = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        self.blit.draw(&mut encoder, output, load, &[draw]);
+        queue.submit([encoder.finish()]);
+        Ok(dirty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::peniko::{Color, Fill};
+    use vello::AaConfig;
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    /// A frame with its first pixel in `color`, and a "stroke" filling the
+    /// pixel at `stroke`, if any, in blue.
+    fn frame(color: Color, stroke: Option<f64>) -> (Scene, Change) {
+        let mut scene = Scene::new();
+        let pixel = |x| Rect::new(x, 0.0, x + 1.0, 1.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &pixel(0.0));
+        let start = scene.encoding().stream_offsets();
+        if let Some(x) = stroke {
+            let blue = Color::from_rgb8(0, 0, 255);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, blue, None, &pixel(x));
+        }
+        let end = scene.encoding().stream_offsets();
+        let change = Change {
+            fingerprint: Fingerprint::of(&scene, start..end),
+            stroke: stroke.map(pixel),
+        };
+        (scene, change)
+    }
+
+    #[test]
+    fn only_what_changed_is_redrawn() {
+        let Some((context, dev_id, mut renderer)) = renderer(&[AaConfig::Area]) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 4, 1);
+        let params = RenderParams {
+            base_color: Color::WHITE,
+            width: 4,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let (red, green) = (Color::from_rgb8(255, 0, 0), Color::from_rgb8(0, 255, 0));
+        let mut pass = DirtyPass::new(device, blit::FORMAT);
+        let (r, g, b, w) = (
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255; 4],
+        );
+        // The stroke's pixel and those its antialiasing might touch.
+        let stroke = Dirty::Rect(Rect::new(1.0, 0.0, 4.0, 1.0));
+        for ((color, x), dirty, pixels) in [
+            ((red, None), Dirty::All, [r, w, w, w]),
+            ((red, Some(2.0)), stroke, [r, w, b, w]),
+            ((red, Some(2.0)), Dirty::Nothing, [r, w, b, w]),
+            ((green, Some(2.0)), Dirty::All, [g, w, b, w]),
+        ] {
+            let (scene, change) = frame(color, x);
+            let redrawn = pass
+                .render(&mut renderer, device, queue, &scene, &view, &params, change)
+                .unwrap();
+            assert_eq!(redrawn, dirty);
+            let output = block_on(Readback::new(device, queue, &output, 4, 1).read(device));
+            assert_eq!(output.unwrap(), pixels.concat(), "{:?}", dirty);
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! DOM helpers for the web page hosting the canvas.
+
+use std::time::Duration;
+
+use log::{error, info, Level};
+use wasm_bindgen::prelude::*;
+
+use crate::fallback::BrowserAdapter;
+use crate::messages::MessageLog;
+use crate::web::SAFE_MODE_SIZE;
+
+/// Remove the page's loading indicator, if there is one.
+pub fn remove_loading_overlay() {
+    if let Some(overlay) = web_sys::window()
+        .and_then(|win| win.document())
+        .and_then(|doc| doc.get_element_by_id("loading"))
+    {
+        overlay.remove();
+    }
+}
+
+/// How serious a banner message is, which sets its colour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn colors(self) -> (&'static str, &'static str) {
+        match self {
+            Severity::Info => ("#e8f0fe", "#1a4480"),
+            Severity::Warning => ("#fff4e5", "#8a4b00"),
+            Severity::Error => ("#fdecea", "#8b1a1a"),
+        }
+    }
+}
+
+/// Show a dismissible banner at the top of the page, leaving the rest of
+/// the page content intact. `message` may contain HTML. Errors take down
+/// the loading overlay, as nothing more is coming.
+pub fn display_banner(severity: Severity, message: &str) -> Option<()> {
+    if severity == Severity::Error {
+        remove_loading_overlay();
+    }
+    let document = web_sys::window()?.document()?;
+    let body = document.body()?;
+    let (background, color) = severity.colors();
+    let banner = document.create_element("div").ok()?;
+    banner.set_class_name("vello-banner");
+    banner
+        .set_attribute(
+            "style",
+            &format!(
+                "position: fixed; top: 1em; left: 50%; transform: translateX(-50%); \
+                 max-width: 40em; padding: 0.75em 2.5em 0.75em 1em; \
+                 font-family: sans-serif; background: {}; color: {}; \
+                 border: 1px solid {}; border-radius: 4px; z-index: 1000;",
+                background, color, color
+            ),
+        )
+        .ok()?;
+    banner.set_inner_html(&format!(
+        r#"{}<button onclick="this.parentElement.remove()" title="Dismiss"
+        style="position: absolute; top: 0.4em; right: 0.5em; border: none;
+        background: none; font-size: 1.2em; cursor: pointer;">&times;</button>"#,
+        message
+    ));
+    body.append_child(&banner).ok()?;
+    Some(())
+}
+
+/// Whether the page has opted out of starting the interactive client with a
+/// `data-no-client` attribute on its root element, as the diagnostics page does.
+pub fn client_disabled() -> bool {
+    web_sys::window()
+        .and_then(|win| win.document())
+        .and_then(|doc| doc.document_element())
+        .is_some_and(|root| root.has_attribute("data-no-client"))
+}
+
+/// Whether the browser exposes WebGPU, as `navigator.gpu`. It can, and
+/// still have no adapter to give.
+pub fn has_webgpu() -> bool {
+    web_sys::window().is_some_and(|win| {
+        js_sys::Reflect::get(&win.navigator(), &"gpu".into())
+            .is_ok_and(|gpu| !gpu.is_undefined() && !gpu.is_null())
+    })
+}
+
+/// What the browser says, in `GPUAdapterInfo`, of the adapter it gives for
+/// `power_preference` (a `powerPreference` value), or `None` if it gives
+/// none. wgpu can't say, and asks for its adapter the same way, so this is
+/// the adapter the client started on. Browsers before `GPUAdapter.info` only
+/// have `requestAdapterInfo()`, which isn't waited for, so only whether it's
+/// the fallback adapter is known.
+pub async fn browser_adapter(power_preference: Option<&str>) -> Option<BrowserAdapter> {
+    let get = |object: &JsValue, key: &str| js_sys::Reflect::get(object, &key.into()).ok();
+    let gpu = get(&web_sys::window()?.navigator(), "gpu")?;
+    let request: js_sys::Function = get(&gpu, "requestAdapter")?.dyn_into().ok()?;
+    let options = js_sys::Object::new();
+    if let Some(preference) = power_preference {
+        js_sys::Reflect::set(&options, &"powerPreference".into(), &preference.into()).ok()?;
+    }
+    let adapter: js_sys::Promise = request.call1(&gpu, &options).ok()?.dyn_into().ok()?;
+    let adapter = wasm_bindgen_futures::JsFuture::from(adapter).await.ok()?;
+    if adapter.is_null() || adapter.is_undefined() {
+        return None;
+    }
+    let info = get(&adapter, "info").filter(JsValue::is_object);
+    let text = |key| info.as_ref().and_then(|info| get(info, key)?.as_string());
+    let fallback = [info.as_ref(), Some(&adapter)]
+        .into_iter()
+        .flatten()
+        .find_map(|object| get(object, "isFallbackAdapter")?.as_bool());
+    Some(BrowserAdapter {
+        vendor: text("vendor").unwrap_or_default(),
+        architecture: text("architecture").unwrap_or_default(),
+        description: text("description").unwrap_or_default(),
+        fallback,
+    })
+}
+
+/// The message shown when WebGPU is available but returns no adapter,
+/// rather than suggesting a browser update that wouldn't help.
+pub fn display_unsupported_gpu_message() -> Option<()> {
+    display_banner(
+        Severity::Error,
+        r#"Your browser supports <a href="https://caniuse.com/webgpu">WebGPU</a>,
+        but it didn't provide a GPU adapter: your GPU or its driver is not
+        supported, or has been blocklisted by the browser. Updating the graphics
+        driver may help; the browser's GPU report (e.g. <code>chrome://gpu</code>)
+        shows why it was rejected."#,
+    )
+}
+
+pub fn display_error_message() -> Option<()> {
+    display_banner(
+        Severity::Error,
+        r#"<a href="https://caniuse.com/webgpu">WebGPU</a>
+        is not enabled. Make sure your browser is updated to
+        <a href="https://chromiumdash.appspot.com/schedule">Chrome M113</a> or
+        another browser compatible with WebGPU."#,
+    )
+}
+
+/// Append the canvas to the document body and focus it. If the body doesn't
+/// exist yet (e.g. the script runs in `<head>`), wait for `DOMContentLoaded`.
+pub fn append_canvas(canvas: web_sys::HtmlCanvasElement) -> Result<(), Box<dyn std::error::Error>> {
+    let document = web_sys::window()
+        .and_then(|win| win.document())
+        .ok_or("no document available")?;
+    if let Some(body) = document.body() {
+        body.append_child(&canvas)
+            .map_err(|e| format!("couldn't append canvas to document body: {:?}", e))?;
+        // Best effort to start with the canvas focused, taking input
+        drop(canvas.focus());
+        return Ok(());
+    }
+    info!("Document body not ready, waiting for DOMContentLoaded");
+    let doc = document.clone();
+    let on_loaded =
+        Closure::once(
+            move || match doc.body().map(|body| body.append_child(&canvas)) {
+                Some(Ok(_)) => drop(canvas.focus()),
+                Some(Err(e)) => error!("couldn't append canvas to document body: {:?}", e),
+                None => error!("document has no body after DOMContentLoaded"),
+            },
+        );
+    document
+        .add_event_listener_with_callback("DOMContentLoaded", on_loaded.as_ref().unchecked_ref())
+        .map_err(|e| format!("couldn't wait for DOMContentLoaded: {:?}", e))?;
+    on_loaded.forget();
+    Ok(())
+}
+
+/// Draw a checkerboard behind the canvas, so that transparent parts of the
+/// canvas are visible. `cell` is the size of each square in CSS pixels.
+pub fn show_checkerboard(canvas: &web_sys::HtmlCanvasElement, cell: f64) {
+    let style = canvas.style();
+    let result = style
+        .set_property(
+            "background",
+            "repeating-conic-gradient(#ccc 0% 25%, #fff 0% 50%)",
+        )
+        .and_then(|_| style.set_property("background-size", &format!("{0}px {0}px", cell * 2.0)));
+    if let Err(e) = result {
+        error!("couldn't set canvas background: {:?}", e);
+    }
+}
+
+/// Report a failure that happened even in safe mode, with the details
+/// needed to report it: what failed, the error, and the browser.
+pub fn display_safe_mode_error(action: &str, error: &str) {
+    let user_agent = web_sys::window()
+        .and_then(|win| win.navigator().user_agent().ok())
+        .unwrap_or_default();
+    let message = format!(
+        "<b>Failed to {} in safe mode.</b><br>\
+         Safe mode uses CPU preparation stages, area antialiasing, FIFO presentation, \
+         no optional device features and a {} x {} surface.<br>\
+         Error: <code>{}</code><br>Browser: <code>{}</code>",
+        action,
+        SAFE_MODE_SIZE.0,
+        SAFE_MODE_SIZE.1,
+        escape_html(error),
+        escape_html(&user_agent)
+    );
+    _ = display_banner(Severity::Error, &message);
+}
+
+/// Show the messages `log` has kept in a collapsible panel in the bottom
+/// left-hand corner, created when first needed and otherwise updated,
+/// staying open or closed as it was. It logs nothing itself, being called
+/// from the logger.
+pub fn show_messages(log: &MessageLog) -> Option<()> {
+    let document = web_sys::window()?.document()?;
+    let panel = match document.get_element_by_id("vello-messages") {
+        Some(panel) => panel,
+        None => {
+            let panel = document.create_element("details").ok()?;
+            panel.set_id("vello-messages");
+            let (background, color) = Severity::Warning.colors();
+            panel
+                .set_attribute(
+                    "style",
+                    &format!(
+                        "position: fixed; bottom: 1em; left: 1em; max-width: 60em; \
+                         max-height: 40vh; overflow: auto; padding: 0.5em 1em; \
+                         font-family: monospace; font-size: 0.8em; background: {}; color: {}; \
+                         border: 1px solid {}; border-radius: 4px; z-index: 1000;",
+                        background, color, color
+                    ),
+                )
+                .ok()?;
+            document.body()?.append_child(&panel).ok()?;
+            panel
+        }
+    };
+    let count = log.messages().count();
+    let errors = log.messages().filter(|m| m.level == Level::Error).count();
+    let mut html = format!(
+        "<summary style=\"cursor: pointer;\">{} warnings and errors, {} of them errors</summary>",
+        count, errors
+    );
+    if log.dropped() > 0 {
+        html += &format!("<div>({} older messages dropped)</div>", log.dropped());
+    }
+    for message in log.messages() {
+        let severity = match message.level {
+            Level::Error => Severity::Error,
+            _ => Severity::Warning,
+        };
+        html += &format!(
+            "<div style=\"color: {}; white-space: pre-wrap;\">{}</div>",
+            severity.colors().1,
+            escape_html(&message.to_string())
+        );
+    }
+    panel.set_inner_html(&html);
+    Some(())
+}
+
+/// `text` made safe to put in HTML, e.g. an error message in a banner.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn blob(mime: &str, data: &[u8]) -> Result<web_sys::Blob, JsValue> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+}
+
+/// Offer `data` to the user as a file download.
+pub fn download(filename: &str, mime: &str, data: &[u8]) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|win| win.document())
+        .ok_or("no document available")?;
+    let blob = blob(mime, data)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+/// Whether the page is visible, rather than e.g. in a background tab or a
+/// minimised window, per the Page Visibility API.
+pub fn page_visible() -> bool {
+    web_sys::window()
+        .and_then(|win| win.document())
+        .is_none_or(|document| !document.hidden())
+}
+
+/// Call `callback` with whether the page is visible each time that changes,
+/// on the document's `visibilitychange` event.
+pub fn on_visibility_change(callback: impl Fn(bool) + 'static) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|win| win.document())
+        .ok_or("no document available")?;
+    let doc = document.clone();
+    let on_change = Closure::<dyn Fn()>::new(move || callback(!doc.hidden()));
+    document
+        .add_event_listener_with_callback("visibilitychange", on_change.as_ref().unchecked_ref())?;
+    on_change.forget();
+    Ok(())
+}
+
+/// Whether `ResizeObserver` reports sizes in device pixels, with
+/// `devicePixelContentBoxSize`. Safari only reports them in CSS pixels.
+pub fn has_device_pixel_content_box() -> bool {
+    js_sys::Reflect::get(&js_sys::global(), &"ResizeObserverEntry".into())
+        .and_then(|entry| js_sys::Reflect::get(&entry, &"prototype".into()))
+        .and_then(|prototype| js_sys::Reflect::has(&prototype, &"devicePixelContentBoxSize".into()))
+        .unwrap_or(false)
+}
+
+/// The canvas's size in device pixels as the browser laid it out, from the
+/// first `ResizeObserver` entry for its device pixel content box. This is
+/// exact, where its CSS size times `devicePixelRatio` can be a fraction of
+/// a pixel out either way. Canvases are in a horizontal writing mode, so
+/// the inline size is the width.
+pub async fn device_pixel_size(canvas: &web_sys::HtmlCanvasElement) -> Result<(u32, u32), JsValue> {
+    if !has_device_pixel_content_box() {
+        return Err("devicePixelContentBoxSize is not supported".into());
+    }
+    let mut observer = Err(JsValue::UNDEFINED);
+    let entry = js_sys::Promise::new(&mut |resolve, _| {
+        let on_resize = Closure::once_into_js(
+            move |entries: js_sys::Array, observer: web_sys::ResizeObserver| {
+                observer.disconnect();
+                _ = resolve.call1(&JsValue::NULL, &entries.get(0));
+            },
+        );
+        observer = web_sys::ResizeObserver::new(on_resize.unchecked_ref());
+    });
+    let options = web_sys::ResizeObserverOptions::new();
+    options.set_box(web_sys::ResizeObserverBoxOptions::DevicePixelContentBox);
+    observer?.observe_with_options(canvas, &options);
+    let entry: web_sys::ResizeObserverEntry = wasm_bindgen_futures::JsFuture::from(entry)
+        .await?
+        .unchecked_into();
+    let size: web_sys::ResizeObserverSize = entry
+        .device_pixel_content_box_size()
+        .get(0)
+        .unchecked_into();
+    Ok((size.inline_size() as u32, size.block_size() as u32))
+}
+
+/// Write a PNG image to the system clipboard, with `navigator.clipboard`.
+/// The browser only allows it from a page that has focus, and may ask the
+/// user first, failing if they refuse.
+pub async fn copy_png(png: &[u8]) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window available")?;
+    let items = js_sys::Object::new();
+    let blob = blob("image/png", png)?;
+    js_sys::Reflect::set(&items, &"image/png".into(), &blob)?;
+    let item = web_sys::ClipboardItem::new_with_record_from_str_to_blob_promise(&items)?;
+    let write = window
+        .navigator()
+        .clipboard()
+        .write(&js_sys::Array::of1(&item));
+    wasm_bindgen_futures::JsFuture::from(write).await?;
+    Ok(())
+}
+
+/// Write text to the system clipboard, with `navigator.clipboard`, which
+/// the browser only allows as it does [`copy_png`].
+pub async fn copy_text(text: &str) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window available")?;
+    let write = window.navigator().clipboard().write_text(text);
+    wasm_bindgen_futures::JsFuture::from(write).await?;
+    Ok(())
+}
+
+/// Call `callback` once after `delay`, with `setTimeout`. It runs as a task
+/// of its own, whether or not any frames are being drawn.
+pub fn set_timeout(delay: Duration, callback: impl FnOnce() + 'static) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window available")?;
+    let callback = Closure::once(callback);
+    window.set_timeout_with_callback_and_timeout_and_arguments_0(
+        callback.as_ref().unchecked_ref(),
+        delay.as_millis().try_into().unwrap_or(i32::MAX),
+    )?;
+    callback.forget();
+    Ok(())
+}
+
+/// Collect the timestamps, in milliseconds, of the `requestAnimationFrame`
+/// callbacks over `duration`, and pass them to `done`. Each frame's callback
+/// requests the next, so they run at the display's refresh rate.
+pub fn animation_frame_times(
+    duration: Duration,
+    done: impl FnOnce(Vec<f64>) + 'static,
+) -> Result<(), JsValue> {
+    request_frame_times(Vec::new(), duration.as_secs_f64() * 1000.0, Box::new(done))
+}
+
+fn request_frame_times(
+    mut times: Vec<f64>,
+    limit: f64,
+    done: Box<dyn FnOnce(Vec<f64>)>,
+) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or("no window available")?;
+    let callback = Closure::once_into_js(move |time: f64| {
+        times.push(time);
+        if time - times[0] >= limit {
+            return done(times);
+        }
+        if let Err(e) = request_frame_times(times, limit, done) {
+            error!("couldn't request an animation frame: {:?}", e);
+        }
+    });
+    window.request_animation_frame(callback.unchecked_ref())?;
+    Ok(())
+}
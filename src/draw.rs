@@ -0,0 +1,632 @@
+//! Scene content.
+
+use std::ops::{Deref, DerefMut};
+use std::str::FromStr;
+use std::time::Duration;
+
+use vello::kurbo::{Affine, BezPath, Cap, Join, Point, Rect, Shape, Stroke, Vec2};
+use vello::peniko::{BlendMode, Color, Extend, Fill, Image, ImageQuality, Mix};
+use vello::Scene;
+
+/// The inset of the demos from the edges of the drawable area, unless drawing full bleed.
+pub const MARGIN: f64 = 50.0;
+
+/// Rotation rate of the animated content, in radians per second.
+const SPIN_RATE: f64 = 0.5;
+
+/// The animation transform at the given time: a rotation about `center`.
+pub fn spin(elapsed: Duration, center: Point) -> Affine {
+    Affine::rotate_about(SPIN_RATE * elapsed.as_secs_f64(), center)
+}
+
+/// Join and cap styles for stroked lines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineStyle {
+    pub join: Join,
+    pub caps: Cap,
+}
+
+impl Default for LineStyle {
+    /// Kurbo's defaults, as used by `Stroke::new`.
+    fn default() -> Self {
+        LineStyle {
+            join: Join::Round,
+            caps: Cap::Round,
+        }
+    }
+}
+
+impl LineStyle {
+    /// A stroke of the given width in this style.
+    pub fn stroke(self, width: f64) -> Stroke {
+        Stroke::new(width).with_join(self.join).with_caps(self.caps)
+    }
+
+    /// This style with the next join kind.
+    pub fn next_join(self) -> Self {
+        let join = match self.join {
+            Join::Bevel => Join::Miter,
+            Join::Miter => Join::Round,
+            Join::Round => Join::Bevel,
+        };
+        LineStyle { join, ..self }
+    }
+
+    /// This style with the next cap kind.
+    pub fn next_caps(self) -> Self {
+        let caps = match self.caps {
+            Cap::Butt => Cap::Square,
+            Cap::Square => Cap::Round,
+            Cap::Round => Cap::Butt,
+        };
+        LineStyle { caps, ..self }
+    }
+}
+
+/// A layer pushed onto a scene, and popped when dropped, so that every push
+/// is matched by a pop however the code drawing into it returns. Draw into
+/// the layer through the guard, which derefs to the scene.
+///
+/// A layer's alpha fades what's drawn into it as a whole, at the cost of a
+/// blend stack entry and a composite over every tile it covers. To fade a
+/// single shape, give its brush a colour with alpha instead, which costs no
+/// more than an opaque fill.
+pub struct Layer<'a> {
+    scene: &'a mut Scene,
+}
+
+impl<'a> Layer<'a> {
+    pub fn push(
+        scene: &'a mut Scene,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) -> Self {
+        scene.push_layer(blend, alpha, transform, clip);
+        Layer { scene }
+    }
+}
+
+impl Deref for Layer<'_> {
+    type Target = Scene;
+
+    fn deref(&self) -> &Scene {
+        self.scene
+    }
+}
+
+impl DerefMut for Layer<'_> {
+    fn deref_mut(&mut self) -> &mut Scene {
+        self.scene
+    }
+}
+
+impl Drop for Layer<'_> {
+    fn drop(&mut self) {
+        self.scene.pop_layer();
+    }
+}
+
+/// Run `draw` with what it draws clipped to `clip`, any shape, placed by
+/// `transform`. Clips nest: drawing with a clip inside `draw` clips to both.
+pub fn with_clip(
+    scene: &mut Scene,
+    transform: Affine,
+    clip: &impl Shape,
+    draw: impl FnOnce(&mut Scene),
+) {
+    let mut layer = Layer::push(scene, Mix::Clip, 1.0, transform, clip);
+    draw(&mut layer);
+}
+
+/// The bordered rectangle for a drawable area of the given size, inset by `margin`.
+pub fn border_rect(width: u32, height: u32, margin: f64) -> Rect {
+    Rect::new(
+        margin,
+        margin,
+        width as f64 - margin * 2.0,
+        height as f64 - margin * 2.0,
+    )
+}
+
+/// Stroke the bordered rectangle into the scene.
+pub fn draw_border(
+    scene: &mut Scene,
+    rect: &Rect,
+    transform: Affine,
+    style: LineStyle,
+    color: Color,
+) {
+    scene.stroke(&style.stroke(1.0), transform, color, None, rect);
+}
+
+/// Draw any shape, filled with `fill` and then outlined with `stroke`, a
+/// colour and line width, either of which may be left out. Open shapes such
+/// as lines have nothing to fill, and only show when stroked.
+pub fn draw_shape(
+    scene: &mut Scene,
+    shape: &impl Shape,
+    fill: Option<Color>,
+    stroke: Option<(Color, f64)>,
+    transform: Affine,
+) {
+    if let Some(color) = fill {
+        scene.fill(Fill::NonZero, transform, color, None, shape);
+    }
+    if let Some((color, width)) = stroke {
+        scene.stroke(&Stroke::new(width), transform, color, None, shape);
+    }
+}
+
+/// The colours of the background and of the border and freehand strokes,
+/// for a light or a dark theme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    pub stroke: Color,
+}
+
+impl Palette {
+    pub const LIGHT: Palette = Palette {
+        background: Color::WHITE,
+        stroke: Color::BLACK,
+    };
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub const DARK: Palette = Palette {
+        background: Color::from_rgb8(0x20, 0x20, 0x20),
+        stroke: Color::from_rgb8(0xe0, 0xe0, 0xe0),
+    };
+}
+
+/// Draw an image with the given sampling quality. `ImageQuality::Low`
+/// samples the nearest pixel, which keeps pixel art sharp when scaled up;
+/// `Medium` interpolates bilinearly, which suits photos. Vello treats
+/// `High` the same as `Medium`.
+pub fn draw_image(scene: &mut Scene, image: &Image, transform: Affine, quality: ImageQuality) {
+    scene.draw_image(&image.clone().with_quality(quality), transform);
+}
+
+/// How a background image is scaled to the surface, keeping its aspect ratio.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackgroundFit {
+    /// Cover the whole surface, cropping the image's overflowing sides.
+    #[default]
+    Cover,
+    /// Show the whole image, leaving the base colour either side.
+    Contain,
+}
+
+impl FromStr for BackgroundFit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cover" => Ok(BackgroundFit::Cover),
+            "contain" => Ok(BackgroundFit::Contain),
+            _ => Err(format!("unknown background fit '{}'", s)),
+        }
+    }
+}
+
+/// The transform drawing an image of the given size centred on an area of
+/// `width` by `height`, scaled to fit it as `fit` says.
+pub fn background_transform(
+    image: (u32, u32),
+    width: u32,
+    height: u32,
+    fit: BackgroundFit,
+) -> Affine {
+    let (sx, sy) = (
+        width as f64 / image.0 as f64,
+        height as f64 / image.1 as f64,
+    );
+    let scale = match fit {
+        BackgroundFit::Cover => sx.max(sy),
+        BackgroundFit::Contain => sx.min(sy),
+    };
+    Affine::translate((
+        (width as f64 - image.0 as f64 * scale) / 2.0,
+        (height as f64 - image.1 as f64 * scale) / 2.0,
+    )) * Affine::scale(scale)
+}
+
+/// Draw a background image over an area of the given size, clipped to it.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_background(
+    scene: &mut Scene,
+    image: &Image,
+    fit: BackgroundFit,
+    width: u32,
+    height: u32,
+) {
+    let area = Rect::new(0.0, 0.0, width as f64, height as f64);
+    let transform = background_transform((image.width, image.height), width, height, fit);
+    scene.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &area);
+    draw_image(scene, image, transform, ImageQuality::Medium);
+    scene.pop_layer();
+}
+
+/// Where a reference image is drawn in `area`: at its own size if it's the
+/// same size as the area, as it is when rendered at the size of the live
+/// half beside it, and otherwise scaled to fit and centred.
+pub fn reference_transform(image: (u32, u32), area: Rect) -> Affine {
+    let fit = background_transform(
+        image,
+        area.width() as u32,
+        area.height() as u32,
+        BackgroundFit::Contain,
+    );
+    Affine::translate(area.origin().to_vec2()) * fit
+}
+
+/// Draw a reference image into the right half of a surface of the given
+/// size, with a 1px divider down the last column of the left half.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_reference(scene: &mut Scene, image: &Image, width: u32, height: u32) {
+    let (left, right) = split_halves(width, height);
+    let transform = reference_transform((image.width, image.height), right);
+    with_clip(scene, Affine::IDENTITY, &right, |scene| {
+        draw_image(scene, image, transform, ImageQuality::Medium);
+    });
+    let divider = Rect::new(left.x1 - 1.0, left.y0, left.x1, left.y1);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgb8(128, 128, 128),
+        None,
+        &divider,
+    );
+}
+
+/// Fill `shape` with `image` as a pattern, extended beyond its bounds with
+/// `extend`: `Pad` repeats the edge pixels, `Repeat` tiles the image and
+/// `Reflect` tiles it mirrored. `transform` applies to both the shape and the
+/// pattern, and `pattern` places the image within the shape's coordinates, so
+/// one image pixel covers `pattern`'s scale in user space.
+pub fn fill_pattern(
+    scene: &mut Scene,
+    shape: &impl Shape,
+    image: &Image,
+    extend: Extend,
+    transform: Affine,
+    pattern: Affine,
+) {
+    let image = image.clone().with_extend(extend);
+    scene.fill(Fill::NonZero, transform, &image, Some(pattern), shape);
+}
+
+/// The stroke for freehand paths, with round joins and caps to suit pen strokes.
+pub fn freehand_stroke() -> Stroke {
+    LineStyle {
+        join: Join::Round,
+        caps: Cap::Round,
+    }
+    .stroke(2.0)
+}
+
+/// Stroke a freehand path.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_freehand(scene: &mut Scene, path: &BezPath, color: Color) {
+    scene.stroke(&freehand_stroke(), Affine::IDENTITY, color, None, path);
+}
+
+/// Split a drawable area into left and right halves.
+pub fn split_halves(width: u32, height: u32) -> (Rect, Rect) {
+    let mid = (width / 2) as f64;
+    (
+        Rect::new(0.0, 0.0, mid, height as f64),
+        Rect::new(mid, 0.0, width as f64, height as f64),
+    )
+}
+
+/// Parse an aspect ratio, either as `W:H` (e.g. `16:9`) or as a single number.
+pub fn parse_aspect(s: &str) -> Result<f64, String> {
+    let aspect = match s.split_once(':') {
+        Some((w, h)) => match (w.trim().parse::<f64>(), h.trim().parse::<f64>()) {
+            (Ok(w), Ok(h)) => w / h,
+            _ => f64::NAN,
+        },
+        None => s.trim().parse().unwrap_or(f64::NAN),
+    };
+    if aspect.is_finite() && aspect > 0.0 {
+        Ok(aspect)
+    } else {
+        Err(format!("invalid aspect ratio '{}'", s))
+    }
+}
+
+/// The largest rectangle with the given aspect ratio (width / height) that
+/// fits centred in `area`, in whole pixels.
+pub fn letterbox(area: Rect, aspect: f64) -> Rect {
+    let width = area.width().min(area.height() * aspect).round();
+    let height = area.height().min(area.width() / aspect).round();
+    let origin = Point::new(
+        area.x0 + ((area.width() - width) / 2.0).floor(),
+        area.y0 + ((area.height() - height) / 2.0).floor(),
+    );
+    Rect::from_origin_size(origin, (width, height))
+}
+
+/// A view transform drawing `content` at `scale`, with its own factor for
+/// each axis, centred in a viewport of the given size.
+pub fn zoom(width: f64, height: f64, content: Rect, scale: Vec2) -> Affine {
+    Affine::translate((width / 2.0, height / 2.0))
+        * Affine::scale_non_uniform(scale.x, scale.y)
+        * Affine::translate(-content.center().to_vec2())
+}
+
+/// The transform mirroring a surface of the given size about its centre:
+/// left to right if `horizontal`, and upside down if `vertical`.
+pub fn flip(width: f64, height: f64, horizontal: bool, vertical: bool) -> Affine {
+    let mut flip = Affine::IDENTITY;
+    if horizontal {
+        flip = Affine::FLIP_X * flip;
+    }
+    if vertical {
+        flip = Affine::FLIP_Y * flip;
+    }
+    let center = Vec2::new(width / 2.0, height / 2.0);
+    Affine::translate(center) * flip * Affine::translate(-center)
+}
+
+/// The scale at which `content` just fills a viewport of the given size.
+pub fn fit_scale(width: f64, height: f64, content: Rect) -> f64 {
+    (width / content.width()).min(height / content.height())
+}
+
+/// Fill the parts of `area` outside `viewport` with `color`.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_letterbox(scene: &mut Scene, area: Rect, viewport: Rect, color: Color) {
+    let mut bars = area.to_path(0.1);
+    bars.extend(viewport.to_path(0.1));
+    scene.fill(Fill::EvenOdd, Affine::IDENTITY, color, None, &bars);
+}
+
+/// Draw content confined to a viewport of the scene.
+///
+/// `draw` is given a scene to fill in viewport-local coordinates (with the
+/// origin at the viewport's top-left corner) along with the viewport size.
+/// The content is then offset into place and clipped to the viewport.
+pub fn draw_in_viewport(
+    scene: &mut Scene,
+    viewport: Rect,
+    draw: impl FnOnce(&mut Scene, u32, u32),
+) {
+    let mut content = Scene::new();
+    draw(
+        &mut content,
+        viewport.width() as u32,
+        viewport.height() as u32,
+    );
+    scene.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &viewport);
+    scene.append(
+        &content,
+        Some(Affine::translate(viewport.origin().to_vec2())),
+    );
+    scene.pop_layer();
+}
+
+/// Build a smooth path through the points, using Catmull-Rom splines
+/// converted to cubic Béziers. The path passes through every point.
+pub fn smooth_path(points: &[Point]) -> BezPath {
+    let mut path = BezPath::new();
+    let Some(&first) = points.first() else {
+        return path;
+    };
+    path.move_to(first);
+    if points.len() == 1 {
+        // Make a single click visible as a dot.
+        path.line_to(first);
+        return path;
+    }
+    let last = points.len() - 1;
+    for i in 0..last {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(last)];
+        path.curve_to(p1 + (p2 - p0) / 6.0, p2 - (p3 - p1) / 6.0, p2);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Circle, Ellipse, Line, RoundedRect};
+
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+    use crate::readback::testing;
+
+    #[test]
+    fn spin_at_frame_n() {
+        let mut clock = ManualClock::new(Duration::from_millis(100));
+        let center = Point::new(100.0, 100.0);
+        for _ in 0..20 {
+            clock.tick();
+        }
+        // 20 frames of 100ms at 0.5 rad/s is one radian.
+        let expected = Affine::rotate_about(1.0, center);
+        let actual = spin(clock.elapsed(), center);
+        for (a, e) in actual.as_coeffs().iter().zip(expected.as_coeffs()) {
+            assert!((a - e).abs() < 1e-9);
+        }
+        // The centre of rotation stays fixed.
+        assert!((actual * center - center).hypot() < 1e-9);
+    }
+
+    fn shape_scene(
+        shape: &impl Shape,
+        fill: Option<Color>,
+        stroke: Option<(Color, f64)>,
+        transform: Affine,
+    ) -> Scene {
+        let mut scene = Scene::new();
+        draw_shape(&mut scene, shape, fill, stroke, transform);
+        scene
+    }
+
+    #[test]
+    fn shapes_of_every_kind_are_drawn() {
+        const SIZE: u32 = 32;
+        let center = Point::new(16.0, 16.0);
+        let mut triangle = BezPath::new();
+        triangle.move_to((4.0, 28.0));
+        triangle.line_to((16.0, 4.0));
+        triangle.line_to((28.0, 28.0));
+        triangle.close_path();
+        let red = Color::from_rgb8(255, 0, 0);
+        let blue = Color::from_rgb8(0, 0, 255);
+        let cases = [
+            (
+                "circle",
+                shape_scene(&Circle::new(center, 8.0), Some(red), None, Affine::IDENTITY),
+            ),
+            (
+                "ellipse",
+                shape_scene(
+                    &Ellipse::new(center, (10.0, 5.0), 0.0),
+                    Some(red),
+                    None,
+                    Affine::IDENTITY,
+                ),
+            ),
+            (
+                "rounded rect",
+                shape_scene(
+                    &RoundedRect::new(8.0, 8.0, 24.0, 24.0, 4.0),
+                    Some(red),
+                    Some((blue, 2.0)),
+                    Affine::IDENTITY,
+                ),
+            ),
+            // Lines have nothing to fill, so only the stroke is drawn.
+            (
+                "line",
+                shape_scene(
+                    &Line::new((0.0, 16.0), (32.0, 16.0)),
+                    Some(blue),
+                    Some((red, 4.0)),
+                    Affine::IDENTITY,
+                ),
+            ),
+            (
+                "path",
+                shape_scene(&triangle, Some(red), None, Affine::translate((0.0, 2.0))),
+            ),
+        ];
+        for (name, scene) in cases {
+            let Some(pixels) = testing::render(&scene, SIZE, SIZE) else {
+                return;
+            };
+            let pixel = |x: u32, y: u32| {
+                let i = ((y * SIZE + x) * 4) as usize;
+                [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+            };
+            // Filled or stroked red through the centre, and nothing in the corner.
+            assert_eq!(pixel(16, 16), [255, 0, 0, 255], "{}", name);
+            assert_eq!(pixel(0, 0), [0, 0, 0, 0], "{}", name);
+        }
+    }
+
+    #[test]
+    fn smooth_path_passes_through_points() {
+        use vello::kurbo::PathEl;
+
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 5.0),
+            Point::new(20.0, 0.0),
+            Point::new(30.0, 5.0),
+        ];
+        let path = smooth_path(&points);
+        let ends: Vec<Point> = path
+            .elements()
+            .iter()
+            .map(|el| match *el {
+                PathEl::MoveTo(p) | PathEl::CurveTo(_, _, p) => p,
+                _ => panic!("unexpected element {:?}", el),
+            })
+            .collect();
+        assert_eq!(ends, points);
+    }
+
+    #[test]
+    fn background_cover_and_contain() {
+        // A 2:1 image on a square area.
+        let cover = background_transform((200, 100), 100, 100, BackgroundFit::Cover);
+        let rect = cover.transform_rect_bbox(Rect::new(0.0, 0.0, 200.0, 100.0));
+        assert_eq!(rect, Rect::new(-50.0, 0.0, 150.0, 100.0));
+        let contain = background_transform((200, 100), 100, 100, BackgroundFit::Contain);
+        let rect = contain.transform_rect_bbox(Rect::new(0.0, 0.0, 200.0, 100.0));
+        assert_eq!(rect, Rect::new(0.0, 25.0, 100.0, 75.0));
+    }
+
+    #[test]
+    fn letterbox_is_centred() {
+        let area = Rect::new(0.0, 0.0, 800.0, 600.0);
+        assert_eq!(parse_aspect("16:9"), Ok(16.0 / 9.0));
+        assert_eq!(parse_aspect("2"), Ok(2.0));
+        assert!(parse_aspect("1:0").is_err());
+        assert_eq!(letterbox(area, 2.0), Rect::new(0.0, 100.0, 800.0, 500.0));
+        assert_eq!(letterbox(area, 1.0), Rect::new(100.0, 0.0, 700.0, 600.0));
+    }
+
+    #[test]
+    fn flips_are_about_the_centre() {
+        assert_eq!(flip(800.0, 600.0, false, false), Affine::IDENTITY);
+        let corner = Point::new(10.0, 20.0);
+        assert_eq!(
+            flip(800.0, 600.0, true, false) * corner,
+            Point::new(790.0, 20.0)
+        );
+        assert_eq!(
+            flip(800.0, 600.0, false, true) * corner,
+            Point::new(10.0, 580.0)
+        );
+        assert_eq!(
+            flip(800.0, 600.0, true, true) * corner,
+            Point::new(790.0, 580.0)
+        );
+    }
+
+    #[test]
+    fn zoom_keeps_content_centred() {
+        let content = border_rect(800, 600, MARGIN);
+        let scale = fit_scale(800.0, 600.0, content);
+        let view = zoom(800.0, 600.0, content, Vec2::new(scale, scale));
+        assert!((view * content.center() - Point::new(400.0, 300.0)).hypot() < 1e-9);
+        // The content fills the width, which is the tighter dimension.
+        let fitted = view.transform_rect_bbox(content);
+        assert!((fitted.width() - 800.0).abs() < 1e-9);
+        assert!(fitted.height() <= 600.0);
+        // Stretched to twice the width and half the height, still centred.
+        let view = zoom(800.0, 600.0, content, Vec2::new(2.0, 0.5));
+        let stretched = view.transform_rect_bbox(content);
+        assert!((stretched.width() - content.width() * 2.0).abs() < 1e-9);
+        assert!((stretched.height() - content.height() * 0.5).abs() < 1e-9);
+        assert!((stretched.center() - Point::new(400.0, 300.0)).hypot() < 1e-9);
+        // Surface points map back to the content they show.
+        let point = Point::new(123.0, 456.0);
+        assert!((view * (view.inverse() * point) - point).hypot() < 1e-9);
+    }
+
+    #[test]
+    fn references_are_shown_at_their_own_size() {
+        let (_, right) = split_halves(800, 600);
+        let transform = reference_transform((400, 600), right);
+        assert_eq!(transform, Affine::translate((400.0, 0.0)));
+        // A larger one is scaled down to fit, centred.
+        let transform = reference_transform((800, 600), right);
+        let shown = transform.transform_rect_bbox(Rect::new(0.0, 0.0, 800.0, 600.0));
+        assert_eq!(shown, Rect::new(400.0, 150.0, 800.0, 450.0));
+    }
+
+    #[test]
+    fn halves_cover_the_area() {
+        let (left, right) = split_halves(801, 600);
+        assert_eq!(left, Rect::new(0.0, 0.0, 400.0, 600.0));
+        assert_eq!(right, Rect::new(400.0, 0.0, 801.0, 600.0));
+    }
+}
@@ -0,0 +1,337 @@
+//! Numerical profiles of an antialiased edge.
+//!
+//! A summary of a whole image can show that AA configs differ, but not how.
+//! Here a known edge is rendered instead: black below the 45° diagonal of a
+//! small white square. The pixels of one row crossing the diagonal go from
+//! black to white, and the values in between are the AA gradient, which can
+//! be read off and compared across configs. Pixels on the diagonal are half
+//! covered. A profile with no values in between is a hard step where a
+//! gradient was expected, and one that isn't ordered from dark to light has
+//! garbage in it.
+//!
+//! The edge can be turned to other angles, for `?edge_test`, which keeps
+//! one tested with the AA config in use and shows whether it passed over
+//! the frame. Whatever the angle, black stays to the left of the edge, so
+//! the profile still goes from black to white.
+//!
+//! A hairline, a 1px stroke along the same diagonal, is profiled the same
+//! way. Crossing the row at 45°, it covers √2 pixels of it in all, so the
+//! darkness of the pixels across it should add up to about that: much less
+//! and the line is being lost, much more and it is smeared.
+
+use std::f64::consts::SQRT_2;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use serde::Serialize;
+use vello::kurbo::{Affine, BezPath, Line, Rect, Stroke};
+use vello::peniko::{Color, Fill};
+use vello::{AaConfig, Scene};
+
+/// The size of the square, in pixels.
+pub const SIZE: u32 = 32;
+/// The row profiled, which crosses the diagonal at its middle pixel.
+const ROW: u32 = SIZE / 2;
+/// How many pixels either side of the diagonal are profiled.
+const REACH: u32 = 4;
+/// The angle of the edge, in degrees clockwise from the x axis, unless
+/// `?edge_test` gives one.
+pub const DEFAULT_ANGLE: f64 = 45.0;
+/// The angles the edge can be turned to. Nearer horizontal, the gradient
+/// along the row is wider than the pixels profiled.
+pub const ANGLES: RangeInclusive<f64> = 15.0..=165.0;
+/// How far a hairline's coverage can be from √2 before it looks wrong.
+const COVERAGE_TOLERANCE: f64 = 0.5;
+
+/// The edge, over an opaque background so that the values don't depend on
+/// the base colour or alpha mode.
+pub fn scene() -> Scene {
+    scene_at(DEFAULT_ANGLE)
+}
+
+/// The edge turned to `angle` degrees about the centre of the middle pixel
+/// of the profiled row, with black to its left. About the centre, rather
+/// than a corner, so that a vertical edge halves the pixel too.
+pub fn scene_at(angle: f64) -> Scene {
+    let mut scene = Scene::new();
+    let size = SIZE as f64;
+    let square = Rect::new(0.0, 0.0, size, size);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, Color::WHITE, None, &square);
+    // Where the edge crosses the top and bottom of the square.
+    let middle = ROW as f64 + 0.5;
+    let run = 1.0 / angle.to_radians().tan();
+    let (top, bottom) = (middle - middle * run, middle + (size - middle) * run);
+    let left = top.min(bottom).min(0.0);
+    let mut left_of = BezPath::new();
+    left_of.move_to((top, 0.0));
+    left_of.line_to((bottom, size));
+    left_of.line_to((left, size));
+    left_of.line_to((left, 0.0));
+    left_of.close_path();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::BLACK,
+        None,
+        &left_of,
+    );
+    scene
+}
+
+/// The hairline along the diagonal, black over white.
+pub fn hairline_scene() -> Scene {
+    let mut scene = Scene::new();
+    let size = SIZE as f64;
+    let square = Rect::new(0.0, 0.0, size, size);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, Color::WHITE, None, &square);
+    let line = Line::new((0.0, 0.0), (size, size));
+    scene.stroke(
+        &Stroke::new(1.0),
+        Affine::IDENTITY,
+        Color::BLACK,
+        None,
+        &line,
+    );
+    scene
+}
+
+/// The red values of the pixels in the profiled row of `pixels`, left to right.
+fn row(pixels: &[u8]) -> Vec<u8> {
+    (ROW - REACH..=ROW + REACH)
+        .map(|x| pixels[((ROW * SIZE + x) * 4) as usize])
+        .collect()
+}
+
+/// The red values of the pixels across the edge, left to right.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EdgeProfile {
+    pub values: Vec<u8>,
+    /// How many values are neither black nor white.
+    pub intermediate: usize,
+    /// Whether the values never get darker from left to right.
+    pub monotonic: bool,
+    /// The largest change between neighbouring values, the steepest part
+    /// of the gradient: 255 for a hard step, and less the smoother it is.
+    pub max_step: u8,
+    /// Whether the profile is a gradient, in order.
+    pub pass: bool,
+}
+
+impl EdgeProfile {
+    /// The profile of the edge in the RGBA `pixels` of a render of [`scene`].
+    pub fn of(pixels: &[u8]) -> Self {
+        let values = row(pixels);
+        let intermediate = values.iter().filter(|&&v| v != 0 && v != 255).count();
+        let monotonic = values.windows(2).all(|pair| pair[0] <= pair[1]);
+        EdgeProfile {
+            intermediate,
+            monotonic,
+            max_step: values
+                .windows(2)
+                .map(|pair| pair[0].abs_diff(pair[1]))
+                .max()
+                .unwrap_or(0),
+            pass: intermediate > 0 && monotonic,
+            values,
+        }
+    }
+
+    /// Whether the profile looks wrong: a hard step or out of order values.
+    pub fn is_abnormal(&self) -> bool {
+        !self.pass
+    }
+}
+
+impl fmt::Display for EdgeProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}, max step {}", self.values, self.max_step)?;
+        if self.intermediate == 0 {
+            write!(f, ", a hard step")?;
+        }
+        if !self.monotonic {
+            write!(f, ", out of order")?;
+        }
+        Ok(())
+    }
+}
+
+/// The edge tested with the AA config in use, for `?edge_test`.
+#[derive(Debug)]
+pub struct EdgeTest {
+    pub angle: f64,
+    /// Whether a test is in flight.
+    pending: bool,
+    /// The config last tested, and its profile if it could be read back.
+    result: Option<(AaConfig, Option<EdgeProfile>)>,
+}
+
+impl EdgeTest {
+    pub fn new(angle: f64) -> Self {
+        EdgeTest {
+            angle,
+            pending: false,
+            result: None,
+        }
+    }
+
+    /// Whether the edge is due to be tested with `aa`: none is in flight,
+    /// and it hasn't been already.
+    pub fn due(&self, aa: AaConfig) -> bool {
+        !self.pending && self.result.as_ref().is_none_or(|&(tested, _)| tested != aa)
+    }
+
+    pub fn started(&mut self) {
+        self.pending = true;
+    }
+
+    /// Keep the profile of a test with `aa`, or `None` if it failed.
+    pub fn finished(&mut self, aa: AaConfig, profile: Option<EdgeProfile>) {
+        self.pending = false;
+        self.result = Some((aa, profile));
+    }
+
+    /// The lines of the panel showing the result, once there is one.
+    pub fn lines(&self) -> Option<String> {
+        let (aa, profile) = self.result.as_ref()?;
+        let heading = format!("edge test {}° {:?}", self.angle, aa);
+        let Some(profile) = profile else {
+            return Some(format!("{}: not read back", heading));
+        };
+        let verdict = if profile.pass { "PASS" } else { "FAIL" };
+        let order = if profile.monotonic {
+            ""
+        } else {
+            ", out of order"
+        };
+        Some(format!(
+            "{}: {}\n{:?}\nmax step {}, {} between{}",
+            heading, verdict, profile.values, profile.max_step, profile.intermediate, order
+        ))
+    }
+}
+
+/// The red values of the pixels across the hairline, left to right.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HairlineProfile {
+    pub values: Vec<u8>,
+    /// The darkness of the pixels added up, as fractions of black.
+    pub coverage: f64,
+}
+
+impl HairlineProfile {
+    /// The profile of the hairline in the RGBA `pixels` of a render of [`hairline_scene`].
+    pub fn of(pixels: &[u8]) -> Self {
+        let values = row(pixels);
+        HairlineProfile {
+            coverage: values.iter().map(|&v| 1.0 - v as f64 / 255.0).sum(),
+            values,
+        }
+    }
+
+    /// Whether the line is much fainter or wider than a pixel.
+    pub fn is_abnormal(&self) -> bool {
+        (self.coverage - SQRT_2).abs() > COVERAGE_TOLERANCE
+    }
+}
+
+impl fmt::Display for HairlineProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}, coverage {:.2}", self.values, self.coverage)?;
+        if self.coverage < SQRT_2 - COVERAGE_TOLERANCE {
+            write!(f, ", too faint")?;
+        } else if self.coverage > SQRT_2 + COVERAGE_TOLERANCE {
+            write!(f, ", too wide")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::AaConfig;
+
+    use super::*;
+    use crate::readback::testing;
+
+    #[test]
+    fn edges_have_a_gradient() {
+        for aa in [AaConfig::Area, AaConfig::Msaa16] {
+            let Some(pixels) = testing::render_with(&scene(), SIZE, SIZE, aa) else {
+                return;
+            };
+            let profile = EdgeProfile::of(&pixels);
+            assert!(!profile.is_abnormal(), "{:?}: {}", aa, profile);
+            assert_eq!(profile.values[0], 0);
+            assert_eq!(profile.values[REACH as usize * 2], 255);
+            // The pixel on the diagonal is half covered.
+            let middle = profile.values[REACH as usize];
+            assert!((120..=136).contains(&middle), "{:?}: {}", aa, profile);
+        }
+    }
+
+    #[test]
+    fn edges_at_other_angles_have_a_gradient() {
+        let mut steps = Vec::new();
+        for angle in [*ANGLES.start(), 30.0, 60.0, 90.0, 135.0, *ANGLES.end()] {
+            let Some(pixels) = testing::render(&scene_at(angle), SIZE, SIZE) else {
+                return;
+            };
+            let profile = EdgeProfile::of(&pixels);
+            assert!(profile.pass, "{}°: {}", angle, profile);
+            steps.push(profile.max_step);
+        }
+        // The gradient steepens as the edge turns towards vertical.
+        assert!(steps[0] < steps[1] && steps[1] < steps[2], "{:?}", steps);
+    }
+
+    #[test]
+    fn edge_tests_say_whether_they_passed() {
+        let mut test = EdgeTest::new(DEFAULT_ANGLE);
+        assert!(test.due(AaConfig::Area));
+        test.started();
+        assert!(!test.due(AaConfig::Area));
+        assert_eq!(test.lines(), None);
+        let mut pixels = vec![0; (SIZE * SIZE * 4) as usize];
+        for (x, value) in (ROW - REACH..=ROW + REACH).zip([0, 0, 0, 32, 128, 224, 255, 200, 255]) {
+            pixels[((ROW * SIZE + x) * 4) as usize] = value;
+        }
+        test.finished(AaConfig::Area, Some(EdgeProfile::of(&pixels)));
+        // Tested again only with another config.
+        assert!(!test.due(AaConfig::Area));
+        assert!(test.due(AaConfig::Msaa16));
+        assert_eq!(
+            test.lines().unwrap(),
+            "edge test 45° Area: FAIL\n[0, 0, 0, 32, 128, 224, 255, 200, 255]\n\
+             max step 96, 4 between, out of order"
+        );
+    }
+
+    #[test]
+    fn hairlines_cover_a_pixel() {
+        for aa in [AaConfig::Area, AaConfig::Msaa16] {
+            let Some(pixels) = testing::render_with(&hairline_scene(), SIZE, SIZE, aa) else {
+                return;
+            };
+            let profile = HairlineProfile::of(&pixels);
+            assert!(!profile.is_abnormal(), "{:?}: {}", aa, profile);
+            // White away from the line.
+            assert_eq!(profile.values[0], 255);
+            assert_eq!(profile.values[REACH as usize * 2], 255);
+        }
+        // A line that isn't there at all.
+        let profile = HairlineProfile::of(&vec![255; (SIZE * SIZE * 4) as usize]);
+        assert!(profile.is_abnormal());
+        assert!(profile.to_string().ends_with("too faint"));
+    }
+
+    #[test]
+    fn steps_are_abnormal() {
+        let mut pixels = vec![0; (SIZE * SIZE * 4) as usize];
+        for x in ROW..SIZE {
+            pixels[((ROW * SIZE + x) * 4) as usize] = 255;
+        }
+        let profile = EdgeProfile::of(&pixels);
+        assert_eq!(profile.intermediate, 0);
+        assert!(profile.is_abnormal());
+    }
+}
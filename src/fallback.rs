@@ -0,0 +1,183 @@
+//! Telling whether frames are rendered on the GPU, or on a slower path.
+//!
+//! Nothing goes wrong when rendering falls back: Vello 0.4 has no way to
+//! ask how it renders, and a software adapter renders the same frames as a
+//! GPU, only far more slowly. So a report of slow, or subtly different,
+//! rendering may come down to the path it took. [`detect`] gathers what's
+//! known explicitly: whether Vello was built with its CPU shaders, which
+//! `?safe` does, and what the adapter says of itself. Natively that is
+//! wgpu's adapter info; on the web wgpu reports nothing of the adapter, so
+//! the browser is asked for the adapter it gives for the same request, and
+//! whether that is its fallback adapter. Timing isn't used: a heavy scene on
+//! a slow GPU can't be told from a light one on a software adapter.
+
+use serde::Serialize;
+
+use vello::wgpu;
+
+/// How frames are rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderPath {
+    /// Vello's shaders, on a GPU.
+    Gpu,
+    /// Vello's CPU shaders, for the stages that have them.
+    CpuShaders,
+    /// A software rasterizer standing in for a GPU, such as SwiftShader,
+    /// llvmpipe or WARP.
+    SoftwareAdapter,
+}
+
+/// What the browser says of the adapter it gives, as `GPUAdapterInfo`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BrowserAdapter {
+    pub vendor: String,
+    pub architecture: String,
+    pub description: String,
+    /// `isFallbackAdapter`, where the browser has it.
+    pub fallback: Option<bool>,
+}
+
+/// The render path found for an adapter, and why.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Detection {
+    pub path: RenderPath,
+    pub reason: String,
+    pub backend: wgpu::Backend,
+    pub adapter: String,
+    /// On the web, what the browser says of its adapter, if it could be asked.
+    pub browser: Option<BrowserAdapter>,
+}
+
+/// Names, in lower case, that software rasterizers give themselves.
+const SOFTWARE_NAMES: [&str; 5] = [
+    "swiftshader",
+    "llvmpipe",
+    "lavapipe",
+    "softpipe",
+    "microsoft basic render driver",
+];
+
+/// Whether `name` is that of a software rasterizer.
+fn is_software(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SOFTWARE_NAMES
+        .iter()
+        .any(|software| name.contains(software))
+}
+
+/// The render path of a renderer built with `use_cpu` on the adapter
+/// `info` describes, with what the browser says of it, if anything.
+pub fn detect(
+    info: &wgpu::AdapterInfo,
+    browser: Option<BrowserAdapter>,
+    use_cpu: bool,
+) -> Detection {
+    let browser_names = browser
+        .iter()
+        .flat_map(|b| [&b.vendor, &b.architecture, &b.description]);
+    let software_name = [&info.name]
+        .into_iter()
+        .chain(browser_names)
+        .find(|name| is_software(name));
+    let (path, reason) = if use_cpu {
+        (
+            RenderPath::CpuShaders,
+            "Vello was built with its CPU shaders".to_string(),
+        )
+    } else if browser.as_ref().is_some_and(|b| b.fallback == Some(true)) {
+        (
+            RenderPath::SoftwareAdapter,
+            "the browser gave its fallback adapter".to_string(),
+        )
+    } else if info.device_type == wgpu::DeviceType::Cpu {
+        (
+            RenderPath::SoftwareAdapter,
+            "the adapter is a CPU".to_string(),
+        )
+    } else if let Some(name) = software_name {
+        let reason = format!("'{}' is a software rasterizer", name);
+        (RenderPath::SoftwareAdapter, reason)
+    } else {
+        (RenderPath::Gpu, "nothing reports a fallback".to_string())
+    };
+    Detection {
+        path,
+        reason,
+        backend: info.backend,
+        adapter: info.name.clone(),
+        browser,
+    }
+}
+
+impl Detection {
+    /// Whether frames are rendered on a slower path than the GPU.
+    pub fn is_degraded(&self) -> bool {
+        self.path != RenderPath::Gpu
+    }
+
+    /// What to tell the user of a degraded path, in HTML.
+    pub fn notice(&self) -> String {
+        let consequence = match self.path {
+            RenderPath::Gpu => return String::new(),
+            RenderPath::CpuShaders => "Parts of each frame are computed on the CPU",
+            RenderPath::SoftwareAdapter => "Frames are rendered in software, not on your GPU",
+        };
+        format!(
+            "{} ({}), so rendering is much slower than usual, and may differ from the GPU's. \
+             Slow or wrong rendering here may not happen on a GPU.",
+            consequence, self.reason
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallbacks_are_detected() {
+        let info = |name: &str, device_type| wgpu::AdapterInfo {
+            name: name.to_string(),
+            vendor: 0,
+            device: 0,
+            device_type,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: wgpu::Backend::Vulkan,
+        };
+        let gpu = info("NVIDIA GeForce RTX 3060", wgpu::DeviceType::DiscreteGpu);
+        assert_eq!(detect(&gpu, None, false).path, RenderPath::Gpu);
+        assert!(!detect(&gpu, None, false).is_degraded());
+        assert_eq!(detect(&gpu, None, true).path, RenderPath::CpuShaders);
+        let cpu = info("Unknown", wgpu::DeviceType::Cpu);
+        assert_eq!(detect(&cpu, None, false).path, RenderPath::SoftwareAdapter);
+        let llvmpipe = info("llvmpipe (LLVM 15.0.7, 256 bits)", wgpu::DeviceType::Other);
+        let detection = detect(&llvmpipe, None, false);
+        assert_eq!(detection.path, RenderPath::SoftwareAdapter);
+        assert!(detection.notice().contains("software rasterizer"));
+
+        // The web's adapter info is empty, leaving the browser's.
+        let web = wgpu::AdapterInfo {
+            backend: wgpu::Backend::BrowserWebGpu,
+            ..info("", wgpu::DeviceType::Other)
+        };
+        assert_eq!(detect(&web, None, false).path, RenderPath::Gpu);
+        let swiftshader = BrowserAdapter {
+            vendor: "google".to_string(),
+            architecture: "swiftshader".to_string(),
+            ..BrowserAdapter::default()
+        };
+        assert_eq!(
+            detect(&web, Some(swiftshader), false).path,
+            RenderPath::SoftwareAdapter
+        );
+        let fallback = BrowserAdapter {
+            fallback: Some(true),
+            ..BrowserAdapter::default()
+        };
+        let detection = detect(&web, Some(fallback), false);
+        assert_eq!(detection.path, RenderPath::SoftwareAdapter);
+        assert_eq!(detection.reason, "the browser gave its fallback adapter");
+    }
+}
@@ -0,0 +1,164 @@
+//! A flipbook of scenes loaded from JSON, for stepping through captured frames.
+//!
+//! Each scene is a list of shapes, each an SVG path with a fill and/or a
+//! stroke, so that the frames of an animation which shows a rendering bug
+//! can be saved and stepped through one at a time to find the frame that
+//! goes wrong:
+//!
+//! ```json
+//! [
+//!   {"shapes": [{"path": "M10 10 L90 10 L50 80 Z", "fill": "red"}]},
+//!   {"shapes": [{"path": "M10 20 L90 20 L50 90 Z", "fill": "red",
+//!                "stroke": "#000", "stroke_width": 2,
+//!                "transform": [1, 0, 0, 1, 5, 0]}]}
+//! ]
+//! ```
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use vello::kurbo::{Affine, BezPath, Stroke};
+use vello::peniko::{color::parse_color, Color, Fill};
+use vello::Scene;
+
+#[derive(Debug, Deserialize)]
+struct ShapeDesc {
+    /// SVG path data.
+    path: String,
+    /// CSS colours.
+    fill: Option<String>,
+    stroke: Option<String>,
+    #[serde(default = "default_stroke_width")]
+    stroke_width: f64,
+    /// The coefficients in the order of the 2D canvas `setTransform`.
+    transform: Option<[f64; 6]>,
+}
+
+fn default_stroke_width() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+struct SceneDesc {
+    shapes: Vec<ShapeDesc>,
+}
+
+fn color(name: &str) -> Result<Color, String> {
+    parse_color(name)
+        .map(|color| color.to_alpha_color())
+        .map_err(|e| format!("invalid colour '{}': {}", name, e))
+}
+
+/// Encode the shapes of a scene description.
+fn encode(desc: &SceneDesc) -> Result<Scene, String> {
+    let mut scene = Scene::new();
+    for (i, shape) in desc.shapes.iter().enumerate() {
+        let path = BezPath::from_svg(&shape.path)
+            .map_err(|e| format!("shape {}: invalid path: {}", i, e))?;
+        let transform = shape.transform.map_or(Affine::IDENTITY, Affine::new);
+        if let Some(fill) = &shape.fill {
+            scene.fill(Fill::NonZero, transform, color(fill)?, None, &path);
+        }
+        if let Some(stroke) = &shape.stroke {
+            let style = Stroke::new(shape.stroke_width);
+            scene.stroke(&style, transform, color(stroke)?, None, &path);
+        }
+    }
+    Ok(scene)
+}
+
+/// A list of encoded scenes, with the one being shown.
+pub struct Flipbook {
+    scenes: Vec<Scene>,
+    index: usize,
+    /// Advance to the next scene every frame.
+    pub playing: bool,
+}
+
+impl fmt::Debug for Flipbook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Flipbook")
+            .field("scenes", &self.scenes.len())
+            .field("index", &self.index)
+            .field("playing", &self.playing)
+            .finish()
+    }
+}
+
+impl Flipbook {
+    /// Load a JSON array of scene descriptions. Fails if there are none, or
+    /// if any is invalid, naming which.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let descs: Vec<SceneDesc> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if descs.is_empty() {
+            return Err("the flipbook has no scenes".into());
+        }
+        let scenes = descs
+            .iter()
+            .enumerate()
+            .map(|(i, desc)| encode(desc).map_err(|e| format!("scene {}: {}", i, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Flipbook {
+            scenes,
+            index: 0,
+            playing: false,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// The index of the current scene.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn current(&self) -> &Scene {
+        &self.scenes[self.index]
+    }
+
+    /// Step forwards or backwards through the scenes, wrapping around at either end.
+    pub fn step(&mut self, forward: bool) {
+        let len = self.scenes.len();
+        self.index = if forward {
+            (self.index + 1) % len
+        } else {
+            (self.index + len - 1) % len
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_and_step() {
+        let json = r#"[
+            {"shapes": [{"path": "M0 0 L10 0 L10 10 Z", "fill": "red"}]},
+            {"shapes": []},
+            {"shapes": [{"path": "M0 0 L5 5", "stroke": "blue", "transform": [2, 0, 0, 2, 0, 0]}]}
+        ]"#;
+        let mut flipbook = Flipbook::from_json(json).unwrap();
+        assert_eq!(flipbook.len(), 3);
+        assert!(!flipbook.current().encoding().path_tags.is_empty());
+        flipbook.step(false);
+        assert_eq!(flipbook.index(), 2);
+        flipbook.step(true);
+        flipbook.step(true);
+        assert_eq!(flipbook.index(), 1);
+        assert!(flipbook.current().encoding().path_tags.is_empty());
+    }
+
+    #[test]
+    fn errors_name_the_scene() {
+        let bad_path = r#"[{"shapes": []}, {"shapes": [{"path": "M0 0 Q", "fill": "red"}]}]"#;
+        let error = Flipbook::from_json(bad_path).err().unwrap();
+        assert!(error.starts_with("scene 1: shape 0"), "{}", error);
+        let bad_color = r#"[{"shapes": [{"path": "M0 0 L1 1", "fill": "reddish"}]}]"#;
+        assert!(Flipbook::from_json(bad_color).is_err());
+        assert!(Flipbook::from_json("[]").is_err());
+    }
+}
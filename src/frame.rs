@@ -0,0 +1,122 @@
+//! Rendering one frame to a surface texture, independently of any event loop.
+//!
+//! The client and the worker both render a frame the same way: render the
+//! scene to the surface texture, present it, and poll the device as the
+//! [`PollStrategy`] says, so that finished work is cleaned up. [`render_frame`] does just that, so a host
+//! that owns its own event loop and surface can render a scene without
+//! going through the client's `ApplicationHandler`.
+//!
+//! The client can also render each frame several times into the same
+//! texture before presenting it, to see whether repeated submissions expose
+//! a problem that one doesn't. Each pass is timed on the CPU, from the start
+//! of its encoding to its submission, as the GPU runs it asynchronously.
+
+use std::str::FromStr;
+
+use log::info;
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+use web_time::Instant;
+
+/// How the device is polled after presenting a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PollStrategy {
+    /// Poll without blocking, cleaning up whatever work has finished.
+    #[default]
+    Poll,
+    /// Block until all the submitted work has finished. Natively this
+    /// serialises the CPU and the GPU; on the web polling does nothing.
+    Wait,
+    /// Don't poll: cleanup waits for the next poll, or the next submission.
+    Skip,
+}
+
+impl FromStr for PollStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "poll" => Ok(PollStrategy::Poll),
+            "wait" => Ok(PollStrategy::Wait),
+            "skip" => Ok(PollStrategy::Skip),
+            _ => Err(format!("unknown poll strategy '{}'", s)),
+        }
+    }
+}
+
+/// When each step of a frame finished, for timing it from the caller's start.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameTimes {
+    pub rendered: Instant,
+    pub presented: Instant,
+    pub polled: Instant,
+    /// Whether the device had no submissions in flight when polled, or
+    /// `None` if it wasn't. Persistently pending work after a non-blocking
+    /// poll shows the GPU falling behind.
+    pub queue_empty: Option<bool>,
+}
+
+/// Render `scene` to `surface_texture`, present it and poll the device with `poll`.
+/// If rendering fails, the texture is dropped without being presented.
+pub fn render_frame(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    scene: &Scene,
+    surface_texture: wgpu::SurfaceTexture,
+    params: &RenderParams,
+    poll: PollStrategy,
+) -> Result<FrameTimes, vello::Error> {
+    render_frame_with(device, surface_texture, poll, 1, |texture| {
+        renderer.render_to_surface(device, queue, scene, texture, params)
+    })
+}
+
+/// As [`render_frame`], with the scene rendered to the texture `passes`
+/// times by `render`, e.g. at a reduced resolution and scaled up. With more
+/// than one pass, the time of each is logged.
+pub fn render_frame_with(
+    device: &wgpu::Device,
+    surface_texture: wgpu::SurfaceTexture,
+    poll: PollStrategy,
+    passes: u32,
+    mut render: impl FnMut(&wgpu::SurfaceTexture) -> Result<(), vello::Error>,
+) -> Result<FrameTimes, vello::Error> {
+    let mut times = Vec::with_capacity(passes as usize);
+    for _ in 0..passes.max(1) {
+        let start = Instant::now();
+        render(&surface_texture)?;
+        times.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    if passes > 1 {
+        let times: Vec<String> = times.iter().map(|ms| format!("{:.2}", ms)).collect();
+        info!("Render passes (ms): {}", times.join(", "));
+    }
+    let rendered = Instant::now();
+    surface_texture.present();
+    let presented = Instant::now();
+    let maintain = match poll {
+        PollStrategy::Poll => Some(wgpu::Maintain::Poll),
+        PollStrategy::Wait => Some(wgpu::Maintain::Wait),
+        PollStrategy::Skip => None,
+    };
+    let queue_empty = maintain.map(|maintain| device.poll(maintain).is_queue_empty());
+    Ok(FrameTimes {
+        rendered,
+        presented,
+        polled: Instant::now(),
+        queue_empty,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_poll_strategy() {
+        assert_eq!("wait".parse(), Ok(PollStrategy::Wait));
+        assert_eq!("skip".parse(), Ok(PollStrategy::Skip));
+        assert!("Poll".parse::<PollStrategy>().is_err());
+    }
+}
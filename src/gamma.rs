@@ -0,0 +1,142 @@
+//! Gamma adjustment of the final image.
+//!
+//! To tell whether colours that look wrong on one display are a gamma
+//! problem, [`GammaPass`] renders the scene into a texture and then draws
+//! that into the output with a small fragment shader raising each colour
+//! component to the power `1 / gamma`. A gamma above 1 brightens the mid
+//! tones and one below 1 darkens them, leaving black and white unchanged.
+//! Vello stores its output with straight alpha, so translucent pixels are
+//! adjusted like opaque ones, and alpha is left alone. Values are written to the output as they are, as Vello's own blit does.
+
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+use crate::blit::{Blit, Source};
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> gamma: vec4<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(source, vec2<i32>(position.xy), 0);
+    return vec4<f32>(pow(color.rgb, vec3<f32>(1.0 / gamma.x)), color.a);
+}
+"#;
+
+/// The texture the scene is rendered into.
+struct Target {
+    source: Source,
+    width: u32,
+    height: u32,
+}
+
+/// Renders scenes with their gamma adjusted, into outputs of one format.
+pub struct GammaPass {
+    blit: Blit,
+    pipeline: wgpu::RenderPipeline,
+    target: Option<Target>,
+}
+
+impl GammaPass {
+    /// Build the pipeline, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        // Of the uniform, only the first component is used.
+        let blit = Blit::new(device, "gamma", SHADER, 1, true);
+        let pipeline = blit.pipeline(device, "fs_main", format, None);
+        GammaPass {
+            blit,
+            pipeline,
+            target: None,
+        }
+    }
+
+    /// The target to render into at the given size, replacing one of another size.
+    fn target(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Target {
+        if let Some(target) = self.target.take() {
+            if target.width == width && target.height == height {
+                return target;
+            }
+        }
+        let usage = wgpu::TextureUsages::STORAGE_BINDING;
+        Target {
+            source: self
+                .blit
+                .source(device, "gamma source", width, height, usage),
+            width,
+            height,
+        }
+    }
+
+    /// Render `scene` with `params`, then draw it into `output` with its
+    /// gamma adjusted by `gamma`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+        gamma: f32,
+    ) -> Result<(), vello::Error> {
+        self.blit.set_uniform(queue, [gamma.to_bits(), 0, 0, 0]);
+        let target = self.target(device, params.width, params.height);
+        let view = &target.source.texture.view;
+        let rendered = renderer.render_to_texture(device, queue, scene, view, params);
+        let target = self.target.insert(target);
+        rendered?;
+
+        let draw = (&self.pipeline, &target.source.bind_group);
+        let load = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        self.blit.submit(device, queue, output, load, &[draw]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Affine, Rect};
+    use vello::peniko::{Color, Fill};
+    use vello::AaConfig;
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    #[test]
+    fn mid_tones_are_adjusted() {
+        let Some((context, dev_id, mut renderer)) = renderer(&[AaConfig::Area]) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 2, 1);
+        // A mid grey pixel, and a translucent white one.
+        let mut scene = Scene::new();
+        let grey = Color::from_rgb8(64, 64, 64);
+        let rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, grey, None, &rect);
+        let white = Color::from_rgba8(255, 255, 255, 128);
+        let rect = Rect::new(1.0, 0.0, 2.0, 1.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, white, None, &rect);
+        let params = RenderParams {
+            base_color: Color::TRANSPARENT,
+            width: 2,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let mut pass = GammaPass::new(device, blit::FORMAT);
+        pass.render(&mut renderer, device, queue, &scene, &view, &params, 2.0)
+            .unwrap();
+        let pixels = block_on(Readback::new(device, queue, &output, 2, 1).read(device)).unwrap();
+        // 255 * (64 / 255)^(1 / 2) is 127.75.
+        let grey = pixels[0];
+        assert!((127..=128).contains(&grey), "{:?}", pixels);
+        // White stays white, and translucent pixels keep their alpha.
+        assert_eq!(pixels[4], 255, "{:?}", pixels);
+        assert!((127..=129).contains(&pixels[7]), "{:?}", pixels);
+    }
+}
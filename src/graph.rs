@@ -0,0 +1,190 @@
+//! A lightweight retained scene graph.
+//!
+//! Each node holds an optional shape with its paint, a transform relative to
+//! its parent, and its children. Nodes keep their encoded content between
+//! frames, so that rendering the graph only re-encodes what has changed:
+//!
+//! - A node's own geometry is encoded once, and again only after its shape
+//!   or paint changes.
+//! - A node also caches its whole subtree, built by appending the children's
+//!   cached subtrees with their transforms applied. When only a transform
+//!   changes, the affected subtrees are re-appended, but none of the path
+//!   geometry is encoded again.
+
+use vello::kurbo::{Affine, BezPath, Shape, Stroke};
+use vello::peniko::{Color, Fill};
+use vello::Scene;
+
+/// How a node's shape is painted: filled, stroked, or both.
+#[derive(Clone, Debug, Default)]
+pub struct Paint {
+    pub fill: Option<Color>,
+    pub stroke: Option<(Stroke, Color)>,
+}
+
+impl Paint {
+    pub fn fill(color: Color) -> Self {
+        Paint {
+            fill: Some(color),
+            stroke: None,
+        }
+    }
+
+    pub fn stroke(width: f64, color: Color) -> Self {
+        Paint::default().with_stroke(width, color)
+    }
+
+    pub fn with_stroke(mut self, width: f64, color: Color) -> Self {
+        self.stroke = Some((Stroke::new(width), color));
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct Node {
+    shape: Option<(BezPath, Paint)>,
+    transform: Affine,
+    children: Vec<Node>,
+    /// The shape or paint changed, so the node's own content must be re-encoded.
+    dirty: bool,
+    /// The children changed, so the subtree cache must be rebuilt.
+    relink: bool,
+    /// The transform changed since the parent last cached this node.
+    moved: bool,
+    /// The node's own shape, encoded in local coordinates.
+    content: Scene,
+    /// The content and all the children, in local coordinates.
+    subtree: Scene,
+}
+
+impl Node {
+    /// An empty group node, for holding children.
+    pub fn group() -> Self {
+        Node {
+            relink: true,
+            ..Default::default()
+        }
+    }
+
+    /// A node drawing `shape` with `paint`.
+    pub fn shape(shape: &impl Shape, paint: Paint) -> Self {
+        Node {
+            shape: Some((shape.to_path(0.1), paint)),
+            dirty: true,
+            relink: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Affine) {
+        if transform != self.transform {
+            self.transform = transform;
+            self.moved = true;
+        }
+    }
+
+    pub fn set_paint(&mut self, paint: Paint) {
+        if let Some((_, current)) = &mut self.shape {
+            *current = paint;
+            self.dirty = true;
+        }
+    }
+
+    /// Add a child, returning its index.
+    pub fn push(&mut self, child: Node) -> usize {
+        self.children.push(child);
+        self.relink = true;
+        self.children.len() - 1
+    }
+
+    /// Access a child for modification. Changes are picked up on the next render.
+    pub fn child_mut(&mut self, index: usize) -> Option<&mut Node> {
+        self.children.get_mut(index)
+    }
+
+    /// Append the graph to `scene`, re-encoding only the nodes that changed.
+    /// Returns the number of nodes whose geometry was encoded.
+    pub fn render_into(&mut self, scene: &mut Scene) -> usize {
+        let mut encoded = 0;
+        self.update(&mut encoded);
+        scene.append(&self.subtree, Some(self.transform));
+        encoded
+    }
+
+    /// Bring the cached content up to date. Returns true if the parent's cache
+    /// of this subtree is out of date.
+    fn update(&mut self, encoded: &mut usize) -> bool {
+        let mut rebuild = self.relink;
+        if self.dirty {
+            self.encode_content();
+            *encoded += 1;
+            rebuild = true;
+        }
+        for child in &mut self.children {
+            rebuild |= child.update(encoded);
+        }
+        if rebuild {
+            self.subtree.reset();
+            self.subtree.append(&self.content, None);
+            for child in &self.children {
+                self.subtree.append(&child.subtree, Some(child.transform));
+            }
+        }
+        let changed = rebuild || self.moved;
+        self.dirty = false;
+        self.relink = false;
+        self.moved = false;
+        changed
+    }
+
+    fn encode_content(&mut self) {
+        self.content.reset();
+        let Some((path, paint)) = &self.shape else {
+            return;
+        };
+        if let Some(color) = paint.fill {
+            self.content
+                .fill(Fill::NonZero, Affine::IDENTITY, color, None, path);
+        }
+        if let Some((stroke, color)) = &paint.stroke {
+            self.content
+                .stroke(stroke, Affine::IDENTITY, *color, None, path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vello::kurbo::Rect;
+
+    fn graph() -> Node {
+        let mut root = Node::group();
+        let square = Rect::new(0.0, 0.0, 10.0, 10.0);
+        root.push(Node::shape(&square, Paint::fill(Color::BLACK)));
+        root.push(Node::shape(&square, Paint::stroke(1.0, Color::BLACK)));
+        root
+    }
+
+    #[test]
+    fn only_changed_nodes_are_encoded() {
+        let mut root = graph();
+        let mut scene = Scene::new();
+        assert_eq!(root.render_into(&mut scene), 2);
+        let first = scene.encoding().path_tags.len();
+
+        // Moving a child re-links the subtree without encoding any geometry.
+        root.child_mut(0)
+            .unwrap()
+            .set_transform(Affine::translate((5.0, 0.0)));
+        scene.reset();
+        assert_eq!(root.render_into(&mut scene), 0);
+        assert_eq!(scene.encoding().path_tags.len(), first);
+
+        root.child_mut(1)
+            .unwrap()
+            .set_paint(Paint::fill(Color::WHITE));
+        scene.reset();
+        assert_eq!(root.render_into(&mut scene), 1);
+    }
+}
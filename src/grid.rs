@@ -0,0 +1,169 @@
+//! A content grid that stays crisp at any pan and zoom.
+//!
+//! Transforming 1px grid lines drawn in content space would scale their
+//! width with the zoom, and put them at fractional pixel positions, where
+//! they are drawn as two half-covered pixel columns. Instead, each grid line
+//! is transformed to the surface, and a 1px line is drawn through the centre
+//! of the device pixel it lands in, so every line covers exactly one pixel
+//! row or column. This only works while the view transform keeps the axes
+//! aligned; when rotated or skewed, the lines are drawn 1px wide along their
+//! transformed positions, unsnapped.
+
+use vello::kurbo::{Affine, BezPath, Line, Point, Rect, Stroke};
+use vello::peniko::Color;
+use vello::Scene;
+
+/// Lines closer together than this on the surface are thinned out, by
+/// doubling the spacing, so that the grid never becomes a solid fill.
+const MIN_PIXEL_SPACING: f64 = 8.0;
+
+/// A grid of lines at multiples of `spacing` in content coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    pub spacing: f64,
+    pub color: Color,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Grid {
+            spacing: 50.0,
+            color: Color::from_rgba8(0, 0, 0, 64),
+        }
+    }
+}
+
+impl Grid {
+    /// The grid lines within `viewport`, in surface coordinates, for content
+    /// drawn with `transform`.
+    fn path(&self, viewport: Rect, transform: Affine) -> BezPath {
+        let mut path = BezPath::new();
+        let [a, b, c, d, e, f] = transform.as_coeffs();
+        if !self.spacing.is_finite() || self.spacing <= 0.0 || transform.determinant() == 0.0 {
+            return path;
+        }
+        let content = transform.inverse().transform_rect_bbox(viewport);
+        let axis_aligned = b == 0.0 && c == 0.0;
+        // The spacing of each axis's lines on the surface.
+        let scale_x = a.hypot(b);
+        let scale_y = c.hypot(d);
+        let step = |scale: f64| {
+            let mut step = self.spacing;
+            while step * scale < MIN_PIXEL_SPACING {
+                step *= 2.0;
+            }
+            step
+        };
+        // The centre of the device pixel a surface coordinate falls in.
+        let snap = |v: f64| v.floor() + 0.5;
+
+        let step_x = step(scale_x);
+        let mut x = (content.x0 / step_x).ceil() * step_x;
+        while x <= content.x1 {
+            if axis_aligned {
+                let sx = snap(a * x + e);
+                path.move_to((sx, viewport.y0));
+                path.line_to((sx, viewport.y1));
+            } else {
+                let line = Line::new(
+                    transform * Point::new(x, content.y0),
+                    transform * Point::new(x, content.y1),
+                );
+                path.move_to(line.p0);
+                path.line_to(line.p1);
+            }
+            x += step_x;
+        }
+        let step_y = step(scale_y);
+        let mut y = (content.y0 / step_y).ceil() * step_y;
+        while y <= content.y1 {
+            if axis_aligned {
+                let sy = snap(d * y + f);
+                path.move_to((viewport.x0, sy));
+                path.line_to((viewport.x1, sy));
+            } else {
+                let line = Line::new(
+                    transform * Point::new(content.x0, y),
+                    transform * Point::new(content.x1, y),
+                );
+                path.move_to(line.p0);
+                path.line_to(line.p1);
+            }
+            y += step_y;
+        }
+        path
+    }
+
+    /// Draw the grid over `viewport` on the surface, for content drawn with
+    /// `transform`. The caller clips to the viewport, which rotated lines
+    /// overhang.
+    pub fn draw(&self, scene: &mut Scene, viewport: Rect, transform: Affine) {
+        let path = self.path(viewport, transform);
+        scene.stroke(&Stroke::new(1.0), Affine::IDENTITY, self.color, None, &path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::PathEl;
+
+    use super::*;
+
+    /// The points the grid's lines start and end at.
+    fn points(path: &BezPath) -> Vec<Point> {
+        path.elements()
+            .iter()
+            .filter_map(|el| match *el {
+                PathEl::MoveTo(p) | PathEl::LineTo(p) => Some(p),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines_are_on_pixel_centres_at_any_zoom() {
+        let grid = Grid {
+            spacing: 10.0,
+            ..Grid::default()
+        };
+        let viewport = Rect::new(0.0, 0.0, 200.0, 100.0);
+        let transform = Affine::translate((3.3, -7.8)) * Affine::scale(1.37);
+        let path = grid.path(viewport, transform);
+        let points = points(&path);
+        assert!(!points.is_empty());
+        for p in points {
+            // Each line is vertical or horizontal, through pixel centres.
+            let on_centre = |v: f64| (v - v.floor() - 0.5).abs() < 1e-9;
+            assert!(on_centre(p.x) || on_centre(p.y), "{:?}", p);
+        }
+        // The lines are 13.7px apart: 15 columns and 7 rows fall in the viewport.
+        assert_eq!(path.elements().len(), (15 + 7) * 2);
+    }
+
+    #[test]
+    fn each_axis_is_thinned_by_its_own_scale() {
+        let grid = Grid {
+            spacing: 10.0,
+            ..Grid::default()
+        };
+        let viewport = Rect::new(0.0, 0.0, 100.0, 100.0);
+        // Columns 20px apart, and rows 5px apart, thinned to every other one.
+        let path = grid.path(viewport, Affine::scale_non_uniform(2.0, 0.5));
+        assert_eq!(path.elements().len(), (6 + 11) * 2);
+        let xs: Vec<f64> = points(&path).iter().take(4).map(|p| p.x).collect();
+        assert_eq!(xs, [0.5, 0.5, 20.5, 20.5]);
+    }
+
+    #[test]
+    fn dense_grids_are_thinned() {
+        let grid = Grid {
+            spacing: 1.0,
+            ..Grid::default()
+        };
+        let viewport = Rect::new(0.0, 0.0, 100.0, 100.0);
+        // 1 unit is 0.5px, so only every 16th line is drawn, 8px apart.
+        let path = grid.path(viewport, Affine::scale(0.5));
+        assert_eq!(path.elements().len(), (13 + 13) * 2);
+        assert!(grid.path(viewport, Affine::scale(0.0)).is_empty());
+    }
+}
@@ -0,0 +1,130 @@
+//! Reading the colour under the cursor, with the inspector.
+//!
+//! While the inspector is on, moving the cursor reads back the pixel under
+//! it: the frame's scene, translated by whole pixels so that the pixel is
+//! at the origin, is rendered into a 1x1 texture and copied back, which
+//! gives the bytes Vello renders there without copying the surface, which
+//! may not allow it. A tooltip beside the cursor shows the value and the
+//! point under the cursor in scene coordinates. The tooltip is drawn away
+//! from the cursor so that it is never in the pixel read.
+//!
+//! Reads are throttled by [`Inspector`]: only one is in flight at a time,
+//! and each starts at least [`INTERVAL`] after the last, so that a mouse
+//! moving fast doesn't queue a render and a buffer mapping for every event.
+//! A move while a read is due is not lost: another read follows once the
+//! one due is done, at wherever the cursor is then.
+
+use std::fmt;
+use std::time::Duration;
+
+use web_time::Instant;
+
+use vello::kurbo::Point;
+
+/// The least time from the start of one read to the start of the next.
+pub const INTERVAL: Duration = Duration::from_millis(50);
+
+/// A pixel read back, and where it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// In surface pixels.
+    pub pixel: (u32, u32),
+    /// The point under the cursor, in scene coordinates.
+    pub point: Point,
+    /// With straight alpha, as Vello renders it.
+    pub rgba: [u8; 4],
+}
+
+impl fmt::Display for Sample {
+    /// The lines of the tooltip.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b, a] = self.rgba;
+        writeln!(f, "rgba {} {} {} {}", r, g, b, a)?;
+        writeln!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)?;
+        writeln!(f, "pixel {}, {}", self.pixel.0, self.pixel.1)?;
+        write!(f, "scene {:.1}, {:.1}", self.point.x, self.point.y)
+    }
+}
+
+/// The last sample, and when to read the next.
+#[derive(Debug, Default)]
+pub struct Inspector {
+    pub sample: Option<Sample>,
+    /// Whether a read is scheduled or in flight.
+    busy: bool,
+    /// Whether the cursor moved since the read due started.
+    moved: bool,
+    /// When the last read started.
+    last: Option<Instant>,
+}
+
+impl Inspector {
+    /// Note the cursor moving at `now`. Returns how long to wait before
+    /// starting a read, or `None` if one is already due.
+    pub fn moved(&mut self, now: Instant) -> Option<Duration> {
+        if self.busy {
+            self.moved = true;
+            return None;
+        }
+        self.busy = true;
+        let since = self.last.map_or(INTERVAL, |last| now - last);
+        Some(INTERVAL.saturating_sub(since))
+    }
+
+    /// Note a read starting at `now`.
+    pub fn started(&mut self, now: Instant) {
+        self.last = Some(now);
+        self.moved = false;
+    }
+
+    /// Keep the sample a read finished with at `now`, if it succeeded.
+    /// Returns how long to wait before the next read, if the cursor moved
+    /// while it was in flight.
+    pub fn finished(&mut self, sample: Option<Sample>, now: Instant) -> Option<Duration> {
+        self.busy = false;
+        if sample.is_some() {
+            self.sample = sample;
+        }
+        if !std::mem::take(&mut self.moved) {
+            return None;
+        }
+        self.moved(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_are_throttled() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut inspector = Inspector::default();
+        // The first move reads at once, and moves while it's due are held.
+        assert_eq!(inspector.moved(ms(0)), Some(Duration::ZERO));
+        inspector.started(ms(0));
+        assert_eq!(inspector.moved(ms(5)), None);
+        assert_eq!(inspector.moved(ms(10)), None);
+        // Until it's done, when the next waits out the interval.
+        let sample = Sample {
+            pixel: (3, 4),
+            point: Point::new(1.5, 2.0),
+            rgba: [255, 128, 0, 255],
+        };
+        assert_eq!(
+            inspector.finished(Some(sample), ms(20)),
+            Some(Duration::from_millis(30))
+        );
+        inspector.started(ms(50));
+        assert_eq!(inspector.finished(None, ms(60)), None);
+        assert_eq!(inspector.sample, Some(sample));
+        // A move long after reads at once again.
+        assert_eq!(inspector.moved(ms(200)), Some(Duration::ZERO));
+        let lines = sample.to_string();
+        assert_eq!(
+            lines,
+            "rgba 255 128 0 255\n#ff8000ff\npixel 3, 4\nscene 1.5, 2.0"
+        );
+    }
+}
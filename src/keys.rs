@@ -0,0 +1,71 @@
+//! Keyboard bindings, and the on-screen legend describing them.
+
+/// A key binding shown in the legend, with the current state of whatever it toggles.
+pub struct Binding {
+    pub key: &'static str,
+    pub action: &'static str,
+    pub state: Option<String>,
+}
+
+impl Binding {
+    pub fn new(key: &'static str, action: &'static str) -> Self {
+        Binding {
+            key,
+            action,
+            state: None,
+        }
+    }
+
+    pub fn with_state(mut self, state: impl ToString) -> Self {
+        self.state = Some(state.to_string());
+        self
+    }
+}
+
+/// Display name for a boolean toggle state.
+pub fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Lay out the bindings as aligned text columns: key, action and state.
+pub fn legend(bindings: &[Binding]) -> String {
+    let key_width = bindings.iter().map(|b| b.key.len()).max().unwrap_or(0);
+    let action_width = bindings.iter().map(|b| b.action.len()).max().unwrap_or(0);
+    bindings
+        .iter()
+        .map(|b| match &b.state {
+            Some(state) => format!(
+                "{:kw$}  {:aw$}  {}",
+                b.key,
+                b.action,
+                state,
+                kw = key_width,
+                aw = action_width
+            ),
+            None => format!("{:kw$}  {}", b.key, b.action, kw = key_width),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legend_columns_align() {
+        let bindings = [
+            Binding::new("a", "MSAA").with_state(on_off(true)),
+            Binding::new("Del", "clear strokes"),
+            Binding::new("o", "overlay").with_state(on_off(false)),
+        ];
+        assert_eq!(
+            legend(&bindings),
+            "a    MSAA           on\nDel  clear strokes\no    overlay        off"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Measuring the time from a key press to the frame that shows it.
+//!
+//! Each press of the latency key toggles a flash covering the whole surface,
+//! and [`LatencyProbe`] keeps when the key was handled. The next frame
+//! presented is the first drawn since, so it is the one with the flash in
+//! or out, and the time from the key to its present is the latency through
+//! the client and the GPU path. It doesn't include the time the browser
+//! took to deliver the key event, nor the display's own latency, which a
+//! camera or photodiode watching the flash can add.
+
+use serde::Serialize;
+use web_time::Instant;
+
+/// The latencies measured so far.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub samples: usize,
+    pub last_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyProbe {
+    /// Whether the flash is drawn.
+    pub flash: bool,
+    /// When the key was handled, until a frame is presented.
+    pending: Option<Instant>,
+    times: Vec<f64>,
+}
+
+impl LatencyProbe {
+    /// Toggle the flash for a key handled at `now`.
+    pub fn pressed(&mut self, now: Instant) {
+        self.flash = !self.flash;
+        self.pending = Some(now);
+    }
+
+    /// Record a frame presented at `presented`. If it was the first since the
+    /// key, returns the latencies with this one added.
+    pub fn presented(&mut self, presented: Instant) -> Option<Summary> {
+        let pressed = self.pending.take()?;
+        let ms = (presented - pressed).as_secs_f64() * 1000.0;
+        self.times.push(ms);
+        Some(Summary {
+            samples: self.times.len(),
+            last_ms: ms,
+            mean_ms: self.times.iter().sum::<f64>() / self.times.len() as f64,
+            max_ms: self.times.iter().copied().fold(0.0, f64::max),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn the_first_present_after_a_key_is_timed() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut probe = LatencyProbe::default();
+        assert_eq!(probe.presented(start), None);
+        probe.pressed(start);
+        assert!(probe.flash);
+        assert_eq!(probe.presented(start + ms(20)).unwrap().last_ms, 20.0);
+        // Later frames aren't the one that changed.
+        assert_eq!(probe.presented(start + ms(40)), None);
+        probe.pressed(start + ms(100));
+        assert!(!probe.flash);
+        let summary = probe.presented(start + ms(140)).unwrap();
+        assert_eq!(
+            summary,
+            Summary {
+                samples: 2,
+                last_ms: 40.0,
+                mean_ms: 30.0,
+                max_ms: 40.0,
+            }
+        );
+    }
+}
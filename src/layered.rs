@@ -0,0 +1,197 @@
+//! Rendering the content and the overlays over it with AA configs of their own.
+//!
+//! Vello takes one AA config per render, for the whole scene. To give the
+//! content one config and the overlays drawn over it, the panels' text
+//! among them, another, [`LayerPass`] renders them as two scenes: the
+//! content over the base colour into one texture, and the overlays over a
+//! transparent base into another. It then draws the overlays over the
+//! content into the output, blending as Vello would have composited them in
+//! one scene. Edges in the overlays are blended over the content's pixels
+//! after it is resolved, rather than sample by sample, so where an overlay
+//! edge crosses a content edge the result differs slightly from one render.
+//! Whether mixing configs like this brings on or avoids a problem says
+//! which layer it is in.
+
+use vello::peniko::Color;
+use vello::wgpu;
+use vello::{AaConfig, RenderParams, Renderer, Scene};
+
+use crate::blit::{Blit, Texture};
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var content: texture_2d<f32>;
+@group(0) @binding(1) var overlays: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let under = textureLoad(content, pixel, 0);
+    let over = textureLoad(overlays, pixel, 0);
+    // Both have straight alpha, as Vello renders them, and so does the result.
+    let alpha = over.a + under.a * (1.0 - over.a);
+    if alpha <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+    let rgb = (over.rgb * over.a + under.rgb * under.a * (1.0 - over.a)) / alpha;
+    return vec4<f32>(rgb, alpha);
+}
+"#;
+
+/// The names of the layers, in the order of their configs.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const NAMES: [&str; 2] = ["content", "overlays"];
+
+/// The textures the layers are rendered into, and the bind group reading them.
+struct Targets {
+    content: Texture,
+    overlays: Texture,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+}
+
+/// Renders the content and overlays of frames with AA configs of their own,
+/// into outputs of one format.
+pub struct LayerPass {
+    blit: Blit,
+    pipeline: wgpu::RenderPipeline,
+    targets: Option<Targets>,
+}
+
+impl LayerPass {
+    /// Build the pipeline, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let blit = Blit::new(device, "layers", SHADER, 2, false);
+        let pipeline = blit.pipeline(device, "fs_main", format, None);
+        LayerPass {
+            blit,
+            pipeline,
+            targets: None,
+        }
+    }
+
+    /// The targets at the given size, replacing those of another size.
+    fn targets(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Targets {
+        if let Some(targets) = self.targets.take() {
+            if targets.width == width && targets.height == height {
+                return targets;
+            }
+        }
+        let content = Texture::target(device, "content layer", width, height);
+        let overlays = Texture::target(device, "overlay layer", width, height);
+        let bind_group = self
+            .blit
+            .bind_group(device, &[&content.view, &overlays.view]);
+        Targets {
+            content,
+            overlays,
+            bind_group,
+            width,
+            height,
+        }
+    }
+
+    /// Render `layers`, the content and the overlays, with `params` but
+    /// with `configs` for each, and draw the overlays over the content into
+    /// `output`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: [&Scene; 2],
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+        configs: [AaConfig; 2],
+    ) -> Result<(), vello::Error> {
+        let targets = self.targets(device, params.width, params.height);
+        let content = RenderParams {
+            antialiasing_method: configs[0],
+            ..*params
+        };
+        let overlays = RenderParams {
+            base_color: Color::TRANSPARENT,
+            antialiasing_method: configs[1],
+            ..*params
+        };
+        let rendered = renderer
+            .render_to_texture(device, queue, layers[0], &targets.content.view, &content)
+            .and_then(|()| {
+                renderer.render_to_texture(
+                    device,
+                    queue,
+                    layers[1],
+                    &targets.overlays.view,
+                    &overlays,
+                )
+            });
+        let targets = self.targets.insert(targets);
+        rendered?;
+
+        let draw = (&self.pipeline, &targets.bind_group);
+        let load = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        self.blit.submit(device, queue, output, load, &[draw]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Affine, Rect};
+    use vello::peniko::Fill;
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    #[test]
+    fn overlays_are_drawn_over_the_content() {
+        let configs = [AaConfig::Area, AaConfig::Msaa16];
+        let Some((context, dev_id, mut renderer)) = renderer(&configs) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 3, 1);
+        let pixel = |x| Rect::new(x, 0.0, x + 1.0, 1.0);
+        // Red content in the first two pixels, and half-transparent blue
+        // overlays over the second and third.
+        let mut content = Scene::new();
+        let red = Color::from_rgb8(255, 0, 0);
+        let rect = pixel(0.0).union(pixel(1.0));
+        content.fill(Fill::NonZero, Affine::IDENTITY, red, None, &rect);
+        let mut overlays = Scene::new();
+        let blue = Color::from_rgba8(0, 0, 255, 128);
+        let rect = pixel(1.0).union(pixel(2.0));
+        overlays.fill(Fill::NonZero, Affine::IDENTITY, blue, None, &rect);
+        // Over transparent black, as `render` draws.
+        let params = RenderParams {
+            base_color: Color::TRANSPARENT,
+            width: 3,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let mut pass = LayerPass::new(device, blit::FORMAT);
+        pass.render(
+            &mut renderer,
+            device,
+            queue,
+            [&content, &overlays],
+            &view,
+            &params,
+            configs,
+        )
+        .unwrap();
+        let pixels = block_on(Readback::new(device, queue, &output, 3, 1).read(device)).unwrap();
+        // As the two drawn in one scene would be.
+        let mut scene = Scene::new();
+        scene.append(&content, None);
+        scene.append(&overlays, None);
+        let expected = crate::readback::testing::render(&scene, 3, 1).unwrap();
+        for (got, want) in pixels.iter().zip(&expected) {
+            assert!(got.abs_diff(*want) <= 2, "{:?} != {:?}", pixels, expected);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Vello's own debug visualizations of a render.
+//!
+//! Built with its `debug_layers` feature, which this crate's feature of the
+//! same name enables, Vello can draw over a frame what its pipeline made of
+//! the scene: each path's bounding box, the line segments curves were
+//! flattened into and their end points, and, after checking on the CPU that
+//! each path's segments join up, red circles where they don't. A path whose
+//! boxes or segments are wrong shows which stage a rendering problem starts
+//! in. Without the feature Vello ignores the layers, so they are only asked
+//! for when [`AVAILABLE`].
+//!
+//! The layers are drawn by `render_to_surface_async`, which reads Vello's
+//! buffers back before drawing them, so a frame with them can't be rendered
+//! in the event loop's handler. [`render`] does it in a future instead.
+
+use std::fmt;
+use std::str::FromStr;
+
+use vello::low_level::DebugLayers;
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+use crate::context::DeviceHandle;
+
+/// Whether the linked Vello draws debug layers.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const AVAILABLE: bool = cfg!(feature = "debug_layers");
+
+/// The layers by name, in the order they're listed.
+const NAMES: [(&str, DebugLayers); 4] = [
+    ("boxes", DebugLayers::BOUNDING_BOXES),
+    ("segments", DebugLayers::LINESOUP_SEGMENTS),
+    ("points", DebugLayers::LINESOUP_POINTS),
+    ("validation", DebugLayers::VALIDATION),
+];
+
+/// Which of Vello's debug layers to draw.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Layers(u8);
+
+impl Layers {
+    pub const ALL: Layers = Layers(DebugLayers::all().bits());
+
+    pub fn get(self) -> DebugLayers {
+        DebugLayers::from_bits(self.0)
+    }
+
+    /// The names of the layers, for logging.
+    pub fn names(self) -> Vec<&'static str> {
+        NAMES
+            .iter()
+            .filter(|(_, layer)| self.get().contains(*layer))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl fmt::Debug for Layers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.names().join(","))
+    }
+}
+
+impl FromStr for Layers {
+    type Err = String;
+
+    /// A comma-separated list of layer names, or `all`, or nothing for all.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s == "all" {
+            return Ok(Layers::ALL);
+        }
+        let mut layers = DebugLayers::none();
+        for name in s.split(',') {
+            let Some((_, layer)) = NAMES.iter().find(|(n, _)| *n == name) else {
+                let names: Vec<_> = NAMES.iter().map(|(n, _)| *n).collect();
+                return Err(format!("'{}' is not one of {}", name, names.join(", ")));
+            };
+            layers = layers | *layer;
+        }
+        Ok(Layers(layers.bits()))
+    }
+}
+
+/// A frame rendered with debug layers, and the renderer it was rendered with.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub struct Frame {
+    pub dev_id: usize,
+    pub format: wgpu::TextureFormat,
+    pub renderer: Renderer,
+    pub result: Result<(), String>,
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("dev_id", &self.dev_id)
+            .field("format", &self.format)
+            .field("result", &self.result)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Render `scene` to `surface_texture` with `layers` drawn over it, and
+/// present it.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub async fn render(
+    handle: &DeviceHandle,
+    renderer: &mut Renderer,
+    scene: &Scene,
+    surface_texture: wgpu::SurfaceTexture,
+    params: &RenderParams,
+    layers: Layers,
+) -> Result<(), vello::Error> {
+    // The async pipeline is the only one that draws debug layers.
+    #[allow(deprecated)]
+    renderer
+        .render_to_surface_async(
+            &handle.device,
+            &handle.queue,
+            scene,
+            &surface_texture,
+            params,
+            layers.get(),
+        )
+        .await?;
+    surface_texture.present();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_layers() {
+        assert_eq!("".parse(), Ok(Layers::ALL));
+        assert_eq!("all".parse(), Ok(Layers::ALL));
+        let layers: Layers = "points,boxes".parse().unwrap();
+        assert_eq!(layers.names(), ["boxes", "points"]);
+        assert!("boxes,lines".parse::<Layers>().is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Requesting device limits above wgpu's defaults.
+//!
+//! Devices are created with `wgpu::Limits::default()`, which every WebGPU
+//! adapter supports, and which caps buffers at 256 MiB and storage buffer
+//! bindings at 128 MiB. A scene complex enough for Vello's buffers to need
+//! more fails to allocate them. A [`LimitsRequest`] asks for larger buffers
+//! instead, or for everything the adapter supports, clamped to what it does
+//! support, so whether a big scene's failure goes away with higher limits
+//! tells a limits problem apart from a GPU bug.
+
+use std::str::FromStr;
+
+use log::{info, warn};
+
+use vello::wgpu;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Which limits new devices are created with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitsRequest {
+    /// wgpu's defaults.
+    #[default]
+    Default,
+    /// The defaults, with buffers and storage buffer bindings of up to this
+    /// many bytes.
+    Buffers(u64),
+    /// Every limit at the most the adapter supports.
+    Max,
+}
+
+impl LimitsRequest {
+    /// The limits to request from an adapter that `supports` these.
+    pub fn limits(self, supported: &wgpu::Limits) -> wgpu::Limits {
+        match self {
+            LimitsRequest::Default => wgpu::Limits::default(),
+            LimitsRequest::Buffers(bytes) => {
+                let binding = bytes.min(supported.max_storage_buffer_binding_size as u64);
+                wgpu::Limits {
+                    max_buffer_size: bytes.min(supported.max_buffer_size),
+                    max_storage_buffer_binding_size: binding as u32,
+                    ..Default::default()
+                }
+            }
+            LimitsRequest::Max => supported.clone(),
+        }
+    }
+
+    /// Log the buffer limits for this request, what the adapter supports and
+    /// what the device was `granted`, warning when the request was clamped.
+    pub fn log(self, supported: &wgpu::Limits, granted: &wgpu::Limits) {
+        if let LimitsRequest::Buffers(bytes) = self {
+            if bytes > supported.max_buffer_size
+                || bytes > supported.max_storage_buffer_binding_size as u64
+            {
+                warn!(
+                    "Requested buffers of {} bytes, but the adapter supports at most {} \
+                     (storage bindings {})",
+                    bytes, supported.max_buffer_size, supported.max_storage_buffer_binding_size
+                );
+            }
+        }
+        info!(
+            "Device limits ({:?}): buffers {} bytes, storage bindings {} bytes",
+            self, granted.max_buffer_size, granted.max_storage_buffer_binding_size
+        );
+    }
+}
+
+impl FromStr for LimitsRequest {
+    type Err = String;
+
+    /// `default`, `max`, or a buffer size in MiB.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(LimitsRequest::Default),
+            "max" => Ok(LimitsRequest::Max),
+            _ => match s.parse::<u64>() {
+                Ok(0) => Err("the buffer size must be at least 1 MiB".to_string()),
+                Ok(mib) => Ok(LimitsRequest::Buffers(mib.saturating_mul(MIB))),
+                Err(_) => Err(format!("'{}' is not 'default', 'max' or a size in MiB", s)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_clamped_to_the_adapter() {
+        let supported = wgpu::Limits {
+            max_buffer_size: 2048 * MIB,
+            max_storage_buffer_binding_size: 1024 * MIB as u32,
+            ..Default::default()
+        };
+        let defaults = wgpu::Limits::default();
+        assert_eq!("default".parse(), Ok(LimitsRequest::Default));
+        assert_eq!(LimitsRequest::Default.limits(&supported), defaults);
+        let request: LimitsRequest = "512".parse().unwrap();
+        let limits = request.limits(&supported);
+        assert_eq!(limits.max_buffer_size, 512 * MIB);
+        assert_eq!(limits.max_storage_buffer_binding_size as u64, 512 * MIB);
+        // Everything else keeps its default.
+        assert_eq!(
+            limits.max_texture_dimension_2d,
+            defaults.max_texture_dimension_2d
+        );
+        let limits = LimitsRequest::Buffers(4096 * MIB).limits(&supported);
+        assert_eq!(limits.max_buffer_size, 2048 * MIB);
+        assert_eq!(limits.max_storage_buffer_binding_size as u64, 1024 * MIB);
+        assert_eq!(LimitsRequest::Max.limits(&supported), supported);
+        assert!("0".parse::<LimitsRequest>().is_err());
+        assert!("big".parse::<LimitsRequest>().is_err());
+    }
+}
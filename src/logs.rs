@@ -0,0 +1,74 @@
+//! The session's log, kept for downloading with a bug report.
+//!
+//! The console is the only place log records normally go, and asking
+//! someone to copy it out is asking for the part they think matters. The
+//! logger also keeps every record it logs, whatever its level, in a
+//! [`MessageLog`] of the most recent [`DEFAULT_CAPACITY`], or N with
+//! `?log_buffer=N`, and `download_logs()` saves them as a text file. Records
+//! are kept from before the options are parsed, so the warnings about them
+//! are in it too.
+
+use std::cell::RefCell;
+
+use crate::messages::MessageLog;
+
+/// How many records are kept without `?log_buffer=N`.
+pub const DEFAULT_CAPACITY: usize = 10_000;
+
+thread_local! {
+    static SESSION: RefCell<MessageLog> = RefCell::new(MessageLog::new(DEFAULT_CAPACITY));
+}
+
+/// Keep `record`, dropping the oldest if full.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn record(record: &log::Record) {
+    SESSION.with_borrow_mut(|log| {
+        log.push(record.level(), record.target(), record.args().to_string())
+    });
+}
+
+/// Keep up to `capacity` records from now on.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn set_capacity(capacity: usize) {
+    SESSION.with_borrow_mut(|log| log.set_capacity(capacity));
+}
+
+/// The records kept, one per line, after a line saying how many older
+/// ones were dropped, if any were.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn to_text() -> String {
+    SESSION.with_borrow(text)
+}
+
+fn text(log: &MessageLog) -> String {
+    let mut text = String::new();
+    if log.dropped() > 0 {
+        text += &format!("({} earlier records dropped)\n", log.dropped());
+    }
+    for message in log.messages() {
+        text += &format!("{}\n", message);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    #[test]
+    fn records_are_one_per_line() {
+        let mut log = MessageLog::new(2);
+        log.push(Level::Info, "vellowasm::client", "first".to_string());
+        assert!(text(&log).ends_with("] INFO vellowasm::client: first\n"));
+        log.push(Level::Debug, "wgpu", "second".to_string());
+        log.push(Level::Error, "wgpu", "third".to_string());
+        let text = text(&log);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "(1 earlier records dropped)");
+        assert!(lines[1].ends_with("] DEBUG wgpu: second"));
+        assert!(lines[2].ends_with("] ERROR wgpu: third"));
+    }
+}
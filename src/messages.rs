@@ -0,0 +1,165 @@
+//! Warnings and errors kept for showing in the page, with `?debug`.
+//!
+//! In a browser, wgpu's validation errors and the client's own warnings go
+//! to the console, which someone reporting a problem may never open. With
+//! `?debug` the logger also records every warning and error in a
+//! [`MessageLog`] of the most recent ones, which the page shows in a
+//! collapsible panel, and devices report uncaptured errors there instead
+//! of panicking on the first one.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+use log::{error, Level};
+use web_time::Instant;
+
+use vello::wgpu;
+
+/// How many messages are kept without `?debug=N`.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const DEFAULT_CAPACITY: usize = 100;
+
+/// A warning or error, and when it was logged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    /// Since the log was started.
+    pub at: Duration,
+    pub level: Level,
+    /// The module it was logged from, such as `wgpu_core::device`.
+    pub target: String,
+    pub text: String,
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:.3}s] {} {}: {}",
+            self.at.as_secs_f64(),
+            self.level,
+            self.target,
+            self.text
+        )
+    }
+}
+
+/// The most recent messages, up to a capacity, and how many older ones
+/// were dropped to make room.
+#[derive(Debug)]
+pub struct MessageLog {
+    started: Instant,
+    capacity: usize,
+    messages: VecDeque<Message>,
+    dropped: usize,
+}
+
+impl MessageLog {
+    pub fn new(capacity: usize) -> Self {
+        MessageLog {
+            started: Instant::now(),
+            capacity,
+            messages: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Keep a message logged now, dropping the oldest if full.
+    pub fn push(&mut self, level: Level, target: &str, text: String) {
+        if self.messages.len() == self.capacity {
+            self.messages.pop_front();
+            self.dropped += 1;
+        }
+        self.messages.push_back(Message {
+            at: self.started.elapsed(),
+            level,
+            target: target.to_string(),
+            text,
+        });
+    }
+
+    /// Keep up to `capacity` messages from now on, dropping the oldest if
+    /// there are more already.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        while self.messages.len() > capacity {
+            self.messages.pop_front();
+            self.dropped += 1;
+        }
+        self.capacity = capacity;
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.messages.iter()
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+thread_local! {
+    static MESSAGES: RefCell<Option<MessageLog>> = const { RefCell::new(None) };
+}
+
+/// Start keeping the last `capacity` warnings and errors.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn enable(capacity: usize) {
+    MESSAGES.set(Some(MessageLog::new(capacity)));
+}
+
+pub fn is_enabled() -> bool {
+    MESSAGES.with_borrow(Option::is_some)
+}
+
+/// Keep `record` if it's a warning or error and messages are being kept,
+/// returning whether it was kept.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn record(record: &log::Record) -> bool {
+    if record.level() > Level::Warn {
+        return false;
+    }
+    MESSAGES.with_borrow_mut(|log| match log {
+        Some(log) => {
+            log.push(record.level(), record.target(), record.args().to_string());
+            true
+        }
+        None => false,
+    })
+}
+
+/// Call `f` with the messages kept, if they are being kept.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn with<R>(f: impl FnOnce(&MessageLog) -> R) -> Option<R> {
+    MESSAGES.with_borrow(|log| log.as_ref().map(f))
+}
+
+/// Log the errors `device` raises outside an error scope, rather than
+/// panicking on the first as wgpu does by default.
+pub fn log_uncaptured_errors(device: &wgpu::Device) {
+    device.on_uncaptured_error(Box::new(|e| {
+        error!(target: "wgpu", "Uncaptured error: {}", e);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oldest_messages_are_dropped() {
+        let mut log = MessageLog::new(2);
+        for i in 0..3 {
+            log.push(Level::Warn, "wgpu", format!("message {}", i));
+        }
+        let texts: Vec<_> = log.messages().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["message 1", "message 2"]);
+        assert_eq!(log.dropped(), 1);
+        let line = log.messages().next().unwrap().to_string();
+        assert!(line.ends_with("] WARN wgpu: message 1"), "{}", line);
+        log.set_capacity(1);
+        let texts: Vec<_> = log.messages().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["message 2"]);
+        assert_eq!(log.dropped(), 2);
+    }
+}
@@ -0,0 +1,119 @@
+//! An overview of all the content, in a corner of the viewport.
+//!
+//! A [`Minimap`] draws the content scene a second time, scaled down to fit
+//! a small box in the bottom right-hand corner, with the part shown in the
+//! main view outlined. It fits the content and the view's extent together,
+//! so the outline stays in the box however far the view is zoomed out or
+//! panned away. Clicking in the box gives the content point under the
+//! click, for the main view to be centred on.
+
+use vello::kurbo::{Affine, Point, Rect, Stroke};
+use vello::peniko::{Color, Fill};
+use vello::Scene;
+
+use crate::bounds;
+use crate::draw;
+
+/// The box's size, as a fraction of the viewport's.
+const FRACTION: f64 = 0.25;
+/// The space between the box and the viewport's edges, clear of the scrollbars.
+const MARGIN: f64 = 12.0;
+/// The space inside the box around what it shows.
+const PADDING: f64 = 4.0;
+
+fn backdrop_color() -> Color {
+    Color::from_rgba8(32, 32, 32, 200)
+}
+
+fn view_color() -> Color {
+    Color::from_rgb8(255, 200, 0)
+}
+
+/// Where the minimap is and how it shows the content.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Minimap {
+    /// The box, on the surface.
+    pub area: Rect,
+    /// From content coordinates to the surface, inside the box.
+    pub transform: Affine,
+    /// The part of the content shown in the main view.
+    pub view: Rect,
+}
+
+impl Minimap {
+    /// The minimap in the corner of `viewport`, showing `content` and the
+    /// main view, drawn on the surface under `content_transform`.
+    pub fn new(viewport: Rect, content: Rect, content_transform: Affine) -> Self {
+        let size = viewport.size() * FRACTION;
+        let corner = Point::new(viewport.x1 - MARGIN, viewport.y1 - MARGIN);
+        let area = Rect::from_points(corner - size.to_vec2(), corner);
+        let view = content_transform.inverse().transform_rect_bbox(viewport);
+        let fit = bounds::fit(area.width(), area.height(), content.union(view), PADDING);
+        Minimap {
+            area,
+            transform: Affine::translate(area.origin().to_vec2()) * fit,
+            view,
+        }
+    }
+
+    /// Draw `content` into the box, with the main view's extent outlined.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn draw(&self, scene: &mut Scene, content: &Scene) {
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            backdrop_color(),
+            None,
+            &self.area,
+        );
+        draw::with_clip(scene, Affine::IDENTITY, &self.area, |scene| {
+            scene.append(content, Some(self.transform));
+            // On the surface, so that the outline is a pixel wide at any scale.
+            let view = self.transform.transform_rect_bbox(self.view);
+            scene.stroke(
+                &Stroke::new(1.0),
+                Affine::IDENTITY,
+                view_color(),
+                None,
+                &view,
+            );
+        });
+    }
+
+    /// The content point shown at `point` on the surface, if it's in the box.
+    pub fn hit(&self, point: Point) -> Option<Point> {
+        self.area
+            .contains(point)
+            .then(|| self.transform.inverse() * point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::Vec2;
+
+    use super::*;
+
+    #[test]
+    fn clicks_map_back_to_content() {
+        let viewport = Rect::new(0.0, 0.0, 400.0, 200.0);
+        let content = Rect::new(0.0, 0.0, 800.0, 400.0);
+        // Zoomed to 200% on the middle of the content.
+        let transform = Affine::translate(Vec2::new(-400.0, -200.0)) * Affine::scale(2.0);
+        let minimap = Minimap::new(viewport, content, transform);
+        assert_eq!(minimap.area, Rect::new(288.0, 138.0, 388.0, 188.0));
+        assert_eq!(minimap.view, Rect::new(200.0, 100.0, 400.0, 200.0));
+        // The content fills the box's height but for the padding.
+        let shown = minimap.transform.transform_rect_bbox(content);
+        assert!(
+            (shown.height() - (50.0 - PADDING * 2.0)).abs() < 1e-9,
+            "{:?}",
+            shown
+        );
+        let center = minimap.transform * Point::new(400.0, 200.0);
+        assert_eq!(minimap.area.center(), center);
+        let hit = minimap.hit(center).unwrap();
+        assert!((hit - Point::new(400.0, 200.0)).hypot() < 1e-9);
+        assert_eq!(minimap.hit(Point::new(10.0, 10.0)), None);
+    }
+}
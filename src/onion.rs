@@ -0,0 +1,192 @@
+//! Onion-skinning: showing the previous frame faintly beneath the current one.
+//!
+//! [`OnionPass`] renders each frame's scene over a transparent base into a
+//! texture of its own and keeps it, so that the next frame can draw it at
+//! [`OPACITY`] over the base colour before drawing its own render on top.
+//! Anything that moves shows where it was a frame ago, and anything that
+//! changes between frames when it shouldn't shows as a ghost beside it. The
+//! first frame, or the first after a resize or after being turned back on,
+//! has nothing before it and is drawn alone.
+
+use vello::peniko::Color;
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+use crate::blit::{self, Blit, Source};
+
+/// The opacity the previous frame is drawn at.
+pub const OPACITY: f32 = 0.3;
+
+/// Prefixed with the `OPACITY` constant.
+const SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+@fragment
+fn fs_current(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
+
+@fragment
+fn fs_previous(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(source, vec2<i32>(position.xy), 0);
+    return vec4<f32>(color.rgb, color.a * OPACITY);
+}
+"#;
+
+/// The textures of this frame and the one before.
+struct Frames {
+    current: Source,
+    previous: Source,
+    /// Whether `previous` holds a frame, rather than nothing yet.
+    has_previous: bool,
+    width: u32,
+    height: u32,
+}
+
+/// Renders scenes over the frame before them, faded, into outputs of one format.
+pub struct OnionPass {
+    blit: Blit,
+    current: wgpu::RenderPipeline,
+    previous: wgpu::RenderPipeline,
+    frames: Option<Frames>,
+}
+
+impl OnionPass {
+    /// Build the pipelines, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = format!("const OPACITY: f32 = {:?};\n{}", OPACITY, SHADER);
+        let blit = Blit::new(device, "onion", &shader, 1, false);
+        // The renders have straight alpha, so they are blended as such.
+        let blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+        OnionPass {
+            current: blit.pipeline(device, "fs_current", format, blend),
+            previous: blit.pipeline(device, "fs_previous", format, blend),
+            blit,
+            frames: None,
+        }
+    }
+
+    /// The frames at the given size, replacing those of another size.
+    fn frames(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Frames {
+        if let Some(frames) = self.frames.take() {
+            if frames.width == width && frames.height == height {
+                return frames;
+            }
+        }
+        let usage = wgpu::TextureUsages::STORAGE_BINDING;
+        let source = || {
+            self.blit
+                .source(device, "onion frame", width, height, usage)
+        };
+        Frames {
+            current: source(),
+            previous: source(),
+            has_previous: false,
+            width,
+            height,
+        }
+    }
+
+    /// Forget the previous frame, so that the next is drawn alone.
+    pub fn clear(&mut self) {
+        if let Some(frames) = &mut self.frames {
+            frames.has_previous = false;
+        }
+    }
+
+    /// Keep the frame last rendered as the previous one, once it has been
+    /// presented. Until then, rendering it again, e.g. in several passes,
+    /// draws it over the same previous frame each time.
+    pub fn finish_frame(&mut self) {
+        if let Some(frames) = &mut self.frames {
+            std::mem::swap(&mut frames.current, &mut frames.previous);
+            frames.has_previous = true;
+        }
+    }
+
+    /// Render `scene` with `params`, and draw it into `output` over the
+    /// base colour and the previous frame at [`OPACITY`].
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+    ) -> Result<(), vello::Error> {
+        let frames = self.frames(device, params.width, params.height);
+        let current_params = RenderParams {
+            base_color: Color::TRANSPARENT,
+            ..*params
+        };
+        let view = &frames.current.texture.view;
+        let rendered = renderer.render_to_texture(device, queue, scene, view, &current_params);
+        let frames = self.frames.insert(frames);
+        rendered?;
+
+        let previous = frames
+            .has_previous
+            .then_some((&self.previous, &frames.previous.bind_group));
+        let current = (&self.current, &frames.current.bind_group);
+        let draws: Vec<_> = previous.into_iter().chain([current]).collect();
+        let load = blit::clear(params.base_color);
+        self.blit.submit(device, queue, output, load, &draws);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Affine, Rect};
+    use vello::peniko::Fill;
+    use vello::AaConfig;
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    #[test]
+    fn previous_frame_shows_faintly() {
+        let Some((context, dev_id, mut renderer)) = renderer(&[AaConfig::Area]) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 2, 1);
+        let params = RenderParams {
+            base_color: Color::WHITE,
+            width: 2,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let mut pass = OnionPass::new(device, blit::FORMAT);
+        let read = || block_on(Readback::new(device, queue, &output, 2, 1).read(device));
+        // A red pixel in the first frame, and a blue one beside it in the second.
+        let mut frame = |x: f64, color: Color| {
+            let mut scene = Scene::new();
+            let rect = Rect::new(x, 0.0, x + 1.0, 1.0);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+            pass.render(&mut renderer, device, queue, &scene, &view, &params)
+                .unwrap();
+            pass.finish_frame();
+        };
+        frame(0.0, Color::from_rgb8(255, 0, 0));
+        // Nothing came before the first frame.
+        assert_eq!(read().unwrap(), [255, 0, 0, 255, 255, 255, 255, 255]);
+        frame(1.0, Color::from_rgb8(0, 0, 255));
+        // The red is faded into the white, under nothing from this frame.
+        let faded = (255.0 * (1.0 - OPACITY)).round() as i32;
+        let pixels = read().unwrap();
+        for (pixel, expected) in pixels.iter().zip([255, faded, faded, 255, 0, 0, 255, 255]) {
+            assert!((*pixel as i32 - expected).abs() <= 1, "{:?}", pixels);
+        }
+
+        // Clearing draws the next frame alone.
+        pass.clear();
+        pass.render(&mut renderer, device, queue, &Scene::new(), &view, &params)
+            .unwrap();
+        assert_eq!(read().unwrap(), [255; 8]);
+    }
+}
@@ -0,0 +1,596 @@
+//! Startup options for the client.
+
+#[cfg(target_arch = "wasm32")]
+use std::cell::RefCell;
+use std::time::Duration;
+
+use vello::peniko::Color;
+use vello::wgpu;
+use vello::AaConfig;
+
+use crate::channels::Channel;
+use crate::draw::BackgroundFit;
+use crate::frame::PollStrategy;
+use crate::grid::Grid;
+use crate::layers::Layers;
+use crate::limits::LimitsRequest;
+use crate::scenes::Demo;
+use crate::surface::SurfaceOptions;
+use crate::text::Hinting;
+
+/// The frame budget of `?stress` without one: a 60Hz frame, with some slack
+/// for the jitter in the times of frames that keep up.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+const STRESS_BUDGET: Duration = Duration::from_millis(18);
+/// How long `?watchdog` without a timeout waits for a frame before recovering.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Options {
+    /// Requested AA config. May be lowered if not supported by the adapter.
+    /// `None` turns MSAA off: Vello always needs a rasterization mode, so
+    /// this renders with area coverage and builds no MSAA pipelines.
+    pub aa_config: Option<AaConfig>,
+    /// The AA config for screenshots, if not the one used live. The renderer
+    /// is built with it too, so it can be higher quality than the live view.
+    pub export_aa: Option<AaConfig>,
+    /// The two AA configs drawn side by side in each frame, if any.
+    pub split_aa: Option<[AaConfig; 2]>,
+    /// The AA configs of the content and of the overlays drawn over it, if
+    /// they are rendered apart.
+    pub layer_aa: Option<[AaConfig; 2]>,
+    /// Keep this many of the latest warnings and errors to show in the page.
+    pub debug_messages: Option<usize>,
+    /// Keep this many of the latest log records, for `download_logs`.
+    pub log_buffer: usize,
+    /// Show only this channel of each frame, as grey.
+    pub channel: Option<Channel>,
+    /// How long to wait between creating the surface and starting the client.
+    pub startup_delay: Duration,
+    /// Log extra per-frame diagnostics.
+    pub verbose: bool,
+    /// Capture and log validation errors raised while rendering.
+    pub capture_errors: bool,
+    /// Redraw continuously rather than only on demand.
+    pub animate: bool,
+    /// Time this many frames, after the warm-up frames, and log a summary.
+    /// Implies `animate`.
+    pub benchmark: Option<usize>,
+    /// Grow the random scene from `shapes` until the mean frame time exceeds
+    /// this, and log the most shapes drawn within it. Implies `animate`.
+    pub stress: Option<Duration>,
+    /// The frames rendered before timing starts, whose times are discarded.
+    pub warmup_frames: usize,
+    /// Stop redrawing after this long without any input.
+    pub idle_timeout: Option<Duration>,
+    /// Try to recover the surface and device if frames are expected but
+    /// none has been presented for this long.
+    pub watchdog: Option<Duration>,
+    /// Cycle through every demo with every AA config, showing each for this long.
+    pub demo_reel: Option<Duration>,
+    /// Present this many frames without vsync at startup, and on `Shift+V`.
+    pub vsync_burst: Option<u32>,
+    /// Advance animation time by a fixed step per frame instead of using the real clock.
+    pub frame_step: Option<Duration>,
+    /// Advance the animation in fixed steps of this length, independently of
+    /// the frame rate, and draw frames at interpolated times.
+    pub timestep: Option<Duration>,
+    /// Draw at most this many frames per second while animating.
+    pub target_fps: Option<f64>,
+    /// Adapter power preference, e.g. to force the discrete GPU on laptops.
+    pub power_preference: wgpu::PowerPreference,
+    /// Use only this backend, or all the available ones if `None`.
+    pub backends: Option<wgpu::Backends>,
+    /// The device limits to request, e.g. larger buffers for complex scenes.
+    pub limits: LimitsRequest,
+    /// How the device is polled after each frame is presented.
+    pub poll: PollStrategy,
+    /// How many times each frame is rendered into the surface texture before
+    /// it is presented.
+    pub passes: u32,
+    /// Render at this many times the surface size, and filter the result
+    /// down into the surface, if above 1.
+    pub supersample: f32,
+    /// Adjust the gamma of the final image by this, if not 1.
+    pub gamma: f32,
+    /// Keep the previous frame's content, drawing each frame over it,
+    /// instead of clearing the surface to the base colour.
+    pub preserve_previous: bool,
+    /// Render only the rectangle of each frame that changed since the last,
+    /// keeping the rest.
+    pub dirty_rect: bool,
+    /// Render the last frame's scene again, rather than building it, while
+    /// nothing drawn in it has changed.
+    pub reuse_scene: bool,
+    /// The demo scene to draw.
+    pub demo: Demo,
+    /// Draw the frame number, surface size and AA mode over the scene.
+    pub debug_overlay: bool,
+    /// Draw the boundaries of Vello's rasterization tiles over the output.
+    pub debug_tiles: bool,
+    /// Outline every filled shape, to show where shapes overlap.
+    pub debug_outlines: bool,
+    /// Draw Vello's debug layers over the output, if it was built with them.
+    pub debug_layers: Option<Layers>,
+    /// Draw a grid over the content, crisp at any zoom, and its spacing and colour.
+    pub show_grid: bool,
+    pub grid: Grid,
+    /// Mirror the whole scene left to right, and flip it upside down.
+    pub mirror: bool,
+    pub flip: bool,
+    /// Show the in-canvas control panel at startup.
+    pub control_panel: bool,
+    /// Discard strokes drawn with the mouse at the end of every frame.
+    /// When false they accumulate, growing the scene until cleared.
+    pub clear_each_frame: bool,
+    /// The surface format, present mode, alpha mode, frame latency and usage.
+    /// Each must be supported by the surface.
+    pub surface: SurfaceOptions,
+    /// Use the most conservative configuration, for flaky drivers: CPU
+    /// preparation stages, area AA, FIFO presentation, no optional device
+    /// features and a small fixed surface size.
+    pub safe_mode: bool,
+    /// Build every supported AA config, and compare renders of the first frame with each.
+    pub compare_aa: bool,
+    /// Log the pixel values across an antialiased edge with each AA config, after the first frame.
+    pub edge_profile: bool,
+    /// Test an antialiased edge at this angle with the AA config in use,
+    /// and show whether it passed over the frame.
+    pub edge_test: Option<f64>,
+    /// Draw the content into a centred area with this aspect ratio (width / height).
+    pub aspect_lock: Option<f64>,
+    /// Draw edge to edge: the drawable area is the whole surface, and the
+    /// demos have no margin, so they reach the surface edges.
+    pub full_bleed: bool,
+    /// The colour of the bars either side of the content when the aspect ratio is locked.
+    pub letterbox_color: Color,
+    /// How a background image set from JS is scaled to the surface.
+    pub background_fit: BackgroundFit,
+    /// Also render every frame into a texture, and compare it with a reference frame captured with the `R` key.
+    pub frame_diff: bool,
+    /// The seed and shape count of the random demo.
+    pub seed: u32,
+    pub shapes: u32,
+    /// The number of nested layers in the layers demo.
+    pub layers: u32,
+    /// The number of sprites in the sprites demo.
+    pub sprites: u32,
+    /// The hinting of the text demo's right column, and its single font size
+    /// in CSS pixels if not showing the full ramp.
+    pub hinting: Hinting,
+    pub font_size: Option<f32>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            aa_config: Some(AaConfig::Msaa16),
+            export_aa: None,
+            split_aa: None,
+            layer_aa: None,
+            debug_messages: None,
+            log_buffer: crate::logs::DEFAULT_CAPACITY,
+            channel: None,
+            startup_delay: Duration::ZERO,
+            verbose: false,
+            capture_errors: false,
+            animate: false,
+            benchmark: None,
+            stress: None,
+            warmup_frames: 10,
+            idle_timeout: None,
+            watchdog: None,
+            demo_reel: None,
+            vsync_burst: None,
+            frame_step: None,
+            timestep: None,
+            target_fps: None,
+            power_preference: wgpu::PowerPreference::default(),
+            backends: None,
+            limits: LimitsRequest::default(),
+            poll: PollStrategy::default(),
+            passes: 1,
+            supersample: 1.0,
+            gamma: 1.0,
+            preserve_previous: false,
+            dirty_rect: false,
+            reuse_scene: false,
+            demo: Demo::default(),
+            debug_overlay: false,
+            debug_tiles: false,
+            debug_outlines: false,
+            debug_layers: None,
+            show_grid: false,
+            grid: Grid::default(),
+            mirror: false,
+            flip: false,
+            control_panel: false,
+            clear_each_frame: true,
+            surface: SurfaceOptions::default(),
+            safe_mode: false,
+            compare_aa: false,
+            edge_profile: false,
+            edge_test: None,
+            aspect_lock: None,
+            full_bleed: false,
+            letterbox_color: Color::BLACK,
+            background_fit: BackgroundFit::default(),
+            frame_diff: false,
+            seed: 1,
+            shapes: 100,
+            layers: 32,
+            sprites: 1000,
+            hinting: Hinting::FULL,
+            font_size: None,
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Options {
+    /// Build the options from the page URL's query string, e.g. `?verbose`.
+    pub fn from_url() -> Self {
+        let search = web_sys::window()
+            .and_then(|win| win.location().search().ok())
+            .unwrap_or_default();
+        Options::from_query(&search)
+    }
+
+    /// Build the options from a URL query string, e.g. one passed to a worker by the page.
+    pub fn from_query(search: &str) -> Self {
+        let mut options = Options::default();
+        if let Ok(params) = web_sys::UrlSearchParams::new_with_str(search) {
+            let params = Query::new(params);
+            options.verbose = params.has("verbose");
+            options.capture_errors = params.has("capture_errors");
+            options.animate = params.has("animate");
+            options.debug_overlay = params.has("overlay");
+            options.debug_tiles = params.has("tiles");
+            options.debug_outlines = params.has("debug_outlines");
+            options.debug_layers = param(&params, "debug_layers");
+            options.channel = param(&params, "channel");
+            options.control_panel = params.has("panel");
+            if let Some(spacing) = params.get("grid") {
+                options.show_grid = true;
+                match spacing.parse::<f64>() {
+                    Ok(spacing) if spacing > 0.0 => options.grid.spacing = spacing,
+                    _ if spacing.is_empty() => {}
+                    _ => log::warn!("Ignoring 'grid': must be a positive spacing"),
+                }
+            }
+            if let Some(color) = params.get("grid_color") {
+                match vello::peniko::color::parse_color(&color) {
+                    Ok(color) => options.grid.color = color.to_alpha_color(),
+                    Err(e) => log::warn!("Ignoring 'grid_color': {}", e),
+                }
+            }
+            options.mirror = params.has("mirror");
+            options.flip = params.has("flip");
+            options.compare_aa = params.has("compare_aa");
+            options.edge_profile = params.has("edge_profile");
+            if let Some(angle) = params.get("edge_test") {
+                match angle.parse::<f64>() {
+                    Ok(angle) if crate::edge::ANGLES.contains(&angle) => {
+                        options.edge_test = Some(angle)
+                    }
+                    _ if angle.is_empty() => options.edge_test = Some(crate::edge::DEFAULT_ANGLE),
+                    _ => log::warn!(
+                        "Ignoring 'edge_test': the angle must be from {} to {} degrees",
+                        crate::edge::ANGLES.start(),
+                        crate::edge::ANGLES.end()
+                    ),
+                }
+            }
+            options.frame_diff = params.has("frame_diff");
+            options.preserve_previous = params.has("preserve_previous");
+            options.dirty_rect = params.has("dirty_rect");
+            options.reuse_scene = params.has("reuse_scene");
+            options.full_bleed = params.has("full_bleed");
+            options.clear_each_frame = !params.has("persist");
+            match param::<usize>(&params, "benchmark") {
+                Some(0) => log::warn!("Ignoring 'benchmark': must be at least 1"),
+                Some(frames) => {
+                    options.benchmark = Some(frames);
+                    options.animate = true;
+                }
+                None => {}
+            }
+            if let Some(budget) = params.get("stress") {
+                match budget.parse::<f64>() {
+                    Ok(ms) if ms > 0.0 && ms.is_finite() => {
+                        options.stress = Some(Duration::from_secs_f64(ms / 1000.0))
+                    }
+                    _ if budget.is_empty() => options.stress = Some(STRESS_BUDGET),
+                    _ => log::warn!("Ignoring 'stress': must be a positive number of ms"),
+                }
+                if options.stress.is_some() {
+                    options.animate = true;
+                    options.demo = Demo::Random;
+                }
+            }
+            if let Some(frames) = param(&params, "warmup") {
+                options.warmup_frames = frames;
+            }
+            if let Some(poll) = param(&params, "poll") {
+                options.poll = poll;
+            }
+            match param::<u32>(&params, "passes") {
+                Some(0) => log::warn!("Ignoring 'passes': must be at least 1"),
+                Some(passes) => options.passes = passes,
+                None => {}
+            }
+            match param::<f32>(&params, "supersample") {
+                Some(factor) if factor.is_finite() && factor >= 1.0 => options.supersample = factor,
+                Some(_) => log::warn!("Ignoring 'supersample': must be at least 1"),
+                None => {}
+            }
+            match param::<f32>(&params, "gamma") {
+                Some(gamma) if gamma.is_finite() && gamma > 0.0 => options.gamma = gamma,
+                Some(_) => log::warn!("Ignoring 'gamma': must be positive"),
+                None => {}
+            }
+            match param::<f64>(&params, "idle") {
+                Some(secs) if secs > 0.0 && secs.is_finite() => {
+                    options.idle_timeout = Some(Duration::from_secs_f64(secs))
+                }
+                Some(_) => log::warn!("Ignoring 'idle': must be a positive number of seconds"),
+                None => {}
+            }
+            if let Some(dwell) = params.get("demo_reel") {
+                match dwell.parse::<f64>() {
+                    Ok(secs) if secs > 0.0 && secs.is_finite() => {
+                        options.demo_reel = Some(Duration::from_secs_f64(secs))
+                    }
+                    _ if dwell.is_empty() => options.demo_reel = Some(crate::reel::DEFAULT_DWELL),
+                    _ => log::warn!("Ignoring 'demo_reel': must be a positive number of seconds"),
+                }
+            }
+            if let Some(timeout) = params.get("watchdog") {
+                match timeout.parse::<f64>() {
+                    Ok(secs) if secs > 0.0 && secs.is_finite() => {
+                        options.watchdog = Some(Duration::from_secs_f64(secs))
+                    }
+                    _ if timeout.is_empty() => options.watchdog = Some(WATCHDOG_TIMEOUT),
+                    _ => log::warn!("Ignoring 'watchdog': must be a positive number of seconds"),
+                }
+            }
+            if let Some(count) = params.get("debug") {
+                match count.parse::<usize>() {
+                    Ok(count) if count > 0 => options.debug_messages = Some(count),
+                    _ if count.is_empty() => {
+                        options.debug_messages = Some(crate::messages::DEFAULT_CAPACITY)
+                    }
+                    _ => log::warn!("Ignoring 'debug': must be at least 1 message"),
+                }
+            }
+            match param::<usize>(&params, "log_buffer") {
+                Some(0) => log::warn!("Ignoring 'log_buffer': must be at least 1 record"),
+                Some(count) => options.log_buffer = count,
+                None => {}
+            }
+            if let Some(frames) = params.get("vsync_burst") {
+                match frames.parse::<u32>() {
+                    Ok(frames) if frames > 0 => options.vsync_burst = Some(frames),
+                    _ if frames.is_empty() => {
+                        options.vsync_burst = Some(crate::burst::DEFAULT_FRAMES)
+                    }
+                    _ => log::warn!("Ignoring 'vsync_burst': must be at least 1 frame"),
+                }
+            }
+            // A zero step would never advance the animation.
+            match param::<f64>(&params, "step") {
+                Some(ms) if ms > 0.0 && ms.is_finite() => {
+                    options.frame_step = Some(Duration::from_secs_f64(ms / 1000.0))
+                }
+                Some(_) => log::warn!("Ignoring 'step': must be a positive number of milliseconds"),
+                None => {}
+            }
+            match param::<f64>(&params, "delay") {
+                Some(ms) if ms >= 0.0 && ms.is_finite() => {
+                    options.startup_delay = Duration::from_secs_f64(ms / 1000.0)
+                }
+                Some(_) => log::warn!("Ignoring 'delay': must be a number of milliseconds"),
+                None => {}
+            }
+            match param::<f64>(&params, "timestep") {
+                Some(ms) if ms > 0.0 && ms.is_finite() => {
+                    options.timestep = Some(Duration::from_secs_f64(ms / 1000.0))
+                }
+                Some(_) => log::warn!("Ignoring 'timestep': must be positive"),
+                None => {}
+            }
+            match param::<f64>(&params, "fps") {
+                Some(fps) if fps.is_finite() && fps > 0.0 => options.target_fps = Some(fps),
+                Some(_) => log::warn!("Ignoring 'fps': must be positive"),
+                None => {}
+            }
+            if let Some(name) = params.get("aa") {
+                match crate::aa::parse(&name) {
+                    Ok(aa) => options.aa_config = aa,
+                    Err(e) => log::warn!("Ignoring 'aa': {}", e),
+                }
+            }
+            if let Some(name) = params.get("export_aa") {
+                match crate::aa::parse(&name) {
+                    Ok(aa) => options.export_aa = Some(aa.unwrap_or(AaConfig::Area)),
+                    Err(e) => log::warn!("Ignoring 'export_aa': {}", e),
+                }
+            }
+            if let Some(names) = params.get("split_aa") {
+                // Nothing compares MSAA16 with area AA.
+                let default = [AaConfig::Msaa16, AaConfig::Area];
+                match crate::aa::parse_pair(&names, default) {
+                    Ok(configs) => options.split_aa = Some(configs),
+                    Err(e) => log::warn!("Ignoring 'split_aa': {}", e),
+                }
+            }
+            if let Some(names) = params.get("layer_aa") {
+                // Nothing gives the overlays' text MSAA16 over area AA content.
+                let default = [AaConfig::Area, AaConfig::Msaa16];
+                match crate::aa::parse_pair(&names, default) {
+                    Ok(configs) => options.layer_aa = Some(configs),
+                    Err(e) => log::warn!("Ignoring 'layer_aa': {}", e),
+                }
+            }
+            if let Some(name) = params.get("alpha") {
+                match crate::alpha::parse(&name) {
+                    Ok(mode) => options.surface.alpha_mode = mode,
+                    Err(e) => log::warn!("Ignoring 'alpha': {}", e),
+                }
+            }
+            if let Some(name) = params.get("format") {
+                match crate::surface::parse_format(&name) {
+                    Ok(format) => options.surface.format = Some(format),
+                    Err(e) => log::warn!("Ignoring 'format': {}", e),
+                }
+            }
+            if let Some(name) = params.get("present") {
+                match crate::surface::parse_present_mode(&name) {
+                    Ok(mode) => options.surface.present_mode = mode,
+                    Err(e) => log::warn!("Ignoring 'present': {}", e),
+                }
+            }
+            if let Some(names) = params.get("surface_usage") {
+                match crate::surface::parse_usage(&names) {
+                    Ok(usage) => options.surface.usage = usage,
+                    Err(e) => log::warn!("Ignoring 'surface_usage': {}", e),
+                }
+            }
+            options.surface.readback = params.has("surface_readback");
+            match param::<u32>(&params, "latency") {
+                Some(0) => log::warn!("Ignoring 'latency': must be at least 1"),
+                Some(latency) => options.surface.frame_latency = latency,
+                None => {}
+            }
+            if let Some(aspect) = params.get("aspect") {
+                match crate::draw::parse_aspect(&aspect) {
+                    Ok(aspect) => options.aspect_lock = Some(aspect),
+                    Err(e) => log::warn!("Ignoring 'aspect': {}", e),
+                }
+            }
+            if let Some(color) = params.get("letterbox") {
+                match vello::peniko::color::parse_color(&color) {
+                    Ok(color) => options.letterbox_color = color.to_alpha_color(),
+                    Err(e) => log::warn!("Ignoring 'letterbox': {}", e),
+                }
+            }
+            if let Some(fit) = param(&params, "background_fit") {
+                options.background_fit = fit;
+            }
+            if let Some(seed) = param(&params, "seed") {
+                options.seed = seed;
+                options.demo = Demo::Random;
+            }
+            if let Some(shapes) = param(&params, "shapes") {
+                options.shapes = shapes;
+            }
+            if let Some(layers) = param(&params, "layers") {
+                options.layers = layers;
+                options.demo = Demo::Layers;
+            }
+            if let Some(sprites) = param(&params, "sprites") {
+                options.sprites = sprites;
+                options.demo = Demo::Sprites;
+            }
+            if let Some(hinting) = param(&params, "hinting") {
+                options.hinting = hinting;
+            }
+            match param::<f32>(&params, "font_size") {
+                Some(size) if size > 0.0 => {
+                    options.font_size = Some(size);
+                    options.demo = Demo::Text;
+                }
+                Some(_) => log::warn!("Ignoring 'font_size': must be positive"),
+                None => {}
+            }
+            if let Some(demo) = param(&params, "scene") {
+                options.demo = demo;
+            }
+            if params.has("safe") {
+                // Overrides any requested AA mode.
+                options.safe_mode = true;
+                options.aa_config = None;
+                options.surface.present_mode = wgpu::PresentMode::Fifo;
+            }
+            if let Some(name) = params.get("backend") {
+                match crate::context::parse_backend(&name) {
+                    Ok(backends) => options.backends = Some(backends),
+                    Err(e) => log::warn!("Ignoring 'backend': {}", e),
+                }
+            }
+            if let Some(limits) = param(&params, "limits") {
+                options.limits = limits;
+            }
+            match params.get("power").as_deref() {
+                None => {}
+                Some("high") => options.power_preference = wgpu::PowerPreference::HighPerformance,
+                Some("low") => options.power_preference = wgpu::PowerPreference::LowPower,
+                Some(other) => log::warn!("Ignoring invalid value for 'power': {}", other),
+            }
+            params.report();
+        }
+        options
+    }
+}
+
+/// The query parameters, remembering the names looked up, so that any
+/// others can be reported as unknown.
+#[cfg(target_arch = "wasm32")]
+struct Query {
+    params: web_sys::UrlSearchParams,
+    known: RefCell<Vec<&'static str>>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Query {
+    fn new(params: web_sys::UrlSearchParams) -> Self {
+        Query {
+            params,
+            known: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn has(&self, name: &'static str) -> bool {
+        self.known.borrow_mut().push(name);
+        self.params.has(name)
+    }
+
+    fn get(&self, name: &'static str) -> Option<String> {
+        self.known.borrow_mut().push(name);
+        self.params.get(name)
+    }
+
+    /// Log the parameters given, so that a shared URL's options show in the
+    /// log, and warn about any that aren't options.
+    fn report(&self) {
+        let known = self.known.borrow();
+        let mut given = Vec::new();
+        for name in self.params.keys().into_iter().filter_map(Result::ok) {
+            let Some(name) = name.as_string() else {
+                continue;
+            };
+            if !known.contains(&name.as_str()) {
+                log::warn!("Ignoring unknown option '{}'", name);
+                continue;
+            }
+            match self.params.get(&name).filter(|value| !value.is_empty()) {
+                Some(value) => given.push(format!("{}={}", name, value)),
+                None => given.push(name),
+            }
+        }
+        if !given.is_empty() {
+            log::info!("Options from the URL: {}", given.join(" "));
+        }
+    }
+}
+
+/// Parse a query parameter value, ignoring (and logging) invalid values.
+#[cfg(target_arch = "wasm32")]
+fn param<T: std::str::FromStr>(params: &Query, name: &'static str) -> Option<T> {
+    let value = params.get(name)?;
+    let parsed = value.parse().ok();
+    if parsed.is_none() {
+        log::warn!("Ignoring invalid value for '{}': {}", name, value);
+    }
+    parsed
+}
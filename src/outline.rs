@@ -0,0 +1,168 @@
+//! Wireframe outlines of the filled paths in a scene.
+//!
+//! A Vello scene doesn't keep the shapes it was given, only their encoding,
+//! so [`fills`] reads the filled paths back out of that: the path tags say
+//! which segments make up each path and which transform and style apply to
+//! it, and the segments' points are taken from the path data in order.
+//! Strokes are skipped, and so is text, whose glyph outlines are only made
+//! when the scene is rendered. Clip shapes are encoded as fills, so they are
+//! outlined too. The outlines are stroked over the scene, thin and in a
+//! colour unlikely to be in it, to show where shapes overlap.
+
+use vello::kurbo::{Affine, BezPath, Point, Stroke};
+use vello::peniko::Color;
+use vello::Scene;
+
+/// The path tags marking a change of transform or style, and the end of a path.
+const TRANSFORM: u8 = 0x20;
+const STYLE: u8 = 0x40;
+const PATH: u8 = 0x10;
+/// The bit of a style's flags set for strokes.
+const STROKE_BIT: u32 = 0x8000_0000;
+
+/// The width of the outlines, in surface pixels.
+const WIDTH: f64 = 1.0;
+
+fn outline_color() -> Color {
+    Color::from_rgb8(255, 0, 255)
+}
+
+/// Reads the points of path segments, in order, from a path data stream.
+struct Points<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Points<'_> {
+    /// The next point, of 32-bit floats or 16-bit integers.
+    fn next(&mut self, f32: bool) -> Point {
+        let size = if f32 { 4 } else { 2 };
+        let mut coord = || {
+            let bytes = &self.data[self.offset..self.offset + size];
+            self.offset += size;
+            match *bytes {
+                [a, b, c, d] => f32::from_le_bytes([a, b, c, d]) as f64,
+                [a, b] => i16::from_le_bytes([a, b]) as f64,
+                _ => unreachable!(),
+            }
+        };
+        let x = coord();
+        Point::new(x, coord())
+    }
+}
+
+/// The filled paths of `scene`, each with its transform applied, in the order they're drawn.
+pub fn fills(scene: &Scene) -> Vec<BezPath> {
+    let encoding = scene.encoding();
+    let mut points = Points {
+        data: &encoding.path_data,
+        offset: 0,
+    };
+    let mut transforms = encoding.transforms.iter();
+    let mut styles = encoding.styles.iter();
+    let mut transform = Affine::IDENTITY;
+    let mut fill = true;
+    let mut path = BezPath::new();
+    // Whether the next segment starts a subpath, and so is preceded by its start point.
+    let mut start = true;
+    let mut fills = vec![];
+    for tag in &encoding.path_tags {
+        match tag.0 {
+            TRANSFORM => {
+                if let Some(t) = transforms.next() {
+                    let [a, b, c, d] = t.matrix.map(f64::from);
+                    let [e, f] = t.translation.map(f64::from);
+                    transform = Affine::new([a, b, c, d, e, f]);
+                }
+            }
+            STYLE => {
+                if let Some(style) = styles.next() {
+                    fill = style.flags_and_miter_limit & STROKE_BIT == 0;
+                }
+            }
+            PATH => {
+                let path = std::mem::take(&mut path);
+                if fill && !path.is_empty() {
+                    fills.push(transform * path);
+                }
+                start = true;
+            }
+            _ if tag.is_path_segment() => {
+                let f32 = tag.is_f32();
+                if start {
+                    path.move_to(points.next(f32));
+                    start = false;
+                }
+                match tag.path_segment_type().0 {
+                    1 => path.line_to(points.next(f32)),
+                    2 => path.quad_to(points.next(f32), points.next(f32)),
+                    _ => path.curve_to(points.next(f32), points.next(f32), points.next(f32)),
+                }
+                if tag.is_subpath_end() {
+                    path.close_path();
+                    start = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    fills
+}
+
+/// Stroke the outline of each filled path of `content` into `scene`, for
+/// `content` drawn with `transform`. The outlines are the same width
+/// whatever the transform.
+pub fn draw_outlines(scene: &mut Scene, content: &Scene, transform: Affine) {
+    let stroke = Stroke::new(WIDTH);
+    for path in fills(content) {
+        let path = transform * path;
+        scene.stroke(&stroke, Affine::IDENTITY, outline_color(), None, &path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Circle, Rect, Shape};
+    use vello::peniko::Fill;
+
+    use super::*;
+
+    #[test]
+    fn filled_paths_are_read_back() {
+        let mut scene = Scene::new();
+        let rect = Rect::new(0.0, 0.0, 10.0, 20.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, Color::BLACK, None, &rect);
+        // Strokes aren't outlined.
+        scene.stroke(
+            &Stroke::new(2.0),
+            Affine::IDENTITY,
+            Color::BLACK,
+            None,
+            &rect,
+        );
+        let circle = Circle::new((0.0, 0.0), 5.0);
+        let moved = Affine::translate((100.0, 50.0));
+        scene.fill(Fill::EvenOdd, moved, Color::WHITE, None, &circle);
+        // Appended scenes keep their paths, with the appended transform.
+        let mut outer = Scene::new();
+        outer.append(&scene, Some(Affine::scale(2.0)));
+
+        let fills = fills(&outer);
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[0].bounding_box(), Rect::new(0.0, 0.0, 20.0, 40.0));
+        let bounds = fills[1].bounding_box();
+        let expected = Rect::new(190.0, 90.0, 210.0, 110.0);
+        for (coord, expected) in [
+            (bounds.x0, expected.x0),
+            (bounds.y0, expected.y0),
+            (bounds.x1, expected.x1),
+            (bounds.y1, expected.y1),
+        ] {
+            assert!((coord - expected).abs() < 0.01, "{:?}", bounds);
+        }
+
+        let mut outlines = Scene::new();
+        draw_outlines(&mut outlines, &outer, Affine::IDENTITY);
+        assert_eq!(outlines.encoding().n_paths, 2);
+    }
+}
@@ -0,0 +1,393 @@
+//! Debug overlays drawn over the scene content, in surface coordinates.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use vello::kurbo::{Affine, BezPath, Cap, Line, Point, Rect, Shape, Stroke};
+use vello::peniko::{color::palette, Color, Fill};
+use vello::Scene;
+
+use crate::text::Text;
+use crate::trace::FrameTiming;
+
+const TEXT_SIZE: f32 = 14.0;
+const PADDING: f64 = 6.0;
+
+/// Size of Vello's fine rasterization tiles in pixels. Vello doesn't export
+/// this, so it mirrors `TILE_WIDTH` and `TILE_HEIGHT` in `vello_encoding`.
+const TILE_SIZE: u32 = 16;
+/// Size of the bins used in Vello's coarse rasterization, in tiles.
+const BIN_TILES: u32 = 16;
+
+/// Draw lines of text on a translucent panel with its top-left at `origin`.
+/// Returns the panel bounds.
+pub fn draw_panel(scene: &mut Scene, text: &Text, lines: &str, origin: Point) -> Rect {
+    let (width, height) = text.measure(lines, TEXT_SIZE);
+    let panel = Rect::from_origin_size(origin, (width + PADDING * 2.0, height + PADDING * 2.0));
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgba8(255, 255, 255, 200),
+        None,
+        &panel,
+    );
+    text.draw(
+        scene,
+        lines,
+        origin + (PADDING, PADDING),
+        TEXT_SIZE,
+        Color::BLACK,
+    );
+    panel
+}
+
+/// How far a tooltip is drawn from the point it is about, on both axes.
+const TOOLTIP_OFFSET: f64 = 16.0;
+
+/// Draw lines of text on a panel beside `anchor`, below and to its right
+/// unless that would take it out of `area`. The panel never covers the
+/// anchor. Returns the panel bounds.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_tooltip(
+    scene: &mut Scene,
+    text: &Text,
+    lines: &str,
+    anchor: Point,
+    area: Rect,
+) -> Rect {
+    let (width, height) = text.measure(lines, TEXT_SIZE);
+    let (width, height) = (width + PADDING * 2.0, height + PADDING * 2.0);
+    let mut origin = anchor + (TOOLTIP_OFFSET, TOOLTIP_OFFSET);
+    if origin.x + width > area.x1 {
+        origin.x = anchor.x - TOOLTIP_OFFSET - width;
+    }
+    if origin.y + height > area.y1 {
+        origin.y = anchor.y - TOOLTIP_OFFSET - height;
+    }
+    draw_panel(scene, text, lines, origin)
+}
+
+/// Draw the boundaries of Vello's tiles over a render target of the given
+/// size, with the bin boundaries emphasised. Each line covers the first
+/// pixel row or column of a tile.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_tile_grid(scene: &mut Scene, width: u32, height: u32) {
+    let mut tiles = BezPath::new();
+    let mut bins = BezPath::new();
+    for i in 0..width.div_ceil(TILE_SIZE) {
+        let x = (i * TILE_SIZE) as f64 + 0.5;
+        let path = if i.is_multiple_of(BIN_TILES) {
+            &mut bins
+        } else {
+            &mut tiles
+        };
+        path.move_to((x, 0.0));
+        path.line_to((x, height as f64));
+    }
+    for i in 0..height.div_ceil(TILE_SIZE) {
+        let y = (i * TILE_SIZE) as f64 + 0.5;
+        let path = if i.is_multiple_of(BIN_TILES) {
+            &mut bins
+        } else {
+            &mut tiles
+        };
+        path.move_to((0.0, y));
+        path.line_to((width as f64, y));
+    }
+    let stroke = Stroke::new(1.0);
+    scene.stroke(
+        &stroke,
+        Affine::IDENTITY,
+        Color::from_rgba8(0, 160, 255, 96),
+        None,
+        &tiles,
+    );
+    scene.stroke(
+        &stroke,
+        Affine::IDENTITY,
+        Color::from_rgba8(255, 0, 160, 160),
+        None,
+        &bins,
+    );
+}
+
+/// The colour of the crosshairs and ruler, opaque so that partly covered
+/// pixels stand out.
+const CROSSHAIR_COLOR: Color = Color::from_rgb8(255, 0, 255);
+/// The length of each arm of the corner marks.
+const CORNER_LENGTH: f64 = 12.0;
+/// The spacing of the ruler's ticks, and their lengths: every fifth is longer.
+const RULER_STEP: u32 = 10;
+const TICK_LENGTH: f64 = 4.0;
+const MAJOR_TICK_LENGTH: f64 = 8.0;
+
+/// A 1px line through the centres of the pixels from `from` to `to`, which
+/// share a row or a column.
+fn pixel_line(path: &mut BezPath, from: (u32, u32), to: (u32, u32)) {
+    let centre = |(x, y): (u32, u32)| Point::new(x as f64 + 0.5, y as f64 + 0.5);
+    let (from, to) = (centre(from), centre(to));
+    // Extended by half a pixel at each end, to cover the end pixels fully.
+    let along = (to - from).normalize() * 0.5;
+    let along = if along.is_finite() {
+        along
+    } else {
+        (0.5, 0.0).into()
+    };
+    path.move_to(from - along);
+    path.line_to(to + along);
+}
+
+/// Draw alignment marks over a render target of the given size, each
+/// covering whole pixels exactly: a crosshair through the centre pixel (the
+/// one right of and below the centre, when a side is even), marks in the
+/// corner pixels, and a ruler with a tick every 10 pixels along the top and
+/// left edges. Content that lines up with them is on the pixels it should
+/// be; a mark drawn as two half-covered rows or columns shows a half-pixel
+/// offset in the rendering itself.
+pub fn draw_crosshairs(scene: &mut Scene, width: u32, height: u32) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let mut path = BezPath::new();
+    let (right, bottom) = (width - 1, height - 1);
+    let (cx, cy) = (width / 2, height / 2);
+    pixel_line(&mut path, (cx, 0), (cx, bottom));
+    pixel_line(&mut path, (0, cy), (right, cy));
+    let arm = CORNER_LENGTH as u32 - 1;
+    for (x, y, towards_x, towards_y) in [
+        (0, 0, arm.min(right), arm.min(bottom)),
+        (right, 0, right.saturating_sub(arm), arm.min(bottom)),
+        (0, bottom, arm.min(right), bottom.saturating_sub(arm)),
+        (
+            right,
+            bottom,
+            right.saturating_sub(arm),
+            bottom.saturating_sub(arm),
+        ),
+    ] {
+        pixel_line(&mut path, (x, y), (towards_x, y));
+        pixel_line(&mut path, (x, y), (x, towards_y));
+    }
+    let tick = |i: u32| {
+        let length = if (i / RULER_STEP).is_multiple_of(5) {
+            MAJOR_TICK_LENGTH
+        } else {
+            TICK_LENGTH
+        };
+        length as u32 - 1
+    };
+    for x in (RULER_STEP..width).step_by(RULER_STEP as usize) {
+        pixel_line(&mut path, (x, 0), (x, tick(x).min(bottom)));
+    }
+    for y in (RULER_STEP..height).step_by(RULER_STEP as usize) {
+        pixel_line(&mut path, (0, y), (tick(y).min(right), y));
+    }
+    scene.stroke(
+        &Stroke::new(1.0).with_caps(Cap::Butt),
+        Affine::IDENTITY,
+        CROSSHAIR_COLOR,
+        None,
+        &path,
+    );
+}
+
+/// How many frame times are kept for the frame time graph.
+const HISTORY_LEN: usize = 120;
+/// Frames slower than this are drawn in red: one frame at 60Hz.
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+/// The frame time shown at the top of the graph. Slower frames are clipped.
+const GRAPH_MAX: Duration = Duration::from_millis(50);
+const BAR_WIDTH: f64 = 2.0;
+const GRAPH_HEIGHT: f64 = 60.0;
+
+/// The most recent frame times, oldest first.
+#[derive(Debug, Default)]
+pub struct FrameHistory {
+    times: VecDeque<Duration>,
+}
+
+impl FrameHistory {
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.times.len() == HISTORY_LEN {
+            self.times.pop_front();
+        }
+        self.times.push_back(frame_time);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.times.iter().copied()
+    }
+}
+
+/// Draw the frame time history as a bar graph with its top-left at `origin`,
+/// with a line marking the frame budget. Returns the graph bounds.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_frame_graph(scene: &mut Scene, history: &FrameHistory, origin: Point) -> Rect {
+    let width = HISTORY_LEN as f64 * BAR_WIDTH;
+    let panel = Rect::from_origin_size(
+        origin,
+        (width + PADDING * 2.0, GRAPH_HEIGHT + PADDING * 2.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgba8(255, 255, 255, 200),
+        None,
+        &panel,
+    );
+    let baseline = origin.y + PADDING + GRAPH_HEIGHT;
+    let height_of =
+        |time: Duration| (time.as_secs_f64() / GRAPH_MAX.as_secs_f64()).min(1.0) * GRAPH_HEIGHT;
+    let mut within = BezPath::new();
+    let mut over = BezPath::new();
+    for (i, time) in history.iter().enumerate() {
+        let x = origin.x + PADDING + (i as f64 + 0.5) * BAR_WIDTH;
+        let bar = Line::new((x, baseline), (x, baseline - height_of(time)));
+        let path = if time > FRAME_BUDGET {
+            &mut over
+        } else {
+            &mut within
+        };
+        path.extend(bar.path_elements(0.1));
+    }
+    let bars = Stroke::new(BAR_WIDTH).with_caps(Cap::Butt);
+    scene.stroke(
+        &bars,
+        Affine::IDENTITY,
+        palette::css::SEA_GREEN,
+        None,
+        &within,
+    );
+    scene.stroke(&bars, Affine::IDENTITY, palette::css::RED, None, &over);
+    let budget_y = baseline - height_of(FRAME_BUDGET);
+    scene.stroke(
+        &Stroke::new(1.0),
+        Affine::IDENTITY,
+        Color::BLACK,
+        None,
+        &Line::new(
+            (origin.x + PADDING, budget_y),
+            (origin.x + PADDING + width, budget_y),
+        ),
+    );
+    panel
+}
+
+/// The height of the frame phase bar.
+const PHASE_HEIGHT: f64 = 10.0;
+/// The side of the colour swatch beside each phase's label.
+const SWATCH: f64 = 10.0;
+
+/// The render, present and poll phases of a frame: their names, colours and
+/// lengths in milliseconds. The timings are from the frame's start, so each
+/// phase is the time since the one before ended.
+fn phases(timing: &FrameTiming) -> [(&'static str, Color, f64); 3] {
+    [
+        ("render", palette::css::STEEL_BLUE, timing.render_ms),
+        (
+            "present",
+            palette::css::ORANGE,
+            (timing.present_ms - timing.render_ms).max(0.0),
+        ),
+        (
+            "poll",
+            palette::css::MEDIUM_PURPLE,
+            (timing.poll_ms - timing.present_ms).max(0.0),
+        ),
+    ]
+}
+
+/// Draw a bar split into the render, present and poll phases of a frame in
+/// proportion to their lengths, above a colour-coded label of each, with its
+/// top-left at `origin`. Returns the panel bounds.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_phase_bar(scene: &mut Scene, text: &Text, timing: &FrameTiming, origin: Point) -> Rect {
+    let phases = phases(timing);
+    let labels = phases
+        .iter()
+        .map(|(name, _, ms)| format!("{} {:.2} ms", name, ms))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let width = HISTORY_LEN as f64 * BAR_WIDTH;
+    let (_, labels_height) = text.measure(&labels, TEXT_SIZE);
+    let panel = Rect::from_origin_size(
+        origin,
+        (
+            width + PADDING * 2.0,
+            PHASE_HEIGHT + labels_height + PADDING * 3.0,
+        ),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgba8(255, 255, 255, 200),
+        None,
+        &panel,
+    );
+    let total: f64 = phases.iter().map(|(_, _, ms)| ms).sum();
+    let mut x = origin.x + PADDING;
+    let top = origin.y + PADDING;
+    let line_height = text.line_height(TEXT_SIZE);
+    let labels_origin = Point::new(x + SWATCH + PADDING, top + PHASE_HEIGHT + PADDING);
+    for (i, (_, color, ms)) in phases.iter().enumerate() {
+        if total > 0.0 {
+            let length = ms / total * width;
+            let segment = Rect::new(x, top, x + length, top + PHASE_HEIGHT);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, *color, None, &segment);
+            x += length;
+        }
+        let y = labels_origin.y + i as f64 * line_height + (line_height - SWATCH) / 2.0;
+        let swatch = Rect::from_origin_size((origin.x + PADDING, y), (SWATCH, SWATCH));
+        scene.fill(Fill::NonZero, Affine::IDENTITY, *color, None, &swatch);
+    }
+    text.draw(scene, &labels, labels_origin, TEXT_SIZE, Color::BLACK);
+    panel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crosshairs_cover_whole_pixels() {
+        let (width, height) = (60, 30);
+        let mut scene = Scene::new();
+        draw_crosshairs(&mut scene, width, height);
+        let Some(pixels) = crate::readback::testing::render(&scene, width, height) else {
+            return;
+        };
+        let alpha = |x: u32, y: u32| pixels[((y * width + x) * 4 + 3) as usize];
+        // The centre column and row, and nothing either side of them.
+        assert_eq!([alpha(29, 20), alpha(30, 20), alpha(31, 20)], [0, 255, 0]);
+        assert_eq!([alpha(40, 14), alpha(40, 15), alpha(40, 16)], [0, 255, 0]);
+        // The corners' marks, along the edge pixels.
+        assert_eq!([alpha(0, 11), alpha(0, 12), alpha(1, 5)], [255, 0, 0]);
+        assert_eq!([alpha(59, 29), alpha(48, 29), alpha(47, 29)], [255, 255, 0]);
+        // Ticks every 10 pixels, with every fifth longer.
+        assert_eq!([alpha(10, 3), alpha(10, 4), alpha(11, 1)], [255, 0, 0]);
+        assert_eq!([alpha(50, 7), alpha(50, 8)], [255, 0]);
+        assert_eq!([alpha(3, 20), alpha(4, 20)], [255, 0]);
+    }
+
+    #[test]
+    fn history_keeps_the_latest_frames() {
+        let mut history = FrameHistory::default();
+        for ms in 0..(HISTORY_LEN as u64 + 10) {
+            history.push(Duration::from_millis(ms));
+        }
+        assert_eq!(history.iter().count(), HISTORY_LEN);
+        assert_eq!(history.iter().next(), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn phases_are_the_times_between_timings() {
+        let timing = FrameTiming {
+            frame: 1,
+            render_ms: 3.0,
+            present_ms: 4.5,
+            poll_ms: 8.0,
+        };
+        let lengths = phases(&timing).map(|(_, _, ms)| ms);
+        assert_eq!(lengths, [3.0, 1.5, 3.5]);
+    }
+}
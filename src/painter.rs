@@ -0,0 +1,215 @@
+//! An immediate-mode drawing API, in the style of SVG and the 2D canvas.
+//!
+//! A [`Painter`] holds the current fill and stroke, and each shape drawn is
+//! encoded straight into its scene with them: filled first, then stroked,
+//! as in SVG. The same API is used from Rust and, through the wasm wrapper
+//! `Drawing`, from JS, so the drawing logic lives only here.
+
+use std::fmt;
+
+use vello::kurbo::{Affine, BezPath, Circle, Line, Point, Rect, Shape, Stroke, SvgParseError};
+use vello::peniko::{Color, Fill};
+use vello::Scene;
+
+use crate::text::Text;
+
+/// A scene being drawn into with the current fill and stroke.
+pub struct Painter {
+    scene: Scene,
+    text: Text,
+    fill: Option<Color>,
+    stroke: Option<(Color, Stroke)>,
+}
+
+impl fmt::Debug for Painter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Painter")
+            .field("paths", &self.scene.encoding().n_paths)
+            .field("fill", &self.fill)
+            .field("stroke", &self.stroke)
+            .finish()
+    }
+}
+
+impl Default for Painter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Painter {
+    /// An empty drawing, filling in black without stroking, like SVG.
+    pub fn new() -> Self {
+        Painter {
+            scene: Scene::new(),
+            text: Text::new(),
+            fill: Some(Color::BLACK),
+            stroke: None,
+        }
+    }
+
+    /// Set the fill of the shapes and text that follow, or `None` not to fill them.
+    pub fn set_fill(&mut self, color: Option<Color>) {
+        self.fill = color;
+    }
+
+    /// Set the stroke of the shapes that follow, or `None` not to stroke them.
+    pub fn set_stroke(&mut self, color: Option<Color>, width: f64) {
+        self.stroke = color.map(|color| (color, Stroke::new(width)));
+    }
+
+    fn shape(&mut self, shape: &impl Shape) {
+        if let Some(color) = self.fill {
+            self.scene
+                .fill(Fill::NonZero, Affine::IDENTITY, color, None, shape);
+        }
+        if let Some((color, style)) = &self.stroke {
+            self.scene
+                .stroke(style, Affine::IDENTITY, *color, None, shape);
+        }
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.shape(&Rect::from_origin_size((x, y), (width, height)));
+    }
+
+    pub fn circle(&mut self, cx: f64, cy: f64, radius: f64) {
+        self.shape(&Circle::new((cx, cy), radius));
+    }
+
+    /// Draw a line. Lines have no area, so they are only stroked.
+    pub fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        if let Some((color, style)) = &self.stroke {
+            let line = Line::new((x0, y0), (x1, y1));
+            self.scene
+                .stroke(style, Affine::IDENTITY, *color, None, &line);
+        }
+    }
+
+    /// Draw an SVG path, given as the data of its `d` attribute, with its own
+    /// fill and stroke rather than the current ones. All the SVG path
+    /// commands are supported, relative as well as absolute: M, L, H, V, C,
+    /// S, Q, T, A and Z. Nothing is drawn if the data is invalid.
+    pub fn draw_path_data(
+        &mut self,
+        d: &str,
+        fill: Option<Color>,
+        stroke: Option<(Color, f64)>,
+    ) -> Result<(), SvgParseError> {
+        let path = BezPath::from_svg(d)?;
+        if let Some(color) = fill {
+            self.scene
+                .fill(Fill::NonZero, Affine::IDENTITY, color, None, &path);
+        }
+        if let Some((color, width)) = stroke {
+            self.scene
+                .stroke(&Stroke::new(width), Affine::IDENTITY, color, None, &path);
+        }
+        Ok(())
+    }
+
+    /// Draw text in the fill colour, with the top-left of its first line at `x`, `y`.
+    pub fn text(&mut self, x: f64, y: f64, size: f32, text: &str) {
+        if let Some(color) = self.fill {
+            self.text
+                .draw(&mut self.scene, text, Point::new(x, y), size, color);
+        }
+    }
+
+    pub fn into_scene(self) -> Scene {
+        self.scene
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::peniko::color::palette;
+
+    use super::*;
+    use crate::readback::testing;
+
+    const SIZE: u32 = 32;
+
+    fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * SIZE + x) * 4) as usize;
+        pixels[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn shapes_use_the_current_paint() {
+        let mut painter = Painter::new();
+        painter.set_fill(Some(palette::css::RED));
+        painter.rect(0.0, 0.0, 16.0, 16.0);
+        painter.set_fill(None);
+        painter.set_stroke(Some(palette::css::BLUE), 4.0);
+        painter.circle(24.0, 24.0, 4.0);
+        painter.line(0.0, 30.0, 16.0, 30.0);
+        painter.set_stroke(None, 1.0);
+        // Neither filled nor stroked, so not drawn at all.
+        painter.circle(24.0, 8.0, 4.0);
+        painter.line(16.0, 0.0, 32.0, 16.0);
+        let scene = painter.into_scene();
+        assert_eq!(scene.encoding().n_paths, 3);
+
+        let Some(pixels) = testing::render(&scene, SIZE, SIZE) else {
+            return;
+        };
+        let red = palette::css::RED.to_rgba8().to_u8_array();
+        let blue = palette::css::BLUE.to_rgba8().to_u8_array();
+        assert_eq!(pixel(&pixels, 8, 8), red);
+        // The circle is stroked but not filled: its outline is blue, its centre empty.
+        assert_eq!(pixel(&pixels, 24, 20), blue);
+        assert_eq!(pixel(&pixels, 24, 24), [0; 4]);
+        assert_eq!(pixel(&pixels, 8, 30), blue);
+        assert_eq!(pixel(&pixels, 24, 8), [0; 4]);
+    }
+
+    #[test]
+    fn path_data_is_filled_and_stroked() {
+        let mut painter = Painter::new();
+        // A relative square, and an arc closed into a half disc.
+        painter
+            .draw_path_data("m0 0 h16 v16 h-16 z", Some(palette::css::RED), None)
+            .unwrap();
+        painter
+            .draw_path_data(
+                "M16 24 A8 8 0 0 1 32 24 Z",
+                None,
+                Some((palette::css::BLUE, 2.0)),
+            )
+            .unwrap();
+        assert!(painter.draw_path_data("M0 0 Q", None, None).is_err());
+        let scene = painter.into_scene();
+        assert_eq!(scene.encoding().n_paths, 2);
+
+        let Some(pixels) = testing::render(&scene, SIZE, SIZE) else {
+            return;
+        };
+        assert_eq!(
+            pixel(&pixels, 8, 8),
+            palette::css::RED.to_rgba8().to_u8_array()
+        );
+        // The top of the arc is stroked, and the half disc inside isn't filled.
+        assert_eq!(
+            pixel(&pixels, 24, 16),
+            palette::css::BLUE.to_rgba8().to_u8_array()
+        );
+        assert_eq!(pixel(&pixels, 24, 21), [0; 4]);
+    }
+
+    #[test]
+    fn text_is_filled() {
+        let mut painter = Painter::new();
+        painter.text(0.0, 0.0, 24.0, "#");
+        let draws = painter.scene.encoding().draw_tags.len();
+        painter.set_fill(None);
+        painter.text(0.0, 0.0, 24.0, "#");
+        assert_eq!(painter.scene.encoding().draw_tags.len(), draws);
+
+        let Some(pixels) = testing::render(&painter.into_scene(), SIZE, SIZE) else {
+            return;
+        };
+        let black = Color::BLACK.to_rgba8().to_u8_array();
+        assert!(pixels.chunks(4).any(|p| p == black));
+    }
+}
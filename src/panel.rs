@@ -0,0 +1,137 @@
+//! A minimal immediate-mode control panel, drawn with Vello.
+//!
+//! The panel is drawn from scratch every frame from the current state of
+//! each control, recording where each control's row landed. Clicks are then
+//! hit-tested against those rows, so the panel keeps no widget tree and the
+//! controls never get out of step with the state they show.
+
+use vello::kurbo::{Affine, Point, Rect, RoundedRect};
+use vello::peniko::{Color, Fill};
+use vello::Scene;
+
+use crate::text::Text;
+
+const TEXT_SIZE: f32 = 14.0;
+const PADDING: f64 = 6.0;
+/// The gap between control rows.
+const ROW_GAP: f64 = 2.0;
+const BACKGROUND: Color = Color::from_rgba8(255, 255, 255, 220);
+const ROW_COLOR: Color = Color::from_rgba8(210, 220, 240, 255);
+
+/// A clickable row of the panel, showing a label and the current state.
+pub struct Control<A> {
+    pub label: &'static str,
+    pub state: String,
+    /// What clicking the row does.
+    pub action: A,
+}
+
+impl<A> Control<A> {
+    pub fn new(label: &'static str, state: impl Into<String>, action: A) -> Self {
+        Control {
+            label,
+            state: state.into(),
+            action,
+        }
+    }
+}
+
+/// The clickable regions of the panel as last drawn.
+#[derive(Debug)]
+pub struct Panel<A> {
+    rows: Vec<(Rect, A)>,
+}
+
+impl<A> Default for Panel<A> {
+    fn default() -> Self {
+        Panel { rows: Vec::new() }
+    }
+}
+
+impl<A: Copy> Panel<A> {
+    /// Draw the panel with its top-left at `origin`: the lines of `info`,
+    /// then a row for each control. Replaces the regions recorded by the
+    /// previous draw. Returns the panel bounds.
+    pub fn draw(
+        &mut self,
+        scene: &mut Scene,
+        text: &Text,
+        origin: Point,
+        info: &str,
+        controls: &[Control<A>],
+    ) -> Rect {
+        let labels: Vec<String> = controls
+            .iter()
+            .map(|c| format!("{}: {}", c.label, c.state))
+            .collect();
+        let (info_width, info_height) = text.measure(info, TEXT_SIZE);
+        let line_height = text.line_height(TEXT_SIZE);
+        let row_height = line_height + PADDING;
+        let width = labels
+            .iter()
+            .map(|label| text.measure(label, TEXT_SIZE).0 + PADDING * 2.0)
+            .fold(info_width, f64::max);
+        let height = info_height + controls.len() as f64 * (row_height + ROW_GAP);
+        let panel = Rect::from_origin_size(origin, (width + PADDING * 2.0, height + PADDING * 2.0));
+        scene.fill(Fill::NonZero, Affine::IDENTITY, BACKGROUND, None, &panel);
+        let inner = origin + (PADDING, PADDING);
+        text.draw(scene, info, inner, TEXT_SIZE, Color::BLACK);
+
+        self.rows.clear();
+        let mut y = inner.y + info_height;
+        for (control, label) in controls.iter().zip(&labels) {
+            let row = Rect::from_origin_size((inner.x, y), (width, row_height));
+            let button = RoundedRect::from_rect(row, 3.0);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, ROW_COLOR, None, &button);
+            let label_origin = Point::new(row.x0 + PADDING, row.y0 + PADDING / 2.0);
+            text.draw(scene, label, label_origin, TEXT_SIZE, Color::BLACK);
+            self.rows.push((row, control.action));
+            y += row_height + ROW_GAP;
+        }
+        panel
+    }
+
+    /// Forget the rows, e.g. when the panel is hidden, so that nothing is hit.
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// The action of the row at `point`, if any.
+    pub fn hit(&self, point: Point) -> Option<A> {
+        self.rows
+            .iter()
+            .find(|(row, _)| row.contains(point))
+            .map(|&(_, action)| action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_hit_the_drawn_rows() {
+        let mut panel = Panel::default();
+        let controls = [Control::new("a", "on", 1), Control::new("b", "off", 2)];
+        let bounds = panel.draw(
+            &mut Scene::new(),
+            &Text::new(),
+            Point::new(10.0, 10.0),
+            "info",
+            &controls,
+        );
+        let (first, second) = (panel.rows[0].0, panel.rows[1].0);
+        assert!(first.y1 < second.y0);
+        assert!(bounds.contains(second.center()));
+        assert_eq!(panel.hit(first.center()), Some(1));
+        assert_eq!(panel.hit(second.center()), Some(2));
+        // The info lines and gaps between rows aren't clickable.
+        assert_eq!(panel.hit(Point::new(first.center().x, 12.0)), None);
+        assert_eq!(
+            panel.hit(Point::new(first.center().x, first.y1 + 1.0)),
+            None
+        );
+        panel.clear();
+        assert_eq!(panel.hit(first.center()), None);
+    }
+}
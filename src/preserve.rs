@@ -0,0 +1,182 @@
+//! Keeping the previous frame's content instead of clearing it.
+//!
+//! Vello's fine stage writes every pixel of its target, compositing the
+//! scene over the base colour, so there is no load op to change: rendering
+//! straight to the surface always clears it. To keep what was drawn before,
+//! [`PreservePass`] renders the scene over a transparent base into a scratch
+//! texture, blends that over a persistent canvas texture that is only
+//! cleared to the base colour when created or resized, and then copies the
+//! canvas to the output. Only what the scene draws changes from one frame to
+//! the next, which is what a later dirty-rectangle renderer will rely on.
+//! Blending is exact over an opaque base colour; over a translucent one the
+//! accumulated alpha is only approximate, as the canvas keeps straight alpha
+//! like Vello's own output.
+
+use vello::peniko::Color;
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+use crate::blit::{self, Blit, Source};
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(position.xy), 0);
+}
+"#;
+
+/// The scratch texture the scene is rendered into, and the canvas it is
+/// accumulated in.
+struct Targets {
+    scratch: Source,
+    canvas: Source,
+    width: u32,
+    height: u32,
+}
+
+/// Renders scenes over the content of the frames before, into outputs of one format.
+pub struct PreservePass {
+    blit: Blit,
+    composite: wgpu::RenderPipeline,
+    copy: wgpu::RenderPipeline,
+    targets: Option<Targets>,
+}
+
+impl PreservePass {
+    /// Build the pipelines, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let blit = Blit::new(device, "preserve", SHADER, 1, false);
+        // The scratch texture has straight alpha, so it is blended over the canvas as such.
+        let blend = Some(wgpu::BlendState::ALPHA_BLENDING);
+        let composite = blit.pipeline(device, "fs_main", blit::FORMAT, blend);
+        let copy = blit.pipeline(device, "fs_main", format, None);
+        PreservePass {
+            blit,
+            composite,
+            copy,
+            targets: None,
+        }
+    }
+
+    /// The targets at the given size, and whether they are new, replacing
+    /// those of another size.
+    fn targets(&mut self, device: &wgpu::Device, width: u32, height: u32) -> (Targets, bool) {
+        if let Some(targets) = self.targets.take() {
+            if targets.width == width && targets.height == height {
+                return (targets, false);
+            }
+        }
+        let source = |label, usage| self.blit.source(device, label, width, height, usage);
+        let targets = Targets {
+            scratch: source("preserve scratch", wgpu::TextureUsages::STORAGE_BINDING),
+            canvas: source("preserve canvas", wgpu::TextureUsages::RENDER_ATTACHMENT),
+            width,
+            height,
+        };
+        (targets, true)
+    }
+
+    /// Forget the content kept so far, so that the next frame starts from the base colour.
+    pub fn clear(&mut self) {
+        self.targets = None;
+    }
+
+    /// Render `scene` with `params` over the frames before, and draw the
+    /// result into `output`. The base colour is only drawn when the canvas
+    /// is first made, or remade at a new size.
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+    ) -> Result<(), vello::Error> {
+        let (targets, fresh) = self.targets(device, params.width, params.height);
+        let scratch_params = RenderParams {
+            base_color: Color::TRANSPARENT,
+            ..*params
+        };
+        let rendered = renderer.render_to_texture(
+            device,
+            queue,
+            scene,
+            &targets.scratch.texture.view,
+            &scratch_params,
+        );
+        let targets = self.targets.insert(targets);
+        rendered?;
+
+        let load = match fresh {
+            true => blit::clear(params.base_color),
+            false => wgpu::LoadOp::Load,
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("preserve"),
+        });
+        let composite = (&self.composite, &targets.scratch.bind_group);
+        self.blit.draw(
+            &mut encoder,
+            &targets.canvas.texture.view,
+            load,
+            &[composite],
+        );
+        let copy = (&self.copy, &targets.canvas.bind_group);
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        self.blit.draw(&mut encoder, output, clear, &[copy]);
+        queue.submit([encoder.finish()]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Affine, Rect};
+    use vello::peniko::Fill;
+    use vello::AaConfig;
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    #[test]
+    fn earlier_frames_are_kept() {
+        let Some((context, dev_id, mut renderer)) = renderer(&[AaConfig::Area]) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 3, 1);
+        let params = RenderParams {
+            base_color: Color::WHITE,
+            width: 3,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let mut pass = PreservePass::new(device, blit::FORMAT);
+        // A red pixel in the first frame, and a blue one beside it in the second.
+        for (x, color) in [
+            (0.0, Color::from_rgb8(255, 0, 0)),
+            (1.0, Color::from_rgb8(0, 0, 255)),
+        ] {
+            let mut scene = Scene::new();
+            let rect = Rect::new(x, 0.0, x + 1.0, 1.0);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+            pass.render(&mut renderer, device, queue, &scene, &view, &params)
+                .unwrap();
+        }
+        let pixels = block_on(Readback::new(device, queue, &output, 3, 1).read(device)).unwrap();
+        assert_eq!(pixels, [255, 0, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255]);
+
+        // Clearing starts again from the base colour.
+        pass.clear();
+        pass.render(&mut renderer, device, queue, &Scene::new(), &view, &params)
+            .unwrap();
+        let pixels = block_on(Readback::new(device, queue, &output, 3, 1).read(device)).unwrap();
+        assert_eq!(pixels, [255; 12]);
+    }
+}
@@ -0,0 +1,606 @@
+//! Reading rendered textures back from the GPU.
+//!
+//! Textures of any uncompressed colour format can be read back, as their
+//! raw bytes, e.g. 8 a pixel of `Rgba16Float` halves. Vello itself only
+//! renders into `Rgba8Unorm`, as its fine stage writes the target as an
+//! `rgba8unorm` storage texture, so [`render_target`] is always 8-bit, and
+//! only textures other passes draw into can be read back at higher depths.
+
+use std::fmt;
+use std::future::Future;
+
+use serde::Serialize;
+
+use vello::kurbo::Affine;
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+/// Create a texture that Vello can render into with `render_to_texture`, and that can be copied from.
+pub fn render_target(device: &wgpu::Device, label: &str, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// Render `scene` into a new texture of the size in `params`, and copy it
+/// into a buffer to read back.
+pub fn render_to_readback(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    scene: &Scene,
+    params: &RenderParams,
+) -> Result<Readback, vello::Error> {
+    let texture = render_target(device, "readback", params.width, params.height);
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    renderer.render_to_texture(device, queue, scene, &view, params)?;
+    Ok(Readback::new(
+        device,
+        queue,
+        &texture,
+        params.width,
+        params.height,
+    ))
+}
+
+/// A tile of a larger image: its position and size, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Split an image into tiles no more than `max` pixels on a side, row by row.
+pub fn tiles(width: u32, height: u32, max: u32) -> Vec<Tile> {
+    let max = max.max(1);
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(max as usize) {
+        for x in (0..width).step_by(max as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: max.min(width - x),
+                height: max.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+/// Render `scene` at the size in `params`, which may be larger than a
+/// texture can be, as tiles no more than `max_tile` pixels on a side. Each
+/// tile renders its own region of the scene, translated to its origin,
+/// into its own texture, and all of them are copied for reading back
+/// before this returns, so the buffers for the whole image are allocated.
+pub fn render_tiled(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &mut Renderer,
+    scene: &Scene,
+    params: &RenderParams,
+    max_tile: u32,
+) -> Result<TiledReadback, vello::Error> {
+    let mut tile_scene = Scene::new();
+    let mut reads = Vec::new();
+    for tile in tiles(params.width, params.height, max_tile) {
+        tile_scene.reset();
+        let origin = Affine::translate((-(tile.x as f64), -(tile.y as f64)));
+        tile_scene.append(scene, Some(origin));
+        let tile_params = RenderParams {
+            width: tile.width,
+            height: tile.height,
+            ..*params
+        };
+        let readback = render_to_readback(device, queue, renderer, &tile_scene, &tile_params)?;
+        reads.push((tile, readback));
+    }
+    Ok(TiledReadback {
+        width: params.width,
+        height: params.height,
+        reads,
+    })
+}
+
+/// The tiles of an image, waiting to be read and stitched together.
+pub struct TiledReadback {
+    width: u32,
+    height: u32,
+    reads: Vec<(Tile, Readback)>,
+}
+
+impl TiledReadback {
+    /// Wait for every tile's copy to complete, as [`Readback::read`] does,
+    /// and return the tightly packed RGBA pixels of the whole image.
+    pub fn read(
+        self,
+        device: &wgpu::Device,
+    ) -> impl Future<Output = Result<Vec<u8>, wgpu::BufferAsyncError>> {
+        let reads: Vec<_> = self
+            .reads
+            .into_iter()
+            .map(|(tile, readback)| (tile, readback.read(device)))
+            .collect();
+        let row_bytes = self.width as usize * 4;
+        let mut pixels = vec![0; row_bytes * self.height as usize];
+        async move {
+            for (tile, read) in reads {
+                let tile_pixels = read.await?;
+                let tile_row = tile.width as usize * 4;
+                for (row, src) in tile_pixels.chunks_exact(tile_row).enumerate() {
+                    let start = (tile.y as usize + row) * row_bytes + tile.x as usize * 4;
+                    pixels[start..start + tile_row].copy_from_slice(src);
+                }
+            }
+            Ok(pixels)
+        }
+    }
+}
+
+/// The bytes of a pixel of `format`, if it can be read back a pixel at a time:
+/// an uncompressed colour format.
+pub fn bytes_per_pixel(format: wgpu::TextureFormat) -> Option<u32> {
+    if format.block_dimensions() != (1, 1) || format.has_depth_aspect() {
+        return None;
+    }
+    format.block_copy_size(None)
+}
+
+/// A texture copied into a mappable buffer, waiting to be read.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl Readback {
+    /// Copy a texture into a buffer that can be read with [`Readback::read`].
+    /// The copy is submitted immediately.
+    ///
+    /// Panics if the texture's format has no [`bytes_per_pixel`].
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let format = texture.format();
+        let bytes_per_pixel = bytes_per_pixel(format)
+            .unwrap_or_else(|| panic!("can't read back {:?} textures", format));
+        // Rows of a texture copy must be aligned.
+        let padded_bytes_per_row =
+            (width * bytes_per_pixel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([encoder.finish()]);
+        Readback {
+            buffer,
+            width,
+            height,
+            bytes_per_pixel,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Wait for the copy to complete, and return the tightly packed pixels, in
+    /// the texture's format: RGBA bytes for the textures Vello renders into.
+    ///
+    /// The buffer can only be mapped once the GPU has finished the copy, and
+    /// so all the rendering submitted before it, so the pixels are never blank
+    /// or partial. Natively the mapping only completes when the device is
+    /// polled, so this waits for the device to be idle with `Maintain::Wait`
+    /// before returning. On the web polling does nothing: the browser completes
+    /// the mapping asynchronously, so the returned future must be awaited,
+    /// e.g. from `spawn_local`, and never blocked on.
+    pub fn read(
+        self,
+        device: &wgpu::Device,
+    ) -> impl Future<Output = Result<Vec<u8>, wgpu::BufferAsyncError>> {
+        let (sender, receiver) = futures_intrusive::channel::shared::oneshot_channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |v| {
+                // The receiver is only dropped if the read was abandoned.
+                _ = sender.send(v);
+            });
+        device.poll(wgpu::Maintain::Wait);
+        async move {
+            receiver.receive().await.expect("channel was closed")?;
+            let row_bytes = (self.width * self.bytes_per_pixel) as usize;
+            let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+            for row in self
+                .buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks(self.padded_bytes_per_row as usize)
+            {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+            self.buffer.unmap();
+            Ok(pixels)
+        }
+    }
+}
+
+/// Encode tightly packed RGBA pixels as a PNG image.
+pub fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(png)
+}
+
+/// Decode a PNG image to tightly packed 8-bit RGBA pixels, returning its
+/// width, height and pixels. Images without alpha are made opaque.
+pub fn decode_png(png: &[u8]) -> Result<(u32, u32, Vec<u8>), png::DecodingError> {
+    let mut decoder = png::Decoder::new(png);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buffer)?;
+    buffer.truncate(frame.buffer_size());
+    let pixels = match frame.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        // Indexed images have been expanded to RGB(A) by `normalize_to_color8`.
+        png::ColorType::Grayscale | png::ColorType::Indexed => {
+            buffer.iter().flat_map(|&v| [v, v, v, 255]).collect()
+        }
+    };
+    Ok((frame.width, frame.height, pixels))
+}
+
+/// The largest difference between corresponding channels of two images of the same size.
+pub fn max_difference(a: &[u8], b: &[u8]) -> u8 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| a.abs_diff(b))
+        .max()
+        .unwrap_or(0)
+}
+
+/// A cheap summary of an image, for comparing renders across machines.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ImageSummary {
+    /// FNV-1a hash of the pixel data.
+    pub hash: u64,
+    /// Mean of each RGBA channel, from 0 to 255.
+    pub mean: [f64; 4],
+}
+
+impl ImageSummary {
+    /// Summarize tightly packed RGBA pixels.
+    pub fn of(pixels: &[u8]) -> Self {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut sums = [0u64; 4];
+        for (i, &byte) in pixels.iter().enumerate() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+            sums[i % 4] += byte as u64;
+        }
+        let count = (pixels.len() / 4).max(1) as f64;
+        ImageSummary {
+            hash,
+            mean: sums.map(|sum| sum as f64 / count),
+        }
+    }
+}
+
+impl fmt::Display for ImageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b, a] = self.mean;
+        write!(
+            f,
+            "hash {:016x}, mean rgba ({:.2}, {:.2}, {:.2}, {:.2})",
+            self.hash, r, g, b, a
+        )
+    }
+}
+
+/// Helpers for tests that render on a native GPU, if there is one.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::future::Future;
+    use std::num::NonZeroUsize;
+    use std::task::{Context, Poll, Waker};
+
+    use vello::peniko::Color;
+    use vello::{AaConfig, AaSupport, RendererOptions};
+
+    use super::*;
+    use crate::blit;
+    use crate::context::RenderContext;
+
+    /// Run a future to completion. Native wgpu completes its futures when the device
+    /// is polled, which `Readback::read` does, so this never has to wait for a wake up.
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    /// Render `scene` over transparent black with area AA and read back its
+    /// RGBA pixels, or `None` if there is no adapter to render with.
+    pub fn render(scene: &Scene, width: u32, height: u32) -> Option<Vec<u8>> {
+        render_with(scene, width, height, AaConfig::Area)
+    }
+
+    /// As [`render`], with `aa`.
+    pub fn render_with(scene: &Scene, width: u32, height: u32, aa: AaConfig) -> Option<Vec<u8>> {
+        let (context, dev_id, mut renderer) = renderer(&[aa])?;
+        let handle = &context.devices[dev_id];
+        let params = params(width, height, aa);
+        let readback =
+            render_to_readback(&handle.device, &handle.queue, &mut renderer, scene, &params)
+                .unwrap();
+        Some(block_on(readback.read(&handle.device)).unwrap())
+    }
+
+    /// As [`render`], in tiles no more than `max_tile` pixels on a side.
+    pub fn render_tiled(scene: &Scene, width: u32, height: u32, max_tile: u32) -> Option<Vec<u8>> {
+        let (context, dev_id, mut renderer) = renderer(&[AaConfig::Area])?;
+        let handle = &context.devices[dev_id];
+        let params = params(width, height, AaConfig::Area);
+        let readback = super::render_tiled(
+            &handle.device,
+            &handle.queue,
+            &mut renderer,
+            scene,
+            &params,
+            max_tile,
+        )
+        .unwrap();
+        Some(block_on(readback.read(&handle.device)).unwrap())
+    }
+
+    /// As [`render`], twice with one renderer, as a scene reused for
+    /// another frame is.
+    pub fn render_twice(scene: &Scene, width: u32, height: u32) -> Option<[Vec<u8>; 2]> {
+        let (context, dev_id, mut renderer) = renderer(&[AaConfig::Area])?;
+        let handle = &context.devices[dev_id];
+        let params = params(width, height, AaConfig::Area);
+        let mut read = || {
+            let readback =
+                render_to_readback(&handle.device, &handle.queue, &mut renderer, scene, &params)
+                    .unwrap();
+            block_on(readback.read(&handle.device)).unwrap()
+        };
+        Some([read(), read()])
+    }
+
+    fn params(width: u32, height: u32, aa: AaConfig) -> RenderParams {
+        RenderParams {
+            base_color: Color::TRANSPARENT,
+            width,
+            height,
+            antialiasing_method: aa,
+        }
+    }
+
+    /// A device and a renderer supporting `configs`, if there is an adapter.
+    pub(crate) fn renderer(configs: &[AaConfig]) -> Option<(RenderContext, usize, Renderer)> {
+        let mut context = RenderContext::new(wgpu::PowerPreference::default());
+        let Some(dev_id) = block_on(context.device(None)) else {
+            eprintln!("No adapter available, skipping rendering");
+            return None;
+        };
+        let handle = &context.devices[dev_id];
+        let renderer = Renderer::new(
+            &handle.device,
+            RendererOptions {
+                surface_format: None,
+                use_cpu: false,
+                antialiasing_support: configs.iter().copied().collect::<AaSupport>(),
+                num_init_threads: NonZeroUsize::new(1),
+            },
+        )
+        .unwrap();
+        Some((context, dev_id, renderer))
+    }
+
+    /// A texture of [`blit::FORMAT`] for passes to draw into and tests to
+    /// read back, and its view.
+    pub(crate) fn texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("test"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: blit::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_means_and_hash() {
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 255];
+        let summary = ImageSummary::of(&pixels);
+        assert_eq!(summary.mean, [127.5, 0.0, 127.5, 255.0]);
+        assert_eq!(summary, ImageSummary::of(&pixels));
+        let swapped = [0, 0, 255, 255, 255, 0, 0, 255];
+        assert_ne!(summary.hash, ImageSummary::of(&swapped).hash);
+        assert_eq!(max_difference(&pixels, &pixels), 0);
+        assert_eq!(max_difference(&pixels, &swapped), 255);
+        assert_eq!(max_difference(&[10, 20], &[12, 15]), 5);
+    }
+
+    #[test]
+    fn tiles_cover_the_image() {
+        let tiles = tiles(40, 24, 16);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[5],
+            Tile {
+                x: 32,
+                y: 16,
+                width: 8,
+                height: 8
+            }
+        );
+        let area: u32 = tiles.iter().map(|t| t.width * t.height).sum();
+        assert_eq!(area, 40 * 24);
+    }
+
+    #[test]
+    fn tiled_renders_match_whole_ones() {
+        use vello::kurbo::Circle;
+        use vello::peniko::{Color, Fill};
+
+        let mut scene = Scene::new();
+        let circle = Circle::new((21.0, 11.5), 10.3);
+        let color = Color::from_rgb8(200, 40, 90);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &circle);
+        let Some(whole) = testing::render(&scene, 40, 24) else {
+            return;
+        };
+        let tiled = testing::render_tiled(&scene, 40, 24, 16).unwrap();
+        assert_eq!(max_difference(&whole, &tiled), 0);
+    }
+
+    #[test]
+    fn higher_depths_read_back_whole() {
+        use crate::context::RenderContext;
+
+        let float = wgpu::TextureFormat::Rgba16Float;
+        assert_eq!(bytes_per_pixel(wgpu::TextureFormat::Rgba8Unorm), Some(4));
+        assert_eq!(bytes_per_pixel(float), Some(8));
+        assert_eq!(bytes_per_pixel(wgpu::TextureFormat::Bc1RgbaUnorm), None);
+        assert_eq!(bytes_per_pixel(wgpu::TextureFormat::Depth32Float), None);
+
+        let mut context = RenderContext::new(wgpu::PowerPreference::default());
+        let Some(dev_id) = testing::block_on(context.device(None)) else {
+            eprintln!("No adapter available, skipping read back");
+            return;
+        };
+        let (device, queue) = (
+            &context.devices[dev_id].device,
+            &context.devices[dev_id].queue,
+        );
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("test"),
+            size: wgpu::Extent3d {
+                width: 3,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("test"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // Levels between those 8 bits can hold.
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 1.0,
+                        g: 0.5,
+                        b: 0.25,
+                        a: 1.0,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        queue.submit([encoder.finish()]);
+        let pixels =
+            testing::block_on(Readback::new(device, queue, &texture, 3, 1).read(device)).unwrap();
+        // Little-endian halves of 1.0, 0.5, 0.25 and 1.0.
+        let pixel = [0x00, 0x3c, 0x00, 0x38, 0x00, 0x34, 0x00, 0x3c];
+        assert_eq!(pixels, pixel.repeat(3));
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 128];
+        let png = encode_png(2, 1, &pixels).unwrap();
+        assert_eq!(decode_png(&png).unwrap(), (2, 1, pixels.to_vec()));
+
+        let mut rgb = Vec::new();
+        let mut encoder = png::Encoder::new(&mut rgb, 1, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&[1, 2, 3])
+            .unwrap();
+        assert_eq!(decode_png(&rgb).unwrap(), (1, 1, vec![1, 2, 3, 255]));
+    }
+}
@@ -0,0 +1,187 @@
+//! Recording input events, and replaying them.
+//!
+//! The client's input handlers are driven by [`InputEvent`]s, converted from
+//! the window's events. While recording, each one is also logged with the
+//! time since recording started. A [`Replay`] later feeds a log back through
+//! the same handlers at the recorded times, so that an interaction that
+//! triggers a problem can be shared and reproduced exactly. Logs are JSON.
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+/// An input event, as handled by the client.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputEvent {
+    /// A key press producing this text, or of the named key, e.g. `ArrowLeft`.
+    Key { text: String },
+    /// A press of the Delete or Backspace key.
+    Clear,
+    /// The cursor moved to this position, in physical pixels.
+    CursorMoved { x: f64, y: f64 },
+    /// The left mouse button was pressed or released.
+    Button { pressed: bool },
+    /// The wheel scrolled the content by this many pixels, positive to
+    /// move it right and down.
+    Scroll { dx: f64, dy: f64 },
+}
+
+/// An input event at a time since recording started.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub time_ms: f64,
+    #[serde(flatten)]
+    pub event: InputEvent,
+}
+
+/// The events recorded since recording started.
+#[derive(Debug)]
+pub struct Recorder {
+    started: Instant,
+    events: Vec<RecordedEvent>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder {
+            started: Instant::now(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        self.record_at(self.started.elapsed(), event);
+    }
+
+    fn record_at(&mut self, time: Duration, event: InputEvent) {
+        self.events.push(RecordedEvent {
+            time_ms: time.as_secs_f64() * 1000.0,
+            event,
+        });
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.events).unwrap_or_default()
+    }
+}
+
+thread_local! {
+    /// The recording in progress, if any.
+    static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Start recording input events, discarding any recording in progress.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn start_recording() {
+    RECORDER.set(Some(Recorder::new()));
+}
+
+/// Stop recording, returning the JSON log of the events, or `None` if there
+/// was no recording in progress.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn stop_recording() -> Option<String> {
+    RECORDER.take().map(|recorder| recorder.to_json())
+}
+
+/// Record an event, if recording.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn record(event: &InputEvent) {
+    RECORDER.with_borrow_mut(|recorder| {
+        if let Some(recorder) = recorder {
+            recorder.record(event.clone());
+        }
+    });
+}
+
+/// A log being replayed, from when the replay started.
+#[derive(Debug)]
+pub struct Replay {
+    events: Vec<RecordedEvent>,
+    next: usize,
+    started: Instant,
+}
+
+impl Replay {
+    /// Parse a JSON log of events to replay, starting now.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let mut events: Vec<RecordedEvent> = serde_json::from_str(json)?;
+        events.sort_by(|a, b| a.time_ms.total_cmp(&b.time_ms));
+        Ok(Replay {
+            events,
+            next: 0,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Take the events due by `elapsed` since the replay started.
+    fn due_at(&mut self, elapsed: Duration) -> Vec<InputEvent> {
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        let due = self.events[self.next..]
+            .iter()
+            .take_while(|e| e.time_ms <= elapsed_ms)
+            .map(|e| e.event.clone())
+            .collect::<Vec<_>>();
+        self.next += due.len();
+        due
+    }
+
+    /// Take the events that are due now.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn due(&mut self) -> Vec<InputEvent> {
+        self.due_at(self.started.elapsed())
+    }
+
+    /// When the next event is due, or `None` once all have been replayed.
+    pub fn next_due(&self) -> Option<Instant> {
+        let event = self.events.get(self.next)?;
+        Some(self.started + Duration::from_secs_f64(event.time_ms.max(0.0) / 1000.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_and_replay_in_order() {
+        let mut recorder = Recorder::new();
+        recorder.record_at(
+            Duration::from_millis(20),
+            InputEvent::Button { pressed: true },
+        );
+        recorder.record_at(
+            Duration::from_millis(10),
+            InputEvent::CursorMoved { x: 1.5, y: 2.0 },
+        );
+        recorder.record_at(
+            Duration::from_millis(30),
+            InputEvent::Key { text: "s".into() },
+        );
+        let json = recorder.to_json();
+        assert!(json.contains(r#""type":"cursor_moved""#));
+
+        let mut replay = Replay::from_json(&json).unwrap();
+        assert_eq!(replay.len(), 3);
+        assert_eq!(
+            replay.due_at(Duration::from_millis(25)),
+            [
+                InputEvent::CursorMoved { x: 1.5, y: 2.0 },
+                InputEvent::Button { pressed: true },
+            ]
+        );
+        assert!(replay.next_due().is_some());
+        assert_eq!(replay.due_at(Duration::from_millis(25)), []);
+        assert_eq!(
+            replay.due_at(Duration::from_secs(1)),
+            [InputEvent::Key { text: "s".into() }]
+        );
+        assert_eq!(replay.next_due(), None);
+    }
+}
@@ -0,0 +1,115 @@
+//! Cycling through every demo with every AA config, unattended.
+//!
+//! A [`Reel`] steps through the matrix of demos and the AA configs the
+//! renderer was built with, drawing each pair for a while before moving on,
+//! and starts over once it has shown them all. Each step is logged, so that
+//! anyone leaving the page running can tell from the log which pair was on
+//! screen when something went wrong. The configs are those of the renderer
+//! at each step, as switching adapters can change them along the way.
+
+use std::time::Duration;
+
+use vello::AaConfig;
+
+use crate::scenes::Demo;
+
+/// How long each step is shown without `?demo_reel=N`.
+pub const DEFAULT_DWELL: Duration = Duration::from_secs(3);
+
+/// A demo and AA config shown by the reel, and where it is in the matrix.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Step {
+    pub demo: Demo,
+    pub aa: AaConfig,
+    /// From 0, out of `total`.
+    pub index: usize,
+    pub total: usize,
+    /// How many times the reel has started over.
+    pub pass: usize,
+}
+
+#[derive(Debug)]
+pub struct Reel {
+    /// How long each step is shown.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub dwell: Duration,
+    /// The AA config, and whether MSAA was on, before the reel started, to
+    /// put back when it stops.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub restore: (AaConfig, bool),
+    /// Every demo, from the one shown when the reel started.
+    demos: Vec<Demo>,
+    /// Steps taken, over every pass.
+    taken: usize,
+}
+
+impl Reel {
+    /// A reel starting from `first`, showing each step for `dwell`, and
+    /// putting back `restore` when it stops.
+    pub fn new(first: Demo, dwell: Duration, restore: (AaConfig, bool)) -> Self {
+        let mut demos = vec![first];
+        loop {
+            let next = demos.last().unwrap().next();
+            if next == first {
+                break;
+            }
+            demos.push(next);
+        }
+        Reel {
+            dwell,
+            restore,
+            demos,
+            taken: 0,
+        }
+    }
+
+    /// Take the next step, with `configs` the AA configs to show each demo
+    /// with, in order.
+    pub fn next(&mut self, configs: &[AaConfig]) -> Step {
+        let configs = match configs {
+            [] => &[AaConfig::Area][..],
+            configs => configs,
+        };
+        let total = self.demos.len() * configs.len();
+        let index = self.taken % total;
+        let step = Step {
+            demo: self.demos[index / configs.len()],
+            aa: configs[index % configs.len()],
+            index,
+            total,
+            pass: self.taken / total,
+        };
+        self.taken += 1;
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_demo_is_shown_with_every_config() {
+        let configs = [AaConfig::Area, AaConfig::Msaa16];
+        let mut reel = Reel::new(Demo::Transforms, DEFAULT_DWELL, (AaConfig::Area, false));
+        let demos = reel.demos.len();
+        let steps: Vec<_> = (0..demos * 2 + 1).map(|_| reel.next(&configs)).collect();
+        assert_eq!(steps[0].demo, Demo::Transforms);
+        assert_eq!(
+            (steps[0].aa, steps[1].aa),
+            (AaConfig::Area, AaConfig::Msaa16)
+        );
+        assert_eq!(steps[1].demo, Demo::Transforms);
+        assert_eq!(steps[2].demo, Demo::Transforms.next());
+        // The last demo is the one before the first, and then it starts over.
+        assert_eq!(steps[demos * 2 - 1].demo.next(), Demo::Transforms);
+        assert_eq!(steps[demos * 2 - 1].total, demos * 2);
+        assert_eq!((steps[demos * 2].index, steps[demos * 2].pass), (0, 1));
+        assert_eq!(steps[demos * 2].demo, Demo::Transforms);
+        // Each pair once per pass.
+        for (i, a) in steps[..demos * 2].iter().enumerate() {
+            assert_eq!(a.index, i);
+            assert!(steps[..i].iter().all(|b| (b.demo, b.aa) != (a.demo, a.aa)));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Drawing the last frame's scene again while nothing in it has changed.
+//!
+//! Every frame normally resets the scene and encodes it all again. With
+//! `?reuse_scene`, a frame drawn while nothing that goes into the scene has
+//! changed renders the scene built for an earlier frame instead: Vello takes
+//! the scene by reference and leaves its encoding as it was, so it can be
+//! rendered any number of times, and only `render_to_surface` is needed for
+//! the new surface texture. [`SceneReuse`] keeps whether the scene is stale,
+//! which events, resizes and anything drawn differently each frame make it,
+//! and times the builds, so that the CPU time reuse saved can be told.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// How many scenes were built and reused, and the time reuse saved.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub built: u64,
+    pub reused: u64,
+    /// The mean time building a scene took.
+    pub mean_build_ms: f64,
+    /// The time building the reused scenes would have taken, at that mean.
+    pub saved_ms: f64,
+}
+
+#[derive(Debug)]
+pub struct SceneReuse {
+    /// Whether something drawn in the scene changed since it was built.
+    stale: bool,
+    /// The size the scene was built at.
+    size: (u32, u32),
+    built: u64,
+    reused: u64,
+    build_time: Duration,
+}
+
+impl Default for SceneReuse {
+    fn default() -> Self {
+        SceneReuse {
+            stale: true,
+            size: (0, 0),
+            built: 0,
+            reused: 0,
+            build_time: Duration::ZERO,
+        }
+    }
+}
+
+impl SceneReuse {
+    /// Note something drawn in the scene changing.
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Whether the scene built last can be drawn again for a frame of
+    /// `size`, given whether anything in it is drawn differently each
+    /// frame. Counts the frame as reused if it can.
+    pub fn reuse(&mut self, size: (u32, u32), varies: bool) -> bool {
+        if self.stale || varies || size != self.size {
+            return false;
+        }
+        self.reused += 1;
+        true
+    }
+
+    /// Note a scene built afresh at `size`, in `time`.
+    pub fn built(&mut self, size: (u32, u32), time: Duration) {
+        self.stale = false;
+        self.size = size;
+        self.built += 1;
+        self.build_time += time;
+    }
+
+    pub fn summary(&self) -> Summary {
+        let mean_build_ms = match self.built {
+            0 => 0.0,
+            built => self.build_time.as_secs_f64() * 1000.0 / built as f64,
+        };
+        Summary {
+            built: self.built,
+            reused: self.reused,
+            mean_build_ms,
+            saved_ms: mean_build_ms * self.reused as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::{Affine, Circle};
+    use vello::peniko::{Color, Fill};
+    use vello::Scene;
+
+    use super::*;
+    use crate::readback::testing;
+
+    #[test]
+    fn scenes_are_rebuilt_only_when_stale() {
+        let ms = Duration::from_millis;
+        let mut reuse = SceneReuse::default();
+        assert!(!reuse.reuse((10, 10), false));
+        reuse.built((10, 10), ms(4));
+        assert!(reuse.reuse((10, 10), false));
+        assert!(reuse.reuse((10, 10), false));
+        // Not when drawn differently each frame, nor at another size.
+        assert!(!reuse.reuse((10, 10), true));
+        assert!(!reuse.reuse((20, 10), false));
+        reuse.built((20, 10), ms(2));
+        reuse.invalidate();
+        assert!(!reuse.reuse((20, 10), false));
+        assert_eq!(
+            reuse.summary(),
+            Summary {
+                built: 2,
+                reused: 2,
+                mean_build_ms: 3.0,
+                saved_ms: 6.0,
+            }
+        );
+    }
+
+    #[test]
+    fn a_scene_renders_the_same_again() {
+        let mut scene = Scene::new();
+        let circle = Circle::new((8.0, 8.0), 6.0);
+        let color = Color::from_rgb8(40, 160, 220);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &circle);
+        let encoded = scene.encoding().path_data.clone();
+        let Some([first, second]) = testing::render_twice(&scene, 16, 16) else {
+            return;
+        };
+        assert_eq!(first, second);
+        // Rendering leaves the encoding as it was, for the next frame.
+        assert_eq!(scene.encoding().path_data, encoded);
+    }
+}
@@ -0,0 +1,216 @@
+//! Rendering at a reduced internal resolution when frames are slow.
+//!
+//! [`RenderScale`] watches frame times and steps the render scale down after
+//! a run of slow frames, and back up after a run of fast ones. Below full
+//! scale, [`Downscaler`] renders the scene into a smaller texture and then
+//! draws that, scaled up, into the surface. Vello can only draw images it
+//! has in its atlas, so the texture is substituted for a placeholder image
+//! using [`Renderer::override_image`]. The same drawing at a scale above 1
+//! supersamples: the scene is rendered larger and the image filtered down
+//! into the surface, bilinearly, which at 2x averages each 2 x 2 block.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::info;
+
+use vello::kurbo::Affine;
+use vello::peniko::{Blob, Image, ImageFormat};
+use vello::wgpu;
+use vello::{RenderParams, Renderer, Scene};
+
+use crate::readback;
+
+/// The render scales to step through, from full resolution down.
+const SCALES: [f64; 3] = [1.0, 0.75, 0.5];
+/// Frames slower than this count towards downscaling.
+const SLOW_FRAME: Duration = Duration::from_millis(33);
+/// Frames faster than this count towards restoring the resolution.
+const FAST_FRAME: Duration = Duration::from_millis(20);
+/// How many consecutive slow or fast frames change the scale.
+const FRAME_RUN: u32 = 10;
+/// The largest target, in either dimension, that fits in Vello's image atlas.
+const MAX_TARGET: u32 = 8192;
+
+/// The current render scale, adjusted according to recent frame times.
+#[derive(Debug, Default)]
+pub struct RenderScale {
+    level: usize,
+    slow: u32,
+    fast: u32,
+}
+
+impl RenderScale {
+    /// The fraction of the full resolution to render at.
+    pub fn scale(&self) -> f64 {
+        SCALES[self.level]
+    }
+
+    /// Record the time taken by a frame, returning the new scale if it changed.
+    pub fn record(&mut self, frame_time: Duration) -> Option<f64> {
+        if frame_time > SLOW_FRAME {
+            self.slow += 1;
+            self.fast = 0;
+        } else if frame_time < FAST_FRAME {
+            self.fast += 1;
+            self.slow = 0;
+        } else {
+            self.slow = 0;
+            self.fast = 0;
+        }
+        let level = if self.slow >= FRAME_RUN && self.level + 1 < SCALES.len() {
+            self.level + 1
+        } else if self.fast >= FRAME_RUN && self.level > 0 {
+            self.level - 1
+        } else {
+            return None;
+        };
+        self.level = level;
+        self.slow = 0;
+        self.fast = 0;
+        Some(self.scale())
+    }
+}
+
+/// The size of the target for rendering `width` x `height` at `scale`, and
+/// the scale used, lowered if needed so that neither side exceeds `limit`.
+fn target_size(width: u32, height: u32, scale: f64, limit: u32) -> (u32, u32, f64) {
+    let scale = scale.min(limit as f64 / width.max(height).max(1) as f64);
+    let width = ((width as f64 * scale).ceil() as u32).clamp(1, limit);
+    let height = ((height as f64 * scale).ceil() as u32).clamp(1, limit);
+    (width, height, scale)
+}
+
+/// A render target at another resolution, and the placeholder image standing in for it.
+struct Target {
+    texture: Arc<wgpu::Texture>,
+    view: wgpu::TextureView,
+    image: Image,
+}
+
+impl Target {
+    fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        // Vello copies overriding textures into its image atlas, so this must allow copies.
+        let texture = readback::render_target(device, "downscaled target", width, height);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // The image data is never read, as the texture overrides it.
+        let image = Image::new(
+            Blob::new(Arc::new(Vec::<u8>::new())),
+            ImageFormat::Rgba8,
+            width,
+            height,
+        );
+        // Vello's override API takes an `Arc`, although wgpu types aren't `Send` on the web.
+        #[allow(clippy::arc_with_non_send_sync)]
+        let texture = Arc::new(texture);
+        Target {
+            texture,
+            view,
+            image,
+        }
+    }
+}
+
+/// Renders scenes at another resolution, and scales the result into the surface.
+#[derive(Default)]
+pub struct Downscaler {
+    target: Option<Target>,
+    scaled: Scene,
+    upscaled: Scene,
+}
+
+impl Downscaler {
+    /// Render `scene` at `scale` times the size in `params`, then draw it at
+    /// full size into `surface`. The scale is lowered if the target wouldn't
+    /// fit the image atlas or the device's texture limit.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        surface: &wgpu::SurfaceTexture,
+        params: &RenderParams,
+        scale: f64,
+    ) -> Result<(), vello::Error> {
+        let limit = MAX_TARGET.min(device.limits().max_texture_dimension_2d);
+        let (width, height, scale) = target_size(params.width, params.height, scale, limit);
+        let target = match self.target.take() {
+            Some(target) if target.image.width == width && target.image.height == height => target,
+            old => {
+                if let Some(old) = old {
+                    renderer.override_image(&old.image, None);
+                }
+                info!("Scaled render target {} x {}", width, height);
+                let target = Target::new(device, width, height);
+                renderer.override_image(
+                    &target.image,
+                    Some(wgpu::ImageCopyTextureBase {
+                        texture: target.texture.clone(),
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    }),
+                );
+                target
+            }
+        };
+
+        self.scaled.reset();
+        self.scaled.append(scene, Some(Affine::scale(scale)));
+        renderer.render_to_texture(
+            device,
+            queue,
+            &self.scaled,
+            &target.view,
+            &RenderParams {
+                width,
+                height,
+                ..*params
+            },
+        )?;
+
+        self.upscaled.reset();
+        self.upscaled
+            .draw_image(&target.image, Affine::scale(1.0 / scale));
+        let result = renderer.render_to_surface(device, queue, &self.upscaled, surface, params);
+        self.target = Some(target);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_steps_down_and_recovers() {
+        let mut scale = RenderScale::default();
+        let slow = Duration::from_millis(50);
+        for _ in 1..FRAME_RUN {
+            assert_eq!(scale.record(slow), None);
+        }
+        assert_eq!(scale.record(slow), Some(0.75));
+        // A single fast frame interrupts the run of slow frames.
+        for _ in 1..FRAME_RUN {
+            scale.record(slow);
+        }
+        scale.record(Duration::from_millis(10));
+        assert_eq!(scale.record(slow), None);
+        let fast = Duration::from_millis(10);
+        for _ in 1..FRAME_RUN {
+            assert_eq!(scale.record(fast), None);
+        }
+        assert_eq!(scale.record(fast), Some(1.0));
+    }
+
+    #[test]
+    fn targets_fit_the_limit() {
+        assert_eq!(target_size(100, 50, 0.75, MAX_TARGET), (75, 38, 0.75));
+        assert_eq!(target_size(100, 50, 2.0, MAX_TARGET), (200, 100, 2.0));
+        // Supersampling a wide surface 4x is capped at the limit.
+        assert_eq!(target_size(3000, 1000, 4.0, 6000), (6000, 2000, 2.0));
+    }
+}
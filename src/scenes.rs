@@ -0,0 +1,936 @@
+//! Built-in demo scenes.
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, PI};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::debug;
+
+use vello::kurbo::{Affine, BezPath, Cap, Circle, Join, Line, Point, Rect, Stroke, Vec2};
+use vello::peniko::{
+    color::palette, Blob, Color, Extend, Fill, Image, ImageFormat, ImageQuality, Mix,
+};
+use vello::Scene;
+
+use crate::colors::InputColor;
+use crate::draw::LineStyle;
+use crate::graph::{Node, Paint};
+use crate::random::RandomScene;
+use crate::sprites::Sprites;
+use crate::text::{Hinting, Text};
+use crate::{alpha, draw, testcard, Options};
+
+/// The content drawn each frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Demo {
+    /// A single stroked rectangle, spinning when animated.
+    #[default]
+    Border,
+    /// Child shapes with their own local transforms inside a rotating parent group.
+    Transforms,
+    /// The border and transforms demos side by side, each clipped to its half.
+    Split,
+    /// Semi-transparent fills, over a drawn checkerboard on the left and
+    /// over whatever is behind the canvas on the right. With a transparent
+    /// alpha mode the page shows a matching checkerboard, so both halves
+    /// should look the same.
+    Alpha,
+    /// The transforms demo built as a retained scene graph, where animating
+    /// only changes transforms and the geometry is encoded just once.
+    Graph,
+    /// Thick zigzag polylines with increasingly sharp corners, with miter
+    /// joins, round joins, and the selected line style in turn.
+    Joins,
+    /// A small image scaled up, sampled with the nearest pixel on the left
+    /// and bilinearly on the right.
+    Images,
+    /// The border demo with its interior filled with the sprite as a
+    /// pattern, extended with the selected mode.
+    Pattern,
+    /// Random shapes generated from a seed, the same on every machine.
+    Random,
+    /// A grid of swatches with the same nominal components interpreted as
+    /// sRGB, linear sRGB and Display P3, one space per row.
+    Colors,
+    /// A ramp of text sizes, unhinted on the left and with the selected
+    /// hinting on the right.
+    Text,
+    /// A motif encoded once into its own scene, and stamped in a grid
+    /// with `Scene::append`, each copy with its own transform.
+    Stamps,
+    /// Many nested translucent layers with varied blend modes, overlapping
+    /// one another, to stress Vello's clip and blend stack.
+    Layers,
+    /// Many small shapes moving and bouncing off the edges, each encoded
+    /// every frame, for watching frame times scale with the count.
+    Sprites,
+    /// Colour bars, a grey ramp and a fine checkerboard filling the drawable
+    /// area, a reference for colour, gamma and sharpness.
+    TestPattern,
+    /// Stripes clipped to a spinning star, with a disc nested inside that
+    /// is clipped again to a circle, showing only where the two overlap.
+    Clips,
+    /// Many overlapping translucent discs, each faded by its brush's alpha
+    /// rather than a layer's.
+    Translucent,
+    /// 1px strokes at many angles and sub-pixel offsets filling the whole
+    /// surface, and the surface's own edges outlined 1px wide.
+    Hairlines,
+}
+
+/// State kept between frames for drawing the demos.
+pub struct DemoState {
+    /// The retained scene graph used by the graph demo.
+    pub graph: Node,
+    /// Join and cap styles for the stroked outlines.
+    pub line_style: LineStyle,
+    /// The colour of the border, following the theme.
+    pub stroke_color: Color,
+    /// The small image drawn by the images demo.
+    pub sprite: Image,
+    /// How the pattern demo extends the sprite beyond its bounds.
+    pub extend: Extend,
+    /// The seed and shape count of the random demo.
+    pub seed: u32,
+    pub shapes: u32,
+    pub random: RandomScene,
+    /// The number of layers the layers demo nests.
+    pub layers: u32,
+    /// The number of sprites in the sprites demo, and the sprites themselves.
+    pub sprite_count: u32,
+    pub sprites: Sprites,
+    pub text: Text,
+    /// How the right column of the text demo is fitted to the pixel grid.
+    pub hinting: Hinting,
+    /// The single size the text demo shows, in CSS pixels, instead of its ramp.
+    pub font_size: Option<f32>,
+    /// The device pixels per CSS pixel, for sizing text.
+    pub scale_factor: f64,
+    /// The inset of the demos from the edges: `MARGIN`, or zero for full bleed.
+    pub margin: f64,
+    /// The stamps demo's motif, encoded once.
+    pub motif: Scene,
+    /// The test pattern's checkerboard tile.
+    pub checker: Image,
+}
+
+impl DemoState {
+    pub fn new(options: &Options) -> Self {
+        DemoState {
+            graph: transforms_graph(),
+            line_style: LineStyle::default(),
+            stroke_color: draw::Palette::LIGHT.stroke,
+            sprite: sprite(),
+            extend: Extend::Repeat,
+            seed: options.seed,
+            shapes: options.shapes,
+            layers: options.layers,
+            sprite_count: options.sprites,
+            sprites: Sprites::default(),
+            random: RandomScene::default(),
+            text: Text::new(),
+            hinting: options.hinting,
+            font_size: options.font_size,
+            scale_factor: 1.0,
+            margin: if options.full_bleed {
+                0.0
+            } else {
+                draw::MARGIN
+            },
+            motif: motif(),
+            checker: testcard::checker(),
+        }
+    }
+}
+
+impl Demo {
+    pub fn name(self) -> &'static str {
+        match self {
+            Demo::Border => "border",
+            Demo::Transforms => "transforms",
+            Demo::Split => "split",
+            Demo::Alpha => "alpha",
+            Demo::Graph => "graph",
+            Demo::Joins => "joins",
+            Demo::Images => "images",
+            Demo::Pattern => "pattern",
+            Demo::Random => "random",
+            Demo::Colors => "colors",
+            Demo::Text => "text",
+            Demo::Stamps => "stamps",
+            Demo::Layers => "layers",
+            Demo::Sprites => "sprites",
+            Demo::TestPattern => "test_pattern",
+            Demo::Clips => "clips",
+            Demo::Translucent => "translucent",
+            Demo::Hairlines => "hairlines",
+        }
+    }
+
+    /// The next demo, for cycling through them all.
+    pub fn next(self) -> Demo {
+        match self {
+            Demo::Border => Demo::Transforms,
+            Demo::Transforms => Demo::Split,
+            Demo::Split => Demo::Alpha,
+            Demo::Alpha => Demo::Graph,
+            Demo::Graph => Demo::Joins,
+            Demo::Joins => Demo::Images,
+            Demo::Images => Demo::Pattern,
+            Demo::Pattern => Demo::Random,
+            Demo::Random => Demo::Colors,
+            Demo::Colors => Demo::Text,
+            Demo::Text => Demo::Stamps,
+            Demo::Stamps => Demo::Layers,
+            Demo::Layers => Demo::Sprites,
+            Demo::Sprites => Demo::TestPattern,
+            Demo::TestPattern => Demo::Clips,
+            Demo::Clips => Demo::Translucent,
+            Demo::Translucent => Demo::Hairlines,
+            Demo::Hairlines => Demo::Border,
+        }
+    }
+
+    /// Draw the demo into a drawable area of the given size, at animation time `elapsed`.
+    pub fn draw(
+        self,
+        scene: &mut Scene,
+        state: &mut DemoState,
+        width: u32,
+        height: u32,
+        elapsed: Duration,
+    ) {
+        match self {
+            Demo::Border => {
+                let rect = draw::border_rect(width, height, state.margin);
+                let transform = draw::spin(elapsed, rect.center());
+                draw::draw_border(
+                    scene,
+                    &rect,
+                    transform,
+                    state.line_style,
+                    state.stroke_color,
+                );
+            }
+            Demo::Transforms => draw_transforms(scene, width, height, elapsed),
+            Demo::Split => {
+                let (left, right) = draw::split_halves(width, height);
+                draw::draw_in_viewport(scene, left, |scene, width, height| {
+                    Demo::Border.draw(scene, state, width, height, elapsed)
+                });
+                draw::draw_in_viewport(scene, right, |scene, width, height| {
+                    Demo::Transforms.draw(scene, state, width, height, elapsed)
+                });
+            }
+            Demo::Alpha => draw_alpha(scene, width, height),
+            Demo::Joins => draw_joins(scene, width, height, state.margin, state.line_style),
+            Demo::Images => draw_images(scene, &state.sprite, width, height, state.margin),
+            Demo::Pattern => {
+                let rect = draw::border_rect(width, height, state.margin);
+                let transform = draw::spin(elapsed, rect.center());
+                // The sprite's origin is at the rect's corner, with each pixel PATTERN_SCALE square.
+                let pattern =
+                    Affine::translate(rect.origin().to_vec2()) * Affine::scale(PATTERN_SCALE);
+                draw::fill_pattern(
+                    scene,
+                    &rect,
+                    &state.sprite,
+                    state.extend,
+                    transform,
+                    pattern,
+                );
+                draw::draw_border(
+                    scene,
+                    &rect,
+                    transform,
+                    state.line_style,
+                    state.stroke_color,
+                );
+            }
+            Demo::Random => state
+                .random
+                .draw(scene, state.seed, state.shapes, width, height),
+            Demo::Colors => draw_swatches(scene, width, height, state.margin),
+            Demo::Text => draw_text_ramp(scene, state, width, height),
+            Demo::Stamps => {
+                let area = draw::border_rect(width, height, state.margin);
+                draw_stamps(scene, &state.motif, area, elapsed);
+            }
+            Demo::Layers => {
+                let area = draw::border_rect(width, height, state.margin);
+                draw_layers(scene, area, state.layers, elapsed);
+            }
+            Demo::Sprites => {
+                let area = draw::border_rect(width, height, state.margin);
+                state.sprites.draw(scene, state.sprite_count, area, elapsed);
+            }
+            Demo::TestPattern => testcard::draw_test_pattern(scene, &state.checker, width, height),
+            Demo::Clips => {
+                let area = draw::border_rect(width, height, state.margin);
+                draw_clips(scene, area, elapsed);
+            }
+            Demo::Translucent => {
+                let area = draw::border_rect(width, height, state.margin);
+                draw_translucent(scene, area, state.shapes, elapsed);
+            }
+            Demo::Hairlines => draw_hairlines(scene, width, height, state.stroke_color),
+            Demo::Graph => {
+                animate_graph(&mut state.graph, width, height, elapsed);
+                let encoded = state.graph.render_into(scene);
+                if encoded > 0 {
+                    debug!("Encoded {} scene graph nodes", encoded);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Demo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "border" => Ok(Demo::Border),
+            "transforms" => Ok(Demo::Transforms),
+            "split" => Ok(Demo::Split),
+            "alpha" => Ok(Demo::Alpha),
+            "graph" => Ok(Demo::Graph),
+            "joins" => Ok(Demo::Joins),
+            "images" => Ok(Demo::Images),
+            "pattern" => Ok(Demo::Pattern),
+            "random" => Ok(Demo::Random),
+            "colors" => Ok(Demo::Colors),
+            "text" => Ok(Demo::Text),
+            "stamps" => Ok(Demo::Stamps),
+            "layers" => Ok(Demo::Layers),
+            "sprites" => Ok(Demo::Sprites),
+            "test_pattern" => Ok(Demo::TestPattern),
+            "clips" => Ok(Demo::Clips),
+            "translucent" => Ok(Demo::Translucent),
+            "hairlines" => Ok(Demo::Hairlines),
+            _ => Err(format!("unknown scene '{}'", s)),
+        }
+    }
+}
+
+const CHILD_COLORS: [Color; 4] = [
+    palette::css::CRIMSON,
+    palette::css::SEA_GREEN,
+    palette::css::ROYAL_BLUE,
+    palette::css::GOLDENROD,
+];
+
+/// Local transform of child `i` relative to its parent group: placed on a
+/// circle of radius `radius`, spinning and scaled independently.
+fn child_transform(i: usize, t: f64, radius: f64) -> Affine {
+    Affine::rotate(i as f64 * FRAC_PI_2)
+        * Affine::translate((radius, 0.0))
+        * Affine::rotate(-t * (i + 1) as f64)
+        * Affine::scale(1.0 - 0.15 * i as f64)
+}
+
+fn draw_transforms(scene: &mut Scene, width: u32, height: u32, elapsed: Duration) {
+    let t = elapsed.as_secs_f64();
+    let size = width.min(height) as f64 / 8.0;
+    let center = Point::new(width as f64 / 2.0, height as f64 / 2.0);
+    let parent = Affine::translate(center.to_vec2()) * Affine::rotate(t * 0.5);
+
+    // Mark the parent's axes, so its rotation is visible.
+    for line in [
+        Line::new((-3.0 * size, 0.0), (3.0 * size, 0.0)),
+        Line::new((0.0, -3.0 * size), (0.0, 3.0 * size)),
+    ] {
+        draw::draw_shape(scene, &line, None, Some((palette::css::GRAY, 1.0)), parent);
+    }
+
+    let square = Rect::from_center_size(Point::ZERO, (size, size));
+    for (i, color) in CHILD_COLORS.into_iter().enumerate() {
+        let child = parent * child_transform(i, t, size * 2.0);
+        draw::draw_shape(
+            scene,
+            &square,
+            Some(color),
+            Some((Color::BLACK, 2.0)),
+            child,
+        );
+        // A grandchild orbiting each child, composed through both groups.
+        let grandchild = child * Affine::translate((size, 0.0)) * Affine::rotate(t * 2.0);
+        let circle = Circle::new(Point::ZERO, size / 6.0);
+        draw::draw_shape(scene, &circle, Some(Color::BLACK), None, grandchild);
+    }
+}
+
+/// Size of the children in the scene graph, which is scaled to fit the surface.
+const GRAPH_SIZE: f64 = 50.0;
+
+/// Build the transforms demo as a scene graph: the parent's axes and its
+/// children, each with an orbiting grandchild. The transforms are set by [`animate_graph`].
+fn transforms_graph() -> Node {
+    let size = GRAPH_SIZE;
+    let mut root = Node::group();
+    let mut axes = BezPath::new();
+    axes.move_to((-3.0 * size, 0.0));
+    axes.line_to((3.0 * size, 0.0));
+    axes.move_to((0.0, -3.0 * size));
+    axes.line_to((0.0, 3.0 * size));
+    root.push(Node::shape(&axes, Paint::stroke(1.0, palette::css::GRAY)));
+
+    let square = Rect::from_center_size(Point::ZERO, (size, size));
+    for color in CHILD_COLORS {
+        let mut child = Node::shape(&square, Paint::fill(color).with_stroke(2.0, Color::BLACK));
+        child.push(Node::shape(
+            &Circle::new(Point::ZERO, size / 6.0),
+            Paint::fill(Color::BLACK),
+        ));
+        root.push(child);
+    }
+    root
+}
+
+/// Set the scene graph's transforms for animation time `elapsed`, matching the transforms demo.
+fn animate_graph(root: &mut Node, width: u32, height: u32, elapsed: Duration) {
+    let t = elapsed.as_secs_f64();
+    let scale = width.min(height) as f64 / 8.0 / GRAPH_SIZE;
+    let center = Point::new(width as f64 / 2.0, height as f64 / 2.0);
+    root.set_transform(
+        Affine::translate(center.to_vec2()) * Affine::scale(scale) * Affine::rotate(t * 0.5),
+    );
+    // The first child is the axes, which don't move.
+    for i in 0..CHILD_COLORS.len() {
+        let Some(child) = root.child_mut(i + 1) else {
+            continue;
+        };
+        child.set_transform(child_transform(i, t, GRAPH_SIZE * 2.0));
+        if let Some(grandchild) = child.child_mut(0) {
+            grandchild
+                .set_transform(Affine::translate((GRAPH_SIZE, 0.0)) * Affine::rotate(t * 2.0));
+        }
+    }
+}
+
+/// Draw rows of zigzags, one row per line style: sharp miter joins, round
+/// joins, then `selected`. Along each row the corners get sharper, so that
+/// the miter limit and the stroke expansion of acute corners can be checked.
+fn draw_joins(scene: &mut Scene, width: u32, height: u32, margin: f64, selected: LineStyle) {
+    let styles = [
+        LineStyle {
+            join: Join::Miter,
+            caps: Cap::Butt,
+        },
+        LineStyle {
+            join: Join::Round,
+            caps: Cap::Round,
+        },
+        selected,
+    ];
+    let row_height = height as f64 / styles.len() as f64;
+    let line_width = (row_height / 8.0).clamp(2.0, 24.0);
+    let thin = Stroke::new(1.0);
+    for (row, style) in styles.into_iter().enumerate() {
+        let top = row as f64 * row_height + row_height * 0.25;
+        let bottom = top + row_height * 0.5;
+        let mut zigzag = BezPath::new();
+        let mut x = margin;
+        zigzag.move_to((x, bottom));
+        // Successively narrower teeth give sharper corners.
+        let mut step = row_height * 0.6;
+        while x + step < width as f64 - margin && step > 2.0 {
+            x += step / 2.0;
+            zigzag.line_to((x, top));
+            x += step / 2.0;
+            zigzag.line_to((x, bottom));
+            step *= 0.8;
+        }
+        let stroke = style.stroke(line_width);
+        scene.stroke(
+            &stroke,
+            Affine::IDENTITY,
+            palette::css::STEEL_BLUE,
+            None,
+            &zigzag,
+        );
+        // The centre line, to show the geometry being expanded.
+        scene.stroke(
+            &thin,
+            Affine::IDENTITY,
+            palette::css::ORANGE_RED,
+            None,
+            &zigzag,
+        );
+    }
+}
+
+/// Width and height of the sprite, in pixels.
+const SPRITE_SIZE: u32 = 8;
+
+/// The size of a sprite pixel in the pattern demo.
+const PATTERN_SCALE: f64 = 4.0;
+
+/// The next image extend mode, for cycling through them.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn next_extend(extend: Extend) -> Extend {
+    match extend {
+        Extend::Pad => Extend::Repeat,
+        Extend::Repeat => Extend::Reflect,
+        Extend::Reflect => Extend::Pad,
+    }
+}
+
+/// A small image with diagonal stripes of the child colours, whose hard edges
+/// show the difference between nearest and bilinear sampling.
+fn sprite() -> Image {
+    let mut data = Vec::with_capacity((SPRITE_SIZE * SPRITE_SIZE * 4) as usize);
+    for y in 0..SPRITE_SIZE {
+        for x in 0..SPRITE_SIZE {
+            let color = CHILD_COLORS[((x + y) % CHILD_COLORS.len() as u32) as usize];
+            data.extend_from_slice(&color.to_rgba8().to_u8_array());
+        }
+    }
+    Image::new(
+        Blob::new(Arc::new(data)),
+        ImageFormat::Rgba8,
+        SPRITE_SIZE,
+        SPRITE_SIZE,
+    )
+}
+
+/// Draw the sprite scaled up to fill each half, with nearest sampling on the left and bilinear on the right.
+fn draw_images(scene: &mut Scene, sprite: &Image, width: u32, height: u32, margin: f64) {
+    let (left, right) = draw::split_halves(width, height);
+    for (half, quality) in [(left, ImageQuality::Low), (right, ImageQuality::Medium)] {
+        let size = (half.width().min(half.height()) - 2.0 * margin).max(1.0);
+        let origin = half.center() - Vec2::new(size, size) / 2.0;
+        let transform =
+            Affine::translate(origin.to_vec2()) * Affine::scale(size / sprite.width as f64);
+        draw::draw_image(scene, sprite, transform, quality);
+    }
+}
+
+/// The nominal RGB components of each column of swatches.
+const SWATCHES: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [1.0, 1.0, 0.0],
+    [0.5, 0.5, 0.5],
+    [0.2, 0.2, 0.2],
+];
+
+/// Draw each of the swatch components as sRGB, linear sRGB and Display P3, in rows.
+fn draw_swatches(scene: &mut Scene, width: u32, height: u32, margin: f64) {
+    let spaces: [fn([f32; 4]) -> InputColor; 3] = [
+        |c| InputColor::Srgb8(c.map(|c| (c * 255.0).round() as u8)),
+        InputColor::Linear,
+        InputColor::DisplayP3,
+    ];
+    let area = Rect::new(
+        margin,
+        margin,
+        width as f64 - margin,
+        height as f64 - margin,
+    );
+    let cell = (
+        area.width() / SWATCHES.len() as f64,
+        area.height() / spaces.len() as f64,
+    );
+    for (row, space) in spaces.into_iter().enumerate() {
+        for (col, [r, g, b]) in SWATCHES.into_iter().enumerate() {
+            let origin = area.origin() + (col as f64 * cell.0, row as f64 * cell.1);
+            let swatch = Rect::from_origin_size(origin, cell).inset(-4.0);
+            let color = space([r, g, b, 1.0]).to_color();
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &swatch);
+        }
+    }
+}
+
+/// The distance between the centres of neighbouring stamps.
+const STAMP_SPACING: f64 = 60.0;
+
+/// A flower of the child colours, 40 units across and centred on the origin,
+/// for the stamps demo. Its rotation shows each stamp's transform.
+fn motif() -> Scene {
+    let mut scene = Scene::new();
+    for (i, color) in CHILD_COLORS.into_iter().enumerate() {
+        let petal = Affine::rotate(i as f64 * FRAC_PI_2) * Affine::translate((10.0, 0.0));
+        scene.fill(
+            Fill::NonZero,
+            petal,
+            color,
+            None,
+            &Circle::new(Point::ZERO, 8.0),
+        );
+    }
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::BLACK,
+        None,
+        &Circle::new(Point::ZERO, 5.0),
+    );
+    scene
+}
+
+/// Stamp `motif` on a grid filling `area`, each copy turned further than
+/// the one before it, and all of them spinning at animation time `elapsed`.
+/// The motif's encoding is copied for each stamp, without being rebuilt.
+fn draw_stamps(scene: &mut Scene, motif: &Scene, area: Rect, elapsed: Duration) {
+    let t = elapsed.as_secs_f64();
+    let cols = (area.width() / STAMP_SPACING).floor().max(1.0) as usize;
+    let rows = (area.height() / STAMP_SPACING).floor().max(1.0) as usize;
+    // Centre the grid in the area.
+    let origin =
+        area.center() - Vec2::new(cols as f64 - 1.0, rows as f64 - 1.0) * (STAMP_SPACING / 2.0);
+    for row in 0..rows {
+        for col in 0..cols {
+            let center = origin + Vec2::new(col as f64, row as f64) * STAMP_SPACING;
+            let turn = (row * cols + col) as f64 * 0.1 + t;
+            let transform = Affine::translate(center.to_vec2()) * Affine::rotate(turn);
+            scene.append(motif, Some(transform));
+        }
+    }
+}
+
+/// The blend modes the layers demo cycles through, one per layer.
+const LAYER_MIXES: [Mix; 16] = [
+    Mix::Normal,
+    Mix::Multiply,
+    Mix::Screen,
+    Mix::Overlay,
+    Mix::Darken,
+    Mix::Lighten,
+    Mix::ColorDodge,
+    Mix::ColorBurn,
+    Mix::HardLight,
+    Mix::SoftLight,
+    Mix::Difference,
+    Mix::Exclusion,
+    Mix::Hue,
+    Mix::Saturation,
+    Mix::Color,
+    Mix::Luminosity,
+];
+
+/// Draw `count` translucent layers, each nested inside the one before, with
+/// the blend modes in turn. Each holds a disc, placed on a spiral so that
+/// every disc overlaps its neighbours, which turns when animated. The layers
+/// are clipped to `area`, over a grey backdrop for the modes to show on.
+fn draw_layers(scene: &mut Scene, area: Rect, count: u32, elapsed: Duration) {
+    let backdrop = Color::from_rgb8(160, 160, 160);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, backdrop, None, &area);
+    if area.is_zero_area() {
+        return;
+    }
+    nest_layers(scene, area, 0, count, elapsed.as_secs_f64());
+}
+
+/// Push layer `i` of `count`, draw its disc, and nest the rest inside it.
+/// The guard pops the layer on the way out.
+fn nest_layers(scene: &mut Scene, area: Rect, i: u32, count: u32, t: f64) {
+    if i == count {
+        return;
+    }
+    let mix = LAYER_MIXES[i as usize % LAYER_MIXES.len()];
+    let mut layer = draw::Layer::push(scene, mix, 0.8, Affine::IDENTITY, &area);
+    let size = area.width().min(area.height());
+    let distance = size / 4.0 * (0.2 + 0.8 * i as f64 / count as f64);
+    let center = area.center() + Vec2::from_angle(i as f64 * 0.7 + t) * distance;
+    let color = CHILD_COLORS[i as usize % CHILD_COLORS.len()].with_alpha(0.6);
+    let disc = Circle::new(center, size / 5.0);
+    layer.fill(Fill::NonZero, Affine::IDENTITY, color, None, &disc);
+    nest_layers(&mut layer, area, i + 1, count, t);
+}
+
+/// A star of `points` points around `center`, alternating between the
+/// `outer` and `inner` radii, with the first point straight up.
+fn star(center: Point, outer: f64, inner: f64, points: u32) -> BezPath {
+    let mut path = BezPath::new();
+    for i in 0..points * 2 {
+        let radius = if i % 2 == 0 { outer } else { inner };
+        let angle = i as f64 * PI / points as f64 - FRAC_PI_2;
+        let point = center + Vec2::from_angle(angle) * radius;
+        if i == 0 {
+            path.move_to(point);
+        } else {
+            path.line_to(point);
+        }
+    }
+    path.close_path();
+    path
+}
+
+/// Fill `area` with stripes clipped to a spinning star, and inside that
+/// clip fill it again clipped to a circle off to one side, so the fill only
+/// shows where the circle and the star overlap.
+fn draw_clips(scene: &mut Scene, area: Rect, elapsed: Duration) {
+    let size = area.width().min(area.height()) / 2.0;
+    if size <= 0.0 {
+        return;
+    }
+    let center = area.center();
+    let star = star(center, size, size * 0.45, 5);
+    draw::with_clip(scene, draw::spin(elapsed, center), &star, |scene| {
+        let width = size / 4.0;
+        let stripes = (area.width() / width).ceil() as usize;
+        for i in 0..stripes {
+            let x = area.x0 + i as f64 * width;
+            let stripe = Rect::new(x, area.y0, x + width, area.y1);
+            let color = CHILD_COLORS[i % 2];
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &stripe);
+        }
+        let circle = Circle::new(center + Vec2::new(size * 0.4, 0.0), size * 0.5);
+        draw::with_clip(scene, Affine::IDENTITY, &circle, |scene| {
+            let color = CHILD_COLORS[2];
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &area);
+        });
+    });
+}
+
+/// The alpha of each of the translucent demo's discs.
+const TRANSLUCENT_ALPHA: f32 = 0.3;
+/// The turn between successive discs, spreading them evenly over the spiral.
+const GOLDEN_ANGLE: f64 = 2.399_963_229_728_653;
+
+/// Where the translucent demo's disc `i` of `count` is, on a spiral out
+/// from the centre of `area`, and its radius.
+fn translucent_disc(area: Rect, i: u32, count: u32, turn: f64) -> Circle {
+    let size = area.width().min(area.height()) / 2.0;
+    let radius = size * 0.3;
+    let distance = (size - radius) * (i as f64 / count as f64).sqrt();
+    let center = area.center() + Vec2::from_angle(i as f64 * GOLDEN_ANGLE + turn) * distance;
+    Circle::new(center, radius)
+}
+
+/// Fill `count` overlapping discs spiralling out over `area`, slowly
+/// turning. Each is translucent by the alpha of its brush's colour, which
+/// costs no more than an opaque fill, where a layer with alpha would need
+/// a blend and a composite of its own for every disc.
+fn draw_translucent(scene: &mut Scene, area: Rect, count: u32, elapsed: Duration) {
+    if area.is_zero_area() {
+        return;
+    }
+    let turn = elapsed.as_secs_f64() * 0.2;
+    for i in 0..count {
+        let disc = translucent_disc(area, i, count, turn);
+        let color = CHILD_COLORS[i as usize % CHILD_COLORS.len()].with_alpha(TRANSLUCENT_ALPHA);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &disc);
+    }
+}
+
+/// The size of the cells the hairlines demo draws one line in each of.
+const HAIRLINE_CELL: f64 = 40.0;
+/// How many angles the hairlines go through, evenly over half a turn.
+const HAIRLINE_ANGLES: usize = 24;
+
+/// The hairline in the cell at `col` and `row`. Successive cells turn it
+/// through [`HAIRLINE_ANGLES`] angles, and move it by a quarter of a pixel
+/// each way in turn, so every angle is drawn at every pixel phase.
+fn hairline(col: usize, row: usize) -> Line {
+    let angle = PI * ((row * 7 + col) % HAIRLINE_ANGLES) as f64 / HAIRLINE_ANGLES as f64;
+    let phase = Vec2::new((col % 4) as f64, (row % 4) as f64) * 0.25;
+    let cell = Vec2::new(col as f64 + 0.5, row as f64 + 0.5) * HAIRLINE_CELL;
+    let center = cell.to_point() + phase;
+    let reach = Vec2::from_angle(angle) * HAIRLINE_CELL * 0.4;
+    Line::new(center - reach, center + reach)
+}
+
+/// Stroke a hairline in every cell of the surface, and its edges. Thin
+/// lines are where AA and rasterization faults show first: a line that
+/// fades, breaks up or thickens at some angles or positions but not others.
+fn draw_hairlines(scene: &mut Scene, width: u32, height: u32, color: Color) {
+    let stroke = Stroke::new(1.0).with_caps(Cap::Butt);
+    let cols = (width as f64 / HAIRLINE_CELL).ceil() as usize;
+    let rows = (height as f64 / HAIRLINE_CELL).ceil() as usize;
+    for row in 0..rows {
+        for col in 0..cols {
+            scene.stroke(&stroke, Affine::IDENTITY, color, None, &hairline(col, row));
+        }
+    }
+    // Through the middle of the outermost pixels.
+    let edges = Rect::new(0.0, 0.0, width as f64, height as f64).inset(-0.5);
+    scene.stroke(&stroke, Affine::IDENTITY, color, None, &edges);
+}
+
+/// The sizes of the text demo's ramp, in CSS pixels.
+const TEXT_SIZES: [f32; 11] = [
+    8.0, 9.0, 10.0, 11.0, 12.0, 14.0, 16.0, 20.0, 24.0, 32.0, 48.0,
+];
+const TEXT_SAMPLE: &str = "Hamburgefonstiv 0123";
+
+fn draw_text_ramp(scene: &mut Scene, state: &DemoState, width: u32, height: u32) {
+    let sizes = match state.font_size {
+        Some(size) => vec![size],
+        None => TEXT_SIZES.to_vec(),
+    };
+    let (left, right) = draw::split_halves(width, height);
+    for (viewport, hinting) in [(left, Hinting::NONE), (right, state.hinting)] {
+        // Drawn in place rather than with `draw_in_viewport`, so that the
+        // glyph positions are in device pixels and snapping stays exact.
+        scene.push_layer(Mix::Clip, 1.0, Affine::IDENTITY, &viewport);
+        let x = viewport.x0.round() + state.margin;
+        let mut y = state.margin;
+        let label = format!("hinting: {}", hinting.name());
+        let label_size = (12.0 * state.scale_factor) as f32;
+        state.text.draw_hinted(
+            scene,
+            &label,
+            Point::new(x, y),
+            label_size,
+            Color::BLACK,
+            hinting,
+        );
+        y += state.text.line_height(label_size) * 2.0;
+        for &size in &sizes {
+            // Sizes are in CSS pixels, and the text is drawn in device pixels.
+            let px = (size as f64 * state.scale_factor) as f32;
+            let line = format!("{}px {}", size, TEXT_SAMPLE);
+            // A fractional origin, as laid out text often has, which only snapping rounds off.
+            let origin = Point::new(x + 0.3, y + 0.3);
+            state
+                .text
+                .draw_hinted(scene, &line, origin, px, Color::BLACK, hinting);
+            y += state.text.line_height(px);
+        }
+        scene.pop_layer();
+    }
+}
+
+/// Checkerboard square colours, matching the page background shown behind a transparent canvas.
+const CHECKER_COLORS: [Color; 2] = [Color::WHITE, Color::from_rgb8(0xcc, 0xcc, 0xcc)];
+
+const ALPHA_FILLS: [Color; 3] = [
+    Color::from_rgba8(255, 0, 0, 128),
+    Color::from_rgba8(0, 128, 0, 128),
+    Color::from_rgba8(0, 0, 255, 128),
+];
+
+fn draw_alpha(scene: &mut Scene, width: u32, height: u32) {
+    let (left, _) = draw::split_halves(width, height);
+    let cell = alpha::CHECKER_SIZE;
+    // Align the drawn squares with the page's checkerboard, which starts at the canvas origin.
+    for row in 0..(left.height() / cell).ceil() as u32 {
+        for col in 0..(left.width() / cell).ceil() as u32 {
+            let origin = Point::new(col as f64 * cell, row as f64 * cell);
+            let square = Rect::from_origin_size(origin, (cell, cell)).intersect(left);
+            let color = CHECKER_COLORS[((row + col) % 2) as usize];
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &square);
+        }
+    }
+    // Overlapping discs straddling the two halves.
+    let radius = width.min(height) as f64 / 5.0;
+    let center = Point::new(width as f64 / 2.0, height as f64 / 2.0);
+    for (i, color) in ALPHA_FILLS.into_iter().enumerate() {
+        let offset = Affine::rotate(i as f64 * 2.0 * FRAC_PI_3) * Point::new(0.0, -radius / 2.0);
+        let disc = Circle::new(center + offset.to_vec2(), radius);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &disc);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readback::testing;
+
+    #[test]
+    fn children_compose_with_parent() {
+        let parent = Affine::translate((100.0, 100.0)) * Affine::rotate(FRAC_PI_2);
+        // Child 0 sits on the parent's x axis, which the parent rotates onto +y.
+        let child = parent * child_transform(0, 0.0, 50.0);
+        let origin = child * Point::ZERO;
+        assert!((origin - Point::new(100.0, 150.0)).hypot() < 1e-9);
+        // Child 1 is a quarter turn further round, onto -x.
+        let child = parent * child_transform(1, 0.0, 50.0);
+        let origin = child * Point::ZERO;
+        assert!((origin - Point::new(50.0, 100.0)).hypot() < 1e-9);
+    }
+    #[test]
+    fn stamps_copy_the_motif() {
+        let motif = motif();
+        let mut scene = Scene::new();
+        // Room for 3 x 2 stamps.
+        let area = Rect::new(0.0, 0.0, 200.0, 150.0);
+        draw_stamps(&mut scene, &motif, area, Duration::ZERO);
+        assert_eq!(scene.encoding().n_paths, motif.encoding().n_paths * 6);
+    }
+
+    #[test]
+    fn every_layer_is_popped() {
+        let mut scene = Scene::new();
+        let area = Rect::new(0.0, 0.0, 200.0, 150.0);
+        draw_layers(&mut scene, area, 40, Duration::ZERO);
+        let encoding = scene.encoding();
+        // A begin and an end clip per layer, none left open.
+        assert_eq!(encoding.n_clips, 80);
+        assert_eq!(encoding.n_open_clips, 0);
+    }
+
+    #[test]
+    fn nested_clips_compose() {
+        const SIZE: u32 = 64;
+        let mut scene = Scene::new();
+        let area = Rect::new(0.0, 0.0, SIZE as f64, SIZE as f64);
+        draw_clips(&mut scene, area, Duration::ZERO);
+        let Some(pixels) = testing::render(&scene, SIZE, SIZE) else {
+            return;
+        };
+        let pixel = |x: u32, y: u32| {
+            let i = ((y * SIZE + x) * 4) as usize;
+            [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+        };
+        let rgba = |color: Color| color.to_rgba8().to_u8_array();
+        // The centre is in both the star and the circle.
+        assert_eq!(pixel(32, 32), rgba(CHILD_COLORS[2]));
+        // The top arm of the star is outside the circle, so shows a stripe.
+        assert_eq!(pixel(32, 8), rgba(CHILD_COLORS[0]));
+        // The right of the circle is between two arms, outside the star.
+        assert_eq!(pixel(59, 32), [0; 4]);
+    }
+
+    #[test]
+    fn hairlines_turn_and_shift() {
+        let line = hairline(0, 0);
+        assert_eq!(line, Line::new((4.0, 20.0), (36.0, 20.0)));
+        // The next cell along is a step round and a quarter pixel across.
+        let line = hairline(1, 0);
+        let step = PI / HAIRLINE_ANGLES as f64;
+        assert!(((line.p1 - line.p0).angle() - step).abs() < 1e-9);
+        assert_eq!(line.p0.midpoint(line.p1), Point::new(60.25, 20.0));
+        // Every angle comes round in a row of cells.
+        let mut angles: Vec<_> = (0..HAIRLINE_ANGLES)
+            .map(|col| {
+                let line = hairline(col, 3);
+                ((line.p1 - line.p0).angle().rem_euclid(PI) / step).round() as usize
+            })
+            .collect();
+        angles.sort();
+        assert_eq!(angles, (0..HAIRLINE_ANGLES).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn translucent_discs_build_up() {
+        const SIZE: u32 = 64;
+        let mut scene = Scene::new();
+        let area = Rect::new(0.0, 0.0, SIZE as f64, SIZE as f64);
+        draw_translucent(&mut scene, area, 2, Duration::ZERO);
+        let Some(pixels) = testing::render(&scene, SIZE, SIZE) else {
+            return;
+        };
+        let alpha = |point: Point| {
+            let (x, y) = (point.x as u32, point.y as u32);
+            pixels[((y * SIZE + x) * 4 + 3) as usize] as f32 / 255.0
+        };
+        // The first disc alone at the centre, and where the second overlaps it.
+        let (first, second) = (
+            translucent_disc(area, 0, 2, 0.0),
+            translucent_disc(area, 1, 2, 0.0),
+        );
+        assert!((alpha(first.center) - TRANSLUCENT_ALPHA).abs() < 0.01);
+        let overlap = first.center.midpoint(second.center);
+        let expected = 1.0 - (1.0 - TRANSLUCENT_ALPHA).powi(2);
+        assert!(
+            (alpha(overlap) - expected).abs() < 0.01,
+            "{}",
+            alpha(overlap)
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Scrolling content larger than the viewport.
+//!
+//! The scroll offset is how far the viewport has been moved across the
+//! content, in surface pixels, and is applied as a translation after the
+//! view transform. It is clamped so that the viewport stays within the
+//! content's bounds, or where it started if the content is smaller: content
+//! that fits in an axis can't be scrolled along it. Scrollbars show where
+//! the viewport is within the scrollable extent of each axis.
+
+use vello::kurbo::{Affine, Rect, RoundedRect, Size, Vec2};
+use vello::peniko::{Color, Fill};
+use vello::Scene;
+
+/// How far one line of a wheel that scrolls by lines moves, in pixels.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub const LINE_HEIGHT: f64 = 40.0;
+
+const BAR_WIDTH: f64 = 6.0;
+const BAR_INSET: f64 = 2.0;
+/// Thumbs are never shorter than this, however long the content.
+const MIN_THUMB: f64 = 20.0;
+
+fn thumb_color() -> Color {
+    Color::from_rgba8(128, 128, 128, 160)
+}
+
+/// The offsets the viewport of size `size` can be scrolled to, given the
+/// bounds of the content in unscrolled viewport coordinates.
+fn range(content: Rect, size: Size) -> Rect {
+    Rect::new(
+        content.x0.min(0.0),
+        content.y0.min(0.0),
+        (content.x1 - size.width).max(0.0),
+        (content.y1 - size.height).max(0.0),
+    )
+}
+
+/// Clamp a scroll offset to the content's bounds.
+pub fn clamp(offset: Vec2, content: Rect, size: Size) -> Vec2 {
+    let range = range(content, size);
+    Vec2::new(
+        offset.x.clamp(range.x0, range.x1),
+        offset.y.clamp(range.y0, range.y1),
+    )
+}
+
+/// The position and length of a thumb along a track `track` long, for a
+/// viewport `view` long scrolled `offset` through `[min, max]`.
+fn thumb(track: f64, view: f64, offset: f64, min: f64, max: f64) -> Option<(f64, f64)> {
+    let scrollable = max - min;
+    if scrollable <= 0.0 {
+        return None;
+    }
+    let length = (track * view / (view + scrollable)).clamp(MIN_THUMB.min(track), track);
+    Some(((offset - min) / scrollable * (track - length), length))
+}
+
+/// The horizontal and vertical scrollbar thumbs in `viewport`, for the
+/// axes the content can be scrolled along.
+fn thumbs(offset: Vec2, content: Rect, viewport: Rect) -> [Option<Rect>; 2] {
+    let range = range(content, viewport.size());
+    // Leave the corner free when both bars are shown.
+    let both = range.width() > 0.0 && range.height() > 0.0;
+    let corner = if both { BAR_WIDTH + BAR_INSET } else { 0.0 };
+    let track_x = viewport.width() - BAR_INSET * 2.0 - corner;
+    let track_y = viewport.height() - BAR_INSET * 2.0 - corner;
+    let horizontal =
+        thumb(track_x, viewport.width(), offset.x, range.x0, range.x1).map(|(start, length)| {
+            let x = viewport.x0 + BAR_INSET + start;
+            let y = viewport.y1 - BAR_INSET - BAR_WIDTH;
+            Rect::new(x, y, x + length, y + BAR_WIDTH)
+        });
+    let vertical =
+        thumb(track_y, viewport.height(), offset.y, range.y0, range.y1).map(|(start, length)| {
+            let x = viewport.x1 - BAR_INSET - BAR_WIDTH;
+            let y = viewport.y0 + BAR_INSET + start;
+            Rect::new(x, y, x + BAR_WIDTH, y + length)
+        });
+    [horizontal, vertical]
+}
+
+/// Draw the scrollbars of `viewport`, on the surface, for content with
+/// bounds `content` in unscrolled viewport coordinates.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn draw_scrollbars(scene: &mut Scene, offset: Vec2, content: Rect, viewport: Rect) {
+    for thumb in thumbs(offset, content, viewport).into_iter().flatten() {
+        let shape = RoundedRect::from_rect(thumb, BAR_WIDTH / 2.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, thumb_color(), None, &shape);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_are_clamped_to_the_content() {
+        let size = Size::new(100.0, 100.0);
+        // Content taller than the viewport, and narrower.
+        let content = Rect::new(10.0, -50.0, 90.0, 300.0);
+        assert_eq!(
+            clamp(Vec2::new(30.0, 500.0), content, size),
+            Vec2::new(0.0, 200.0)
+        );
+        assert_eq!(
+            clamp(Vec2::new(-30.0, -500.0), content, size),
+            Vec2::new(0.0, -50.0)
+        );
+    }
+
+    #[test]
+    fn thumbs_track_the_offset() {
+        let viewport = Rect::new(0.0, 0.0, 100.0, 104.0);
+        // Three viewports tall, and no wider than one.
+        let content = Rect::new(0.0, 0.0, 100.0, 312.0);
+        let [horizontal, vertical] = thumbs(Vec2::ZERO, content, viewport);
+        assert_eq!(horizontal, None);
+        // A third of the track, at its top.
+        assert_eq!(
+            vertical,
+            Some(Rect::new(92.0, 2.0, 98.0, 35.333333333333336))
+        );
+        let [_, vertical] = thumbs(Vec2::new(0.0, 208.0), content, viewport);
+        assert_eq!(vertical.unwrap().y1, 102.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! A built-in rendering correctness check.
+//!
+//! Renders four solid quadrants of known colours into a texture, reads it
+//! back, and compares the centre pixel of each quadrant with the colour
+//! drawn. Channel swaps (e.g. BGRA presented as RGBA) and sRGB mishandling
+//! (colours encoded twice, or not at all) both show up as mismatches here,
+//! independently of the surface and the compositor.
+//!
+//! A second check renders a single pixel of a known colour and reads it
+//! back. Rows copied out of a texture are padded to 256 bytes, so this one
+//! pixel row is mostly padding, and reading back the wrong bytes shows up.
+
+use std::error::Error;
+use std::num::NonZeroUsize;
+
+use serde::Serialize;
+
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::{color::palette, Color, Fill};
+use vello::wgpu;
+use vello::{AaConfig, AaSupport, RenderParams, Renderer, RendererOptions, Scene};
+
+use crate::readback::{self, Readback};
+
+/// The width and height of the rendered image.
+const SIZE: u32 = 64;
+/// The largest difference allowed in any channel.
+const TOLERANCE: u8 = 2;
+/// The quadrants in reading order: top left, top right, bottom left, bottom right.
+const QUADRANTS: [(&str, Color); 4] = [
+    ("red", palette::css::RED),
+    ("green", palette::css::LIME),
+    ("blue", palette::css::BLUE),
+    ("white", palette::css::WHITE),
+];
+
+/// The result of checking one quadrant.
+#[derive(Debug, Serialize)]
+pub struct Quadrant {
+    pub name: &'static str,
+    pub expected: [u8; 4],
+    pub measured: [u8; 4],
+    pub pass: bool,
+}
+
+/// The result of the self-test.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub pass: bool,
+    pub quadrants: Vec<Quadrant>,
+}
+
+/// The result of reading back a single pixel.
+#[derive(Debug, Serialize)]
+pub struct PixelReport {
+    pub expected: [u8; 4],
+    pub measured: [u8; 4],
+    pub pass: bool,
+}
+
+/// The colour of the single pixel, with every channel different.
+fn pixel_color() -> Color {
+    Color::from_rgba8(32, 96, 160, 255)
+}
+
+/// Whether each channel of `measured` is within the tolerance of `expected`.
+fn matches(expected: [u8; 4], measured: [u8; 4]) -> bool {
+    expected
+        .iter()
+        .zip(measured)
+        .all(|(&e, m)| e.abs_diff(m) <= TOLERANCE)
+}
+
+/// The bounds of each quadrant of the image.
+fn quadrant_rects() -> impl Iterator<Item = Rect> {
+    let half = (SIZE / 2) as f64;
+    (0..4).map(move |i| {
+        let origin = ((i % 2) as f64 * half, (i / 2) as f64 * half);
+        Rect::from_origin_size(origin, (half, half))
+    })
+}
+
+/// The scene drawn by the self-test.
+fn scene() -> Scene {
+    let mut scene = Scene::new();
+    for (rect, (_, color)) in quadrant_rects().zip(QUADRANTS) {
+        scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &rect);
+    }
+    scene
+}
+
+/// Compare the centre pixel of each quadrant of tightly packed RGBA pixels
+/// with the colour drawn there.
+fn check(pixels: &[u8]) -> Report {
+    let quadrants: Vec<Quadrant> = quadrant_rects()
+        .zip(QUADRANTS)
+        .map(|(rect, (name, color))| {
+            let centre = rect.center();
+            let offset = (centre.y as usize * SIZE as usize + centre.x as usize) * 4;
+            let measured: [u8; 4] = pixels[offset..offset + 4].try_into().unwrap();
+            let expected = color.to_rgba8().to_u8_array();
+            Quadrant {
+                name,
+                expected,
+                measured,
+                pass: matches(expected, measured),
+            }
+        })
+        .collect();
+    Report {
+        pass: quadrants.iter().all(|q| q.pass),
+        quadrants,
+    }
+}
+
+/// Render `scene` at `size` on `device` with a renderer of its own, and read it back.
+async fn render(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scene: &Scene,
+    size: u32,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut renderer = Renderer::new(
+        device,
+        RendererOptions {
+            surface_format: None,
+            use_cpu: false,
+            antialiasing_support: AaSupport::area_only(),
+            num_init_threads: NonZeroUsize::new(1),
+        },
+    )?;
+    let texture = readback::render_target(device, "self-test", size, size);
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    renderer.render_to_texture(
+        device,
+        queue,
+        scene,
+        &view,
+        &RenderParams {
+            base_color: Color::TRANSPARENT,
+            width: size,
+            height: size,
+            antialiasing_method: AaConfig::Area,
+        },
+    )?;
+    Ok(Readback::new(device, queue, &texture, size, size)
+        .read(device)
+        .await?)
+}
+
+/// Render the quadrants on `device`, read them back and check them.
+pub async fn run(device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Report, Box<dyn Error>> {
+    let pixels = render(device, queue, &scene(), SIZE).await?;
+    Ok(check(&pixels))
+}
+
+/// Render a single pixel on `device`, read it back and check it.
+pub async fn single_pixel(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Result<PixelReport, Box<dyn Error>> {
+    let mut scene = Scene::new();
+    let pixel = Rect::new(0.0, 0.0, 1.0, 1.0);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, pixel_color(), None, &pixel);
+    let pixels = render(device, queue, &scene, 1).await?;
+    let expected = pixel_color().to_rgba8().to_u8_array();
+    let measured: [u8; 4] = pixels[..4].try_into()?;
+    Ok(PixelReport {
+        expected,
+        measured,
+        pass: matches(expected, measured),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RenderContext;
+    use crate::readback::testing::block_on;
+
+    /// Pixels with each quadrant filled with a single colour.
+    fn pixels(colors: [[u8; 4]; 4]) -> Vec<u8> {
+        let half = SIZE / 2;
+        (0..SIZE * SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % SIZE, i / SIZE);
+                colors[((y / half) * 2 + x / half) as usize]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_detects_swapped_channels() {
+        let rgba = QUADRANTS.map(|(_, color)| color.to_rgba8().to_u8_array());
+        assert!(check(&pixels(rgba)).pass);
+
+        let bgra = rgba.map(|[r, g, b, a]| [b, g, r, a]);
+        let report = check(&pixels(bgra));
+        assert!(!report.pass);
+        let failed: Vec<_> = report
+            .quadrants
+            .iter()
+            .filter(|q| !q.pass)
+            .map(|q| q.name)
+            .collect();
+        assert_eq!(failed, ["red", "blue"]);
+    }
+
+    #[test]
+    fn renders_known_colors() {
+        let mut context = RenderContext::new(wgpu::PowerPreference::default());
+        let Some(dev_id) = block_on(context.device(None)) else {
+            eprintln!("No adapter available, skipping the GPU self-test");
+            return;
+        };
+        let device_handle = &context.devices[dev_id];
+        let report = block_on(run(&device_handle.device, &device_handle.queue)).unwrap();
+        assert!(report.pass, "{:?}", report);
+        let (device, queue) = (&device_handle.device, &device_handle.queue);
+        let report = block_on(single_pixel(device, queue)).unwrap();
+        assert!(report.pass, "{:?}", report);
+    }
+}
@@ -0,0 +1,174 @@
+//! Comparing two AA configs side by side, in one frame.
+//!
+//! [`SplitPass`] takes the frame's scene, already assembled, and renders it
+//! twice: with the first config into one texture and with the second into
+//! another, each half the output's width. It then draws the first into the
+//! left half of the output and the second into the right. The scene is the
+//! same both times, shifted so that the middle of the frame is in the middle
+//! of each half, so the halves show the same geometry at the same pixel
+//! positions and differ only in how it was antialiased.
+
+use vello::kurbo::{Affine, Vec2};
+use vello::wgpu;
+use vello::{AaConfig, RenderParams, Renderer, Scene};
+
+use crate::blit::{Blit, Texture};
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var left: texture_2d<f32>;
+@group(0) @binding(1) var right: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> split: vec4<u32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let pixel = vec2<u32>(position.xy);
+    if pixel.x < split.x {
+        return textureLoad(left, pixel, 0);
+    }
+    return textureLoad(right, vec2<u32>(pixel.x - split.x, pixel.y), 0);
+}
+"#;
+
+/// The textures the halves are rendered into, and the bind group reading them.
+struct Targets {
+    left: Texture,
+    right: Texture,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+}
+
+/// The width of the left half of an output `width` wide, and of the
+/// textures rendered for each half, which is that of the right half.
+fn halves(width: u32) -> (u32, u32) {
+    (width / 2, width - width / 2)
+}
+
+/// Renders scenes with two AA configs, side by side, into outputs of one format.
+pub struct SplitPass {
+    blit: Blit,
+    pipeline: wgpu::RenderPipeline,
+    targets: Option<Targets>,
+}
+
+impl SplitPass {
+    /// Build the pipeline, for outputs of `format`.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        // Of the uniform, only the first component is used.
+        let blit = Blit::new(device, "split", SHADER, 2, true);
+        let pipeline = blit.pipeline(device, "fs_main", format, None);
+        SplitPass {
+            blit,
+            pipeline,
+            targets: None,
+        }
+    }
+
+    /// The targets for an output of the given size, replacing those for another size.
+    fn targets(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Targets {
+        if let Some(targets) = self.targets.take() {
+            if targets.width == width && targets.height == height {
+                return targets;
+            }
+        }
+        let (_, half) = halves(width);
+        let left = Texture::target(device, "split half", half, height);
+        let right = Texture::target(device, "split half", half, height);
+        let bind_group = self.blit.bind_group(device, &[&left.view, &right.view]);
+        Targets {
+            left,
+            right,
+            bind_group,
+            width,
+            height,
+        }
+    }
+
+    /// Render `scene` with `params`, but with `configs[0]` into the left half
+    /// of `output` and `configs[1]` into the right.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        renderer: &mut Renderer,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        output: &wgpu::TextureView,
+        params: &RenderParams,
+        configs: [AaConfig; 2],
+    ) -> Result<(), vello::Error> {
+        let (left, half) = halves(params.width);
+        self.blit.set_uniform(queue, [left, 0, 0, 0]);
+        // The one scene both halves replay, with the frame's middle in theirs.
+        let shift = (half as f64 - params.width as f64) / 2.0;
+        let mut shifted = Scene::new();
+        shifted.append(scene, Some(Affine::translate(Vec2::new(shift, 0.0))));
+
+        let targets = self.targets(device, params.width, params.height);
+        let render = |renderer: &mut Renderer, view, aa| {
+            let params = RenderParams {
+                width: half,
+                antialiasing_method: aa,
+                ..*params
+            };
+            renderer.render_to_texture(device, queue, &shifted, view, &params)
+        };
+        let rendered = render(renderer, &targets.left.view, configs[0])
+            .and_then(|()| render(renderer, &targets.right.view, configs[1]));
+        let targets = self.targets.insert(targets);
+        rendered?;
+
+        let draw = (&self.pipeline, &targets.bind_group);
+        let load = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        self.blit.submit(device, queue, output, load, &[draw]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vello::kurbo::Rect;
+    use vello::peniko::{Color, Fill};
+
+    use super::*;
+    use crate::blit;
+    use crate::readback::testing::{block_on, renderer, texture};
+    use crate::readback::Readback;
+
+    #[test]
+    fn both_halves_show_the_middle() {
+        let Some((context, dev_id, mut renderer)) = renderer(&[AaConfig::Area]) else {
+            return;
+        };
+        let handle = &context.devices[dev_id];
+        let (device, queue) = (&handle.device, &handle.queue);
+        let (output, view) = texture(device, 4, 1);
+        // A red pixel just left of the middle, on white.
+        let mut scene = Scene::new();
+        let red = Color::from_rgb8(255, 0, 0);
+        let rect = Rect::new(1.0, 0.0, 2.0, 1.0);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, red, None, &rect);
+        let params = RenderParams {
+            base_color: Color::WHITE,
+            width: 4,
+            height: 1,
+            antialiasing_method: AaConfig::Area,
+        };
+        let mut pass = SplitPass::new(device, blit::FORMAT);
+        let configs = [AaConfig::Area; 2];
+        pass.render(
+            &mut renderer,
+            device,
+            queue,
+            &scene,
+            &view,
+            &params,
+            configs,
+        )
+        .unwrap();
+        let pixels = block_on(Readback::new(device, queue, &output, 4, 1).read(device)).unwrap();
+        // Just left of the middle of each half.
+        let (red, white) = ([255, 0, 0, 255], [255; 4]);
+        assert_eq!(pixels, [red, white, red, white].concat());
+    }
+}
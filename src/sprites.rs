@@ -0,0 +1,164 @@
+//! Many small moving shapes, for stressing the renderer.
+//!
+//! Each sprite keeps its own position and velocity between frames, and is
+//! moved by the time since the last frame, bouncing off the edges of the
+//! drawable area. Every sprite is encoded again each frame, so the scene
+//! costs grow with the count, which makes frame times scale visibly as it
+//! is raised and hitches stand out against a steady load.
+
+use std::f64::consts::FRAC_PI_4;
+use std::time::Duration;
+
+use log::info;
+
+use vello::kurbo::{Affine, Circle, Point, Rect, Vec2};
+use vello::peniko::{Color, Fill};
+use vello::Scene;
+
+use crate::random::Rng;
+
+/// The seed the sprites are generated from, so every run starts the same.
+const SEED: u64 = 179;
+/// The half size of each sprite, in pixels.
+const RADIUS: f64 = 6.0;
+/// The fastest a sprite moves in each axis, in pixels per second.
+const MAX_SPEED: f64 = 200.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpriteShape {
+    Circle,
+    Square,
+    Diamond,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub shape: SpriteShape,
+    pub color: Color,
+    /// The sprite's centre, in the drawable area.
+    pub pos: Point,
+    /// Pixels per second.
+    pub vel: Vec2,
+}
+
+impl Sprite {
+    /// Move by `vel` for `dt` seconds, bouncing off the edges of `bounds`.
+    fn step(&mut self, dt: f64, bounds: Rect) {
+        self.pos += self.vel * dt;
+        let inner = bounds.inset(-RADIUS);
+        if self.pos.x < inner.x0 {
+            self.pos.x = inner.x0;
+            self.vel.x = self.vel.x.abs();
+        } else if self.pos.x > inner.x1 {
+            self.pos.x = inner.x1;
+            self.vel.x = -self.vel.x.abs();
+        }
+        if self.pos.y < inner.y0 {
+            self.pos.y = inner.y0;
+            self.vel.y = self.vel.y.abs();
+        } else if self.pos.y > inner.y1 {
+            self.pos.y = inner.y1;
+            self.vel.y = -self.vel.y.abs();
+        }
+    }
+
+    fn draw(&self, scene: &mut Scene) {
+        let square = Rect::from_center_size(Point::ZERO, (RADIUS * 2.0, RADIUS * 2.0));
+        let at = Affine::translate(self.pos.to_vec2());
+        match self.shape {
+            SpriteShape::Circle => {
+                let circle = Circle::new(self.pos, RADIUS);
+                scene.fill(Fill::NonZero, Affine::IDENTITY, self.color, None, &circle);
+            }
+            SpriteShape::Square => scene.fill(Fill::NonZero, at, self.color, None, &square),
+            SpriteShape::Diamond => {
+                let transform = at * Affine::rotate(FRAC_PI_4);
+                scene.fill(Fill::NonZero, transform, self.color, None, &square);
+            }
+        }
+    }
+}
+
+/// The sprites, and the animation time they were last moved to.
+#[derive(Default)]
+pub struct Sprites {
+    sprites: Vec<Sprite>,
+    last: Duration,
+}
+
+impl Sprites {
+    /// Scatter `count` sprites across `bounds`, with random shapes, colours
+    /// and velocities.
+    fn generate(&mut self, count: u32, bounds: Rect) {
+        info!("Sprites demo with {} sprites", count);
+        let mut rng = Rng::new(SEED);
+        let shapes = [
+            SpriteShape::Circle,
+            SpriteShape::Square,
+            SpriteShape::Diamond,
+        ];
+        self.sprites = (0..count)
+            .map(|_| {
+                let shape = shapes[(rng.next_u64() % shapes.len() as u64) as usize];
+                let [r, g, b, _] = (rng.next_u64() as u32).to_le_bytes();
+                Sprite {
+                    shape,
+                    color: Color::from_rgba8(r, g, b, 200),
+                    pos: Point::new(
+                        rng.range(bounds.x0, bounds.x1),
+                        rng.range(bounds.y0, bounds.y1),
+                    ),
+                    vel: Vec2::new(
+                        rng.range(-MAX_SPEED, MAX_SPEED),
+                        rng.range(-MAX_SPEED, MAX_SPEED),
+                    ),
+                }
+            })
+            .collect();
+    }
+
+    /// Move the sprites to animation time `elapsed` within `bounds`, making
+    /// `count` of them if the count has changed, and draw them all.
+    pub fn draw(&mut self, scene: &mut Scene, count: u32, bounds: Rect, elapsed: Duration) {
+        if self.sprites.len() != count as usize {
+            self.generate(count, bounds);
+            self.last = elapsed;
+        }
+        // The clock goes back when animation is restarted; don't move then.
+        let dt = elapsed.saturating_sub(self.last).as_secs_f64();
+        self.last = elapsed;
+        for sprite in &mut self.sprites {
+            sprite.step(dt, bounds);
+            sprite.draw(scene);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprites_bounce_off_the_edges() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let mut sprite = Sprite {
+            shape: SpriteShape::Circle,
+            color: Color::BLACK,
+            pos: Point::new(90.0, 50.0),
+            vel: Vec2::new(100.0, -100.0),
+        };
+        sprite.step(0.1, bounds);
+        assert_eq!(sprite.pos, Point::new(100.0 - RADIUS, 40.0));
+        assert_eq!(sprite.vel, Vec2::new(-100.0, -100.0));
+        sprite.step(1.0, bounds);
+        assert_eq!(sprite.pos, Point::new(RADIUS, RADIUS));
+        assert_eq!(sprite.vel, Vec2::new(100.0, 100.0));
+
+        let mut sprites = Sprites::default();
+        let mut scene = Scene::new();
+        sprites.draw(&mut scene, 50, bounds, Duration::from_secs(1));
+        sprites.draw(&mut scene, 50, bounds, Duration::from_secs(3));
+        assert_eq!(sprites.sprites.len(), 50);
+        assert!(sprites.sprites.iter().all(|s| bounds.contains(s.pos)));
+    }
+}
@@ -0,0 +1,90 @@
+//! Client statistics, shared with the JS side as JSON.
+
+use std::cell::RefCell;
+use std::mem::size_of_val;
+
+use serde::Serialize;
+
+use vello::Scene;
+
+use crate::clock::RefreshRate;
+use crate::{bench, latency, reuse, stress};
+
+/// Encoded resource usage of a scene.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct SceneSize {
+    pub paths: u32,
+    pub path_segments: u32,
+    pub clips: u32,
+    pub draw_objects: usize,
+    pub transforms: usize,
+    pub styles: usize,
+    /// Total size of the encoded streams in bytes.
+    pub encoded_bytes: usize,
+}
+
+impl SceneSize {
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn of(scene: &Scene) -> Self {
+        let enc = scene.encoding();
+        SceneSize {
+            paths: enc.n_paths,
+            path_segments: enc.n_path_segments,
+            clips: enc.n_clips,
+            draw_objects: enc.draw_tags.len(),
+            transforms: enc.transforms.len(),
+            styles: enc.styles.len(),
+            encoded_bytes: size_of_val(enc.path_tags.as_slice())
+                + enc.path_data.len()
+                + size_of_val(enc.draw_tags.as_slice())
+                + enc.draw_data.len()
+                + size_of_val(enc.transforms.as_slice())
+                + size_of_val(enc.styles.as_slice()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Stats {
+    pub frames: u64,
+    /// Redraws requested by the event loop, and how many of them reached
+    /// `present`. The rest were skipped, e.g. on a surface error or an empty surface.
+    pub redraws_requested: u64,
+    pub frames_presented: u64,
+    /// Presented frames whose submissions were still in flight when the
+    /// device was polled, out of those polled.
+    pub frames_polled: u64,
+    pub pending_after_poll: u64,
+    pub scene: SceneSize,
+    /// Whether the device supports pipeline statistics queries. Even when it
+    /// does, no counts are collected, as Vello records its passes internally.
+    pub pipeline_statistics: bool,
+    /// Time from the start of `run_app` to the first frame being presented,
+    /// including surface creation and renderer initialisation.
+    pub time_to_first_frame_ms: Option<f64>,
+    /// The summary of the `?benchmark` run, once finished.
+    pub benchmark: Option<bench::Summary>,
+    /// The summary of the `?stress` run, once finished.
+    pub stress: Option<stress::Summary>,
+    /// The display's refresh rate, once measured.
+    pub refresh: Option<RefreshRate>,
+    /// The times from pressing the latency key to presenting the flash.
+    pub input_latency: Option<latency::Summary>,
+    /// How often the scene was reused with `?reuse_scene`, and the time saved.
+    pub scene_reuse: Option<reuse::Summary>,
+}
+
+thread_local! {
+    static STATS: RefCell<Stats> = RefCell::default();
+}
+
+/// Update the shared statistics.
+pub fn update(f: impl FnOnce(&mut Stats)) {
+    STATS.with_borrow_mut(f)
+}
+
+/// The current statistics as a JSON string.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn to_json() -> String {
+    STATS.with_borrow(|s| serde_json::to_string(s).unwrap_or_default())
+}
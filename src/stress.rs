@@ -0,0 +1,142 @@
+//! Growing the scene until frames take too long.
+//!
+//! A [`Stress`] run draws the seeded random scene from a small shape count
+//! up, and after each second of frames compares their mean time with the
+//! frame budget. If they kept within it, the count is raised by a quarter and
+//! measured again; once they don't, the last count that did is the most
+//! this GPU sustains within the budget, a single number to compare machines
+//! by. The first frame at each count is discarded, as it pays for
+//! generating the new shapes.
+
+use std::time::Duration;
+
+use log::info;
+use serde::Serialize;
+use web_time::Instant;
+
+/// How long each count is measured for.
+const LEVEL_TIME: Duration = Duration::from_secs(1);
+/// Counts are never raised beyond this, however fast the GPU.
+const MAX_SHAPES: u32 = 1_000_000;
+
+/// The result of a finished stress run.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub budget_ms: f64,
+    /// The most shapes drawn within the budget, and their mean frame time.
+    /// None kept within it if `shapes` is 0.
+    pub shapes: u32,
+    pub mean_ms: f64,
+    /// The count whose frames went over the budget, or `None` if the run
+    /// stopped at the largest count it tries.
+    pub exceeded_at: Option<u32>,
+}
+
+/// What to do after a frame of a stress run.
+#[derive(Debug, PartialEq)]
+pub enum Progress {
+    /// Keep drawing the current count.
+    Measuring,
+    /// Draw this many shapes from now on.
+    Grow(u32),
+    Done(Summary),
+}
+
+/// A stress run in progress.
+#[derive(Debug)]
+pub struct Stress {
+    budget: Duration,
+    shapes: u32,
+    /// When the first frame at the current count was recorded.
+    level_start: Option<Instant>,
+    /// The times of the frames since then.
+    times: Vec<Duration>,
+    /// The largest count so far that kept within the budget, and its mean frame time.
+    sustained: Option<(u32, f64)>,
+}
+
+impl Stress {
+    /// Find the most shapes drawn with a mean frame time within `budget`,
+    /// starting from `shapes`.
+    pub fn new(budget: Duration, shapes: u32) -> Self {
+        Stress {
+            budget,
+            shapes: shapes.clamp(1, MAX_SHAPES),
+            level_start: None,
+            times: Vec::new(),
+            sustained: None,
+        }
+    }
+
+    /// The number of shapes to draw.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn shapes(&self) -> u32 {
+        self.shapes
+    }
+
+    /// Record a frame at `now` that took `frame_time` since the one before.
+    pub fn record(&mut self, now: Instant, frame_time: Duration) -> Progress {
+        let Some(start) = self.level_start else {
+            self.level_start = Some(now);
+            return Progress::Measuring;
+        };
+        self.times.push(frame_time);
+        if now - start < LEVEL_TIME {
+            return Progress::Measuring;
+        }
+        let mean = self.times.iter().sum::<Duration>() / self.times.len() as u32;
+        let mean_ms = mean.as_secs_f64() * 1000.0;
+        info!(
+            "Stress: {} shapes, mean frame time {:.2} ms",
+            self.shapes, mean_ms
+        );
+        let over = mean > self.budget;
+        if !over {
+            self.sustained = Some((self.shapes, mean_ms));
+        }
+        if over || self.shapes == MAX_SHAPES {
+            let (shapes, mean_ms) = self.sustained.unwrap_or((0, 0.0));
+            return Progress::Done(Summary {
+                budget_ms: self.budget.as_secs_f64() * 1000.0,
+                shapes,
+                mean_ms,
+                exceeded_at: over.then_some(self.shapes),
+            });
+        }
+        self.shapes = (self.shapes + self.shapes.div_ceil(4)).min(MAX_SHAPES);
+        self.level_start = None;
+        self.times.clear();
+        Progress::Grow(self.shapes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_grow_until_over_budget() {
+        let ms = Duration::from_millis;
+        let mut stress = Stress::new(ms(20), 100);
+        let mut now = Instant::now();
+        // A second of frames at each count, slowing down as it grows.
+        let mut run = |stress: &mut Stress, frame_time: Duration| loop {
+            now += frame_time;
+            match stress.record(now, frame_time) {
+                Progress::Measuring => {}
+                progress => return progress,
+            }
+        };
+        assert_eq!(run(&mut stress, ms(10)), Progress::Grow(125));
+        assert_eq!(run(&mut stress, ms(19)), Progress::Grow(157));
+        assert_eq!(
+            run(&mut stress, ms(25)),
+            Progress::Done(Summary {
+                budget_ms: 20.0,
+                shapes: 125,
+                mean_ms: 19.0,
+                exceeded_at: Some(157),
+            })
+        );
+    }
+}
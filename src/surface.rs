@@ -0,0 +1,311 @@
+//! Explicit surface configuration.
+//!
+//! Each choice in the surface configuration is given as a [`SurfaceOptions`]
+//! field, checked against the surface's capabilities by [`configure`],
+//! and the resolved configuration is logged, so that nothing about the
+//! swapchain is left to defaults.
+
+use std::error::Error;
+
+use log::warn;
+
+use vello::wgpu;
+
+use crate::alpha;
+
+/// The surface formats Vello can render to.
+const VELLO_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Bgra8Unorm,
+];
+
+/// The requested surface configuration.
+#[derive(Clone, Debug)]
+pub struct SurfaceOptions {
+    /// The texture format, or `None` for the first supported format Vello can render to.
+    pub format: Option<wgpu::TextureFormat>,
+    pub present_mode: wgpu::PresentMode,
+    pub alpha_mode: wgpu::CompositeAlphaMode,
+    /// The number of frames the surface may queue ahead of the display.
+    pub frame_latency: u32,
+    /// Must include `RENDER_ATTACHMENT`, which Vello's blit to the surface needs.
+    pub usage: wgpu::TextureUsages,
+    /// Also allow copies from the surface's textures, to read back what is
+    /// presented, if the surface supports it.
+    pub readback: bool,
+}
+
+impl Default for SurfaceOptions {
+    fn default() -> Self {
+        SurfaceOptions {
+            format: None,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            frame_latency: 2,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            readback: false,
+        }
+    }
+}
+
+/// Parse a surface format name. Only the formats Vello can render to are accepted.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn parse_format(name: &str) -> Result<wgpu::TextureFormat, String> {
+    match name {
+        "rgba8unorm" => Ok(wgpu::TextureFormat::Rgba8Unorm),
+        "bgra8unorm" => Ok(wgpu::TextureFormat::Bgra8Unorm),
+        _ => Err(format!("unknown surface format '{}'", name)),
+    }
+}
+
+/// Parse a comma separated list of texture usage names, which are added to
+/// the `RENDER_ATTACHMENT` usage Vello needs.
+pub fn parse_usage(names: &str) -> Result<wgpu::TextureUsages, String> {
+    let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+    for name in names.split(',').filter(|name| !name.is_empty()) {
+        usage |= match name {
+            "render_attachment" => wgpu::TextureUsages::RENDER_ATTACHMENT,
+            "texture_binding" => wgpu::TextureUsages::TEXTURE_BINDING,
+            "copy_src" => wgpu::TextureUsages::COPY_SRC,
+            "copy_dst" => wgpu::TextureUsages::COPY_DST,
+            "storage_binding" => wgpu::TextureUsages::STORAGE_BINDING,
+            _ => return Err(format!("unknown texture usage '{}'", name)),
+        };
+    }
+    Ok(usage)
+}
+
+/// Parse a present mode name.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn parse_present_mode(name: &str) -> Result<wgpu::PresentMode, String> {
+    match name {
+        "auto" => Ok(wgpu::PresentMode::AutoVsync),
+        "auto_no_vsync" => Ok(wgpu::PresentMode::AutoNoVsync),
+        "fifo" => Ok(wgpu::PresentMode::Fifo),
+        "fifo_relaxed" => Ok(wgpu::PresentMode::FifoRelaxed),
+        "immediate" => Ok(wgpu::PresentMode::Immediate),
+        "mailbox" => Ok(wgpu::PresentMode::Mailbox),
+        _ => Err(format!("unknown present mode '{}'", name)),
+    }
+}
+
+/// The present modes tried in turn when the requested one isn't supported,
+/// ending with one that always is.
+const PRESENT_FALLBACKS: [wgpu::PresentMode; 3] = [
+    wgpu::PresentMode::Mailbox,
+    wgpu::PresentMode::Fifo,
+    wgpu::PresentMode::AutoVsync,
+];
+
+/// The `requested` present mode if it is among the `supported` ones, or
+/// else the first of the fallbacks that is. The automatic modes are always
+/// supported, as wgpu picks one of the surface's modes for them.
+pub fn choose_present_mode(
+    requested: wgpu::PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    let is_supported = |mode: &wgpu::PresentMode| {
+        matches!(
+            mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) || supported.contains(mode)
+    };
+    std::iter::once(requested)
+        .chain(PRESENT_FALLBACKS)
+        .find(is_supported)
+        .unwrap_or(wgpu::PresentMode::AutoVsync)
+}
+
+/// The largest size with the same aspect ratio as `width` by `height` that
+/// has neither side over `max`, the device's largest 2D texture dimension.
+pub fn clamp_size(width: u32, height: u32, max: u32) -> (u32, u32) {
+    if width <= max && height <= max {
+        return (width, height);
+    }
+    let scale = max as f64 / width.max(height) as f64;
+    let clamp = |side: u32| ((side as f64 * scale).floor() as u32).clamp(1, max);
+    (clamp(width), clamp(height))
+}
+
+/// Resolve the configuration for a surface of the given size from the
+/// requested options, failing if the surface doesn't support any of them.
+/// Only the present mode and read-back are optional: an unsupported present
+/// mode falls back to a supported one, and without support for read-back
+/// it is left out.
+pub fn configure(
+    backend: wgpu::Backend,
+    capabilities: &wgpu::SurfaceCapabilities,
+    options: &SurfaceOptions,
+    width: u32,
+    height: u32,
+) -> Result<wgpu::SurfaceConfiguration, Box<dyn Error>> {
+    let format = match options.format {
+        Some(format) if capabilities.formats.contains(&format) => format,
+        Some(format) => {
+            return Err(format!(
+                "surface format {:?} is not supported (supported: {:?})",
+                format, capabilities.formats
+            )
+            .into())
+        }
+        None => capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|format| VELLO_FORMATS.contains(format))
+            .ok_or(vello::Error::UnsupportedSurfaceFormat)?,
+    };
+
+    let present_mode = choose_present_mode(options.present_mode, &capabilities.present_modes);
+    if present_mode != options.present_mode {
+        warn!(
+            "Present mode {:?} is not supported (supported: {:?}), falling back to {:?}",
+            options.present_mode, capabilities.present_modes, present_mode
+        );
+    }
+
+    let alpha_modes = alpha::supported_modes(backend, capabilities);
+    alpha::check(&alpha_modes, options.alpha_mode)?;
+
+    if !options
+        .usage
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        || !capabilities.usages.contains(options.usage)
+    {
+        return Err(format!(
+            "surface usage {:?} must include RENDER_ATTACHMENT and be within {:?}",
+            options.usage, capabilities.usages
+        )
+        .into());
+    }
+
+    let copy = wgpu::TextureUsages::COPY_SRC;
+    let usage = if !options.readback {
+        options.usage
+    } else if capabilities.usages.contains(copy) {
+        options.usage | copy
+    } else {
+        warn!(
+            "Surface usages {:?} don't include COPY_SRC, so the surface can't be read back",
+            capabilities.usages
+        );
+        options.usage
+    };
+
+    Ok(wgpu::SurfaceConfiguration {
+        usage,
+        format,
+        width,
+        height,
+        present_mode,
+        desired_maximum_frame_latency: options.frame_latency,
+        alpha_mode: options.alpha_mode,
+        view_formats: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities() -> wgpu::SurfaceCapabilities {
+        wgpu::SurfaceCapabilities {
+            formats: vec![
+                wgpu::TextureFormat::Rgba16Float,
+                wgpu::TextureFormat::Bgra8Unorm,
+            ],
+            present_modes: vec![wgpu::PresentMode::Fifo],
+            alpha_modes: vec![wgpu::CompositeAlphaMode::Opaque],
+            usages: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        }
+    }
+
+    #[test]
+    fn clamps_to_max_dimension() {
+        assert_eq!(clamp_size(1920, 1080, 8192), (1920, 1080));
+        assert_eq!(clamp_size(10240, 5760, 8192), (8192, 4608));
+        assert_eq!(clamp_size(100, 20000, 8192), (40, 8192));
+        assert_eq!(clamp_size(100000, 1, 8192), (8192, 1));
+    }
+
+    #[test]
+    fn resolves_supported_options() {
+        let backend = wgpu::Backend::Vulkan;
+        let config =
+            configure(backend, &capabilities(), &SurfaceOptions::default(), 64, 32).unwrap();
+        assert_eq!(config.format, wgpu::TextureFormat::Bgra8Unorm);
+        assert_eq!((config.width, config.height), (64, 32));
+
+        let unsupported = [
+            SurfaceOptions {
+                format: Some(wgpu::TextureFormat::Rgba8Unorm),
+                ..Default::default()
+            },
+            SurfaceOptions {
+                usage: wgpu::TextureUsages::COPY_SRC,
+                ..Default::default()
+            },
+        ];
+        for options in unsupported {
+            assert!(configure(backend, &capabilities(), &options, 64, 32).is_err());
+        }
+    }
+
+    #[test]
+    fn present_modes_fall_back_to_supported_ones() {
+        use wgpu::PresentMode::*;
+        assert_eq!(
+            choose_present_mode(Immediate, &[Fifo, Immediate]),
+            Immediate
+        );
+        assert_eq!(choose_present_mode(Immediate, &[Fifo, Mailbox]), Mailbox);
+        assert_eq!(choose_present_mode(Mailbox, &[Fifo]), Fifo);
+        assert_eq!(choose_present_mode(FifoRelaxed, &[]), AutoVsync);
+        assert_eq!(choose_present_mode(AutoNoVsync, &[Fifo]), AutoNoVsync);
+        // Configuring the surface falls back rather than failing.
+        let options = SurfaceOptions {
+            present_mode: Mailbox,
+            ..Default::default()
+        };
+        let backend = wgpu::Backend::Vulkan;
+        let config = configure(backend, &capabilities(), &options, 64, 32).unwrap();
+        assert_eq!(config.present_mode, Fifo);
+    }
+
+    #[test]
+    fn usages_add_to_render_attachment() {
+        let render = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        assert_eq!(parse_usage(""), Ok(render));
+        assert_eq!(
+            parse_usage("texture_binding,copy_src"),
+            Ok(render | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC)
+        );
+        assert!(parse_usage("texture_binding,sampled").is_err());
+        // Usages the surface doesn't support are reported rather than dropped.
+        let options = SurfaceOptions {
+            usage: parse_usage("texture_binding").unwrap(),
+            ..Default::default()
+        };
+        let backend = wgpu::Backend::Vulkan;
+        assert!(configure(backend, &capabilities(), &options, 64, 32).is_err());
+    }
+
+    #[test]
+    fn readback_adds_copies_when_supported() {
+        let backend = wgpu::Backend::Vulkan;
+        let options = SurfaceOptions {
+            readback: true,
+            ..Default::default()
+        };
+        let config = configure(backend, &capabilities(), &options, 64, 32).unwrap();
+        let copy = wgpu::TextureUsages::COPY_SRC;
+        assert_eq!(config.usage, wgpu::TextureUsages::RENDER_ATTACHMENT | copy);
+        // Without support it's left out, rather than failing.
+        let capabilities = wgpu::SurfaceCapabilities {
+            usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ..capabilities()
+        };
+        let config = configure(backend, &capabilities, &options, 64, 32).unwrap();
+        assert_eq!(config.usage, wgpu::TextureUsages::RENDER_ATTACHMENT);
+    }
+}
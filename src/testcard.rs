@@ -0,0 +1,116 @@
+//! A test pattern for checking colour, gamma and sharpness.
+//!
+//! The pattern is laid out like the SMPTE colour bars, simplified: seven
+//! bars at 75% of white, yellow, cyan, green, magenta, red and blue across
+//! the top two thirds; below them a strip of the blue, magenta, cyan and
+//! grey bars in reverse order, separated by black; then a ramp from black
+//! to white; and at the bottom a checkerboard of single pixels. Every band
+//! and bar starts and ends on a whole pixel, so none of their edges are
+//! antialiased, and the pattern fills the drawable area whatever its size.
+//! A display, or a pipeline, that gets colour or gamma wrong shows it in
+//! the bars and the ramp; one that blurs shows the checkerboard as grey.
+
+use std::sync::Arc;
+
+use vello::kurbo::{Affine, Rect};
+use vello::peniko::{Blob, Color, Extend, Fill, Gradient, Image, ImageFormat, ImageQuality};
+use vello::Scene;
+
+/// The level of the bars, 75% of full scale.
+const BAR: u8 = 191;
+
+/// The colour bars across the top, left to right.
+const BARS: [[u8; 3]; 7] = [
+    [BAR, BAR, BAR],
+    [BAR, BAR, 0],
+    [0, BAR, BAR],
+    [0, BAR, 0],
+    [BAR, 0, BAR],
+    [BAR, 0, 0],
+    [0, 0, BAR],
+];
+
+/// The strip below the bars: some of them in reverse order, alternating with black.
+const REVERSED: [[u8; 3]; 7] = [BARS[6], [0; 3], BARS[4], [0; 3], BARS[2], [0; 3], BARS[0]];
+
+/// The bottom edge of each band, in twelfths of the height: bars, reversed
+/// strip, ramp and checkerboard.
+const BANDS: [u32; 4] = [8, 9, 10, 12];
+
+fn rgb([r, g, b]: [u8; 3]) -> Color {
+    Color::from_rgb8(r, g, b)
+}
+
+/// A two by two checkerboard of black and white pixels, to be repeated.
+pub fn checker() -> Image {
+    let (black, white) = ([0, 0, 0, 255], [255; 4]);
+    let data = [black, white, white, black].concat();
+    Image::new(Blob::new(Arc::new(data)), ImageFormat::Rgba8, 2, 2)
+        .with_extend(Extend::Repeat)
+        .with_quality(ImageQuality::Low)
+}
+
+/// The edges of `count` runs across `width`, as even as whole pixels allow.
+fn columns(width: u32, count: u32) -> impl Iterator<Item = (f64, f64)> {
+    (0..count).map(move |i| {
+        let edge = |i: u32| (width * i / count) as f64;
+        (edge(i), edge(i + 1))
+    })
+}
+
+/// Draw the test pattern filling a drawable area of the given size,
+/// repeating `checker` for the checkerboard.
+pub fn draw_test_pattern(scene: &mut Scene, checker: &Image, width: u32, height: u32) {
+    let [bars, reversed, ramp, board] = BANDS.map(|twelfths| (height * twelfths / 12) as f64);
+    for ((x0, x1), color) in columns(width, 7).zip(BARS) {
+        let rect = Rect::new(x0, 0.0, x1, bars);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, rgb(color), None, &rect);
+    }
+    for ((x0, x1), color) in columns(width, 7).zip(REVERSED) {
+        let rect = Rect::new(x0, bars, x1, reversed);
+        scene.fill(Fill::NonZero, Affine::IDENTITY, rgb(color), None, &rect);
+    }
+    let width = width as f64;
+    // Starting at the first pixel's centre, so that it's black, and ending
+    // short of the last one's, so that it's padded with white: Vello looks
+    // gradients up in a table, whose last entry falls just short of the end.
+    let gradient = Gradient::new_linear((0.5, 0.0), (width - 1.0, 0.0))
+        .with_stops([Color::BLACK, Color::WHITE]);
+    let rect = Rect::new(0.0, reversed, width, ramp);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, &gradient, None, &rect);
+    let rect = Rect::new(0.0, ramp, width, board);
+    scene.fill(Fill::NonZero, Affine::IDENTITY, checker, None, &rect);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readback::testing;
+
+    #[test]
+    fn bands_render_exactly() {
+        let (width, height) = (70, 48);
+        let mut scene = Scene::new();
+        draw_test_pattern(&mut scene, &checker(), width, height);
+        let Some(pixels) = testing::render(&scene, width, height) else {
+            return;
+        };
+        let pixel = |x: u32, y: u32| {
+            let i = ((y * width + x) * 4) as usize;
+            [pixels[i], pixels[i + 1], pixels[i + 2]]
+        };
+        // Each bar is 10 pixels wide, the bars 32 rows high and the strip 4.
+        for (i, (bar, reversed)) in BARS.iter().zip(REVERSED).enumerate() {
+            let x = i as u32 * 10;
+            assert_eq!((pixel(x, 0), pixel(x + 9, 31)), (*bar, *bar), "bar {}", i);
+            assert_eq!(pixel(x, 32), reversed, "strip {}", i);
+        }
+        let ramp: Vec<u8> = (0..width).map(|x| pixel(x, 38)[0]).collect();
+        assert!(ramp.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", ramp);
+        assert_eq!((ramp[0], ramp[69]), (0, 255));
+        for (x, y) in [(0, 40), (1, 41), (5, 47)] {
+            assert_eq!(pixel(x, y), [0; 3]);
+            assert_eq!(pixel(x + 1, y), [255; 3]);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Simple text drawing using a bundled monospace font, or one loaded from JS.
+//!
+//! A loaded font replaces the bundled one for every [`Text`] made after it
+//! is set with [`set_font`], so drawings and scenes made later use it too.
+
+use std::cell::RefCell;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use skrifa::instance::{LocationRef, Size};
+use skrifa::raw::FileRef;
+use skrifa::{FontRef, MetadataProvider};
+
+use vello::kurbo::{Affine, Point};
+use vello::peniko::{Blob, Color, Fill, Font};
+use vello::{Glyph, Scene};
+
+const FONT_DATA: &[u8] = include_bytes!("../assets/DejaVuSansMono.ttf");
+
+thread_local! {
+    /// The font new text is drawn in, if not the bundled one.
+    static LOADED_FONT: RefCell<Option<Font>> = const { RefCell::new(None) };
+}
+
+/// Parse the TrueType or OpenType font in `bytes`, or the first font of a
+/// collection, checking that it has glyph outlines to draw.
+pub fn parse_font(bytes: Vec<u8>) -> Result<Font, String> {
+    let font = Font::new(Blob::new(Arc::new(bytes)), 0);
+    let file = FileRef::new(font.data.as_ref()).map_err(|e| format!("invalid font: {}", e))?;
+    let font_ref = match file {
+        FileRef::Font(font) => font,
+        FileRef::Collection(collection) => collection
+            .get(0)
+            .map_err(|e| format!("invalid font collection: {}", e))?,
+    };
+    if font_ref.outline_glyphs().format().is_none() {
+        return Err("the font has no glyph outlines".to_string());
+    }
+    Ok(font)
+}
+
+/// Draw text made from now on in `font`, or in the bundled font if `None`.
+pub fn set_font(font: Option<Font>) {
+    LOADED_FONT.set(font);
+}
+
+/// How glyphs are fitted to the pixel grid. Both matter most at small sizes,
+/// where an edge straddling two pixels is drawn as two half-covered ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hinting {
+    /// Apply the font's hinting instructions to the glyph outlines. Vello
+    /// only does this when the text transform is a translation and scale.
+    pub hint: bool,
+    /// Round the origin and each glyph position to whole pixels. Text is
+    /// drawn in physical pixels, so this snaps to the device's pixel grid at
+    /// any scale factor.
+    pub snap: bool,
+}
+
+impl Hinting {
+    pub const NONE: Hinting = Hinting {
+        hint: false,
+        snap: false,
+    };
+    pub const FULL: Hinting = Hinting {
+        hint: true,
+        snap: true,
+    };
+
+    pub fn name(self) -> &'static str {
+        match (self.hint, self.snap) {
+            (false, false) => "none",
+            (true, false) => "hint",
+            (false, true) => "snap",
+            (true, true) => "full",
+        }
+    }
+
+    /// The next combination, for cycling through them all.
+    pub fn next(self) -> Hinting {
+        Hinting {
+            hint: self.hint != self.snap,
+            snap: !self.snap,
+        }
+    }
+}
+
+impl FromStr for Hinting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hint, snap) = match s {
+            "none" => (false, false),
+            "hint" => (true, false),
+            "snap" => (false, true),
+            "full" => (true, true),
+            _ => return Err(format!("unknown hinting '{}'", s)),
+        };
+        Ok(Hinting { hint, snap })
+    }
+}
+
+pub struct Text {
+    font: Font,
+}
+
+impl Text {
+    /// Text in the font set with [`set_font`], or the bundled one.
+    pub fn new() -> Self {
+        let font = LOADED_FONT.with_borrow(Clone::clone);
+        Text {
+            font: font.unwrap_or_else(|| Font::new(Blob::new(Arc::new(FONT_DATA)), 0)),
+        }
+    }
+
+    fn font_ref(&self) -> FontRef<'_> {
+        match FileRef::new(self.font.data.as_ref()).expect("font should parse") {
+            FileRef::Font(font) => font,
+            FileRef::Collection(collection) => collection
+                .get(self.font.index)
+                .expect("font index should exist"),
+        }
+    }
+
+    /// The distance between baselines of successive lines.
+    pub fn line_height(&self, size: f32) -> f64 {
+        let metrics = self
+            .font_ref()
+            .metrics(Size::new(size), LocationRef::default());
+        (metrics.ascent - metrics.descent + metrics.leading) as f64
+    }
+
+    /// The width and height of the (possibly multi-line) text.
+    pub fn measure(&self, text: &str, size: f32) -> (f64, f64) {
+        let font = self.font_ref();
+        let charmap = font.charmap();
+        let glyph_metrics = font.glyph_metrics(Size::new(size), LocationRef::default());
+        let width = text
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|ch| {
+                        glyph_metrics
+                            .advance_width(charmap.map(ch).unwrap_or_default())
+                            .unwrap_or_default() as f64
+                    })
+                    .sum::<f64>()
+            })
+            .fold(0.0, f64::max);
+        let lines = text.lines().count().max(1);
+        (width, lines as f64 * self.line_height(size))
+    }
+
+    /// Draw the text with the top-left of its first line at `origin`.
+    pub fn draw(&self, scene: &mut Scene, text: &str, origin: Point, size: f32, color: Color) {
+        self.draw_hinted(scene, text, origin, size, color, Hinting::NONE);
+    }
+
+    /// Draw the text with the top-left of its first line at `origin`, fitted
+    /// to the pixel grid as given by `hinting`.
+    pub fn draw_hinted(
+        &self,
+        scene: &mut Scene,
+        text: &str,
+        origin: Point,
+        size: f32,
+        color: Color,
+        hinting: Hinting,
+    ) {
+        let ascent = self
+            .font_ref()
+            .metrics(Size::new(size), LocationRef::default())
+            .ascent;
+        let mut baseline = Point::new(origin.x, origin.y + ascent as f64);
+        if hinting.snap {
+            baseline = baseline.round();
+        }
+        scene
+            .draw_glyphs(&self.font)
+            .font_size(size)
+            .hint(hinting.hint)
+            .transform(Affine::translate(baseline.to_vec2()))
+            .brush(color)
+            .draw(
+                Fill::NonZero,
+                self.layout(text, size, hinting.snap).into_iter(),
+            );
+    }
+
+    /// Position the glyphs of the text relative to the first baseline,
+    /// rounding each position to a whole pixel if `snap` is set.
+    fn layout(&self, text: &str, size: f32, snap: bool) -> Vec<Glyph> {
+        let font = self.font_ref();
+        let charmap = font.charmap();
+        let font_size = Size::new(size);
+        let metrics = font.metrics(font_size, LocationRef::default());
+        let glyph_metrics = font.glyph_metrics(font_size, LocationRef::default());
+        let line_height = metrics.ascent - metrics.descent + metrics.leading;
+
+        let mut pen_x = 0.0f32;
+        let mut pen_y = 0.0;
+        text.chars()
+            .filter_map(|ch| {
+                if ch == '\n' {
+                    pen_x = 0.0;
+                    pen_y += line_height;
+                    return None;
+                }
+                let id = charmap.map(ch).unwrap_or_default();
+                let (x, y) = if snap {
+                    (pen_x.round(), pen_y.round())
+                } else {
+                    (pen_x, pen_y)
+                };
+                pen_x += glyph_metrics.advance_width(id).unwrap_or_default();
+                Some(Glyph {
+                    id: id.to_u32(),
+                    x,
+                    y,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monospace_measure() {
+        let text = Text::new();
+        let (one, line_height) = text.measure("a", 16.0);
+        assert!(one > 0.0);
+        let (four, _) = text.measure("abcd", 16.0);
+        assert!((four - 4.0 * one).abs() < 1e-3);
+        // The widest line sets the width, and each line adds to the height.
+        let (width, height) = text.measure("ab\nabcd\n", 16.0);
+        assert!((width - four).abs() < 1e-3);
+        assert!((height - 2.0 * line_height).abs() < 1e-3);
+    }
+
+    #[test]
+    fn loaded_fonts_replace_the_bundled_one() {
+        assert!(parse_font(b"not a font".to_vec()).is_err());
+        let font = parse_font(FONT_DATA.to_vec()).unwrap();
+        set_font(Some(font.clone()));
+        assert_eq!(Text::new().font, font);
+        set_font(None);
+        assert_eq!(Text::new().font.data.as_ref(), FONT_DATA);
+    }
+
+    #[test]
+    fn snapped_layout() {
+        let text = Text::new();
+        // The advance at 13px is fractional, so only snapping gives whole pixels.
+        let free = text.layout("abc\nde", 13.0, false);
+        let snapped = text.layout("abc\nde", 13.0, true);
+        assert!(free.iter().any(|g| g.x.fract() != 0.0));
+        for (free, snapped) in free.iter().zip(&snapped) {
+            assert_eq!((snapped.x, snapped.y), (free.x.round(), free.y.round()));
+        }
+        assert_eq!(snapped.len(), 5);
+    }
+
+    #[test]
+    fn hinting_cycles_through_all() {
+        let mut hinting = Hinting::NONE;
+        let mut names = Vec::new();
+        for _ in 0..4 {
+            names.push(hinting.name());
+            assert_eq!(hinting.name().parse::<Hinting>(), Ok(hinting));
+            hinting = hinting.next();
+        }
+        assert_eq!(names, ["none", "snap", "hint", "full"]);
+        assert_eq!(hinting, Hinting::NONE);
+    }
+}
@@ -0,0 +1,112 @@
+//! A trace of recent frame timings, exportable as CSV.
+//!
+//! The timings logged for each frame are also kept here, so that a trace
+//! can be collected on the machine showing a problem and attached to a bug
+//! report for analysis, rather than copied out of the console. Only the
+//! most recent [`CAPACITY`] frames are kept.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// The most frames kept, about three minutes at 60Hz.
+pub const CAPACITY: usize = 10_000;
+
+/// The timings of one presented frame, in milliseconds from its start.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTiming {
+    pub frame: u64,
+    pub render_ms: f64,
+    pub present_ms: f64,
+    pub poll_ms: f64,
+}
+
+/// The timings of the most recent frames, oldest first.
+#[derive(Debug)]
+pub struct Trace {
+    frames: VecDeque<FrameTiming>,
+    capacity: usize,
+}
+
+impl Trace {
+    pub fn new(capacity: usize) -> Self {
+        Trace {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add a frame, dropping the oldest if full.
+    pub fn push(&mut self, timing: FrameTiming) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(timing);
+    }
+
+    /// The most recent frame, if any.
+    pub fn last(&self) -> Option<FrameTiming> {
+        self.frames.back().copied()
+    }
+
+    /// The frames as CSV, with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("frame,render_ms,present_ms,poll_ms\n");
+        for t in &self.frames {
+            // Writing to a string can't fail.
+            _ = writeln!(
+                csv,
+                "{},{:.3},{:.3},{:.3}",
+                t.frame, t.render_ms, t.present_ms, t.poll_ms
+            );
+        }
+        csv
+    }
+}
+
+thread_local! {
+    static TRACE: RefCell<Trace> = RefCell::new(Trace::new(CAPACITY));
+}
+
+/// Add a frame to the shared trace.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn record(timing: FrameTiming) {
+    TRACE.with_borrow_mut(|trace| trace.push(timing));
+}
+
+/// The most recent frame in the shared trace.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn last() -> Option<FrameTiming> {
+    TRACE.with_borrow(Trace::last)
+}
+
+/// The shared trace as CSV.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn to_csv() -> String {
+    TRACE.with_borrow(Trace::to_csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_frames() {
+        let mut trace = Trace::new(2);
+        for frame in 1..=3 {
+            trace.push(FrameTiming {
+                frame,
+                render_ms: frame as f64,
+                present_ms: 1.5,
+                poll_ms: 2.25,
+            });
+        }
+        assert_eq!(
+            trace.to_csv(),
+            "frame,render_ms,present_ms,poll_ms\n\
+             2,2.000,1.500,2.250\n\
+             3,3.000,1.500,2.250\n"
+        );
+        assert_eq!(trace.last().map(|t| t.frame), Some(3));
+    }
+}
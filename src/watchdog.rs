@@ -0,0 +1,82 @@
+//! Noticing when frames stop being presented.
+//!
+//! A hung GPU doesn't report an error. Instead, rendering fails or the
+//! surface texture never becomes available, every frame is skipped, and the
+//! canvas just stops changing. The [`Watchdog`] is told of each present and
+//! checked on a timer of its own, independent of redraws. If frames are
+//! expected but none has been presented for longer than the timeout, it
+//! trips, once until the next present. Time spent not expecting frames
+//! (paused, idle or hidden) doesn't count towards the timeout.
+
+use std::time::Duration;
+
+use web_time::Instant;
+
+#[derive(Debug)]
+pub struct Watchdog {
+    timeout: Duration,
+    /// When a frame was last presented, or frames last started being expected.
+    last_present: Instant,
+    /// Whether the watchdog has tripped since that present.
+    tripped: bool,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Watchdog {
+            timeout,
+            last_present: now,
+            tripped: false,
+        }
+    }
+
+    /// How often to check, so that a stall is noticed soon after the timeout.
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    pub fn period(&self) -> Duration {
+        self.timeout / 4
+    }
+
+    /// Record a frame presented at `now`. If the watchdog had tripped,
+    /// returns how long frames had stopped for.
+    pub fn presented(&mut self, now: Instant) -> Option<Duration> {
+        let stalled = now - self.last_present;
+        self.last_present = now;
+        std::mem::take(&mut self.tripped).then_some(stalled)
+    }
+
+    /// Check at `now`, when frames are or aren't `expected`. Returns how long
+    /// it has been since the last present when that first exceeds the timeout.
+    pub fn check(&mut self, now: Instant, expected: bool) -> Option<Duration> {
+        if !expected {
+            self.last_present = now;
+            return None;
+        }
+        let stalled = now - self.last_present;
+        if self.tripped || stalled <= self.timeout {
+            return None;
+        }
+        self.tripped = true;
+        Some(stalled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_once_per_stall() {
+        let secs = Duration::from_secs;
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(secs(5), start);
+        assert_eq!(watchdog.check(start + secs(4), true), None);
+        assert_eq!(watchdog.check(start + secs(6), true), Some(secs(6)));
+        assert_eq!(watchdog.check(start + secs(8), true), None);
+        assert_eq!(watchdog.presented(start + secs(9)), Some(secs(9)));
+        assert_eq!(watchdog.presented(start + secs(10)), None);
+        // Time without frames expected doesn't count.
+        assert_eq!(watchdog.check(start + secs(20), false), None);
+        assert_eq!(watchdog.check(start + secs(24), true), None);
+        assert_eq!(watchdog.check(start + secs(26), true), Some(secs(6)));
+    }
+}
@@ -0,0 +1,709 @@
+//! Web entry points and the functions exported to JS.
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::Arc;
+use web_time::Instant;
+
+use wasm_bindgen::convert::TryFromJsValue;
+use wasm_bindgen::prelude::*;
+
+use log::{error, info};
+
+use winit::dpi::PhysicalSize;
+use winit::error::EventLoopError;
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
+use winit::platform::web::WindowExtWebSys;
+use winit::window::{Window, WindowAttributes};
+
+use vello::kurbo::{Affine, Vec2};
+use vello::peniko::{color::parse_color, Blob, Color, Image, ImageFormat};
+use vello::{wgpu, Scene};
+
+use crate::client::{
+    run, set_draw_callback, set_present_callback, DrawCallback, PresentCallback, PresentInfo,
+    UserEvent,
+};
+use crate::config::{self, ClientConfig};
+use crate::context::{self, DeviceError, RenderContext};
+use crate::flipbook::Flipbook;
+use crate::painter::Painter;
+use crate::record::{self, Replay};
+use crate::{alpha, checkup, dom, logs, messages, readback, selftest, text, trace, Options};
+
+thread_local! {
+    /// Proxy used to send events to the running client.
+    static PROXY: RefCell<Option<EventLoopProxy<UserEvent>>> = const { RefCell::new(None) };
+    /// Set once `start_app` has run, so that calling it again does nothing.
+    static STARTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Errors starting the client's event loop.
+#[derive(Debug)]
+pub enum StartError {
+    /// An event loop has already been created. winit allows only one, so the
+    /// client can't be started twice, even if the first attempt failed later on.
+    AlreadyRunning,
+    /// The event loop couldn't be created.
+    EventLoop(EventLoopError),
+}
+
+impl fmt::Display for StartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartError::AlreadyRunning => {
+                write!(f, "the client's event loop has already been created")
+            }
+            StartError::EventLoop(e) => write!(f, "failed to create the event loop: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StartError {}
+
+/// Logs to the browser's console, keeps every record for `download_logs`,
+/// and with `?debug` also keeps warnings and errors to show in the page.
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        console_log::log(record);
+        logs::record(record);
+        if messages::record(record) {
+            messages::with(dom::show_messages);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Send an event to the running client.
+fn send_event(event: UserEvent) -> Result<(), JsError> {
+    PROXY.with_borrow(|proxy| {
+        proxy
+            .as_ref()
+            .ok_or_else(|| JsError::new("client is not running"))?
+            .send_event(event)
+            .map_err(|_| JsError::new("event loop has closed"))
+    })
+}
+
+/// Fixed surface size used in safe mode, in physical pixels.
+pub(crate) const SAFE_MODE_SIZE: (u32, u32) = (640, 480);
+
+fn window_attributes() -> WindowAttributes {
+    Window::default_attributes()
+        //.with_inner_size(LogicalSize::new(1044, 800))
+        .with_resizable(true)
+        .with_title("Vello test client")
+}
+
+#[wasm_bindgen(start)]
+pub fn start_app() {
+    if STARTED.replace(true) {
+        log::warn!("start_app has already been called, ignoring");
+        return;
+    }
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    // Before parsing the options, so that any invalid ones are reported.
+    log::set_logger(&LOGGER).expect("could not initialize logger");
+    log::set_max_level(log::LevelFilter::Info);
+    let options = Options::from_url();
+    logs::set_capacity(options.log_buffer);
+    if let Some(capacity) = options.debug_messages {
+        messages::enable(capacity);
+    }
+    if options.verbose {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
+    if web_sys::window().is_none() {
+        // On a worker, rendering is started from JS with `WorkerRenderer::create`.
+        info!("No window, not starting the client");
+        return;
+    }
+    if dom::client_disabled() {
+        info!("The page doesn't want the client, not starting it");
+        return;
+    }
+    if let Err(e) = run_app(options) {
+        error!("run_app error: {}", e);
+    } else {
+        info!("run_app exit with no error");
+    }
+}
+
+/// Return the current client statistics as a JSON string.
+#[wasm_bindgen]
+pub fn stats() -> String {
+    crate::stats::to_json()
+}
+
+/// Return a JSON report of the adapter, surface and device capabilities:
+/// adapter info, supported surface formats, present and alpha modes, the
+/// features (including MSAA sample counts) of each format, and device limits.
+/// Returns undefined until the client has started.
+#[wasm_bindgen]
+pub fn surface_capabilities() -> Option<String> {
+    crate::diagnostics::capabilities_json()
+}
+
+/// Return a JSON object of the versions of this crate, Vello and wgpu, and
+/// the info of the adapter in use, including its driver. Returns undefined
+/// until the client has started.
+#[wasm_bindgen]
+pub fn version_info() -> Option<String> {
+    crate::diagnostics::versions_json()
+}
+
+/// Resize the render surface to the given size in physical pixels.
+#[wasm_bindgen]
+pub fn resize(width: u32, height: u32) -> Result<(), JsError> {
+    if width == 0 || height == 0 {
+        return Err(JsError::new("width and height must be non-zero"));
+    }
+    send_event(UserEvent::Resize(width, height))
+}
+
+/// Set a transform applied to all the scene content, e.g. for pan and zoom
+/// implemented in JS. The coefficients are in the same order as the canvas
+/// 2D context's `setTransform(a, b, c, d, e, f)`. The matrix must be invertible.
+#[wasm_bindgen]
+pub fn set_view_transform(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Result<(), JsError> {
+    let transform = Affine::new([a, b, c, d, e, f]);
+    let det = transform.determinant();
+    if !transform.is_finite() || det == 0.0 || !det.is_finite() {
+        return Err(JsError::new("view transform must be finite and invertible"));
+    }
+    send_event(UserEvent::ViewTransform(transform))
+}
+
+/// Zoom the demo area, centred in the view, by `scale_x` horizontally and
+/// `scale_y` vertically, e.g. to stretch one axis of a plot. Both must be
+/// positive; pass the same value twice for a uniform zoom.
+#[wasm_bindgen]
+pub fn set_zoom(scale_x: f64, scale_y: f64) -> Result<(), JsError> {
+    let valid = |s: f64| s.is_finite() && s > 0.0;
+    if !valid(scale_x) || !valid(scale_y) {
+        return Err(JsError::new("zoom scales must be positive"));
+    }
+    send_event(UserEvent::Zoom(Vec2::new(scale_x, scale_y)))
+}
+
+/// Set the view transform to fit all the content, including any freehand
+/// strokes, in the view, like the `F` key.
+#[wasm_bindgen]
+pub fn fit_to_view() -> Result<(), JsError> {
+    send_event(UserEvent::FitToView)
+}
+
+/// Set the number of frames the surface may queue ahead of the display,
+/// reconfiguring it. Must be at least 1.
+#[wasm_bindgen]
+pub fn set_frame_latency(latency: u32) -> Result<(), JsError> {
+    if latency == 0 {
+        return Err(JsError::new("frame latency must be at least 1"));
+    }
+    send_event(UserEvent::FrameLatency(latency))
+}
+
+/// Render at `factor` times the surface size and filter the result down into
+/// the surface, for supersampled antialiasing. 1 turns it off. Must be at least 1.
+#[wasm_bindgen]
+pub fn set_supersample(factor: f32) -> Result<(), JsError> {
+    if !factor.is_finite() || factor < 1.0 {
+        return Err(JsError::new("supersample factor must be at least 1"));
+    }
+    send_event(UserEvent::Supersample(factor))
+}
+
+/// Adjust the gamma of the final image: each colour component is raised to
+/// the power `1 / gamma`, so values above 1 brighten the mid tones. 1 turns
+/// the adjustment off. Must be positive.
+#[wasm_bindgen]
+pub fn set_gamma(gamma: f32) -> Result<(), JsError> {
+    if !gamma.is_finite() || gamma <= 0.0 {
+        return Err(JsError::new("gamma must be positive"));
+    }
+    send_event(UserEvent::Gamma(gamma))
+}
+
+/// Keep each frame's content and draw the next frame over it, instead of
+/// clearing to the base colour. Turning it back on starts from a clear frame.
+#[wasm_bindgen]
+pub fn set_preserve_previous(preserve: bool) -> Result<(), JsError> {
+    send_event(UserEvent::PreservePrevious(preserve))
+}
+
+/// Switch to the random scene, generated from `seed`.
+#[wasm_bindgen]
+pub fn set_seed(seed: u32) -> Result<(), JsError> {
+    send_event(UserEvent::Seed(seed))
+}
+
+/// Call `callback` after each frame is presented, with an object holding the
+/// frame number, size, and the times in milliseconds from the start of the
+/// frame to rendering, presenting and polling the device. Pass `undefined`
+/// to remove the callback.
+#[wasm_bindgen]
+pub fn on_present(callback: Option<js_sys::Function>) {
+    set_present_callback(callback.map(|callback| -> PresentCallback {
+        Box::new(move |info| {
+            let info = serde_json::to_string(info)
+                .ok()
+                .and_then(|json| js_sys::JSON::parse(&json).ok());
+            if let Some(info) = info {
+                if let Err(e) = callback.call1(&JsValue::NULL, &info) {
+                    error!("Present callback failed: {:?}", e);
+                }
+            }
+        })
+    }));
+}
+
+/// Call `callback` after each frame is presented, for embedding the client in a Rust app.
+/// Replaces any callback set with `on_present`.
+pub fn set_on_present(callback: impl FnMut(&PresentInfo) + 'static) {
+    set_present_callback(Some(Box::new(callback)));
+}
+
+/// Call `callback(width, height, frame, elapsed)` as each frame's content is
+/// drawn, with the drawable size, the frame number as given to `on_present`,
+/// and the animation time in seconds, and draw the `Drawing` it returns over
+/// the demo. With `?step` the time depends only on the frame number, so the
+/// content of frame N is reproducible. Pass `undefined` to remove it.
+#[wasm_bindgen]
+pub fn on_draw(callback: Option<js_sys::Function>) {
+    set_draw_callback(callback.map(|callback| -> DrawCallback {
+        Box::new(move |scene, width, height, frame, elapsed| {
+            let args = js_sys::Array::of4(
+                &width.into(),
+                &height.into(),
+                &(frame as f64).into(),
+                &elapsed.into(),
+            );
+            match callback.apply(&JsValue::NULL, &args) {
+                Ok(value) if value.is_undefined() => {}
+                Ok(value) => match Drawing::try_from_js_value(value) {
+                    Ok(drawing) => scene.append(&drawing.painter.into_scene(), None),
+                    Err(_) => error!("Draw callback returned something other than a Drawing"),
+                },
+                Err(e) => error!("Draw callback failed: {:?}", e),
+            }
+        })
+    }));
+}
+
+/// Draw into the scene as each frame's content is drawn, for embedding the
+/// client in a Rust app, with the scene, the drawable width and height,
+/// the frame number and the animation time in seconds. Replaces any
+/// callback set with `on_draw`.
+pub fn set_on_draw(callback: impl FnMut(&mut Scene, u32, u32, u64, f64) + 'static) {
+    set_draw_callback(Some(Box::new(callback)));
+}
+
+/// Draw a PNG image behind the content, over the base colour, scaled to the
+/// surface as `?background_fit` says. Pass `undefined` to remove it.
+#[wasm_bindgen]
+pub fn set_background(png: Option<Vec<u8>>) -> Result<(), JsError> {
+    let image = png.as_deref().map(decode_image).transpose()?;
+    send_event(UserEvent::Background(image))
+}
+
+/// Draw a PNG image of a known-good render in the right half of the
+/// surface, and the live content in the left half, for comparing the two.
+/// Pass `undefined` to go back to drawing the content over the whole surface.
+#[wasm_bindgen]
+pub fn set_reference_image(png: Option<Vec<u8>>) -> Result<(), JsError> {
+    let image = png.as_deref().map(decode_image).transpose()?;
+    send_event(UserEvent::ReferenceImage(image))
+}
+
+fn decode_image(png: &[u8]) -> Result<Image, JsError> {
+    let (width, height, pixels) = readback::decode_png(png)?;
+    Ok(Image::new(
+        Blob::new(Arc::new(pixels)),
+        ImageFormat::Rgba8,
+        width,
+        height,
+    ))
+}
+
+/// Load a flipbook of scenes from JSON, drawn one at a time instead of the
+/// demo, stepped through with the arrow keys or played with `B`. Pass
+/// `undefined` to remove it. The format is described in `flipbook.rs`.
+#[wasm_bindgen]
+pub fn load_flipbook(json: Option<String>) -> Result<(), JsError> {
+    let flipbook = json
+        .map(|json| Flipbook::from_json(&json))
+        .transpose()
+        .map_err(|e| JsError::new(&e))?;
+    send_event(UserEvent::Flipbook(flipbook))
+}
+
+/// Draw text in the TrueType or OpenType font in `bytes` from now on, e.g.
+/// fetched by the page, or in the bundled font if `None`. If the font doesn't
+/// parse, the error is returned and text goes back to the bundled font.
+#[wasm_bindgen]
+pub fn load_font(bytes: Option<Vec<u8>>) -> Result<(), JsError> {
+    match bytes.map(text::parse_font).transpose() {
+        Ok(font) => send_event(UserEvent::Font(font)),
+        Err(e) => {
+            send_event(UserEvent::Font(None))?;
+            Err(JsError::new(&e))
+        }
+    }
+}
+
+/// A drawing built up shape by shape from JS, with the current fill and
+/// stroke, e.g. `const d = new Drawing(); d.set_fill("red"); d.rect(10, 10,
+/// 50, 50); show_drawing(d)`. Forwards to the `Painter` used from Rust.
+#[wasm_bindgen]
+pub struct Drawing {
+    painter: Painter,
+}
+
+/// Parse an optional CSS colour.
+fn css_color(color: Option<String>) -> Result<Option<Color>, JsError> {
+    color
+        .map(|color| {
+            parse_color(&color)
+                .map(|color| color.to_alpha_color())
+                .map_err(|e| JsError::new(&format!("invalid colour '{}': {}", color, e)))
+        })
+        .transpose()
+}
+
+#[wasm_bindgen]
+impl Drawing {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Drawing {
+        Drawing {
+            painter: Painter::new(),
+        }
+    }
+
+    /// Set the fill of the shapes and text that follow to a CSS colour, or
+    /// `undefined` not to fill them.
+    pub fn set_fill(&mut self, color: Option<String>) -> Result<(), JsError> {
+        self.painter.set_fill(css_color(color)?);
+        Ok(())
+    }
+
+    /// Set the stroke of the shapes that follow to a CSS colour and width,
+    /// or `undefined` not to stroke them.
+    pub fn set_stroke(&mut self, color: Option<String>, width: f64) -> Result<(), JsError> {
+        self.painter.set_stroke(css_color(color)?, width);
+        Ok(())
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.painter.rect(x, y, width, height);
+    }
+
+    pub fn circle(&mut self, cx: f64, cy: f64, radius: f64) {
+        self.painter.circle(cx, cy, radius);
+    }
+
+    pub fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        self.painter.line(x0, y0, x1, y1);
+    }
+
+    /// Draw an SVG path from the data of its `d` attribute, filled and/or
+    /// stroked with the CSS colours given rather than the current ones.
+    pub fn draw_path_data(
+        &mut self,
+        d: &str,
+        fill: Option<String>,
+        stroke: Option<String>,
+        stroke_width: f64,
+    ) -> Result<(), JsError> {
+        let stroke = css_color(stroke)?.map(|color| (color, stroke_width));
+        self.painter
+            .draw_path_data(d, css_color(fill)?, stroke)
+            .map_err(|e| JsError::new(&format!("invalid path data: {}", e)))
+    }
+
+    pub fn text(&mut self, x: f64, y: f64, size: f32, text: &str) {
+        self.painter.text(x, y, size, text);
+    }
+}
+
+impl Default for Drawing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draw a `Drawing` over the demo every frame, under any freehand strokes.
+/// The drawing is consumed. Pass `undefined` to remove it.
+#[wasm_bindgen]
+pub fn show_drawing(drawing: Option<Drawing>) -> Result<(), JsError> {
+    send_event(UserEvent::Drawing(drawing.map(|d| Box::new(d.painter))))
+}
+
+/// Start recording the client's input events, with their times, discarding
+/// any recording in progress.
+#[wasm_bindgen]
+pub fn start_recording() {
+    record::start_recording();
+    info!("Recording input");
+}
+
+/// Stop recording input, returning the events recorded as a JSON array, or
+/// undefined if there was no recording in progress.
+#[wasm_bindgen]
+pub fn stop_recording() -> Option<String> {
+    let log = record::stop_recording();
+    info!("Stopped recording input");
+    log
+}
+
+/// Replay a JSON log of input events from `stop_recording`, feeding them
+/// through the client's input handlers at their recorded times. Live input
+/// is ignored until the replay has finished.
+#[wasm_bindgen]
+pub fn replay(log: &str) -> Result<(), JsError> {
+    let replay = Replay::from_json(log)?;
+    send_event(UserEvent::Replay(replay))
+}
+
+/// The render, present and poll times of the most recent frames, as CSV
+/// with a row per frame.
+#[wasm_bindgen]
+pub fn frame_times_csv() -> String {
+    trace::to_csv()
+}
+
+/// Download the recent frame times as a CSV file.
+#[wasm_bindgen]
+pub fn download_frame_times() -> Result<(), JsError> {
+    dom::download("frame-times.csv", "text/csv", trace::to_csv().as_bytes())
+        .map_err(|e| JsError::new(&format!("download failed: {:?}", e)))
+}
+
+/// Download the log records of the session so far as a text file.
+#[wasm_bindgen]
+pub fn download_logs() -> Result<(), JsError> {
+    dom::download("log.txt", "text/plain", logs::to_text().as_bytes())
+        .map_err(|e| JsError::new(&format!("download failed: {:?}", e)))
+}
+
+/// The client's state as of the last frame presented, as a string to share:
+/// the AA mode, demo, view transform and scroll, colours, present mode, and
+/// the random demo's shape count and seed.
+#[wasm_bindgen]
+pub fn save_config() -> Result<String, JsError> {
+    config::current().ok_or_else(|| JsError::new("no frame presented yet"))
+}
+
+/// Put back the state a string from `save_config()` holds.
+#[wasm_bindgen]
+pub fn restore_config(text: &str) -> Result<(), JsError> {
+    let config = ClientConfig::decode(text).map_err(|e| JsError::new(&e))?;
+    send_event(UserEvent::RestoreConfig(config))
+}
+
+/// Download the current frame as a PNG, once it has finished rendering.
+#[wasm_bindgen]
+pub fn screenshot() -> Result<(), JsError> {
+    send_event(UserEvent::Screenshot)
+}
+
+/// Download the current frame as a PNG of the given size, with the scene
+/// scaled to fit. Sizes beyond the GPU's texture limit are rendered in tiles.
+#[wasm_bindgen]
+pub fn export_image(width: u32, height: u32) -> Result<(), JsError> {
+    if width == 0 || height == 0 {
+        return Err(JsError::new("width and height must be positive"));
+    }
+    send_event(UserEvent::Export(width, height))
+}
+
+/// Capture the next `count` frames presented and download them as a ZIP of
+/// PNGs, named by their index in the capture.
+#[wasm_bindgen]
+pub fn export_frames(count: u32) -> Result<(), JsError> {
+    if count == 0 {
+        return Err(JsError::new("count must be positive"));
+    }
+    send_event(UserEvent::ExportFrames(count))
+}
+
+/// Render four quadrants of known colours on a new device, read them back and
+/// check the centre of each. Returns a JSON report with the expected and
+/// measured colour of each quadrant, and whether they all matched.
+#[wasm_bindgen]
+pub async fn self_test() -> Result<String, JsError> {
+    let mut context = RenderContext::new(wgpu::PowerPreference::default());
+    let dev_id = context
+        .device(None)
+        .await
+        .ok_or_else(|| JsError::new("no compatible device"))?;
+    let device_handle = &context.devices[dev_id];
+    let report = selftest::run(&device_handle.device, &device_handle.queue)
+        .await
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    if report.pass {
+        info!("Self-test passed");
+    } else {
+        error!("Self-test failed: {:?}", report.quadrants);
+    }
+    Ok(serde_json::to_string(&report)?)
+}
+
+/// Run every diagnostic check on a new device, without the interactive
+/// client, and return the report as JSON: the versions, the adapter, the
+/// device's features and limits, the colour self-test, a single pixel read
+/// back, and a hash and edge profile of a render with each AA config.
+/// A check that can't run is listed in `errors`, and the rest still run.
+#[wasm_bindgen]
+pub async fn run_diagnostics() -> Result<String, JsError> {
+    let mut context = RenderContext::new(wgpu::PowerPreference::default());
+    let dev_id = context
+        .device(None)
+        .await
+        .ok_or_else(|| JsError::new("no compatible device"))?;
+    let report = checkup::run(&context.devices[dev_id]).await;
+    Ok(serde_json::to_string(&report)?)
+}
+
+/// List the adapters that can be switched to, as a JSON array of objects with
+/// the `index` to pass to `switch_adapter`, and the adapter's `name`,
+/// `backend`, `device_type` and `driver`.
+#[wasm_bindgen]
+pub async fn adapters() -> Result<String, JsError> {
+    let context = RenderContext::new(wgpu::PowerPreference::default());
+    let adapters = context::list_adapters(&context.instance).await;
+    let listings: Vec<_> = adapters.iter().map(|(listing, _)| listing).collect();
+    Ok(serde_json::to_string(&listings)?)
+}
+
+/// Switch the running client to the adapter at `index` in the `adapters` list,
+/// recreating the device and renderer and reconfiguring the surface. The
+/// switch happens asynchronously; if it fails, the error is logged and the
+/// client keeps its current adapter.
+#[wasm_bindgen]
+pub fn switch_adapter(index: usize) -> Result<(), JsError> {
+    send_event(UserEvent::SwitchAdapter(index))
+}
+
+pub fn run_app(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let started = Instant::now();
+    if PROXY.with_borrow(Option::is_some) {
+        return Err(StartError::AlreadyRunning.into());
+    }
+    let event_loop = EventLoop::<UserEvent>::with_user_event()
+        .build()
+        .map_err(|e| match e {
+            EventLoopError::RecreationAttempt => StartError::AlreadyRunning,
+            e => StartError::EventLoop(e),
+        })?;
+    event_loop.set_control_flow(ControlFlow::Wait);
+    PROXY.set(Some(event_loop.create_proxy()));
+    let mut render_cx = RenderContext::with_backends(options.power_preference, options.backends);
+    render_cx.limits = options.limits;
+    if options.safe_mode {
+        info!("Safe mode: CPU stages, area AA, FIFO present, no optional features");
+        render_cx.optional_features = wgpu::Features::empty();
+    }
+    #[allow(deprecated)]
+    let window = Arc::new(event_loop.create_window(window_attributes()).unwrap());
+    // On wasm, append the canvas to the document body
+    let canvas = window.canvas().ok_or("window has no canvas")?;
+    dom::append_canvas(canvas)?;
+    wasm_bindgen_futures::spawn_local(async move {
+        let (width, height, scale_factor) = web_sys::window()
+            .map(|w| {
+                (
+                    w.inner_width().unwrap().as_f64().unwrap(),
+                    w.inner_height().unwrap().as_f64().unwrap(),
+                    w.device_pixel_ratio(),
+                )
+            })
+            .unwrap();
+        info!("Window {} x {}, scale {}", width, height, scale_factor);
+        let size: PhysicalSize<u32> = if options.safe_mode {
+            SAFE_MODE_SIZE.into()
+        } else {
+            PhysicalSize::from_logical::<_, f64>((width, height), scale_factor)
+        };
+        if let Some(sz) = window.request_inner_size(size) {
+            info!("Request inner size: {} x {}", sz.width, sz.height);
+        } else {
+            info!("Resize deferred");
+        }
+        info!("scaled size {} x {}", size.width, size.height);
+        // Once laid out at the requested size, the canvas's exact size in
+        // device pixels, which later resizes report too, where available.
+        let size = match window.canvas() {
+            Some(canvas) if !options.safe_mode => {
+                match dom::device_pixel_size(&canvas).await {
+                    Ok((width, height)) if width > 0 && height > 0 => {
+                        info!("Device pixel content box {} x {}", width, height);
+                        PhysicalSize::new(width, height)
+                    }
+                    Ok(_) => size,
+                    Err(e) => {
+                        info!("Sizing from devicePixelRatio, without a device pixel content box: {:?}", e);
+                        size
+                    }
+                }
+            }
+            _ => size,
+        };
+        let surface = render_cx
+            .create_surface(window.clone(), size.width, size.height, &options.surface)
+            .await;
+        match surface {
+            Ok(surface) => {
+                if alpha::is_transparent(surface.config.alpha_mode) {
+                    if let Some(canvas) = window.canvas() {
+                        dom::show_checkerboard(&canvas, alpha::CHECKER_SIZE / scale_factor);
+                    }
+                }
+                // No error handling here; if the event loop has finished, we don't need to send them the surface
+                let delay = options.startup_delay;
+                if delay.is_zero() {
+                    run(event_loop, render_cx, surface, window, options, started);
+                    return;
+                }
+                // The surface is configured and waiting, and nothing has been rendered yet.
+                info!(
+                    "Waiting {:?} after creating the surface before starting",
+                    delay
+                );
+                let start = move || run(event_loop, render_cx, surface, window, options, started);
+                if let Err(e) = dom::set_timeout(delay, start) {
+                    error!("Failed to delay starting the client: {:?}", e);
+                }
+            }
+            Err(e) if options.safe_mode => {
+                error!("Failed to create surface in safe mode: {}", e);
+                dom::display_safe_mode_error("create the surface", &e.to_string());
+            }
+            Err(e) if !dom::has_webgpu() => {
+                error!("WebGPU is not available: {}", e);
+                _ = dom::display_error_message();
+            }
+            // WebGPU is there but has no adapter to give: the GPU itself is the problem.
+            Err(e) if e.downcast_ref() == Some(&DeviceError::NoAdapter) => {
+                error!("WebGPU is available, but no adapter was returned");
+                _ = dom::display_unsupported_gpu_message();
+            }
+            Err(e) => {
+                error!("Failed to create surface: {}", e);
+                _ = dom::display_banner(dom::Severity::Error, &dom::escape_html(&e.to_string()));
+            }
+        }
+    });
+    Ok(())
+}
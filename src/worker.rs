@@ -0,0 +1,199 @@
+//! Rendering to an `OffscreenCanvas` from a Web Worker.
+//!
+//! winit needs the DOM, so it can't run on a worker. Instead the worker
+//! script drives a [`WorkerRenderer`] directly: the page transfers control of
+//! its canvas to the worker, which creates the renderer from it and calls
+//! `render` from its own `requestAnimationFrame` loop. Size changes are
+//! forwarded from the page as messages. See `www/worker.html`.
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
+use wasm_bindgen::prelude::*;
+
+use log::{debug, info};
+
+use vello::peniko::{color::palette, Color};
+use vello::util::RenderSurface;
+use vello::wgpu;
+use vello::{AaConfig, AaSupport, Renderer, RendererOptions, Scene};
+
+use crate::clock::{Clock, ManualClock, RealClock};
+use crate::context::RenderContext;
+use crate::diagnostics::{self, CapabilityReport, VersionInfo};
+use crate::frame::PollStrategy;
+use crate::scenes::{Demo, DemoState};
+use crate::stats::{self, SceneSize};
+use crate::{aa, alpha, frame, Options};
+
+/// A renderer drawing the demo scenes into an `OffscreenCanvas`.
+#[wasm_bindgen]
+pub struct WorkerRenderer {
+    canvas: web_sys::OffscreenCanvas,
+    context: RenderContext,
+    surface: RenderSurface<'static>,
+    renderer: Renderer,
+    scene: Scene,
+    aa_config: AaConfig,
+    base_color: Color,
+    verbose: bool,
+    animate: bool,
+    poll: PollStrategy,
+    /// Set when the content must be redrawn even if not animating.
+    needs_redraw: bool,
+    frame: u64,
+    clock: Box<dyn Clock>,
+    demo: Demo,
+    demo_state: DemoState,
+}
+
+#[wasm_bindgen]
+impl WorkerRenderer {
+    /// Create a renderer for a canvas transferred from the page, using options
+    /// from `search`, a URL query string such as the page's `location.search`.
+    pub async fn create(
+        canvas: web_sys::OffscreenCanvas,
+        search: &str,
+    ) -> Result<WorkerRenderer, JsError> {
+        let options = Options::from_query(search);
+        if options.verbose {
+            log::set_max_level(log::LevelFilter::Debug);
+        }
+        let (width, height) = (canvas.width(), canvas.height());
+        info!("Worker canvas {} x {}", width, height);
+        let mut context = RenderContext::with_backends(options.power_preference, options.backends);
+        let surface = context
+            .create_surface(
+                wgpu::SurfaceTarget::OffscreenCanvas(canvas.clone()),
+                width,
+                height,
+                &options.surface,
+            )
+            .await
+            .map_err(|e| JsError::new(&e.to_string()))?;
+
+        let device_handle = &context.devices[surface.dev_id];
+        let aa_config = options.aa_config.map_or(AaConfig::Area, |aa| {
+            aa::select(&device_handle.adapter, surface.format, aa)
+        });
+        info!("Using AA config {:?}", aa_config);
+        diagnostics::set_versions(&VersionInfo::new(&device_handle.adapter));
+        diagnostics::set_capabilities(CapabilityReport::new(
+            &device_handle.adapter,
+            &device_handle.device,
+            &surface.surface,
+            surface.format,
+        ));
+        let aa_support = [aa_config].into_iter().collect::<AaSupport>();
+        let renderer = Renderer::new(
+            &device_handle.device,
+            RendererOptions {
+                surface_format: Some(surface.format),
+                use_cpu: false,
+                antialiasing_support: aa_support,
+                num_init_threads: NonZeroUsize::new(1),
+            },
+        )
+        .map_err(|e| JsError::new(&e.to_string()))?;
+        aa::log_pipelines(&device_handle.adapter, surface.format, aa_support);
+        let base_color = if alpha::is_transparent(surface.config.alpha_mode) {
+            Color::TRANSPARENT
+        } else {
+            palette::css::WHITE
+        };
+
+        Ok(WorkerRenderer {
+            canvas,
+            context,
+            surface,
+            renderer,
+            scene: Scene::new(),
+            aa_config,
+            base_color,
+            verbose: options.verbose,
+            animate: options.animate,
+            poll: options.poll,
+            needs_redraw: true,
+            frame: 0,
+            clock: match options.frame_step {
+                Some(step) => Box::new(ManualClock::new(step)),
+                None => Box::new(RealClock::new()),
+            },
+            demo: options.demo,
+            demo_state: DemoState::new(&options),
+        })
+    }
+
+    /// Resize the canvas and surface to the given size in physical pixels.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsError> {
+        if width == 0 || height == 0 {
+            return Err(JsError::new("width and height must be non-zero"));
+        }
+        self.context
+            .resize_surface(&mut self.surface, width, height);
+        // The surface size may have been clamped, and the canvas must match it.
+        self.canvas.set_width(self.surface.config.width);
+        self.canvas.set_height(self.surface.config.height);
+        self.needs_redraw = true;
+        info!("Worker resize to {} x {}", width, height);
+        Ok(())
+    }
+
+    /// Draw and present a frame, if anything has changed or the scene is animated.
+    /// Intended to be called from the worker's `requestAnimationFrame` callback.
+    pub fn render(&mut self) -> Result<(), JsError> {
+        if !self.animate && !self.needs_redraw {
+            return Ok(());
+        }
+        self.needs_redraw = false;
+        let width = self.surface.config.width;
+        let height = self.surface.config.height;
+
+        self.scene.reset();
+        let elapsed = if self.animate {
+            self.clock.elapsed()
+        } else {
+            Duration::ZERO
+        };
+        self.demo.draw(
+            &mut self.scene,
+            &mut self.demo_state,
+            width,
+            height,
+            elapsed,
+        );
+        self.clock.tick();
+        self.frame += 1;
+        let scene_size = SceneSize::of(&self.scene);
+        if self.verbose {
+            debug!("Frame {}: {:?}", self.frame, scene_size);
+        }
+        stats::update(|s| {
+            s.frames = self.frame;
+            s.scene = scene_size;
+        });
+
+        let device_handle = &self.context.devices[self.surface.dev_id];
+        let surface_texture = self
+            .surface
+            .surface
+            .get_current_texture()
+            .map_err(|e| JsError::new(&format!("failed to get surface texture: {}", e)))?;
+        frame::render_frame(
+            &device_handle.device,
+            &device_handle.queue,
+            &mut self.renderer,
+            &self.scene,
+            surface_texture,
+            &vello::RenderParams {
+                base_color: self.base_color,
+                width,
+                height,
+                antialiasing_method: self.aa_config,
+            },
+            self.poll,
+        )
+        .map_err(|e| JsError::new(&format!("failed to render to surface: {}", e)))?;
+        Ok(())
+    }
+}
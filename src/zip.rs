@@ -0,0 +1,145 @@
+//! Writing ZIP archives of files that are already compressed.
+//!
+//! [`ZipWriter`] stores each file as it is given, uncompressed, which is all
+//! PNGs need, as deflating them again gains next to nothing. Each file is
+//! written into the archive as it is added, with its local header, and only
+//! its name, size, CRC and offset are kept for the central directory that
+//! [`ZipWriter::finish`] writes at the end. Archives are limited to the
+//! original format's 4 GiB and 65535 files, with no ZIP64 extensions.
+
+/// The date of every file, 1980-01-01, the earliest DOS date, as a DOS date.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// A file written into the archive, for the central directory.
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// An archive written into memory.
+#[derive(Default)]
+pub struct ZipWriter {
+    bytes: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    /// Add a file called `name` holding `data`, or fail if the archive
+    /// would be too big.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let too_big = || format!("'{}' would make the archive too big", name);
+        let size = u32::try_from(data.len()).map_err(|_| too_big())?;
+        let offset = u32::try_from(self.bytes.len()).map_err(|_| too_big())?;
+        if self.entries.len() == u16::MAX as usize {
+            return Err(too_big());
+        }
+        let crc = crc32fast::hash(data);
+        let bytes = &mut self.bytes;
+        bytes.extend(0x04034b50u32.to_le_bytes());
+        Self::common_header(bytes, name, crc, size);
+        bytes.extend(name.as_bytes());
+        bytes.extend(data);
+        u32::try_from(self.bytes.len()).map_err(|_| too_big())?;
+        self.entries.push(Entry {
+            name: name.to_string(),
+            crc,
+            size,
+            offset,
+        });
+        Ok(())
+    }
+
+    /// The fields a file's local header and its central directory entry share.
+    fn common_header(bytes: &mut Vec<u8>, name: &str, crc: u32, size: u32) {
+        // Version 1.0 is enough to extract stored files.
+        bytes.extend(10u16.to_le_bytes());
+        // Flags: the name is UTF-8.
+        bytes.extend((1u16 << 11).to_le_bytes());
+        // Stored, not compressed.
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(DOS_DATE.to_le_bytes());
+        bytes.extend(crc.to_le_bytes());
+        bytes.extend(size.to_le_bytes());
+        bytes.extend(size.to_le_bytes());
+        bytes.extend((name.len() as u16).to_le_bytes());
+        // No extra field.
+        bytes.extend(0u16.to_le_bytes());
+    }
+
+    /// Write the central directory, and return the whole archive.
+    pub fn finish(mut self) -> Vec<u8> {
+        let start = self.bytes.len() as u32;
+        let bytes = &mut self.bytes;
+        for entry in &self.entries {
+            bytes.extend(0x02014b50u32.to_le_bytes());
+            // Made by version 1.0.
+            bytes.extend(10u16.to_le_bytes());
+            Self::common_header(bytes, &entry.name, entry.crc, entry.size);
+            // No comment, on disk 0, with no attributes.
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(0u16.to_le_bytes());
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend(entry.offset.to_le_bytes());
+            bytes.extend(entry.name.as_bytes());
+        }
+        let size = bytes.len() as u32 - start;
+        let count = self.entries.len() as u16;
+        bytes.extend(0x06054b50u32.to_le_bytes());
+        // All on disk 0.
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(count.to_le_bytes());
+        bytes.extend(count.to_le_bytes());
+        bytes.extend(size.to_le_bytes());
+        bytes.extend(start.to_le_bytes());
+        // No comment.
+        bytes.extend(0u16.to_le_bytes());
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn files_are_stored_and_listed() {
+        let mut zip = ZipWriter::default();
+        zip.add("a.txt", b"hello").unwrap();
+        zip.add("b.txt", b"").unwrap();
+        let bytes = zip.finish();
+        // The first file, right after its local header.
+        assert_eq!(u32_at(&bytes, 0), 0x04034b50);
+        assert_eq!(u32_at(&bytes, 14), crc32fast::hash(b"hello"));
+        assert_eq!(u32_at(&bytes, 18), 5);
+        assert_eq!(&bytes[30..35], b"a.txt");
+        assert_eq!(&bytes[35..40], b"hello");
+        // The end record, counting both files in the central directory.
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), 0x06054b50);
+        assert_eq!(u16_at(&bytes, end + 10), 2);
+        let (size, start) = (u32_at(&bytes, end + 12), u32_at(&bytes, end + 16));
+        assert_eq!((start + size) as usize, end);
+        let central = start as usize;
+        assert_eq!(u32_at(&bytes, central), 0x02014b50);
+        assert_eq!(u32_at(&bytes, central + 42), 0);
+        assert_eq!(&bytes[central + 46..central + 51], b"a.txt");
+        // The second file's entry points at its local header.
+        let second = central + 51;
+        assert_eq!(&bytes[second + 46..second + 51], b"b.txt");
+        assert_eq!(u32_at(&bytes, second + 42), 40);
+        assert_eq!(u32_at(&bytes, 40), 0x04034b50);
+    }
+}
@@ -0,0 +1,25 @@
+// Renders to a canvas transferred from worker.html, off the main thread.
+import init, { WorkerRenderer } from "./vellowasm.js";
+
+let renderer = null;
+
+function frame() {
+    try {
+        renderer.render();
+    } catch (e) {
+        console.error("Render failed:", e);
+        return;
+    }
+    self.requestAnimationFrame(frame);
+}
+
+self.onmessage = async (event) => {
+    const { canvas, search, resize } = event.data;
+    if (canvas) {
+        await init();
+        renderer = await WorkerRenderer.create(canvas, search);
+        self.requestAnimationFrame(frame);
+    } else if (resize && renderer) {
+        renderer.resize(resize.width, resize.height);
+    }
+};